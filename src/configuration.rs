@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 
@@ -9,12 +9,15 @@ use serde::{Deserialize, Deserializer};
 use std::time::Duration;
 
 mod legacy_translation;
+mod migrations;
+mod validation;
 #[allow(deprecated)]
 pub(crate) use legacy_translation::auth::translate_legacy_auth_to_typed;
 #[allow(deprecated)]
 pub(crate) use legacy_translation::ratelimit::translate_legacy_ratelimit_to_typed;
 #[allow(deprecated)]
 pub(crate) use legacy_translation::ratelimit::translate_legacy_report_to_typed;
+pub use validation::{ConfigError, ConfigErrorKind, ConfigValidator, ValidatedConfig};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ConditionalData {
@@ -50,10 +53,22 @@ pub struct TypedAction {
     pub is_guard: bool,
     #[serde(default)]
     pub sources: Vec<String>,
+    /// A field the request body must also hold for the action to apply
+    #[serde(default)]
+    pub request_body_field: Option<RequestBodyField>,
     #[serde(flatten)]
     pub operation: Operation,
 }
 
+/// The value `equals` at the JSON pointer `pointer` of the request body. A
+/// body that is not JSON, or lacks the field, does not hold it.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RequestBodyField {
+    pub pointer: String,
+    pub equals: serde_json::Value,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum Operation {
@@ -62,6 +77,10 @@ pub enum Operation {
     Headers(HeadersOperation),
     Store(StoreOperation),
     Fail(FailOperation),
+    #[serde(rename = "requestHeaderMutation")]
+    RequestHeaderMutation(RequestHeaderMutationOperation),
+    #[serde(rename = "removeHeaders")]
+    RemoveHeaders(HeadersOperation),
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -70,6 +89,9 @@ pub struct GrpcOperation {
     pub var: String,
     pub service: String,
     pub message_builder: String,
+    /// Overrides the timeout of the service for this call
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
     #[serde(default)]
     pub on_reply: Vec<TypedAction>,
 }
@@ -94,6 +116,18 @@ pub struct HeadersOperation {
     pub headers: String,
 }
 
+/// Rewrites the inbound request headers, removals applying last so a header can
+/// be both stripped and never set, e.g. to drop a spoofed identity header.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RequestHeaderMutationOperation {
+    #[serde(default)]
+    pub set: Vec<(String, String)>,
+    #[serde(default)]
+    pub append: Vec<(String, String)>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct StoreOperation {
@@ -117,18 +151,79 @@ pub enum ActionConfig {
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
 pub struct RouteRuleConditions {
     pub hostnames: Vec<String>,
     #[serde(default)]
-    pub predicates: Vec<String>,
+    pub predicates: RoutePredicates,
+}
+
+/// Route predicates are either a plain list, all of which must hold, or a list
+/// under an `and` or `or` key stating how they combine.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum RoutePredicates {
+    All(Vec<String>),
+    Composed(PredicateComposition),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum PredicateComposition {
+    And(Vec<String>),
+    Or(Vec<String>),
+}
+
+impl RoutePredicates {
+    pub fn expressions(&self) -> &[String] {
+        match self {
+            RoutePredicates::All(predicates)
+            | RoutePredicates::Composed(PredicateComposition::And(predicates))
+            | RoutePredicates::Composed(PredicateComposition::Or(predicates)) => predicates,
+        }
+    }
+}
+
+impl Default for RoutePredicates {
+    fn default() -> Self {
+        RoutePredicates::All(Vec::new())
+    }
+}
+
+impl From<Vec<String>> for RoutePredicates {
+    fn from(predicates: Vec<String>) -> Self {
+        RoutePredicates::All(predicates)
+    }
 }
 
 #[derive(Default, Deserialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ActionSet {
     pub name: String,
     pub route_rule_conditions: RouteRuleConditions,
     pub actions: Vec<ActionConfig>,
+    #[serde(default)]
+    pub required_capabilities: Vec<String>,
+    /// Dispatches every action at once instead of each after the previous one
+    /// completes, for action sets whose actions do not depend on one another
+    #[serde(default)]
+    pub parallel: bool,
+    /// Fraction of matching requests, between 0.0 and 1.0, whose debug logs
+    /// are emitted; all of them when unset
+    #[serde(default)]
+    pub log_sample_rate: Option<f32>,
+    /// Order in which action sets matching the same request are tried, lower first
+    #[serde(default)]
+    pub priority: i32,
+    /// Prefixes the descriptor keys of the legacy rate limit actions with
+    /// `{namespace}/`, so that policies sharing a Limitador do not collide
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Only requests whose normalized path, ignoring the query, starts with the
+    /// whole segments of this prefix match the action set. Among the action sets of the same priority, the
+    /// longest matching prefix is tried first.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -166,6 +261,28 @@ pub enum FailureMode {
     Allow,
 }
 
+/// What becomes of the requests received while Envoy's overload manager is active
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverloadMode {
+    /// Let the request through unchecked
+    Allow,
+    /// Reply with a 503
+    Deny,
+}
+
+/// What becomes of the requests Envoy marks as internal with `x-envoy-internal`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum InternalRequestPolicy {
+    /// Let the request through unchecked and leave it out of the access log
+    Skip,
+    /// Let the request through unchecked
+    Allow,
+    /// Apply the named action set, whatever the hostname and route predicates
+    UseActionSet(String),
+}
+
 #[derive(Deserialize, Debug, Clone, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ServiceType {
@@ -178,6 +295,9 @@ pub enum ServiceType {
     RateLimitReport,
     Tracing,
     Dynamic,
+    #[cfg(feature = "http-callout")]
+    #[serde(rename = "http-callout")]
+    HttpCallout,
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
@@ -186,6 +306,62 @@ pub struct Observability {
     pub http_header_identifier: Option<String>,
     pub default_level: Option<String>,
     pub tracing: Option<Tracing>,
+    #[serde(default)]
+    pub default_header_values: HashMap<String, String>,
+    #[serde(default)]
+    pub publish_config_hash: bool,
+    #[serde(default)]
+    pub trace_generation: Option<TraceGeneration>,
+    #[serde(default)]
+    pub tracing_header_style: TracingHeaderStyle,
+    /// Counts the outcomes of auth and rate limit calls per action set
+    #[serde(default)]
+    pub action_set_metrics: bool,
+    /// Logs a JSON line per request with the decisions taken, once it completes
+    #[serde(default)]
+    pub access_log: bool,
+}
+
+/// The trace context headers forwarded on the gRPC calls made for a request.
+/// `x-request-id` is not one of them: every call carries the id the shim
+/// tracks the request by, whatever the style.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TracingHeaderStyle {
+    #[default]
+    W3C,
+    Zipkin,
+    Both,
+}
+
+impl TracingHeaderStyle {
+    const ZIPKIN_HEADERS: [&'static str; 5] = [
+        "x-b3-traceid",
+        "x-b3-spanid",
+        "x-b3-parentspanid",
+        "x-b3-flags",
+        "x-b3-sampled",
+    ];
+
+    pub fn includes_w3c(&self) -> bool {
+        matches!(self, TracingHeaderStyle::W3C | TracingHeaderStyle::Both)
+    }
+
+    /// The request headers copied as they are onto the outgoing calls, W3C
+    /// headers are injected from the extracted trace context instead
+    pub fn forwarded_headers(&self) -> &'static [&'static str] {
+        match self {
+            TracingHeaderStyle::W3C => &[],
+            TracingHeaderStyle::Zipkin | TracingHeaderStyle::Both => &Self::ZIPKIN_HEADERS,
+        }
+    }
+}
+
+/// Starts a new W3C trace for requests that arrive without one.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct TraceGeneration {
+    #[serde(default)]
+    pub sampled: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -194,8 +370,12 @@ pub struct Tracing {
 }
 
 #[derive(Deserialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct PluginConfiguration {
+    /// The schema version the configuration is written against; older ones
+    /// are migrated to the current version before being parsed
+    #[serde(default = "default_version")]
+    pub version: u32,
     #[serde(default)]
     pub request_data: HashMap<String, String>,
     pub services: HashMap<String, Service>,
@@ -204,37 +384,354 @@ pub struct PluginConfiguration {
     pub observability: Observability,
     #[serde(default = "default_descriptor_service")]
     pub descriptor_service: String,
+    #[serde(default)]
+    pub inherit_deadline_from_request: bool,
+    #[serde(default = "Capabilities::default_set")]
+    pub registered_capabilities: HashSet<String>,
+    #[serde(default)]
+    pub trigger_on_trailers: bool,
+    #[serde(default)]
+    pub computed_properties: Vec<ComputedProperty>,
+    #[serde(default = "default_drain_timeout")]
+    pub drain_timeout: Timeout,
+    #[serde(default)]
+    pub dynamic_actions_queue: Option<String>,
+    #[serde(default = "default_max_request_body_size")]
+    pub max_request_body_size: usize,
+    #[serde(default)]
+    pub response_body_injection: bool,
+    /// The largest response body buffered to inject the quota into, larger
+    /// ones go through untouched
+    #[serde(default = "default_max_response_body_size")]
+    pub max_response_body_size: usize,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub wildcard_match: bool,
+    /// Answers with a 504 the requests whose gRPC call got no response from the
+    /// host within this long, disabled when unset. Requests whose call is to a
+    /// service failing open, or in dry run, go on without its response instead.
+    #[serde(default)]
+    pub grpc_watchdog_timeout: Option<Timeout>,
+    /// Request paths let through without matching any action set, such as
+    /// health probes. An entry ending in `*` matches every path it prefixes by
+    /// whole segments. Paths are normalized before being matched.
+    #[serde(default)]
+    pub bypass_paths: Vec<String>,
+    /// Upstream response headers never forwarded to clients, such as internal
+    /// request ids
+    #[serde(default)]
+    pub response_headers_to_remove: Vec<String>,
+    /// Request trailers sent along as metadata with the auth, rate limit and
+    /// dynamic service calls dispatched once they are received, such as tokens
+    /// of gRPC-bridged clients; rate limit reports are sent without them
+    #[serde(default)]
+    pub forwarded_trailers: Vec<String>,
+    /// How requests Envoy flags with `x-envoy-overloaded` are handled, without
+    /// calling any service; the flag is ignored when unset, as well as on
+    /// requests not flagged `x-envoy-internal`
+    #[serde(default)]
+    pub overload_mode: Option<OverloadMode>,
+    /// Name and value pairs sent as metadata with every gRPC call, such as
+    /// `["x-region", "eu-west-1"]`; trace context headers take precedence
+    #[serde(default)]
+    pub static_forwarded_headers: Vec<(String, String)>,
+    /// How requests Envoy flags with `x-envoy-internal` are handled; they are
+    /// treated like any other when unset. The header is only trustworthy when
+    /// the connection manager has `use_remote_address: true`, as Envoy then
+    /// strips it from external requests.
+    #[serde(default)]
+    pub internal_request_policy: Option<InternalRequestPolicy>,
+}
+
+/// An action pushed at runtime through the dynamic actions queue, appended to the
+/// named action set until `expires_at_ms` (milliseconds since the Unix epoch).
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DynamicActionSpec {
+    pub target_action_set: String,
+    pub action: ActionConfig,
+    pub expires_at_ms: u64,
+}
+
+/// A configuration applied on top of the running one instead of replacing it,
+/// told apart by `"delta": true`: action sets are removed by name, then upserted.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ConfigDelta {
+    pub delta: bool,
+    #[serde(default)]
+    pub remove_action_sets: Vec<String>,
+    #[serde(default)]
+    pub upsert_action_sets: Vec<ActionSet>,
+}
+
+impl ConfigDelta {
+    /// Whether `raw` holds a delta rather than a full configuration, which
+    /// [`ConfigValidator::validate_delta`] then checks
+    pub fn is_delta(raw: &[u8]) -> bool {
+        serde_json::from_slice::<serde_json::Value>(raw)
+            .is_ok_and(|document| document.get("delta") == Some(&serde_json::Value::Bool(true)))
+    }
+}
+
+/// A property derived from a CEL expression, resolved lazily by its `name` path.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ComputedProperty {
+    pub name: String,
+    pub expression: String,
+}
+
+fn default_version() -> u32 {
+    migrations::DEFAULT_VERSION
 }
 
 fn default_descriptor_service() -> String {
     "kuadrant-operator-grpc".to_string()
 }
 
+fn default_max_request_body_size() -> usize {
+    1024 * 1024
+}
+
+fn default_max_response_body_size() -> usize {
+    1024 * 1024
+}
+
+fn default_drain_timeout() -> Timeout {
+    Timeout(Duration::from_secs(10))
+}
+
 impl PluginConfiguration {
     #[cfg(test)]
     pub fn new(services: HashMap<String, Service>, action_sets: Vec<ActionSet>) -> Self {
         Self {
+            version: migrations::CURRENT_VERSION,
             request_data: HashMap::new(),
             services,
             action_sets,
             observability: Default::default(),
             descriptor_service: default_descriptor_service(),
+            inherit_deadline_from_request: false,
+            registered_capabilities: Capabilities::default_set(),
+            trigger_on_trailers: false,
+            computed_properties: Vec::new(),
+            drain_timeout: default_drain_timeout(),
+            dynamic_actions_queue: None,
+            max_request_body_size: default_max_request_body_size(),
+            response_body_injection: false,
+            max_response_body_size: default_max_response_body_size(),
+            dry_run: false,
+            wildcard_match: false,
+            grpc_watchdog_timeout: None,
+            bypass_paths: Vec::new(),
+            response_headers_to_remove: Vec::new(),
+            forwarded_trailers: Vec::new(),
+            overload_mode: None,
+            static_forwarded_headers: Vec::new(),
+            internal_request_policy: None,
         }
     }
 }
 
+/// Capabilities an action set may require from the deployment the
+/// configuration is loaded into.
+pub struct Capabilities;
+
+impl Capabilities {
+    pub fn default_set() -> HashSet<String> {
+        ["auth", "rate_limit"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, Default, PartialEq)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Service {
     #[serde(rename = "type")]
     pub service_type: ServiceType,
-    pub endpoint: String,
+    pub endpoint: ClusterConfig,
     // Deny/Allow request when faced with an irrecoverable failure.
     pub failure_mode: FailureMode,
     #[serde(default)]
     pub timeout: Timeout,
     pub grpc_service: Option<String>,
     pub grpc_method: Option<String>,
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    #[serde(default)]
+    pub response_cache: Option<ResponseCacheConfig>,
+    #[serde(default)]
+    pub error_response: Option<ErrorResponse>,
+    /// Replies with the denial reason a service reports in the
+    /// `grpc-status-details-bin` trailer of a failed call, when its `failureMode`
+    /// is `deny`
+    #[serde(default)]
+    pub use_grpc_status_details: bool,
+    /// Checks the upstream with `grpc.health.v1.Health/Check` once configured
+    #[serde(default)]
+    pub health_check: bool,
+    /// Takes the `dynamic_metadata` of an auth reply carrying none from the
+    /// Envoy dynamic metadata under this `filter_metadata` key instead
+    #[serde(default)]
+    pub dynamic_metadata_key: Option<String>,
+    #[cfg(feature = "http-callout")]
+    #[serde(default)]
+    pub http_callout: Option<HttpCalloutConfig>,
+}
+
+/// The HTTP endpoint an `http-callout` service POSTs the JSON built by an
+/// action to, and where the decision sits in the JSON it answers with.
+#[cfg(feature = "http-callout")]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HttpCalloutConfig {
+    pub path: String,
+    /// JSONPath to a boolean of the response body, e.g. `$.result.allowed`;
+    /// the request is allowed when it is `true`
+    pub decision_path: String,
+}
+
+/// The Envoy cluster calls to a service are dispatched to, given either as its
+/// plain name or as an object with the name under `clusterName`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(from = "ClusterEndpoint")]
+pub struct ClusterConfig {
+    pub name: String,
+    /// `:authority` of the calls in place of the cluster name, for an upstream
+    /// behind a cluster shared by several hosts. TLS, SNI included, is
+    /// originated by the cluster itself.
+    pub authority_override: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ClusterEndpoint {
+    Name(String),
+    #[serde(rename_all = "camelCase")]
+    Cluster {
+        cluster_name: String,
+        #[serde(default)]
+        authority_override: Option<String>,
+    },
+}
+
+impl From<ClusterEndpoint> for ClusterConfig {
+    fn from(endpoint: ClusterEndpoint) -> Self {
+        match endpoint {
+            ClusterEndpoint::Name(name) => name.into(),
+            ClusterEndpoint::Cluster {
+                cluster_name,
+                authority_override,
+            } => ClusterConfig {
+                name: cluster_name,
+                authority_override,
+            },
+        }
+    }
+}
+
+impl From<String> for ClusterConfig {
+    fn from(name: String) -> Self {
+        ClusterConfig {
+            name,
+            ..Default::default()
+        }
+    }
+}
+
+/// Reply sent in place of the default `500` when a call to a service with
+/// `failureMode: deny` fails. `{status}` and `{message}` in the body are
+/// replaced with the status code and a description of the failure.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    #[serde(deserialize_with = "deserialize_status_code")]
+    pub status: u32,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+}
+
+impl ErrorResponse {
+    pub fn render_body(&self, message: &str) -> Option<String> {
+        self.body.as_ref().map(|body| {
+            body.replace("{status}", &self.status.to_string())
+                .replace("{message}", message)
+        })
+    }
+}
+
+fn deserialize_status_code<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let status = u32::deserialize(deserializer)?;
+    if (100..=599).contains(&status) {
+        Ok(status)
+    } else {
+        Err(D::Error::custom(format!(
+            "invalid HTTP status code {status}, expected 100-599"
+        )))
+    }
+}
+
+/// Reuses the over limit responses of a rate limit service for identical
+/// descriptors for `ttl`, keeping at most `maxEntries` of them.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseCacheConfig {
+    #[serde(default = "default_response_cache_ttl")]
+    pub ttl: Timeout,
+    #[serde(default = "default_response_cache_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_response_cache_ttl() -> Timeout {
+    Timeout(Duration::from_secs(1))
+}
+
+fn default_response_cache_max_entries() -> usize {
+    1024
+}
+
+/// Re-dispatches calls answered with `UNAVAILABLE`, up to `maxAttempts` calls in
+/// total. The first retry waits `baseDelay`, each later one `multiplier` times
+/// as long as the one before.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    #[serde(default = "default_retry_base_delay")]
+    pub base_delay: Timeout,
+    #[serde(default = "default_retry_multiplier")]
+    pub multiplier: f64,
+}
+
+fn default_retry_base_delay() -> Timeout {
+    Timeout(Duration::from_millis(25))
+}
+
+fn default_retry_multiplier() -> f64 {
+    2.0
+}
+
+/// Skips calls to a service with `failureMode: allow` once it failed
+/// `failureThreshold` times in a row, until `coolDown` has elapsed.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerConfig {
+    pub failure_threshold: u32,
+    #[serde(default = "default_cool_down")]
+    pub cool_down: Timeout,
+}
+
+fn default_cool_down() -> Timeout {
+    Timeout(Duration::from_secs(30))
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -361,7 +858,7 @@ mod test {
 
         if let Some(auth_service) = services.get("authorino") {
             assert_eq!(auth_service.service_type, ServiceType::Auth);
-            assert_eq!(auth_service.endpoint, "authorino-cluster");
+            assert_eq!(auth_service.endpoint.name, "authorino-cluster");
             assert_eq!(auth_service.failure_mode, FailureMode::Deny);
             assert_eq!(auth_service.timeout, Timeout(Duration::from_millis(24)))
         } else {
@@ -370,7 +867,7 @@ mod test {
 
         if let Some(rl_service) = services.get("limitador") {
             assert_eq!(rl_service.service_type, ServiceType::RateLimit);
-            assert_eq!(rl_service.endpoint, "limitador-cluster");
+            assert_eq!(rl_service.endpoint.name, "limitador-cluster");
             assert_eq!(rl_service.failure_mode, FailureMode::Allow);
             assert_eq!(rl_service.timeout, Timeout(Duration::from_millis(42)))
         } else {
@@ -379,7 +876,8 @@ mod test {
 
         let predicates = &plugin_config.action_sets[0]
             .route_rule_conditions
-            .predicates;
+            .predicates
+            .expressions();
         assert_eq!(predicates.len(), 3);
 
         let actions = &plugin_config.action_sets[0].actions;
@@ -554,7 +1052,8 @@ mod test {
 
         let predicates = &plugin_config.action_sets[0]
             .route_rule_conditions
-            .predicates;
+            .predicates
+            .expressions();
         assert_eq!(predicates.len(), 0);
 
         let actions = &plugin_config.action_sets[0].actions;
@@ -566,6 +1065,35 @@ mod test {
         assert_eq!(action.predicates.len(), 0);
     }
 
+    #[test]
+    fn parse_route_predicates_composition() {
+        let conditions = serde_json::from_str::<RouteRuleConditions>(
+            r#"{"hostnames": ["example.com"], "predicates": {"or": ["a == 1", "b == 2"]}}"#,
+        )
+        .expect("composed predicates to parse");
+        assert_eq!(
+            conditions.predicates,
+            RoutePredicates::Composed(PredicateComposition::Or(vec![
+                "a == 1".to_string(),
+                "b == 2".to_string()
+            ]))
+        );
+
+        let conditions = serde_json::from_str::<RouteRuleConditions>(
+            r#"{"hostnames": ["example.com"], "predicates": ["a == 1"]}"#,
+        )
+        .expect("predicate list to parse");
+        assert_eq!(
+            conditions.predicates,
+            RoutePredicates::All(vec!["a == 1".to_string()])
+        );
+
+        assert!(serde_json::from_str::<RouteRuleConditions>(
+            r#"{"hostnames": ["example.com"], "predicates": {"xor": ["a == 1"]}}"#,
+        )
+        .is_err());
+    }
+
     #[test]
     fn parse_config_invalid_data() {
         // data item fields are mutually exclusive
@@ -672,7 +1200,7 @@ mod test {
             .expect("dynamic service to be set");
 
         assert_eq!(dynamic_service.service_type, ServiceType::Dynamic);
-        assert_eq!(dynamic_service.endpoint, "limitador-cluster");
+        assert_eq!(dynamic_service.endpoint.name, "limitador-cluster");
         assert_eq!(dynamic_service.failure_mode, FailureMode::Deny);
         assert_eq!(
             dynamic_service.grpc_service.as_ref(),
@@ -684,6 +1212,100 @@ mod test {
         );
     }
 
+    #[test]
+    fn parse_service_cluster_config() {
+        let config = r#"{
+            "services": {
+                "plaintext": {
+                    "type": "ratelimit",
+                    "endpoint": { "clusterName": "limitador-cluster" },
+                    "failureMode": "deny"
+                },
+                "shared": {
+                    "type": "ratelimit",
+                    "endpoint": {
+                        "clusterName": "shared-cluster",
+                        "authorityOverride": "limitador.kuadrant.svc"
+                    },
+                    "failureMode": "deny"
+                }
+            },
+            "actionSets": []
+        }"#;
+
+        let plugin_config =
+            serde_json::from_str::<PluginConfiguration>(config).expect("config to parse");
+
+        let plaintext = &plugin_config.services["plaintext"].endpoint;
+        assert_eq!(
+            plaintext,
+            &ClusterConfig::from("limitador-cluster".to_string())
+        );
+
+        let shared = &plugin_config.services["shared"].endpoint;
+        assert_eq!(shared.name, "shared-cluster");
+        assert_eq!(
+            shared.authority_override.as_deref(),
+            Some("limitador.kuadrant.svc")
+        );
+    }
+
+    #[test]
+    fn parse_service_error_response() {
+        let config = r#"{
+            "services": {
+                "authorino": {
+                    "type": "auth",
+                    "endpoint": "authorino-cluster",
+                    "failureMode": "deny",
+                    "errorResponse": {
+                        "status": 503,
+                        "headers": { "content-type": "application/json" },
+                        "body": "{\"code\": {status}, \"error\": \"{message}\", \"id\": \"{requestId}\"}"
+                    }
+                }
+            },
+            "actionSets": []
+        }"#;
+
+        let plugin_config =
+            serde_json::from_str::<PluginConfiguration>(config).expect("config to parse");
+        let error_response = plugin_config.services["authorino"]
+            .error_response
+            .as_ref()
+            .expect("error response to be set");
+
+        assert_eq!(error_response.status, 503);
+        assert_eq!(
+            error_response.headers.get("content-type"),
+            Some(&"application/json".to_string())
+        );
+        assert_eq!(
+            error_response.render_body("auth service failed"),
+            Some(
+                r#"{"code": 503, "error": "auth service failed", "id": "{requestId}"}"#.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parse_service_error_response_rejects_invalid_status() {
+        let config = r#"{
+            "services": {
+                "authorino": {
+                    "type": "auth",
+                    "endpoint": "authorino-cluster",
+                    "failureMode": "deny",
+                    "errorResponse": { "status": 42 }
+                }
+            },
+            "actionSets": []
+        }"#;
+
+        let res = serde_json::from_str::<PluginConfiguration>(config);
+        assert!(res.is_err_and(|e| e.to_string().contains("invalid HTTP status code 42")));
+    }
+
     #[test]
     fn parse_grpc_action_with_on_reply() {
         let config = r#"{
@@ -920,6 +1542,43 @@ mod test {
         assert_eq!(fail.log_message, "error has occurred");
     }
 
+    #[test]
+    fn parse_request_header_mutation_action() {
+        let config = r#"{
+            "services": {},
+            "actionSets": [{
+                "name": "test-mutation",
+                "routeRuleConditions": {
+                    "hostnames": ["example.com"]
+                },
+                "actions": [{
+                    "type": "requestHeaderMutation",
+                    "predicate": "true",
+                    "terminal": false,
+                    "set": [["x-user-id", "alice"]],
+                    "remove": ["x-user-id"]
+                }]
+            }]
+        }"#;
+
+        let res = serde_json::from_str::<PluginConfiguration>(config);
+        assert!(res.is_ok());
+
+        let plugin_config = res.expect("result is ok");
+        let ActionConfig::Typed(typed) = &plugin_config.action_sets[0].actions[0] else {
+            unreachable!("expected typed action");
+        };
+        let Operation::RequestHeaderMutation(mutation) = &typed.operation else {
+            unreachable!("expected request header mutation operation");
+        };
+        assert_eq!(
+            mutation.set,
+            vec![("x-user-id".to_string(), "alice".to_string())]
+        );
+        assert!(mutation.append.is_empty());
+        assert_eq!(mutation.remove, vec!["x-user-id".to_string()]);
+    }
+
     #[test]
     fn parse_mixed_legacy_and_typed_actions() {
         let config = r#"{
@@ -1003,6 +1662,22 @@ mod test {
         assert!(typed_action.is_guard);
     }
 
+    #[test]
+    fn test_request_body_field() {
+        let config = r#"{
+            "type": "deny",
+            "predicate": "true",
+            "terminal": true,
+            "denyWith": "DenyResponse{status: 403u}",
+            "requestBodyField": { "pointer": "/messages/0/role", "equals": "system" }
+        }"#;
+
+        let typed_action: TypedAction = serde_json::from_str(config).expect("valid config");
+        let field = typed_action.request_body_field.expect("request body field");
+        assert_eq!(field.pointer, "/messages/0/role");
+        assert_eq!(field.equals, serde_json::json!("system"));
+    }
+
     #[test]
     fn test_is_guard_can_be_set_to_false() {
         let config = r#"{
@@ -1019,4 +1694,12 @@ mod test {
         let typed_action: TypedAction = serde_json::from_str(config).expect("valid config");
         assert!(!typed_action.is_guard);
     }
+
+    #[test]
+    fn config_deltas_are_told_apart_by_their_flag() {
+        assert!(!ConfigDelta::is_delta(CONFIG.as_bytes()));
+        assert!(!ConfigDelta::is_delta(br#"{"delta": false}"#));
+        assert!(!ConfigDelta::is_delta(b"{ not json"));
+        assert!(ConfigDelta::is_delta(br#"{"delta": true, "services": {}}"#));
+    }
 }
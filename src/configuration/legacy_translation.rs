@@ -66,7 +66,14 @@ pub(super) mod ratelimit {
         None
     }
 
-    fn build_ratelimit_descriptor_entry_cel(item: &DataItem) -> String {
+    fn descriptor_key(namespace: Option<&str>, key: &str) -> String {
+        match namespace {
+            Some(namespace) => format!("{namespace}/{key}"),
+            None => key.to_string(),
+        }
+    }
+
+    fn build_ratelimit_descriptor_entry_cel(item: &DataItem, namespace: Option<&str>) -> String {
         let (key, value_cel) = match &item.item {
             DataType::Static(s) => (
                 s.key.as_str(),
@@ -77,17 +84,20 @@ pub(super) mod ratelimit {
 
         format!(
             r#"envoy.extensions.common.ratelimit.v3.RateLimitDescriptor.Entry {{ key: "{}", value: {} }}"#,
-            escape_cel_string(key),
+            escape_cel_string(&descriptor_key(namespace, key)),
             value_cel,
         )
     }
 
-    fn build_ratelimit_entry_list_cel(cd: &ConditionalData) -> Option<String> {
+    fn build_ratelimit_entry_list_cel(
+        cd: &ConditionalData,
+        namespace: Option<&str>,
+    ) -> Option<String> {
         let entries: Vec<String> = cd
             .data
             .iter()
             .filter(|item| !is_ratelimit_known_attr(item))
-            .map(build_ratelimit_descriptor_entry_cel)
+            .map(|item| build_ratelimit_descriptor_entry_cel(item, namespace))
             .collect();
 
         if entries.is_empty() {
@@ -104,10 +114,13 @@ pub(super) mod ratelimit {
         }
     }
 
-    fn build_ratelimit_descriptors_cel(conditional_data: &[ConditionalData]) -> Option<String> {
+    fn build_ratelimit_descriptors_cel(
+        conditional_data: &[ConditionalData],
+        namespace: Option<&str>,
+    ) -> Option<String> {
         let entry_parts: Vec<String> = conditional_data
             .iter()
-            .filter_map(build_ratelimit_entry_list_cel)
+            .filter_map(|cd| build_ratelimit_entry_list_cel(cd, namespace))
             .collect();
 
         if entry_parts.is_empty() {
@@ -125,6 +138,7 @@ pub(super) mod ratelimit {
         scope: &str,
         conditional_data: &[ConditionalData],
         request_data: &[((String, String), String)],
+        namespace: Option<&str>,
     ) -> String {
         let domain_cel = find_ratelimit_known_attr_cel(conditional_data, "ratelimit.domain")
             .unwrap_or_else(|| format!(r#""{}""#, escape_cel_string(scope)));
@@ -135,7 +149,7 @@ pub(super) mod ratelimit {
 
         let mut descriptors = vec![];
 
-        if let Some(desc) = build_ratelimit_descriptors_cel(conditional_data) {
+        if let Some(desc) = build_ratelimit_descriptors_cel(conditional_data, namespace) {
             descriptors.push(desc);
         }
 
@@ -150,7 +164,7 @@ pub(super) mod ratelimit {
                     };
                     format!(
                         r#"envoy.extensions.common.ratelimit.v3.RateLimitDescriptor.Entry {{ key: "{}", value: string({}) }}"#,
-                        escape_cel_string(&key),
+                        escape_cel_string(&descriptor_key(namespace, &key)),
                         value_expr
                     )
                 })
@@ -231,6 +245,7 @@ pub(super) mod ratelimit {
                 terminal: true,
                 is_guard: true,
                 sources: vec![],
+                request_body_field: None,
                 operation: Operation::Deny(DenyOperation {
                     deny_with: format!(
                         r#"DenyResponse{{status: 429u, headers: {}.response_headers_to_add, body: "Too Many Requests\n"}}"#,
@@ -243,6 +258,7 @@ pub(super) mod ratelimit {
                 terminal: false,
                 is_guard: true,
                 sources: vec![],
+                request_body_field: None,
                 operation: Operation::Headers(HeadersOperation {
                     target: HeadersTarget::Response,
                     headers: format!("{}.response_headers_to_add", name),
@@ -253,6 +269,7 @@ pub(super) mod ratelimit {
                 terminal: true,
                 is_guard: true,
                 sources: vec![],
+                request_body_field: None,
                 operation: Operation::Fail(FailOperation {
                     log_message: format!("Unknown rate limit response code from {}", name),
                 }),
@@ -264,11 +281,16 @@ pub(super) mod ratelimit {
     pub(crate) fn translate_legacy_ratelimit_to_typed(
         action: &Action,
         request_data: &[((String, String), String)],
+        namespace: Option<&str>,
     ) -> TypedAction {
         const RESPONSE_VAR: &str = "ratelimit_response";
 
-        let message_builder =
-            build_ratelimit_message_builder(&action.scope, &action.conditional_data, request_data);
+        let message_builder = build_ratelimit_message_builder(
+            &action.scope,
+            &action.conditional_data,
+            request_data,
+            namespace,
+        );
 
         let predicate = build_ratelimit_predicate(&action.predicates, &action.conditional_data);
 
@@ -279,10 +301,12 @@ pub(super) mod ratelimit {
             terminal: false,
             is_guard: true,
             sources: action.sources.clone(),
+            request_body_field: None,
             operation: Operation::Grpc(GrpcOperation {
                 var: RESPONSE_VAR.to_string(),
                 service: action.service.clone(),
                 message_builder,
+                timeout_ms: None,
                 on_reply,
             }),
         }
@@ -293,11 +317,16 @@ pub(super) mod ratelimit {
     pub(crate) fn translate_legacy_report_to_typed(
         action: &Action,
         request_data: &[((String, String), String)],
+        namespace: Option<&str>,
     ) -> TypedAction {
         const RESPONSE_VAR: &str = "report_response";
 
-        let message_builder =
-            build_ratelimit_message_builder(&action.scope, &action.conditional_data, request_data);
+        let message_builder = build_ratelimit_message_builder(
+            &action.scope,
+            &action.conditional_data,
+            request_data,
+            namespace,
+        );
 
         let predicate = build_ratelimit_predicate(&action.predicates, &action.conditional_data);
 
@@ -306,6 +335,7 @@ pub(super) mod ratelimit {
             terminal: false,
             is_guard: false,
             sources: vec![],
+            request_body_field: None,
             operation: Operation::Fail(FailOperation {
                 log_message: "Rate limit report failed: invalid gRPC response".to_string(),
             }),
@@ -316,10 +346,12 @@ pub(super) mod ratelimit {
             terminal: false,
             is_guard: false,
             sources: action.sources.clone(),
+            request_body_field: None,
             operation: Operation::Grpc(GrpcOperation {
                 var: RESPONSE_VAR.to_string(),
                 service: action.service.clone(),
                 message_builder,
+                timeout_ms: None,
                 on_reply,
             }),
         }
@@ -476,7 +508,7 @@ pub(super) mod ratelimit {
             };
             let request_data = vec![];
 
-            let typed = translate_legacy_ratelimit_to_typed(&action, &request_data);
+            let typed = translate_legacy_ratelimit_to_typed(&action, &request_data, None);
 
             assert_eq!(typed.predicate, "true");
             assert!(!typed.terminal);
@@ -513,7 +545,7 @@ pub(super) mod ratelimit {
             };
             let request_data = vec![];
 
-            let typed = translate_legacy_ratelimit_to_typed(&action, &request_data);
+            let typed = translate_legacy_ratelimit_to_typed(&action, &request_data, None);
 
             assert_eq!(typed.predicate, "auth.identity.user == 'alice'");
 
@@ -541,7 +573,7 @@ pub(super) mod ratelimit {
                 r#""production""#.to_string(),
             )];
 
-            let typed = translate_legacy_ratelimit_to_typed(&action, &request_data);
+            let typed = translate_legacy_ratelimit_to_typed(&action, &request_data, None);
 
             assert!(matches!(&typed.operation,
                 Operation::Grpc(grpc_op) if
@@ -553,6 +585,55 @@ pub(super) mod ratelimit {
             ));
         }
 
+        #[test]
+        fn test_translate_legacy_ratelimit_with_namespace() {
+            let action = Action {
+                service: "limitador".to_string(),
+                scope: "default".to_string(),
+                predicates: vec![],
+                conditional_data: vec![ConditionalData {
+                    predicates: vec![],
+                    data: vec![DataItem {
+                        item: DataType::Static(StaticItem {
+                            key: "tier".to_string(),
+                            value: "gold".to_string(),
+                        }),
+                    }],
+                }],
+                sources: vec![],
+            };
+            let request_data = vec![(
+                ("".to_string(), "env".to_string()),
+                r#""production""#.to_string(),
+            )];
+            let message_builder = |namespace| match translate_legacy_ratelimit_to_typed(
+                &action,
+                &request_data,
+                namespace,
+            )
+            .operation
+            {
+                Operation::Grpc(grpc_op) => grpc_op.message_builder,
+                _ => unreachable!("rate limit actions are gRPC calls"),
+            };
+
+            let team_a = message_builder(Some("team-a"));
+            let team_b = message_builder(Some("team-b"));
+            assert_ne!(team_a, team_b);
+            assert_eq!(
+                team_a,
+                r#"envoy.service.ratelimit.v3.RateLimitRequest {
+    domain: "default",
+    hits_addend: 1u,
+    descriptors: [envoy.extensions.common.ratelimit.v3.RateLimitDescriptor { entries: [envoy.extensions.common.ratelimit.v3.RateLimitDescriptor.Entry { key: "team-a/tier", value: "gold" }] }, envoy.extensions.common.ratelimit.v3.RateLimitDescriptor { entries: [envoy.extensions.common.ratelimit.v3.RateLimitDescriptor.Entry { key: "team-a/env", value: string("production") }] }]
+}"#
+            );
+
+            let unscoped = message_builder(None);
+            assert!(unscoped.contains(r#"key: "tier""#));
+            assert!(unscoped.contains(r#"key: "env""#));
+        }
+
         #[test]
         fn test_translate_legacy_ratelimit_full() {
             let action = Action {
@@ -586,7 +667,7 @@ pub(super) mod ratelimit {
                 r#""production""#.to_string(),
             )];
 
-            let typed = translate_legacy_ratelimit_to_typed(&action, &request_data);
+            let typed = translate_legacy_ratelimit_to_typed(&action, &request_data, None);
 
             assert_eq!(typed.predicate, "true");
 
@@ -611,7 +692,7 @@ pub(super) mod ratelimit {
             };
             let request_data = vec![];
 
-            let typed = translate_legacy_report_to_typed(&action, &request_data);
+            let typed = translate_legacy_report_to_typed(&action, &request_data, None);
 
             assert_eq!(typed.predicate, "true");
             assert!(!typed.terminal);
@@ -657,7 +738,7 @@ pub(super) mod ratelimit {
             };
             let request_data = vec![];
 
-            let typed = translate_legacy_report_to_typed(&action, &request_data);
+            let typed = translate_legacy_report_to_typed(&action, &request_data, None);
 
             assert_eq!(typed.predicate, "true");
             assert!(!typed.is_guard);
@@ -700,7 +781,7 @@ pub(super) mod ratelimit {
             };
             let request_data = vec![];
 
-            let typed = translate_legacy_report_to_typed(&action, &request_data);
+            let typed = translate_legacy_report_to_typed(&action, &request_data, None);
 
             assert!(!typed.is_guard);
             assert!(matches!(&typed.operation,
@@ -756,7 +837,7 @@ pub(super) mod ratelimit {
                 r#""east""#.to_string(),
             )];
 
-            let typed = translate_legacy_report_to_typed(&action, &request_data);
+            let typed = translate_legacy_report_to_typed(&action, &request_data, None);
 
             assert_eq!(typed.predicate, r#"request.path.startsWith("/api")"#);
             assert!(!typed.is_guard);
@@ -903,10 +984,12 @@ pub(super) mod auth {
             terminal: false,
             is_guard: true,
             sources: action.sources.clone(),
+            request_body_field: None,
             operation: Operation::Grpc(GrpcOperation {
                 var: RESPONSE_VAR.to_string(),
                 service: action.service.clone(),
                 message_builder,
+                timeout_ms: None,
                 on_reply,
             }),
         }
@@ -919,6 +1002,7 @@ pub(super) mod auth {
                 terminal: true,
                 is_guard: true,
                 sources: vec![],
+                request_body_field: None,
                 operation: Operation::Deny(DenyOperation {
                     deny_with: format!(
                         r#"DenyResponse{{status: ({name}.denied_response.status.code != 0) ? uint({name}.denied_response.status.code) : 403u, headers: {name}.denied_response.headers, body: {name}.denied_response.body}}"#,
@@ -938,6 +1022,7 @@ pub(super) mod auth {
                 terminal: true,
                 is_guard: true,
                 sources: vec![],
+                request_body_field: None,
                 operation: Operation::Fail(FailOperation {
                     log_message: "Unsupported field in OkHttpResponse".to_string(),
                 }),
@@ -950,22 +1035,54 @@ pub(super) mod auth {
                 terminal: false,
                 is_guard: true,
                 sources: vec![],
+                request_body_field: None,
                 operation: Operation::Store(StoreOperation {
                     path: "auth".to_string(),
                     value: format!("{}.dynamic_metadata", name),
                     export_to_host: true,
                 }),
             },
+            TypedAction {
+                predicate: format!(
+                    "has({name}.ok_response) && has({name}.ok_response.dynamic_metadata)",
+                    name = name
+                ),
+                terminal: false,
+                is_guard: true,
+                sources: vec![],
+                request_body_field: None,
+                operation: Operation::Store(StoreOperation {
+                    path: "authz".to_string(),
+                    value: format!("{}.ok_response.dynamic_metadata", name),
+                    export_to_host: true,
+                }),
+            },
             TypedAction {
                 predicate: format!("has({}.ok_response)", name),
                 terminal: false,
                 is_guard: true,
                 sources: vec![],
+                request_body_field: None,
                 operation: Operation::Headers(HeadersOperation {
                     target: HeadersTarget::Request,
                     headers: format!("{}.ok_response.headers", name),
                 }),
             },
+            TypedAction {
+                predicate: format!(
+                    "has({name}.ok_response) && has({name}.dynamic_metadata) && \
+                     has({name}.dynamic_metadata.response_headers_to_remove)",
+                    name = name
+                ),
+                terminal: false,
+                is_guard: true,
+                sources: vec![],
+                request_body_field: None,
+                operation: Operation::RemoveHeaders(HeadersOperation {
+                    target: HeadersTarget::Response,
+                    headers: format!("{}.dynamic_metadata.response_headers_to_remove", name),
+                }),
+            },
             TypedAction {
                 predicate: format!(
                     "!has({}.denied_response) && !has({}.ok_response)",
@@ -974,6 +1091,7 @@ pub(super) mod auth {
                 terminal: true,
                 is_guard: true,
                 sources: vec![],
+                request_body_field: None,
                 operation: Operation::Fail(FailOperation {
                     log_message: format!("Auth response contained no http_response from {}", name),
                 }),
@@ -1150,7 +1268,7 @@ pub(super) mod auth {
         fn test_build_auth_on_reply_structure() {
             let on_reply = build_auth_on_reply("auth_response");
 
-            assert_eq!(on_reply.len(), 5);
+            assert_eq!(on_reply.len(), 7);
 
             assert_eq!(on_reply[0].predicate, "has(auth_response.denied_response)");
             assert!(on_reply[0].terminal);
@@ -1170,16 +1288,30 @@ pub(super) mod auth {
             assert!(!on_reply[2].terminal);
             assert!(matches!(on_reply[2].operation, Operation::Store(_)));
 
-            assert_eq!(on_reply[3].predicate, "has(auth_response.ok_response)");
+            assert_eq!(
+                on_reply[3].predicate,
+                "has(auth_response.ok_response) && has(auth_response.ok_response.dynamic_metadata)"
+            );
             assert!(!on_reply[3].terminal);
-            assert!(matches!(on_reply[3].operation, Operation::Headers(_)));
+            assert!(matches!(on_reply[3].operation, Operation::Store(_)));
+
+            assert_eq!(on_reply[4].predicate, "has(auth_response.ok_response)");
+            assert!(!on_reply[4].terminal);
+            assert!(matches!(on_reply[4].operation, Operation::Headers(_)));
+
+            assert_eq!(
+                on_reply[5].predicate,
+                "has(auth_response.ok_response) && has(auth_response.dynamic_metadata) && has(auth_response.dynamic_metadata.response_headers_to_remove)"
+            );
+            assert!(!on_reply[5].terminal);
+            assert!(matches!(on_reply[5].operation, Operation::RemoveHeaders(_)));
 
             assert_eq!(
-                on_reply[4].predicate,
+                on_reply[6].predicate,
                 "!has(auth_response.denied_response) && !has(auth_response.ok_response)"
             );
-            assert!(on_reply[4].terminal);
-            assert!(matches!(on_reply[4].operation, Operation::Fail(_)));
+            assert!(on_reply[6].terminal);
+            assert!(matches!(on_reply[6].operation, Operation::Fail(_)));
         }
 
         #[test]
@@ -1191,6 +1323,11 @@ pub(super) mod auth {
                     store_op.path == "auth" &&
                     store_op.value == "test_var.dynamic_metadata"
             ));
+            assert!(matches!(&on_reply[3].operation,
+                Operation::Store(store_op) if
+                    store_op.path == "authz" &&
+                    store_op.value == "test_var.ok_response.dynamic_metadata"
+            ));
         }
 
         #[test]
@@ -1222,24 +1359,35 @@ pub(super) mod auth {
         fn test_build_auth_on_reply_ok_response_headers() {
             let on_reply = build_auth_on_reply("check_resp");
 
-            assert!(matches!(&on_reply[3].operation,
+            assert!(matches!(&on_reply[4].operation,
                 Operation::Headers(headers_op) if
                     matches!(headers_op.target, HeadersTarget::Request) &&
                     headers_op.headers == "check_resp.ok_response.headers"
             ));
         }
 
+        #[test]
+        fn test_build_auth_on_reply_response_headers_to_remove() {
+            let on_reply = build_auth_on_reply("check_resp");
+
+            assert!(matches!(&on_reply[5].operation,
+                Operation::RemoveHeaders(headers_op) if
+                    matches!(headers_op.target, HeadersTarget::Response) &&
+                    headers_op.headers == "check_resp.dynamic_metadata.response_headers_to_remove"
+            ));
+        }
+
         #[test]
         fn test_build_auth_on_reply_fallback_failure() {
             let on_reply = build_auth_on_reply("auth_result");
 
-            assert!(matches!(&on_reply[4].operation,
+            assert!(matches!(&on_reply[6].operation,
                 Operation::Fail(fail_op) if
                     fail_op.log_message == "Auth response contained no http_response from auth_result"
             ));
 
             assert_eq!(
-                on_reply[4].predicate,
+                on_reply[6].predicate,
                 "!has(auth_result.denied_response) && !has(auth_result.ok_response)"
             );
         }
@@ -1297,7 +1445,7 @@ pub(super) mod auth {
     metadata_context: envoy.config.core.v3.Metadata{}
   }
 }"# &&
-                    grpc_op.on_reply.len() == 5
+                    grpc_op.on_reply.len() == 7
             ));
         }
 
@@ -1406,7 +1554,7 @@ pub(super) mod auth {
                 Operation::Grpc(grpc_op) if
                     grpc_op.var == "auth_response" &&
                     grpc_op.service == "authorino" &&
-                    grpc_op.on_reply.len() == 5 &&
+                    grpc_op.on_reply.len() == 7 &&
                     grpc_op.message_builder == r#"envoy.service.auth.v3.CheckRequest {
   attributes: envoy.service.auth.v3.AttributeContext {
     request: envoy.service.auth.v3.AttributeContext.Request {
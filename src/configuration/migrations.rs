@@ -0,0 +1,110 @@
+use std::fmt::{Display, Formatter};
+
+use serde_json::{Map, Value};
+
+/// The schema version configurations are migrated to before being parsed
+pub const CURRENT_VERSION: u32 = 1;
+
+/// The version of a configuration that does not state one
+pub const DEFAULT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationError {
+    /// A `version` that is not a positive integer
+    InvalidVersion(Value),
+    /// A version this build knows no schema for, e.g. one written for a newer shim
+    UnsupportedVersion(u32),
+}
+
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::InvalidVersion(version) => {
+                write!(
+                    f,
+                    "invalid version `{version}`, expected a positive integer"
+                )
+            }
+            MigrationError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported version {version}, expected {DEFAULT_VERSION} to {CURRENT_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+type Migration = fn(Map<String, Value>) -> Result<Map<String, Value>, MigrationError>;
+
+/// `MIGRATIONS[n]` takes a configuration from version `n + 1` to `n + 2`;
+/// none so far, as version 1 is the only schema yet
+const MIGRATIONS: [Migration; (CURRENT_VERSION - DEFAULT_VERSION) as usize] = [];
+
+/// The version `raw` states, [`DEFAULT_VERSION`] when it states none
+pub fn version_of(raw: &Value) -> Result<u32, MigrationError> {
+    match raw.get("version") {
+        None | Some(Value::Null) => Ok(DEFAULT_VERSION),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v > 0)
+            .ok_or_else(|| MigrationError::InvalidVersion(version.clone())),
+    }
+}
+
+/// Migrates `raw`, written against version `from`, to [`CURRENT_VERSION`]
+/// one version at a time. Anything but an object is returned as is, for the
+/// validation to report.
+pub fn migrate(raw: Value, from: u32) -> Result<Value, MigrationError> {
+    if !(DEFAULT_VERSION..=CURRENT_VERSION).contains(&from) {
+        return Err(MigrationError::UnsupportedVersion(from));
+    }
+    let Value::Object(mut configuration) = raw else {
+        return Ok(raw);
+    };
+    let pending = MIGRATIONS.iter().skip((from - DEFAULT_VERSION) as usize);
+    for (to, migration) in (from + 1..).zip(pending) {
+        configuration = migration(configuration)?;
+        configuration.insert("version".to_string(), Value::from(to));
+    }
+    Ok(Value::Object(configuration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn unversioned_configurations_are_version_one() {
+        assert_eq!(version_of(&json!({})), Ok(1));
+        assert_eq!(version_of(&json!({"version": null})), Ok(1));
+        assert_eq!(version_of(&json!({"version": 2})), Ok(2));
+        for invalid in [json!(0), json!(-1), json!(1.5), json!("2")] {
+            assert_eq!(
+                version_of(&json!({ "version": invalid.clone() })),
+                Err(MigrationError::InvalidVersion(invalid))
+            );
+        }
+    }
+
+    #[test]
+    fn current_configurations_are_left_alone() {
+        let v1 = json!({"version": 1, "observability": {"accessLog": true}});
+        assert_eq!(migrate(v1.clone(), 1), Ok(v1));
+        assert_eq!(migrate(json!([]), 1), Ok(json!([])));
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        assert_eq!(
+            migrate(json!({}), 2),
+            Err(MigrationError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            migrate(json!({}), 0),
+            Err(MigrationError::UnsupportedVersion(0))
+        );
+    }
+}
@@ -0,0 +1,1111 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use serde::de::{self, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+use super::{
+    migrations, ActionSet, ConfigDelta, PluginConfiguration, RouteRuleConditions, Service, Timeout,
+};
+use crate::data::cel::{Expression, Predicate};
+
+const REQUIRED_SERVICE_FIELDS: [&str; 3] = ["type", "endpoint", "failureMode"];
+
+const DYNAMIC_SERVICE_FIELDS: [&str; 2] = ["grpcService", "grpcMethod"];
+
+const RATE_LIMIT_SERVICE_TYPES: [&str; 3] = ["ratelimit", "ratelimit-check", "ratelimit-report"];
+
+const RATE_LIMIT_DOMAIN_KEY: &str = "ratelimit.domain";
+
+/// Longest DNS name, which Limitador namespaces are expected to fit in
+const MAX_DOMAIN_LENGTH: usize = 253;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigErrorKind {
+    /// Not a JSON document
+    Syntax,
+    MissingField,
+    UnknownField,
+    InvalidExpression,
+    DuplicateActionSet,
+    InvalidTimeout,
+    /// A rate limit domain that is blank or longer than a DNS name
+    InvalidDomain,
+    /// Well-formed, yet not a plugin configuration, e.g. a value of the wrong type
+    Invalid,
+}
+
+/// A problem with the plugin configuration, found at `path`, e.g.
+/// `$.services.limitador.timeout`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub path: String,
+    pub kind: ConfigErrorKind,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(path: impl Into<String>, kind: ConfigErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:?}: {}", self.path, self.kind, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// A configuration that passed [`ConfigValidator::validate`]
+#[derive(Debug)]
+pub struct ValidatedConfig(PluginConfiguration);
+
+impl ValidatedConfig {
+    pub fn into_inner(self) -> PluginConfiguration {
+        self.0
+    }
+}
+
+/// Checks the raw plugin configuration as a whole, so that every problem with
+/// it is reported at once instead of only the first one serde stops at.
+pub struct ConfigValidator {
+    errors: Vec<ConfigError>,
+    rate_limit_services: HashSet<String>,
+}
+
+impl ConfigValidator {
+    pub fn validate(raw: &[u8]) -> Result<ValidatedConfig, Vec<ConfigError>> {
+        let document: Value = serde_json::from_slice(raw).map_err(|e| {
+            vec![ConfigError::new(
+                "$",
+                ConfigErrorKind::Syntax,
+                e.to_string(),
+            )]
+        })?;
+        let document = migrations::version_of(&document)
+            .and_then(|version| migrations::migrate(document, version))
+            .map_err(|e| {
+                vec![ConfigError::new(
+                    "$.version",
+                    ConfigErrorKind::Invalid,
+                    e.to_string(),
+                )]
+            })?;
+
+        let mut validator = ConfigValidator {
+            errors: Vec::new(),
+            rate_limit_services: HashSet::new(),
+        };
+        validator.check_configuration(&document);
+        if !validator.errors.is_empty() {
+            return Err(validator.errors);
+        }
+
+        serde_json::from_value::<PluginConfiguration>(document)
+            .map(ValidatedConfig)
+            .map_err(|e| {
+                vec![ConfigError::new(
+                    "$",
+                    ConfigErrorKind::Invalid,
+                    e.to_string(),
+                )]
+            })
+    }
+
+    /// Checks a config delta the way [`ConfigValidator::validate`] checks the
+    /// action sets of a full configuration, given the names of the running
+    /// rate limit services
+    pub fn validate_delta(
+        raw: &[u8],
+        rate_limit_services: HashSet<String>,
+    ) -> Result<ConfigDelta, Vec<ConfigError>> {
+        let document: Value = serde_json::from_slice(raw).map_err(|e| {
+            vec![ConfigError::new(
+                "$",
+                ConfigErrorKind::Syntax,
+                e.to_string(),
+            )]
+        })?;
+
+        let mut validator = ConfigValidator {
+            errors: Vec::new(),
+            rate_limit_services,
+        };
+        validator.check_delta(&document);
+        if !validator.errors.is_empty() {
+            return Err(validator.errors);
+        }
+
+        serde_json::from_value::<ConfigDelta>(document).map_err(|e| {
+            vec![ConfigError::new(
+                "$",
+                ConfigErrorKind::Invalid,
+                e.to_string(),
+            )]
+        })
+    }
+
+    fn check_delta(&mut self, document: &Value) {
+        let Some(delta) = self.object(document, "$") else {
+            return;
+        };
+        self.check_fields(delta, "$", fields_of::<ConfigDelta>());
+        match delta.get("removeActionSets") {
+            None => {}
+            Some(Value::Array(names)) if names.iter().all(Value::is_string) => {}
+            Some(_) => self.invalid(
+                "$.removeActionSets",
+                "expected an array of action set names",
+            ),
+        }
+        match delta.get("upsertActionSets") {
+            None => {}
+            Some(Value::Array(action_sets)) => {
+                self.check_action_sets(action_sets, "$.upsertActionSets")
+            }
+            Some(_) => self.invalid("$.upsertActionSets", "expected an array of action sets"),
+        }
+    }
+
+    fn check_configuration(&mut self, document: &Value) {
+        let Some(configuration) = self.object(document, "$") else {
+            return;
+        };
+        self.check_fields(configuration, "$", fields_of::<PluginConfiguration>());
+        for (field, path) in [
+            ("drainTimeout", "$.drainTimeout"),
+            ("grpcWatchdogTimeout", "$.grpcWatchdogTimeout"),
+        ] {
+            if let Some(timeout) = configuration.get(field).filter(|t| !t.is_null()) {
+                self.check_timeout(timeout, path);
+            }
+        }
+
+        if let Some(Value::Array(properties)) = configuration.get("computedProperties") {
+            for (index, property) in properties.iter().enumerate() {
+                if let Some(expression) = property.get("expression") {
+                    self.check_expression(
+                        expression,
+                        &format!("$.computedProperties[{index}].expression"),
+                    );
+                }
+            }
+        }
+
+        match configuration.get("services") {
+            Some(services) => {
+                if let Some(services) = self.object(services, "$.services") {
+                    for (name, service) in services {
+                        self.check_service(service, &format!("$.services.{name}"));
+                        let service_type = service.get("type").and_then(Value::as_str);
+                        if service_type.is_some_and(|t| RATE_LIMIT_SERVICE_TYPES.contains(&t)) {
+                            self.rate_limit_services.insert(name.clone());
+                        }
+                    }
+                }
+            }
+            None => self.missing_field("$", "services"),
+        }
+
+        match configuration.get("actionSets") {
+            Some(Value::Array(action_sets)) => self.check_action_sets(action_sets, "$.actionSets"),
+            Some(_) => self.invalid("$.actionSets", "expected an array of action sets"),
+            None => self.missing_field("$", "actionSets"),
+        }
+    }
+
+    fn check_service(&mut self, service: &Value, path: &str) {
+        let Some(service) = self.object(service, path) else {
+            return;
+        };
+        self.check_fields(service, path, fields_of::<Service>());
+        for field in REQUIRED_SERVICE_FIELDS {
+            if !service.contains_key(field) {
+                self.missing_field(path, field);
+            }
+        }
+        if service.get("type").and_then(Value::as_str) == Some("dynamic") {
+            for field in DYNAMIC_SERVICE_FIELDS {
+                if !service.contains_key(field) {
+                    self.missing_field(path, field);
+                }
+            }
+        }
+        if service.get("type").and_then(Value::as_str) == Some("http-callout")
+            && !service.contains_key("httpCallout")
+        {
+            self.missing_field(path, "httpCallout");
+        }
+        if let Some(timeout) = service.get("timeout") {
+            self.check_timeout(timeout, &format!("{path}.timeout"));
+        }
+        // Only ext_authz leaves the reply of a service as dynamic metadata
+        if service
+            .get("dynamicMetadataKey")
+            .is_some_and(|key| !key.is_null())
+            && service.get("type").and_then(Value::as_str) != Some("auth")
+        {
+            self.invalid(
+                &format!("{path}.dynamicMetadataKey"),
+                "only supported on auth services",
+            );
+        }
+        if let Some(Value::Object(retry_policy)) = service.get("retryPolicy") {
+            if let Some(base_delay) = retry_policy.get("baseDelay") {
+                self.check_timeout(base_delay, &format!("{path}.retryPolicy.baseDelay"));
+            }
+            // A retry waiting less than the one before would hammer a failing upstream
+            if retry_policy
+                .get("multiplier")
+                .and_then(Value::as_f64)
+                .is_some_and(|multiplier| multiplier < 1.0)
+            {
+                self.invalid(
+                    &format!("{path}.retryPolicy.multiplier"),
+                    "expected a multiplier of at least 1",
+                );
+            }
+        }
+    }
+
+    fn check_action_sets(&mut self, action_sets: &[Value], path: &str) {
+        let action_sets_path = path;
+        let mut first_index_of: HashMap<&str, usize> = HashMap::new();
+        for (index, action_set) in action_sets.iter().enumerate() {
+            let path = format!("{action_sets_path}[{index}]");
+            let Some(action_set) = self.object(action_set, &path) else {
+                continue;
+            };
+            self.check_fields(action_set, &path, fields_of::<ActionSet>());
+
+            match action_set.get("name").and_then(Value::as_str) {
+                Some(name) => {
+                    if let Some(first) = first_index_of.get(name).copied() {
+                        self.errors.push(ConfigError::new(
+                            format!("{path}.name"),
+                            ConfigErrorKind::DuplicateActionSet,
+                            format!(
+                                "action set {name:?} is already defined at {action_sets_path}[{first}]"
+                            ),
+                        ));
+                    } else {
+                        first_index_of.insert(name, index);
+                    }
+                }
+                None => self.missing_field(&path, "name"),
+            }
+
+            match action_set.get("routeRuleConditions") {
+                Some(conditions) => self.check_route_rule_conditions(
+                    conditions,
+                    &format!("{path}.routeRuleConditions"),
+                ),
+                None => self.missing_field(&path, "routeRuleConditions"),
+            }
+
+            match action_set.get("actions") {
+                Some(Value::Array(actions)) => {
+                    for (action_index, action) in actions.iter().enumerate() {
+                        self.check_action(action, &format!("{path}.actions[{action_index}]"));
+                    }
+                }
+                Some(_) => self.invalid(&format!("{path}.actions"), "expected an array of actions"),
+                None => self.missing_field(&path, "actions"),
+            }
+        }
+    }
+
+    fn check_route_rule_conditions(&mut self, conditions: &Value, path: &str) {
+        let Some(conditions) = self.object(conditions, path) else {
+            return;
+        };
+        self.check_fields(conditions, path, fields_of::<RouteRuleConditions>());
+        let predicates_path = format!("{path}.predicates");
+        match conditions.get("predicates") {
+            None => {}
+            Some(Value::Object(composition)) => {
+                for (operator, predicates) in composition {
+                    let path = format!("{predicates_path}.{operator}");
+                    // An empty composition would match every request
+                    if predicates.as_array().is_some_and(Vec::is_empty) {
+                        self.invalid(&path, "expected at least one predicate");
+                    } else {
+                        self.check_predicates(predicates, &path);
+                    }
+                }
+            }
+            Some(predicates) => self.check_predicates(predicates, &predicates_path),
+        }
+    }
+
+    fn check_action(&mut self, action: &Value, path: &str) {
+        let Some(action) = self.object(action, path) else {
+            return;
+        };
+        if let Some(predicate) = action.get("predicate") {
+            self.check_predicate(predicate, &format!("{path}.predicate"));
+        }
+        if let Some(predicates) = action.get("predicates") {
+            self.check_predicates(predicates, &format!("{path}.predicates"));
+        }
+        self.check_action_expressions(action, path);
+        let calls_rate_limit = action
+            .get("service")
+            .and_then(Value::as_str)
+            .is_some_and(|service| self.rate_limit_services.contains(service));
+        if calls_rate_limit {
+            self.check_rate_limit_domains(action, path);
+        }
+    }
+
+    /// The CEL expressions of an action besides its predicates: the data of a
+    /// legacy action, or whatever its operation builds for a typed one
+    fn check_action_expressions(&mut self, action: &Map<String, Value>, path: &str) {
+        if let Some(Value::Array(conditional_data)) = action.get("conditionalData") {
+            for (index, conditional) in conditional_data.iter().enumerate() {
+                let path = format!("{path}.conditionalData[{index}]");
+                if let Some(predicates) = conditional.get("predicates") {
+                    self.check_predicates(predicates, &format!("{path}.predicates"));
+                }
+                let Some(Value::Array(data)) = conditional.get("data") else {
+                    continue;
+                };
+                for (item_index, item) in data.iter().enumerate() {
+                    if let Some(expression) = item.get("expression").and_then(|e| e.get("value")) {
+                        self.check_expression(
+                            expression,
+                            &format!("{path}.data[{item_index}].expression.value"),
+                        );
+                    }
+                }
+            }
+        }
+
+        for field in ["messageBuilder", "denyWith", "headers", "value"] {
+            if let Some(expression) = action.get(field) {
+                self.check_expression(expression, &format!("{path}.{field}"));
+            }
+        }
+        if let Some(Value::Array(on_reply)) = action.get("onReply") {
+            for (index, action) in on_reply.iter().enumerate() {
+                self.check_action(action, &format!("{path}.onReply[{index}]"));
+            }
+        }
+    }
+
+    /// The domain of a legacy rate limit action is its scope, unless a
+    /// `ratelimit.domain` static data item overrides it
+    fn check_rate_limit_domains(&mut self, action: &Map<String, Value>, path: &str) {
+        if let Some(scope) = action.get("scope").and_then(Value::as_str) {
+            self.check_domain(scope, &format!("{path}.scope"));
+        }
+        let Some(Value::Array(conditional_data)) = action.get("conditionalData") else {
+            return;
+        };
+        for (index, conditional) in conditional_data.iter().enumerate() {
+            let Some(Value::Array(data)) = conditional.get("data") else {
+                continue;
+            };
+            for (item_index, item) in data.iter().enumerate() {
+                let Some(item) = item.get("static") else {
+                    continue;
+                };
+                if item.get("key").and_then(Value::as_str) != Some(RATE_LIMIT_DOMAIN_KEY) {
+                    continue;
+                }
+                if let Some(domain) = item.get("value").and_then(Value::as_str) {
+                    self.check_domain(
+                        domain,
+                        &format!("{path}.conditionalData[{index}].data[{item_index}].static.value"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_domain(&mut self, domain: &str, path: &str) {
+        let message = if domain.trim().is_empty() {
+            format!("rate limit domain {domain:?} is blank")
+        } else if domain.len() > MAX_DOMAIN_LENGTH {
+            format!(
+                "rate limit domain is {} characters long, at most {MAX_DOMAIN_LENGTH} allowed",
+                domain.len()
+            )
+        } else {
+            return;
+        };
+        self.errors.push(ConfigError::new(
+            path,
+            ConfigErrorKind::InvalidDomain,
+            message,
+        ));
+    }
+
+    fn check_predicates(&mut self, predicates: &Value, path: &str) {
+        match predicates {
+            Value::Array(predicates) => {
+                for (index, predicate) in predicates.iter().enumerate() {
+                    self.check_predicate(predicate, &format!("{path}[{index}]"));
+                }
+            }
+            _ => self.invalid(path, "expected an array of predicates"),
+        }
+    }
+
+    fn check_predicate(&mut self, predicate: &Value, path: &str) {
+        let Some(predicate) = predicate.as_str() else {
+            return self.invalid(path, "expected a CEL expression");
+        };
+        let result = match Predicate::new(predicate) {
+            Ok(compiled) => compiled.compile_check().map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            self.errors.push(ConfigError::new(
+                path,
+                ConfigErrorKind::InvalidExpression,
+                format!("invalid predicate {predicate:?}: {e}"),
+            ));
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Value, path: &str) {
+        let Some(expression) = expression.as_str() else {
+            return self.invalid(path, "expected a CEL expression");
+        };
+        // Only parsed: unlike a predicate, its result may be of any type, and
+        // message builders construct messages from the host's descriptors
+        if let Err(e) = Expression::new(expression) {
+            self.errors.push(ConfigError::new(
+                path,
+                ConfigErrorKind::InvalidExpression,
+                format!("invalid expression {expression:?}: {e}"),
+            ));
+        }
+    }
+
+    fn check_timeout(&mut self, timeout: &Value, path: &str) {
+        match serde_json::from_value::<Timeout>(timeout.clone()) {
+            Ok(Timeout(Duration::ZERO)) => self.errors.push(ConfigError::new(
+                path,
+                ConfigErrorKind::InvalidTimeout,
+                "timeout must be greater than zero",
+            )),
+            Ok(_) => {}
+            Err(e) => self.errors.push(ConfigError::new(
+                path,
+                ConfigErrorKind::InvalidTimeout,
+                format!("invalid timeout {timeout}: {e}"),
+            )),
+        }
+    }
+
+    fn check_fields(&mut self, object: &Map<String, Value>, path: &str, known: &[&str]) {
+        for field in object.keys() {
+            if !known.contains(&field.as_str()) {
+                self.errors.push(ConfigError::new(
+                    format!("{path}.{field}"),
+                    ConfigErrorKind::UnknownField,
+                    format!("unknown field {field:?}, expected one of {known:?}"),
+                ));
+            }
+        }
+    }
+
+    fn object<'a>(&mut self, value: &'a Value, path: &str) -> Option<&'a Map<String, Value>> {
+        let object = value.as_object();
+        if object.is_none() {
+            self.invalid(path, "expected an object");
+        }
+        object
+    }
+
+    fn missing_field(&mut self, path: &str, field: &str) {
+        self.errors.push(ConfigError::new(
+            format!("{path}.{field}"),
+            ConfigErrorKind::MissingField,
+            format!("missing field {field:?}"),
+        ));
+    }
+
+    fn invalid(&mut self, path: &str, message: &str) {
+        self.errors
+            .push(ConfigError::new(path, ConfigErrorKind::Invalid, message));
+    }
+}
+
+/// The fields serde knows for `T`, so that unknown ones are reported where
+/// they are rather than only the first one serde stops at
+fn fields_of<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    /// Records the fields a derived `Deserialize` asks for, without any input
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for FieldNames<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(config: &str) -> Result<ValidatedConfig, Vec<ConfigError>> {
+        ConfigValidator::validate(config.as_bytes())
+    }
+
+    fn error_kinds(config: &str) -> Vec<(String, ConfigErrorKind)> {
+        validate(config)
+            .expect_err("config to be rejected")
+            .into_iter()
+            .map(|e| (e.path, e.kind))
+            .collect()
+    }
+
+    const SERVICES: &str = r#"{
+        "limitador": {
+            "type": "ratelimit",
+            "endpoint": "limitador-cluster",
+            "failureMode": "deny",
+            "timeout": "20ms"
+        }
+    }"#;
+
+    fn with_action_sets(action_sets: &str) -> String {
+        format!(r#"{{ "services": {SERVICES}, "actionSets": {action_sets} }}"#)
+    }
+
+    #[test]
+    fn accepts_a_valid_config() {
+        let config = with_action_sets(
+            r#"[{
+                "name": "toystore",
+                "routeRuleConditions": {
+                    "hostnames": ["*.toystore.com"],
+                    "predicates": { "or": ["request.method == 'GET'"] }
+                },
+                "actions": [{
+                    "service": "limitador",
+                    "scope": "toystore",
+                    "predicates": ["request.path.startsWith('/admin')"]
+                }]
+            }]"#,
+        );
+        let config = validate(&config).expect("config to be valid").into_inner();
+        assert_eq!(config.action_sets.len(), 1);
+    }
+
+    #[test]
+    fn unversioned_configs_are_version_one() {
+        let unversioned = format!(
+            r#"{{
+                "services": {SERVICES},
+                "actionSets": [],
+                "observability": {{ "defaultLevel": "INFO", "accessLog": true }}
+            }}"#
+        );
+        let v1 = format!(
+            r#"{{
+                "version": 1,
+                "services": {SERVICES},
+                "actionSets": [],
+                "observability": {{ "defaultLevel": "INFO", "accessLog": true }}
+            }}"#
+        );
+        let unversioned = validate(&unversioned).expect("unversioned config to be valid");
+        let v1 = validate(&v1).expect("v1 config to be valid");
+        assert_eq!(format!("{unversioned:?}"), format!("{v1:?}"));
+
+        let config = unversioned.into_inner();
+        assert_eq!(config.version, 1);
+        assert!(config.observability.access_log);
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        let config = format!(r#"{{ "version": 2, "services": {SERVICES}, "actionSets": [] }}"#);
+        assert_eq!(
+            error_kinds(&config),
+            vec![("$.version".to_string(), ConfigErrorKind::Invalid)]
+        );
+
+        let config =
+            format!(r#"{{ "services": {SERVICES}, "actionSets": [], "accessLog": true }}"#);
+        assert_eq!(
+            error_kinds(&config),
+            vec![("$.accessLog".to_string(), ConfigErrorKind::UnknownField)]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        assert_eq!(
+            error_kinds("{ services: }"),
+            vec![("$".to_string(), ConfigErrorKind::Syntax)]
+        );
+    }
+
+    #[test]
+    fn reports_missing_service_fields() {
+        let config = r#"{
+            "services": {
+                "custom": { "type": "dynamic", "endpoint": "custom-cluster" }
+            },
+            "actionSets": []
+        }"#;
+        assert_eq!(
+            error_kinds(config),
+            vec![
+                (
+                    "$.services.custom.failureMode".to_string(),
+                    ConfigErrorKind::MissingField
+                ),
+                (
+                    "$.services.custom.grpcService".to_string(),
+                    ConfigErrorKind::MissingField
+                ),
+                (
+                    "$.services.custom.grpcMethod".to_string(),
+                    ConfigErrorKind::MissingField
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn http_callout_services_require_their_endpoint() {
+        let config = r#"{
+            "services": {
+                "opa": { "type": "http-callout", "endpoint": "opa-cluster", "failureMode": "deny" }
+            },
+            "actionSets": []
+        }"#;
+        assert_eq!(
+            error_kinds(config),
+            vec![(
+                "$.services.opa.httpCallout".to_string(),
+                ConfigErrorKind::MissingField
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_predicates() {
+        let config = with_action_sets(
+            r#"[{
+                "name": "toystore",
+                "routeRuleConditions": {
+                    "hostnames": [],
+                    "predicates": ["request.method =="]
+                },
+                "actions": [{
+                    "service": "limitador",
+                    "scope": "toystore",
+                    "predicates": ["'not a bool'"]
+                }]
+            }]"#,
+        );
+        assert_eq!(
+            error_kinds(&config),
+            vec![
+                (
+                    "$.actionSets[0].routeRuleConditions.predicates[0]".to_string(),
+                    ConfigErrorKind::InvalidExpression
+                ),
+                (
+                    "$.actionSets[0].actions[0].predicates[0]".to_string(),
+                    ConfigErrorKind::InvalidExpression
+                ),
+            ]
+        );
+
+        for operator in ["and", "or"] {
+            let config = with_action_sets(&format!(
+                r#"[{{
+                    "name": "toystore",
+                    "routeRuleConditions": {{
+                        "hostnames": [],
+                        "predicates": {{ "{operator}": [] }}
+                    }},
+                    "actions": []
+                }}]"#
+            ));
+            assert_eq!(
+                error_kinds(&config),
+                vec![(
+                    format!("$.actionSets[0].routeRuleConditions.predicates.{operator}"),
+                    ConfigErrorKind::Invalid
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn reports_invalid_expressions() {
+        let config = format!(
+            r#"{{
+                "services": {SERVICES},
+                "computedProperties": [{{ "name": "derived.tier", "expression": "auth.tier +" }}],
+                "actionSets": [{{
+                    "name": "toystore",
+                    "routeRuleConditions": {{ "hostnames": [] }},
+                    "actions": [
+                        {{
+                            "service": "limitador",
+                            "scope": "toystore",
+                            "conditionalData": [{{
+                                "predicates": ["request.method =="],
+                                "data": [{{ "expression": {{ "key": "user", "value": "auth.(" }} }}]
+                            }}]
+                        }},
+                        {{
+                            "type": "grpc",
+                            "predicate": "true",
+                            "terminal": false,
+                            "var": "reply",
+                            "service": "limitador",
+                            "messageBuilder": "Request{{",
+                            "onReply": [{{
+                                "type": "headers",
+                                "predicate": "true",
+                                "terminal": false,
+                                "target": "response",
+                                "headers": "[['x-reply', reply.code"
+                            }}]
+                        }}
+                    ]
+                }}]
+            }}"#
+        );
+        assert_eq!(
+            error_kinds(&config),
+            vec![
+                (
+                    "$.computedProperties[0].expression".to_string(),
+                    ConfigErrorKind::InvalidExpression
+                ),
+                (
+                    "$.actionSets[0].actions[0].conditionalData[0].predicates[0]".to_string(),
+                    ConfigErrorKind::InvalidExpression
+                ),
+                (
+                    "$.actionSets[0].actions[0].conditionalData[0].data[0].expression.value"
+                        .to_string(),
+                    ConfigErrorKind::InvalidExpression
+                ),
+                (
+                    "$.actionSets[0].actions[1].messageBuilder".to_string(),
+                    ConfigErrorKind::InvalidExpression
+                ),
+                (
+                    "$.actionSets[0].actions[1].onReply[0].headers".to_string(),
+                    ConfigErrorKind::InvalidExpression
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_action_set_names() {
+        let action_set = r#"{
+            "name": "toystore",
+            "routeRuleConditions": { "hostnames": [] },
+            "actions": []
+        }"#;
+        let errors = validate(&with_action_sets(&format!("[{action_set}, {action_set}]")))
+            .expect_err("config to be rejected");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "$.actionSets[1].name");
+        assert_eq!(errors[0].kind, ConfigErrorKind::DuplicateActionSet);
+        assert!(errors[0].message.contains("$.actionSets[0]"));
+    }
+
+    #[test]
+    fn reports_invalid_timeouts() {
+        let config = r#"{
+            "services": {
+                "zero": {
+                    "type": "auth",
+                    "endpoint": "authorino-cluster",
+                    "failureMode": "deny",
+                    "timeout": "0s"
+                },
+                "negative": {
+                    "type": "auth",
+                    "endpoint": "authorino-cluster",
+                    "failureMode": "deny",
+                    "timeout": "-5ms"
+                }
+            },
+            "actionSets": []
+        }"#;
+        let mut errors = error_kinds(config);
+        errors.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "$.services.negative.timeout".to_string(),
+                    ConfigErrorKind::InvalidTimeout
+                ),
+                (
+                    "$.services.zero.timeout".to_string(),
+                    ConfigErrorKind::InvalidTimeout
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_retry_policies() {
+        let config = r#"{
+            "services": {
+                "limitador": {
+                    "type": "ratelimit",
+                    "endpoint": "limitador-cluster",
+                    "failureMode": "allow",
+                    "retryPolicy": { "maxAttempts": 3, "baseDelay": "0s", "multiplier": 0.5 }
+                }
+            },
+            "actionSets": []
+        }"#;
+        assert_eq!(
+            error_kinds(config),
+            vec![
+                (
+                    "$.services.limitador.retryPolicy.baseDelay".to_string(),
+                    ConfigErrorKind::InvalidTimeout
+                ),
+                (
+                    "$.services.limitador.retryPolicy.multiplier".to_string(),
+                    ConfigErrorKind::Invalid
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_dynamic_metadata_keys_of_non_auth_services() {
+        let config = r#"{
+            "services": {
+                "authorino": {
+                    "type": "auth",
+                    "endpoint": "authorino-cluster",
+                    "failureMode": "deny",
+                    "dynamicMetadataKey": "envoy.filters.http.ext_authz"
+                },
+                "limitador": {
+                    "type": "ratelimit",
+                    "endpoint": "limitador-cluster",
+                    "failureMode": "allow",
+                    "dynamicMetadataKey": "envoy.filters.http.ratelimit"
+                }
+            },
+            "actionSets": []
+        }"#;
+        assert_eq!(
+            error_kinds(config),
+            vec![(
+                "$.services.limitador.dynamicMetadataKey".to_string(),
+                ConfigErrorKind::Invalid
+            )]
+        );
+    }
+
+    fn rate_limit_action(scope: &str) -> String {
+        with_action_sets(&format!(
+            r#"[{{
+                "name": "toystore",
+                "routeRuleConditions": {{ "hostnames": [] }},
+                "actions": [{{ "service": "limitador", "scope": {scope:?} }}]
+            }}]"#
+        ))
+    }
+
+    #[test]
+    fn reports_blank_rate_limit_domains() {
+        for blank in ["", " \t "] {
+            assert_eq!(
+                error_kinds(&rate_limit_action(blank)),
+                vec![(
+                    "$.actionSets[0].actions[0].scope".to_string(),
+                    ConfigErrorKind::InvalidDomain
+                )],
+                "{blank:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_over_long_rate_limit_domains() {
+        assert!(validate(&rate_limit_action(&"a".repeat(MAX_DOMAIN_LENGTH))).is_ok());
+        assert_eq!(
+            error_kinds(&rate_limit_action(&"a".repeat(MAX_DOMAIN_LENGTH + 1))),
+            vec![(
+                "$.actionSets[0].actions[0].scope".to_string(),
+                ConfigErrorKind::InvalidDomain
+            )]
+        );
+    }
+
+    #[test]
+    fn checks_the_domain_overriding_the_scope() {
+        assert!(validate(&rate_limit_action("kuadrant/toystore")).is_ok());
+
+        let config = with_action_sets(
+            r#"[{
+                "name": "toystore",
+                "routeRuleConditions": { "hostnames": [] },
+                "actions": [{
+                    "service": "limitador",
+                    "scope": "toystore",
+                    "conditionalData": [{
+                        "data": [{ "static": { "key": "ratelimit.domain", "value": "  " } }]
+                    }]
+                }]
+            }]"#,
+        );
+        assert_eq!(
+            error_kinds(&config),
+            vec![(
+                "$.actionSets[0].actions[0].conditionalData[0].data[0].static.value".to_string(),
+                ConfigErrorKind::InvalidDomain
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_fields() {
+        let config = format!(
+            r#"{{
+                "services": {SERVICES},
+                "actionSets": [],
+                "acessLog": true
+            }}"#
+        );
+        let errors = validate(&config).expect_err("config to be rejected");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "$.acessLog");
+        assert_eq!(errors[0].kind, ConfigErrorKind::UnknownField);
+    }
+
+    #[test]
+    fn reports_every_error_at_once() {
+        let config = r#"{
+            "services": {
+                "limitador": {
+                    "type": "ratelimit",
+                    "endpoint": "limitador-cluster",
+                    "timeout": "0ms",
+                    "retries": 3
+                }
+            },
+            "actionSets": [
+                {
+                    "name": "toystore",
+                    "routeRuleConditions": { "hostnames": [], "predicates": ["1 +"] },
+                    "actions": []
+                },
+                {
+                    "name": "toystore",
+                    "routeRuleConditions": { "hostnames": [] },
+                    "actions": []
+                }
+            ]
+        }"#;
+        let kinds: Vec<ConfigErrorKind> = validate(config)
+            .expect_err("config to be rejected")
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ConfigErrorKind::UnknownField,
+                ConfigErrorKind::MissingField,
+                ConfigErrorKind::InvalidTimeout,
+                ConfigErrorKind::InvalidExpression,
+                ConfigErrorKind::DuplicateActionSet,
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_config_deltas() {
+        let delta = ConfigValidator::validate_delta(
+            br#"{
+                "delta": true,
+                "removeActionSets": ["old"],
+                "upsertActionSets": [{
+                    "name": "new",
+                    "routeRuleConditions": { "hostnames": ["example.com"] },
+                    "actions": []
+                }]
+            }"#,
+            HashSet::new(),
+        )
+        .expect("a valid delta");
+        assert_eq!(delta.remove_action_sets, vec!["old"]);
+        assert_eq!(delta.upsert_action_sets[0].name, "new");
+
+        let errors = ConfigValidator::validate_delta(
+            br#"{
+                "delta": true,
+                "services": {},
+                "upsertActionSets": [{
+                    "name": "new",
+                    "routeRuleConditions": { "hostnames": [], "predicates": ["1 +"] },
+                    "actions": [{ "service": "limitador", "scope": " " }]
+                }]
+            }"#,
+            HashSet::from(["limitador".to_string()]),
+        )
+        .expect_err("delta to be rejected");
+        let errors: Vec<_> = errors.into_iter().map(|e| (e.path, e.kind)).collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("$.services".to_string(), ConfigErrorKind::UnknownField),
+                (
+                    "$.upsertActionSets[0].routeRuleConditions.predicates[0]".to_string(),
+                    ConfigErrorKind::InvalidExpression
+                ),
+                (
+                    "$.upsertActionSets[0].actions[0].scope".to_string(),
+                    ConfigErrorKind::InvalidDomain
+                ),
+            ]
+        );
+    }
+}
@@ -1,9 +1,20 @@
+use cel::objects::ValueType;
 use chrono::{DateTime, FixedOffset};
+use prost::Message;
+use prost_types::value::Kind;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
 
+use crate::data::client_ip::parse_address;
 use crate::data::Headers;
-use crate::kuadrant::CachedValue;
+use crate::kuadrant::{CachedValue, ReqRespCtx};
+
+/// Bumped every time metadata is stored for the request, so results derived from
+/// it can be invalidated without comparing the values themselves.
+pub const METADATA_GENERATION_PATH: &str = "kuadrant.metadata.generation";
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AttributeState<T> {
@@ -28,6 +39,10 @@ pub enum AttributeError {
     NotAvailable(String),
     Retrieval(String),
     Parse(String),
+    /// Not an IP address, with or without a port
+    InvalidAddress(String),
+    /// Not a non-negative count of nanoseconds
+    InvalidDuration(String),
     Set(String),
 }
 
@@ -45,6 +60,12 @@ impl Display for AttributeError {
             AttributeError::Parse(msg) => {
                 write!(f, "AttributeError::Parse {{ {msg:?} }}")
             }
+            AttributeError::InvalidAddress(msg) => {
+                write!(f, "AttributeError::InvalidAddress {{ {msg:?} }}")
+            }
+            AttributeError::InvalidDuration(msg) => {
+                write!(f, "AttributeError::InvalidDuration {{ {msg:?} }}")
+            }
             AttributeError::Set(msg) => {
                 write!(f, "AttributeError::Set {{ {msg:?} }}")
             }
@@ -57,6 +78,14 @@ pub trait AttributeValue {
     where
         Self: Sized;
 
+    /// The CEL type whose values parse into `Self`, if any
+    fn cel_type() -> Option<ValueType>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
     fn from_cached(cached: &CachedValue) -> Result<Option<Self>, AttributeError>
     where
         Self: Sized,
@@ -79,6 +108,10 @@ impl AttributeValue for String {
             ))
         })
     }
+
+    fn cel_type() -> Option<ValueType> {
+        Some(ValueType::String)
+    }
 }
 
 impl AttributeValue for i64 {
@@ -91,6 +124,10 @@ impl AttributeValue for i64 {
             ))),
         }
     }
+
+    fn cel_type() -> Option<ValueType> {
+        Some(ValueType::Int)
+    }
 }
 
 impl AttributeValue for u64 {
@@ -103,6 +140,10 @@ impl AttributeValue for u64 {
             ))),
         }
     }
+
+    fn cel_type() -> Option<ValueType> {
+        Some(ValueType::UInt)
+    }
 }
 
 impl AttributeValue for f64 {
@@ -115,12 +156,20 @@ impl AttributeValue for f64 {
             ))),
         }
     }
+
+    fn cel_type() -> Option<ValueType> {
+        Some(ValueType::Float)
+    }
 }
 
 impl AttributeValue for Vec<u8> {
     fn parse(raw_attribute: Vec<u8>) -> Result<Self, AttributeError> {
         Ok(raw_attribute)
     }
+
+    fn cel_type() -> Option<ValueType> {
+        Some(ValueType::Bytes)
+    }
 }
 
 impl AttributeValue for bool {
@@ -133,6 +182,10 @@ impl AttributeValue for bool {
             raw_attribute.len()
         )))
     }
+
+    fn cel_type() -> Option<ValueType> {
+        Some(ValueType::Bool)
+    }
 }
 
 impl AttributeValue for DateTime<FixedOffset> {
@@ -148,6 +201,167 @@ impl AttributeValue for DateTime<FixedOffset> {
             ))),
         }
     }
+
+    fn cel_type() -> Option<ValueType> {
+        Some(ValueType::Timestamp)
+    }
+}
+
+/// Reads addresses such as `source.address`, dropping the port when present
+impl AttributeValue for IpAddr {
+    fn parse(raw_attribute: Vec<u8>) -> Result<Self, AttributeError> {
+        let address = String::from_utf8(raw_attribute).map_err(|err| {
+            AttributeError::InvalidAddress(format!(
+                "parse: address is not valid UTF-8, error: {err}"
+            ))
+        })?;
+        parse_address(&address).ok_or_else(|| {
+            AttributeError::InvalidAddress(format!("parse: invalid IP address {address:?}"))
+        })
+    }
+
+    fn cel_type() -> Option<ValueType> {
+        Some(ValueType::String)
+    }
+}
+
+/// Reads durations such as `request.duration`, encoded by the host as the
+/// nanoseconds of a protobuf `Duration`, like timestamps are
+impl AttributeValue for Duration {
+    fn parse(raw_attribute: Vec<u8>) -> Result<Self, AttributeError> {
+        let ra_len = raw_attribute.len();
+        let bytes = <[u8; 8]>::try_from(raw_attribute).map_err(|_| {
+            AttributeError::InvalidDuration(format!(
+                "parse: Duration expected to be 8 bytes, but got {ra_len}",
+            ))
+        })?;
+        let nanos = i64::from_le_bytes(bytes);
+        u64::try_from(nanos).map(Duration::from_nanos).map_err(|_| {
+            AttributeError::InvalidDuration(format!("parse: negative Duration {nanos}ns"))
+        })
+    }
+}
+
+/// An element of a list attribute, the list being stored as the protobuf
+/// `ListValue` holding each element as a `Value`
+pub trait ListElement: Sized {
+    fn to_value(&self) -> prost_types::Value;
+
+    fn from_value(value: prost_types::Value) -> Result<Self, AttributeError>;
+}
+
+fn unexpected_list_element(expected: &str, value: prost_types::Value) -> AttributeError {
+    AttributeError::Parse(format!(
+        "parse: {expected} list element expected, but got {:?}",
+        value.kind
+    ))
+}
+
+impl ListElement for String {
+    fn to_value(&self) -> prost_types::Value {
+        prost_types::Value {
+            kind: Some(Kind::StringValue(self.clone())),
+        }
+    }
+
+    fn from_value(value: prost_types::Value) -> Result<Self, AttributeError> {
+        match value.kind {
+            Some(Kind::StringValue(string)) => Ok(string),
+            _ => Err(unexpected_list_element("String", value)),
+        }
+    }
+}
+
+/// Stored as a `NumberValue`, integers beyond 2^53 lose precision
+impl ListElement for i64 {
+    fn to_value(&self) -> prost_types::Value {
+        prost_types::Value {
+            kind: Some(Kind::NumberValue(*self as f64)),
+        }
+    }
+
+    fn from_value(value: prost_types::Value) -> Result<Self, AttributeError> {
+        match value.kind {
+            Some(Kind::NumberValue(number))
+                if number.fract() == 0.0
+                    && (i64::MIN as f64..i64::MAX as f64).contains(&number) =>
+            {
+                Ok(number as i64)
+            }
+            _ => Err(unexpected_list_element("Int", value)),
+        }
+    }
+}
+
+impl ListElement for f64 {
+    fn to_value(&self) -> prost_types::Value {
+        prost_types::Value {
+            kind: Some(Kind::NumberValue(*self)),
+        }
+    }
+
+    fn from_value(value: prost_types::Value) -> Result<Self, AttributeError> {
+        match value.kind {
+            Some(Kind::NumberValue(number)) => Ok(number),
+            _ => Err(unexpected_list_element("Float", value)),
+        }
+    }
+}
+
+impl ListElement for bool {
+    fn to_value(&self) -> prost_types::Value {
+        prost_types::Value {
+            kind: Some(Kind::BoolValue(*self)),
+        }
+    }
+
+    fn from_value(value: prost_types::Value) -> Result<Self, AttributeError> {
+        match value.kind {
+            Some(Kind::BoolValue(boolean)) => Ok(boolean),
+            _ => Err(unexpected_list_element("Bool", value)),
+        }
+    }
+}
+
+/// An element of any kind, as the list holds it
+impl ListElement for prost_types::Value {
+    fn to_value(&self) -> prost_types::Value {
+        self.clone()
+    }
+
+    fn from_value(value: prost_types::Value) -> Result<Self, AttributeError> {
+        Ok(value)
+    }
+}
+
+/// The serialized `ListValue` of `values`, as read back into a `Vec<T>`
+pub fn encode_list<T: ListElement>(values: &[T]) -> Vec<u8> {
+    prost_types::ListValue {
+        values: values.iter().map(ListElement::to_value).collect(),
+    }
+    .encode_to_vec()
+}
+
+impl<T: ListElement> AttributeValue for Vec<T> {
+    fn parse(raw_attribute: Vec<u8>) -> Result<Self, AttributeError> {
+        prost_types::ListValue::decode(raw_attribute.as_slice())
+            .map_err(|err| {
+                AttributeError::Parse(format!("parse: failed to parse ListValue, error: {err}"))
+            })?
+            .values
+            .into_iter()
+            .map(T::from_value)
+            .collect()
+    }
+}
+
+/// The protobuf `Struct` the host holds for a map-valued property
+impl AttributeValue for prost_types::Struct {
+    fn parse(raw_attribute: Vec<u8>) -> Result<Self, AttributeError> {
+        prost_types::Struct::decode(raw_attribute.as_slice()).map_err(|err| {
+            AttributeError::Parse(format!("parse: failed to parse Struct, error: {err}"))
+        })
+    }
 }
 
 impl AttributeValue for Headers {
@@ -167,6 +381,8 @@ impl AttributeValue for Headers {
     }
 }
 
+/// A single empty segment is spelled like the path with no segments, so
+/// both are kept as the latter for `Display` to round-trip.
 #[derive(Clone, Hash, PartialEq, Eq)]
 pub struct Path {
     tokens: Vec<String>,
@@ -179,7 +395,7 @@ impl Display for Path {
             "{}",
             self.tokens
                 .iter()
-                .map(|t| t.replace('.', "\\."))
+                .map(|t| t.replace('\\', "\\\\").replace('.', "\\."))
                 .collect::<Vec<String>>()
                 .join(".")
         )
@@ -192,6 +408,27 @@ impl Debug for Path {
     }
 }
 
+/// The escape sequence starting at `offset`, in bytes, is neither `\.` nor
+/// `\\`, or the path ends on a lone backslash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathParseError {
+    pub offset: usize,
+}
+
+impl Error for PathParseError {}
+
+impl Display for PathParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PathParseError {{ invalid escape at byte {} }}",
+            self.offset
+        )
+    }
+}
+
+/// Lenient conversion for paths spelled out in code: an unknown escape keeps
+/// the escaped character and a trailing backslash is dropped.
 impl From<&str> for Path {
     fn from(value: &str) -> Self {
         let mut token = String::new();
@@ -213,19 +450,74 @@ impl From<&str> for Path {
         }
         tokens.push(token);
 
-        Self { tokens }
+        Self::from_tokens(tokens)
+    }
+}
+
+impl FromStr for Path {
+    type Err = PathParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Path::parse(s)
     }
 }
 
 impl Path {
     pub fn new<T: Into<String>>(tokens: Vec<T>) -> Self {
-        Self {
-            tokens: tokens.into_iter().map(|i| i.into()).collect(),
+        Self::from_tokens(tokens.into_iter().map(|i| i.into()).collect())
+    }
+
+    fn from_tokens(tokens: Vec<String>) -> Self {
+        if tokens.len() == 1 && tokens[0].is_empty() {
+            return Self { tokens: Vec::new() };
         }
+        Self { tokens }
     }
+
+    /// Builds a path from unescaped segments, which may contain dots themselves.
+    pub fn from_parts<I, S>(parts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::from_tokens(
+            parts
+                .into_iter()
+                .map(|part| part.as_ref().to_string())
+                .collect(),
+        )
+    }
+
+    /// Parses a dot separated path. Within a segment `\.` is a literal dot and
+    /// `\\` a literal backslash; a backslash followed by anything else is
+    /// rejected. The empty string is the path with no segments, which makes
+    /// parsing the inverse of `Display`.
+    pub fn parse(value: &str) -> Result<Self, PathParseError> {
+        let mut token = String::new();
+        let mut tokens: Vec<String> = Vec::new();
+        let mut chars = value.char_indices();
+        while let Some((offset, ch)) = chars.next() {
+            match ch {
+                '.' => tokens.push(std::mem::take(&mut token)),
+                '\\' => match chars.next() {
+                    Some((_, next @ ('.' | '\\'))) => token.push(next),
+                    _ => return Err(PathParseError { offset }),
+                },
+                _ => token.push(ch),
+            }
+        }
+        tokens.push(token);
+
+        Ok(Self::from_tokens(tokens))
+    }
+
     pub fn tokens(&self) -> Vec<&str> {
         self.tokens.iter().map(String::as_str).collect()
     }
+
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.tokens.iter().map(String::as_str)
+    }
 }
 
 pub fn wasm_prop(tokens: &[&str]) -> Path {
@@ -233,3 +525,292 @@ pub fn wasm_prop(tokens: &[&str]) -> Path {
     flat_attr.push_str(tokens.join("\\.").as_str());
     flat_attr.as_str().into()
 }
+
+/// Every value set under `prefix`, such as `kuadrant`, by walking the `Struct`
+/// the host returns for it. Nested structs are descended into, any other
+/// value, lists included, is listed at its full path.
+pub fn list_attributes(
+    ctx: &ReqRespCtx,
+    prefix: &str,
+) -> Result<AttributeState<Vec<(Path, prost_types::Value)>>, AttributeError> {
+    let prefix = Path::from(prefix);
+    Ok(ctx
+        .get_attribute_ref::<prost_types::Struct>(&prefix)?
+        .map(|root| {
+            let mut attributes = Vec::new();
+            if let Some(root) = root {
+                collect_fields(prefix.tokens.clone(), root, &mut attributes);
+            }
+            attributes
+        }))
+}
+
+/// The `Struct` a filter, such as `envoy.filters.http.ext_authz`, left in the
+/// dynamic metadata of the request under `filter_metadata_key`
+pub fn dynamic_metadata(
+    ctx: &ReqRespCtx,
+    filter_metadata_key: &str,
+) -> Result<AttributeState<Option<prost_types::Struct>>, AttributeError> {
+    ctx.get_attribute_ref::<prost_types::Struct>(&Path::from_parts([
+        "metadata",
+        "filter_metadata",
+        filter_metadata_key,
+    ]))
+}
+
+fn collect_fields(
+    parent: Vec<String>,
+    fields: prost_types::Struct,
+    attributes: &mut Vec<(Path, prost_types::Value)>,
+) {
+    for (key, value) in fields.fields {
+        let mut tokens = parent.clone();
+        tokens.push(key);
+        match value.kind {
+            Some(Kind::StructValue(nested)) => collect_fields(tokens, nested, attributes),
+            _ => attributes.push((Path { tokens }, value)),
+        }
+    }
+}
+
+pub fn get_metadata_generation(ctx: &ReqRespCtx) -> u64 {
+    match ctx.get_attribute_or_default::<u64>(METADATA_GENERATION_PATH) {
+        Ok(AttributeState::Available(generation)) => generation,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn lists_round_trip_through_list_value() {
+        assert_eq!(Vec::<String>::parse(encode_list::<String>(&[])), Ok(vec![]));
+        assert!(encode_list::<i64>(&[]).is_empty());
+
+        assert_eq!(Vec::<i64>::parse(encode_list(&[7i64])), Ok(vec![7]));
+
+        let values = vec![
+            "".to_string(),
+            "a,b;c=d".to_string(),
+            "quote \" and \\ backslash".to_string(),
+            "multi\nline\ttab \u{0}".to_string(),
+            "ünïcödé 🚀".to_string(),
+        ];
+        assert_eq!(Vec::<String>::parse(encode_list(&values)), Ok(values));
+    }
+
+    #[test]
+    fn list_elements_must_have_the_expected_kind() {
+        assert!(matches!(
+            Vec::<i64>::parse(encode_list(&["1".to_string()])),
+            Err(AttributeError::Parse(_))
+        ));
+        assert!(matches!(
+            Vec::<i64>::parse(encode_list(&[1.5f64])),
+            Err(AttributeError::Parse(_))
+        ));
+        assert!(matches!(
+            Vec::<bool>::parse(vec![0xff, 0xff]),
+            Err(AttributeError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn ip_addresses_drop_the_port() {
+        assert_eq!(
+            IpAddr::parse(b"10.0.0.1:51234".to_vec()),
+            Ok(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        );
+        assert_eq!(
+            IpAddr::parse(b"[2001:db8::1]:443".to_vec()),
+            Ok(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)))
+        );
+        assert_eq!(
+            IpAddr::parse(b"::1".to_vec()),
+            Ok(IpAddr::V6(Ipv6Addr::LOCALHOST))
+        );
+        assert!(matches!(
+            IpAddr::parse(b"localhost:80".to_vec()),
+            Err(AttributeError::InvalidAddress(_))
+        ));
+        assert!(matches!(
+            IpAddr::parse(vec![0xff, 0xfe]),
+            Err(AttributeError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn durations_are_non_negative_nanoseconds() {
+        assert_eq!(
+            Duration::parse(1_500_000_000i64.to_le_bytes().to_vec()),
+            Ok(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            Duration::parse(i64::MAX.to_le_bytes().to_vec()),
+            Ok(Duration::from_nanos(i64::MAX as u64))
+        );
+        // past i64::MAX the nanoseconds wrap around to negative
+        assert!(matches!(
+            Duration::parse(u64::MAX.to_le_bytes().to_vec()),
+            Err(AttributeError::InvalidDuration(_))
+        ));
+        assert!(matches!(
+            Duration::parse(vec![1, 2, 3]),
+            Err(AttributeError::InvalidDuration(_))
+        ));
+    }
+
+    #[test]
+    fn bytes_pass_through() {
+        assert_eq!(<Vec<u8>>::parse(Vec::new()), Ok(Vec::new()));
+        assert_eq!(<Vec<u8>>::parse(vec![0, 0xff]), Ok(vec![0, 0xff]));
+    }
+
+    #[test]
+    fn lists_the_attributes_under_a_prefix() {
+        use crate::kuadrant::MockWasmHost;
+        use std::sync::Arc;
+
+        let string = |s: &str| prost_types::Value {
+            kind: Some(Kind::StringValue(s.to_string())),
+        };
+        let number = |n: f64| prost_types::Value {
+            kind: Some(Kind::NumberValue(n)),
+        };
+        let object = |fields: Vec<(&str, prost_types::Value)>| prost_types::Value {
+            kind: Some(Kind::StructValue(prost_types::Struct {
+                fields: fields
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            })),
+        };
+        let Some(Kind::StructValue(kuadrant)) = object(vec![
+            (
+                "identity",
+                object(vec![("user", string("alice")), ("tier", number(2.0))]),
+            ),
+            ("dotted.key", string("x")),
+            ("empty", object(vec![])),
+        ])
+        .kind
+        else {
+            unreachable!("built a struct")
+        };
+
+        let ctx = ReqRespCtx::new(Arc::new(
+            MockWasmHost::new().with_property("kuadrant".into(), kuadrant.encode_to_vec()),
+        ));
+        assert_eq!(
+            list_attributes(&ctx, "kuadrant"),
+            Ok(AttributeState::Available(vec![
+                (Path::from_parts(["kuadrant", "dotted.key"]), string("x")),
+                (Path::from("kuadrant.identity.tier"), number(2.0)),
+                (Path::from("kuadrant.identity.user"), string("alice")),
+            ]))
+        );
+
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        assert_eq!(
+            list_attributes(&ctx, "kuadrant"),
+            Ok(AttributeState::Available(vec![]))
+        );
+    }
+
+    #[test]
+    fn reads_the_dynamic_metadata_under_a_key() {
+        use crate::kuadrant::MockWasmHost;
+        use std::sync::Arc;
+
+        let metadata = prost_types::Struct {
+            fields: [(
+                "identity".to_string(),
+                prost_types::Value {
+                    kind: Some(Kind::StringValue("alice".to_string())),
+                },
+            )]
+            .into(),
+        };
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new().with_property(
+            Path::from_parts([
+                "metadata",
+                "filter_metadata",
+                "envoy.filters.http.ext_authz",
+            ]),
+            metadata.encode_to_vec(),
+        )));
+        assert_eq!(
+            dynamic_metadata(&ctx, "envoy.filters.http.ext_authz"),
+            Ok(AttributeState::Available(Some(metadata)))
+        );
+        assert_eq!(
+            dynamic_metadata(&ctx, "ext_authz"),
+            Ok(AttributeState::Available(None))
+        );
+    }
+
+    #[test]
+    fn path_from_parts_escapes_dots() {
+        let path = Path::from_parts(["filter_state", "wasm.kuadrant.user"]);
+        assert_eq!(path.to_string(), "filter_state.wasm\\.kuadrant\\.user");
+        assert_eq!(
+            path.segments().collect::<Vec<_>>(),
+            vec!["filter_state", "wasm.kuadrant.user"]
+        );
+        assert_eq!(Path::from(path.to_string().as_str()), path);
+    }
+
+    #[test]
+    fn empty_path_has_no_segments() {
+        let path = Path::from_parts(Vec::<String>::new());
+        assert_eq!(path.to_string(), "");
+        assert_eq!(path.segments().count(), 0);
+        assert_eq!("".parse::<Path>().unwrap(), path);
+        assert_eq!(Path::from_parts([""]), path);
+    }
+
+    #[test]
+    fn path_keeps_unicode_and_backslashes() {
+        let path = Path::from_parts(["métadonnées", "a\\b", "ключ"]);
+        assert_eq!(path.to_string().parse::<Path>().unwrap(), path);
+    }
+
+    #[test]
+    fn parse_unescapes_dots_and_backslashes() {
+        let path = Path::parse("filter_state.kuadrant\\.auth\\.identity.a\\\\b").unwrap();
+        assert_eq!(
+            path.segments().collect::<Vec<_>>(),
+            vec!["filter_state", "kuadrant.auth.identity", "a\\b"]
+        );
+    }
+
+    #[test]
+    fn parse_reports_offset_of_invalid_escape() {
+        assert_eq!(
+            Path::parse("auth.id\\entity"),
+            Err(PathParseError { offset: 7 })
+        );
+        assert_eq!("ключ\\".parse::<Path>(), Err(PathParseError { offset: 8 }));
+        assert_eq!(Path::from("auth.id\\entity").to_string(), "auth.identity");
+    }
+
+    proptest! {
+        #[test]
+        fn path_round_trips_through_display(segments in prop::collection::vec(".*", 1..6)) {
+            let path = Path::from_parts(&segments);
+            let parsed: Path = path.to_string().parse().unwrap();
+            prop_assert_eq!(parsed, path);
+        }
+
+        #[test]
+        fn parse_is_idempotent_over_display(segments in prop::collection::vec("\\PC*", 0..6)) {
+            let rendered = Path::from_parts(&segments).to_string();
+            let reparsed = Path::parse(&rendered).unwrap();
+            prop_assert_eq!(reparsed.to_string(), rendered);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::data::attribute::{AttributeError, AttributeState, Path};
+use crate::data::attribute::{AttributeError, AttributeState, AttributeValue, Path};
 use crate::data::cel::errors::{CelError, EvaluationError};
 use crate::data::grpc::{is_grpc_content_type, parse_grpc_path};
 use crate::data::Headers;
@@ -14,6 +14,8 @@ use std::borrow::Cow;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::ops::{BitAnd, BitOr};
 use std::sync::{Arc, OnceLock};
 #[cfg(feature = "debug-host-behaviour")]
 use tracing::debug;
@@ -43,6 +45,7 @@ pub(crate) mod errors {
     pub enum CelError {
         Property(AttributeError),
         Resolve(ExecutionError),
+        TypeMismatch { expected: String, found: String },
     }
 
     impl Error for CelError {
@@ -50,6 +53,7 @@ pub(crate) mod errors {
             match self {
                 CelError::Property(err) => Some(err),
                 CelError::Resolve(err) => Some(err),
+                CelError::TypeMismatch { .. } => None,
             }
         }
     }
@@ -75,6 +79,12 @@ pub(crate) mod errors {
                 CelError::Resolve(e) => {
                     write!(f, "CelError::Resolve {{ {e:?} }}")
                 }
+                CelError::TypeMismatch { expected, found } => {
+                    write!(
+                        f,
+                        "CelError::TypeMismatch {{ expected: {expected}, found: {found} }}"
+                    )
+                }
             }
         }
     }
@@ -92,8 +102,9 @@ pub(crate) mod errors {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
             write!(
                 f,
-                "EvaluationError {{ expression: {:?}, message: {} }}",
-                self.expression, self.message
+                "EvaluationError {{ expression: {}, message: {} }}",
+                self.expression.to_cel_string(),
+                self.message
             )
         }
     }
@@ -149,6 +160,9 @@ fn is_host_property_root(root: &str) -> bool {
     HOST_PROPERTY_ROOTS.contains(&root)
 }
 
+/// Roots the filter resolves itself from host properties, such as `kuadrant.tls`
+const FILTER_PROPERTY_ROOT: &str = "kuadrant";
+
 impl Expression {
     pub fn new_expression(expression: &str, extended: bool) -> Result<Self, ParseErrors> {
         let source = expression.to_string();
@@ -200,6 +214,12 @@ impl Expression {
         Self::new_expression(expression, false)
     }
 
+    /// The expression in a canonical form, whatever the spacing and
+    /// parentheses of the source it was parsed from
+    pub fn to_cel_string(&self) -> String {
+        printer::to_cel_string(&self.expression)
+    }
+
     #[cfg(test)]
     pub fn new_extended(expression: &str) -> Result<Self, ParseErrors> {
         Self::new_expression(expression, true)
@@ -217,9 +237,9 @@ impl Expression {
             .iter()
             .filter(|attr| {
                 attr.path
-                    .tokens()
-                    .first()
-                    .is_some_and(|root| is_host_property_root(root))
+                    .segments()
+                    .next()
+                    .is_some_and(is_host_property_root)
             })
             .map(|attr| attr.path.clone())
             .collect();
@@ -307,6 +327,31 @@ impl Expression {
         Ok(AttributeState::Available(result))
     }
 
+    /// Evaluates the expression into a `T`, read back through [`AttributeValue::parse`]
+    /// as if the host held the result. A `null` result is `None`.
+    pub fn evaluate_typed<T: AttributeValue>(
+        &self,
+        req_ctx: &ReqRespCtx,
+        cel_ctx: &mut Context<'_>,
+    ) -> Result<AttributeState<Option<T>>, CelError> {
+        let value = match self.eval(req_ctx, cel_ctx)? {
+            AttributeState::Pending => return Ok(AttributeState::Pending),
+            AttributeState::Available(Value::Null) => return Ok(AttributeState::Available(None)),
+            AttributeState::Available(value) => value,
+        };
+        let found = value.type_of();
+        let bytes = match (T::cel_type(), host_bytes(&value)) {
+            (Some(expected), Some(bytes)) if expected.to_string() == found.to_string() => bytes,
+            (expected, _) => {
+                return Err(CelError::TypeMismatch {
+                    expected: expected.map_or("unsupported".to_string(), |t| t.to_string()),
+                    found: found.to_string(),
+                })
+            }
+        };
+        Ok(AttributeState::Available(Some(T::parse(bytes)?)))
+    }
+
     /// Add support for `queryMap`, see [`decode_query_string`]
     fn add_extended_capabilities(ctx: &mut Context) {
         ctx.add_function("queryMap", decode_query_string);
@@ -316,10 +361,18 @@ impl Expression {
         data::AttributeMap::new(self.attributes.clone()).into(req_ctx)
     }
 
+    pub fn request_body_values(&self) -> &[String] {
+        &self.request_body_values
+    }
+
     pub fn response_body_values(&self) -> &[String] {
         &self.response_body_values
     }
 
+    pub fn attribute_paths(&self) -> impl Iterator<Item = &Path> {
+        self.attributes.iter().map(|attr| &attr.path)
+    }
+
     // todo(@adam-cattermole): Temporary method for legacy config translation, remove when legacy support is dropped
     pub fn source(&self) -> &str {
         &self.source
@@ -491,6 +544,27 @@ fn decode_query_string(This(s): This<Arc<String>>, Arguments(args): Arguments) -
     Ok(map.into())
 }
 
+/// The CEL literal of `json`, typed as the request body values are read, see
+/// `RequestBodyTask`: non-negative integers are `uint`s.
+fn cel_literal(json: &JsonValue) -> String {
+    match json {
+        JsonValue::Number(n) if n.is_u64() => format!("{n}u"),
+        JsonValue::Array(items) => format!(
+            "[{}]",
+            items.iter().map(cel_literal).collect::<Vec<_>>().join(", ")
+        ),
+        JsonValue::Object(fields) => format!(
+            "{{{}}}",
+            fields
+                .iter()
+                .map(|(k, v)| format!("{}: {}", JsonValue::String(k.clone()), cel_literal(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => json.to_string(),
+    }
+}
+
 fn add_string_extensions(ctx: &mut Context<'_>) {
     ctx.add_function("charAt", strings::char_at);
     ctx.add_function("indexOf", strings::index_of);
@@ -502,13 +576,67 @@ fn add_string_extensions(ctx: &mut Context<'_>) {
     ctx.add_function("replace", strings::replace);
     ctx.add_function("split", strings::split);
     ctx.add_function("substring", strings::substring);
+    ctx.add_function("normalizePath", strings::normalize_path);
+    ctx.add_function("kuadrant.base64decode", encoding::base64_decode);
+    ctx.add_function("kuadrant.base64encode", encoding::base64_encode);
 }
 
+pub mod encoding;
+mod printer;
 pub mod strings;
 
+/// The bytes the host would hold for a value of a scalar CEL type
+fn host_bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::String(s) => Some(s.as_bytes().to_vec()),
+        Value::Int(i) => Some(i.to_le_bytes().to_vec()),
+        Value::UInt(u) => Some(u.to_le_bytes().to_vec()),
+        Value::Float(f) => Some(f.to_le_bytes().to_vec()),
+        Value::Bool(b) => Some(vec![u8::from(*b)]),
+        Value::Bytes(b) => Some(b.to_vec()),
+        Value::Timestamp(t) => t.timestamp_nanos_opt().map(|n| n.to_le_bytes().to_vec()),
+        _ => None,
+    }
+}
+
+/// An [`Expression`] known to evaluate to a `T`, see [`Expression::evaluate_typed`]
 #[derive(Clone, Debug, PartialEq)]
-pub struct Predicate {
+pub struct TypedExpression<T> {
     expression: Expression,
+    value_type: PhantomData<T>,
+}
+
+pub type BoolExpression = TypedExpression<bool>;
+
+impl<T> From<Expression> for TypedExpression<T> {
+    fn from(expression: Expression) -> Self {
+        Self {
+            expression,
+            value_type: PhantomData,
+        }
+    }
+}
+
+impl<T: AttributeValue> TypedExpression<T> {
+    pub fn evaluate(
+        &self,
+        req_ctx: &ReqRespCtx,
+        cel_ctx: &mut Context<'_>,
+    ) -> Result<AttributeState<Option<T>>, CelError> {
+        self.expression.evaluate_typed(req_ctx, cel_ctx)
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Predicate {
+    expression: BoolExpression,
+    /// Set by [`Predicate::from_json_pointer`], whose request body field tests
+    /// false rather than pending once the body is complete without it
+    missing_body_field_is_false: bool,
 }
 
 pub type PredicateResult = Result<AttributeState<bool>, EvaluationError>;
@@ -516,7 +644,34 @@ pub type PredicateResult = Result<AttributeState<bool>, EvaluationError>;
 impl Predicate {
     pub fn new(predicate: &str) -> Result<Self, ParseErrors> {
         Ok(Self {
-            expression: Expression::new(predicate)?,
+            expression: Expression::new(predicate)?.into(),
+            missing_body_field_is_false: false,
+        })
+    }
+
+    /// Whether the request body, read as JSON, holds `expected` at `pointer`.
+    /// A body that is not JSON, or lacks the field, tests false.
+    pub fn from_json_pointer(pointer: &str, expected: JsonValue) -> Result<Self, ParseErrors> {
+        let pointer = JsonValue::String(pointer.to_string());
+        Ok(Self {
+            expression: Expression::new(&format!(
+                "{REQUEST_BODY_JSON_FN}({pointer}) == {}",
+                cel_literal(&expected)
+            ))?
+            .into(),
+            missing_body_field_is_false: true,
+        })
+    }
+
+    /// This predicate, holding only where `predicate` holds too
+    pub fn requiring(self, predicate: &str) -> Result<Self, ParseErrors> {
+        Ok(Self {
+            expression: Expression::new(&format!(
+                "({predicate}) && ({})",
+                self.expression().source
+            ))?
+            .into(),
+            missing_body_field_is_false: self.missing_body_field_is_false,
         })
     }
 
@@ -526,7 +681,8 @@ impl Predicate {
     #[cfg(test)]
     pub fn route_rule(predicate: &str) -> Result<Self, ParseErrors> {
         Ok(Self {
-            expression: Expression::new_extended(predicate)?,
+            expression: Expression::new_extended(predicate)?.into(),
+            missing_body_field_is_false: false,
         })
     }
 
@@ -540,24 +696,111 @@ impl Predicate {
         req_ctx: &ReqRespCtx,
         cel_ctx: &mut Context<'_>,
     ) -> PredicateResult {
-        match self.expression.eval(req_ctx, cel_ctx) {
+        match self.expression.evaluate(req_ctx, cel_ctx) {
+            Ok(AttributeState::Pending)
+                if self.missing_body_field_is_false && req_ctx.is_request_end_of_stream() =>
+            {
+                Ok(AttributeState::Available(false))
+            }
             Ok(AttributeState::Pending) => Ok(AttributeState::Pending),
-            Ok(AttributeState::Available(value)) => match value {
-                Value::Bool(result) => Ok(AttributeState::Available(result)),
-                _ => Err(EvaluationError::new(
-                    self.expression.clone(),
-                    format!("Expected boolean value, got {value:?}"),
-                )),
-            },
+            Ok(AttributeState::Available(Some(result))) => Ok(AttributeState::Available(result)),
+            Ok(AttributeState::Available(None)) => Err(EvaluationError::new(
+                self.expression().clone(),
+                "Expected boolean value, got null".to_string(),
+            )),
             Err(err) => Err(EvaluationError::new(
-                self.expression.clone(),
+                self.expression().clone(),
                 err.to_string(),
             )),
         }
     }
 
-    pub fn response_body_values(&self) -> &[String] {
-        self.expression.response_body_values()
+    /// Resolves the predicate without any request data, so that errors that do
+    /// not depend on it, e.g. mismatched operand types or a non-boolean result,
+    /// surface when the configuration is loaded instead of on every request.
+    /// References to request data are left for [`Predicate::test`] to resolve.
+    pub fn compile_check(&self) -> Result<(), CelError> {
+        let mut cel_ctx = Context::default();
+        add_string_extensions(&mut cel_ctx);
+        if self.expression().extended {
+            Expression::add_extended_capabilities(&mut cel_ctx)
+        }
+        match Value::resolve(&self.expression().expression, &cel_ctx) {
+            Ok(Value::Bool(_)) | Err(ExecutionError::UndeclaredReference(_)) => Ok(()),
+            Ok(value) => Err(ExecutionError::UnexpectedType {
+                got: format!("{value:?}"),
+                want: "bool".to_string(),
+            }
+            .into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn expression(&self) -> &Expression {
+        self.expression.expression()
+    }
+}
+
+/// [`Predicate`]s combined with `&` and `|`. The left-hand side is tested first
+/// and the right-hand side is only tested when the left one leaves the outcome open.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompoundPredicate {
+    Single(Predicate),
+    And(Box<CompoundPredicate>, Box<CompoundPredicate>),
+    Or(Box<CompoundPredicate>, Box<CompoundPredicate>),
+}
+
+impl CompoundPredicate {
+    pub fn apply(&self, req_ctx: &ReqRespCtx) -> PredicateResult {
+        match self {
+            CompoundPredicate::Single(predicate) => predicate.test(req_ctx),
+            CompoundPredicate::And(left, right) => match left.apply(req_ctx)? {
+                AttributeState::Available(true) => right.apply(req_ctx),
+                outcome => Ok(outcome),
+            },
+            CompoundPredicate::Or(left, right) => match left.apply(req_ctx)? {
+                AttributeState::Available(false) => right.apply(req_ctx),
+                outcome => Ok(outcome),
+            },
+        }
+    }
+}
+
+impl From<Predicate> for CompoundPredicate {
+    fn from(predicate: Predicate) -> Self {
+        CompoundPredicate::Single(predicate)
+    }
+}
+
+impl<R: Into<CompoundPredicate>> BitAnd<R> for Predicate {
+    type Output = CompoundPredicate;
+
+    fn bitand(self, rhs: R) -> CompoundPredicate {
+        CompoundPredicate::from(self) & rhs
+    }
+}
+
+impl<R: Into<CompoundPredicate>> BitOr<R> for Predicate {
+    type Output = CompoundPredicate;
+
+    fn bitor(self, rhs: R) -> CompoundPredicate {
+        CompoundPredicate::from(self) | rhs
+    }
+}
+
+impl<R: Into<CompoundPredicate>> BitAnd<R> for CompoundPredicate {
+    type Output = CompoundPredicate;
+
+    fn bitand(self, rhs: R) -> CompoundPredicate {
+        CompoundPredicate::And(Box::new(self), Box::new(rhs.into()))
+    }
+}
+
+impl<R: Into<CompoundPredicate>> BitOr<R> for CompoundPredicate {
+    type Output = CompoundPredicate;
+
+    fn bitor(self, rhs: R) -> CompoundPredicate {
+        CompoundPredicate::Or(Box::new(self), Box::new(rhs.into()))
     }
 }
 
@@ -573,12 +816,12 @@ impl PredicateVec for Vec<Predicate> {
 
         let paths: Vec<Path> = self
             .iter()
-            .flat_map(|p| &p.expression.attributes)
+            .flat_map(|p| &p.expression().attributes)
             .filter(|attr| {
                 attr.path
-                    .tokens()
-                    .first()
-                    .is_some_and(|root| is_host_property_root(root))
+                    .segments()
+                    .next()
+                    .is_some_and(is_host_property_root)
             })
             .map(|attr| attr.path.clone())
             .collect();
@@ -600,6 +843,38 @@ impl PredicateVec for Vec<Predicate> {
     }
 }
 
+impl PredicateVec for Vec<CompoundPredicate> {
+    fn apply(&self, req_ctx: &ReqRespCtx) -> PredicateResult {
+        // Only prefetch for the predicates always tested, the operands of a
+        // composition must not be resolved unless they are reached
+        let paths: Vec<Path> = self
+            .iter()
+            .filter_map(|p| match p {
+                CompoundPredicate::Single(predicate) => Some(predicate),
+                _ => None,
+            })
+            .flat_map(|p| &p.expression().attributes)
+            .filter(|attr| {
+                attr.path
+                    .segments()
+                    .next()
+                    .is_some_and(is_host_property_root)
+            })
+            .map(|attr| attr.path.clone())
+            .collect();
+        req_ctx.ensure_attributes(&paths);
+
+        for predicate in self.iter() {
+            match predicate.apply(req_ctx)? {
+                AttributeState::Available(true) => continue,
+                outcome => return Ok(outcome),
+            }
+        }
+
+        Ok(AttributeState::Available(true))
+    }
+}
+
 pub struct Attribute {
     path: Path,
     cel_type: Option<ValueType>,
@@ -801,9 +1076,12 @@ fn new_well_known_attribute_map() -> HashMap<Path, ValueType> {
             ValueType::String,
         ),
         ("ratelimit.domain".into(), ValueType::String),
+        ("kuadrant.client_ip".into(), ValueType::String),
+        ("kuadrant.trace_id".into(), ValueType::String),
         ("connection.id".into(), ValueType::UInt),
         ("ratelimit.hits_addend".into(), ValueType::Int),
         ("request.headers".into(), ValueType::Map),
+        ("request.trailers".into(), ValueType::Map),
         ("request.context_extensions".into(), ValueType::Map),
         ("source.labels".into(), ValueType::Map),
         ("destination.labels".into(), ValueType::Map),
@@ -911,7 +1189,7 @@ pub fn debug_all_well_known_attributes() {
 
 pub mod data {
     use crate::data::attribute::{AttributeError, AttributeState};
-    use crate::data::cel::{is_host_property_root, Attribute};
+    use crate::data::cel::{is_host_property_root, Attribute, FILTER_PROPERTY_ROOT};
     use crate::kuadrant::ReqRespCtx;
     use cel::objects::{Key, Map};
     use cel::Value;
@@ -973,13 +1251,23 @@ pub mod data {
                 format!("{}.{}", path_prefix, key)
             };
 
+            match req_ctx.get_computed_value(&current_path)? {
+                Some(AttributeState::Available(computed_val)) => {
+                    out.insert(key.into(), computed_val);
+                    continue;
+                }
+                Some(AttributeState::Pending) => return Ok(AttributeState::Pending),
+                None => {}
+            }
             if let Some(stored_val) = req_ctx.get_stored_value(&current_path) {
                 out.insert(key.into(), stored_val.clone());
                 continue;
             }
             if path_prefix.is_empty()
                 && !is_host_property_root(&key)
+                && key != FILTER_PROPERTY_ROOT
                 && !req_ctx.has_stored_prefix(&format!("{}.", key))
+                && !req_ctx.has_computed_prefix(&format!("{}.", key))
             {
                 continue;
             }
@@ -1079,7 +1367,10 @@ pub mod data {
 #[cfg(test)]
 mod tests {
     use crate::data::attribute::AttributeState;
-    use crate::data::cel::{known_attribute_for, Expression, Predicate};
+    use crate::data::cel::errors::CelError;
+    use crate::data::cel::{
+        known_attribute_for, BoolExpression, Expression, Predicate, TypedExpression,
+    };
     use crate::kuadrant::MockWasmHost;
     use crate::kuadrant::ReqRespCtx;
     use cel::objects::ValueType;
@@ -1099,6 +1390,209 @@ mod tests {
         );
     }
 
+    #[test]
+    fn predicates_read_tls_peer_certificate() {
+        let mock_host = MockWasmHost::new()
+            .with_property(
+                "connection.subject_peer_certificate".into(),
+                b"CN=client.example.com,O=Acme\\, Inc.".to_vec(),
+            )
+            .with_property(
+                "connection.uri_san_peer_certificate".into(),
+                b"spiffe://acme/client".to_vec(),
+            );
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+        let predicate = Predicate::new(
+            "kuadrant.tls.common_name == 'client.example.com' && kuadrant.tls.uri_san.startsWith('spiffe://')",
+        )
+        .expect("This is valid CEL!");
+        assert_eq!(
+            predicate.test(&ctx).expect("This must evaluate properly!"),
+            AttributeState::Available(true)
+        );
+
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let predicate =
+            Predicate::new("kuadrant.tls.common_name == null").expect("This is valid CEL!");
+        assert_eq!(
+            predicate.test(&ctx).expect("This must evaluate properly!"),
+            AttributeState::Available(true)
+        );
+    }
+
+    #[test]
+    fn predicates_read_trace_id() {
+        let mock_host = MockWasmHost::new().with_map(
+            "request.headers".to_string(),
+            vec![(
+                "traceparent".to_string(),
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string(),
+            )],
+        );
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+        let predicate = Predicate::new("kuadrant.trace_id == '0af7651916cd43dd8448eb211c80319c'")
+            .expect("This is valid CEL!");
+        assert_eq!(
+            predicate.test(&ctx).expect("This must evaluate properly!"),
+            AttributeState::Available(true)
+        );
+
+        let mock_host = MockWasmHost::new().with_map("request.headers".to_string(), vec![]);
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+        let predicate = Predicate::new("kuadrant.trace_id == null").expect("This is valid CEL!");
+        assert_eq!(
+            predicate.test(&ctx).expect("This must evaluate properly!"),
+            AttributeState::Available(true)
+        );
+    }
+
+    #[test]
+    fn predicates_read_client_ip() {
+        let mock_host = MockWasmHost::new()
+            .with_map(
+                "request.headers".to_string(),
+                vec![(
+                    "x-forwarded-for".to_string(),
+                    "[2001:db8::1]:443, 10.0.0.1".to_string(),
+                )],
+            )
+            .with_property("source.address".into(), b"10.0.0.2:51234".to_vec());
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+        let predicate =
+            Predicate::new("kuadrant.client_ip == '2001:db8::1'").expect("This is valid CEL!");
+        assert_eq!(
+            predicate.test(&ctx).expect("This must evaluate properly!"),
+            AttributeState::Available(true)
+        );
+
+        let mock_host =
+            MockWasmHost::new().with_property("source.address".into(), b"10.0.0.2:51234".to_vec());
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+        let predicate =
+            Predicate::new("kuadrant.client_ip == '10.0.0.2'").expect("This is valid CEL!");
+        assert_eq!(
+            predicate.test(&ctx).expect("This must evaluate properly!"),
+            AttributeState::Available(true)
+        );
+    }
+
+    #[test]
+    fn compile_check_rejects_static_errors() {
+        for invalid in ["1 + 'a' == 2", "'foo'", "[1, 2].size()"] {
+            let predicate = Predicate::new(invalid).expect("This is valid CEL syntax!");
+            assert!(predicate.compile_check().is_err(), "{invalid} was accepted");
+        }
+        for valid in [
+            "true",
+            "request.method == 'GET'",
+            "'abc'.startsWith('a')",
+            "auth.identity.anonymous",
+        ] {
+            let predicate = Predicate::new(valid).expect("This is valid CEL!");
+            assert!(predicate.compile_check().is_ok(), "{valid} was rejected");
+        }
+    }
+
+    #[test]
+    fn evaluate_typed_reads_scalar_results() {
+        let mock_host = MockWasmHost::new()
+            .with_property("source.port".into(), 65432_i64.to_le_bytes().to_vec());
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+        let mut cel_ctx = cel::Context::default();
+
+        let port: TypedExpression<i64> = Expression::new("source.port + 1").unwrap().into();
+        assert_eq!(
+            port.evaluate(&ctx, &mut cel_ctx).unwrap(),
+            AttributeState::Available(Some(65433))
+        );
+        let name: TypedExpression<String> = Expression::new("'ku' + 'adrant'").unwrap().into();
+        assert_eq!(
+            name.evaluate(&ctx, &mut cel_ctx).unwrap(),
+            AttributeState::Available(Some("kuadrant".to_string()))
+        );
+        let flag: BoolExpression = Expression::new("source.port > 1024").unwrap().into();
+        assert_eq!(
+            flag.evaluate(&ctx, &mut cel_ctx).unwrap(),
+            AttributeState::Available(Some(true))
+        );
+        assert_eq!(
+            Expression::new("null")
+                .unwrap()
+                .evaluate_typed::<String>(&ctx, &mut cel_ctx)
+                .unwrap(),
+            AttributeState::Available(None)
+        );
+    }
+
+    #[test]
+    fn evaluation_errors_print_the_expression() {
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let predicate = Predicate::new("(1+ 2)").expect("This is valid CEL!");
+        let error = predicate.test(&ctx).expect_err("a non-boolean result");
+        assert!(
+            error
+                .to_string()
+                .starts_with("EvaluationError { expression: 1 + 2, message: "),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn evaluate_typed_rejects_type_mismatch() {
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let mut cel_ctx = cel::Context::default();
+
+        let expression = Expression::new("'42'").unwrap();
+        assert_eq!(
+            expression.evaluate_typed::<i64>(&ctx, &mut cel_ctx),
+            Err(CelError::TypeMismatch {
+                expected: "int".to_string(),
+                found: "string".to_string(),
+            })
+        );
+        let expression = Expression::new("42u").unwrap();
+        assert!(matches!(
+            expression.evaluate_typed::<i64>(&ctx, &mut cel_ctx),
+            Err(CelError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn compound_predicates_short_circuit() {
+        let mock_host = MockWasmHost::new()
+            .with_property("source.port".into(), 65432_i64.to_le_bytes().to_vec())
+            .with_pending_property("destination.port".into());
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+        let matching = || Predicate::new("source.port == 65432").expect("This is valid CEL!");
+        let failing = || Predicate::new("source.port == 1").expect("This is valid CEL!");
+        let pending = || Predicate::new("destination.port == 80").expect("This is valid CEL!");
+
+        // The pending right-hand side is never resolved once the outcome is known
+        assert_eq!(
+            (failing() & pending()).apply(&ctx).unwrap(),
+            AttributeState::Available(false)
+        );
+        assert_eq!(
+            (matching() | pending()).apply(&ctx).unwrap(),
+            AttributeState::Available(true)
+        );
+
+        assert_eq!(
+            (matching() & pending()).apply(&ctx).unwrap(),
+            AttributeState::Pending
+        );
+        assert_eq!(
+            (failing() | pending()).apply(&ctx).unwrap(),
+            AttributeState::Pending
+        );
+        assert_eq!(
+            (failing() | failing() | matching() & matching())
+                .apply(&ctx)
+                .unwrap(),
+            AttributeState::Available(true)
+        );
+    }
+
     #[test]
     fn expressions_sort_properties() {
         let value = Expression::new(
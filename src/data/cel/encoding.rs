@@ -0,0 +1,117 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use cel::{ExecutionError, ResolveResult, Value};
+use std::sync::Arc;
+
+/// Decodes standard or URL-safe base64, with or without padding
+pub fn base64_decode(encoded: Arc<String>) -> ResolveResult {
+    let unpadded = encoded.trim_end_matches('=');
+    let engine = if unpadded.contains(['-', '_']) {
+        URL_SAFE_NO_PAD
+    } else {
+        STANDARD_NO_PAD
+    };
+    match engine.decode(unpadded) {
+        Ok(bytes) => Ok(Value::Bytes(bytes.into())),
+        Err(e) => Err(ExecutionError::FunctionError {
+            function: "kuadrant.base64decode".to_owned(),
+            message: format!("Invalid base64 `{encoded}`: {e}"),
+        }),
+    }
+}
+
+/// Encodes to standard, padded, base64
+pub fn base64_encode(bytes: Arc<Vec<u8>>) -> ResolveResult {
+    Ok(STANDARD.encode(bytes.as_slice()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::data::attribute::AttributeState;
+    use crate::data::cel::{EvalResult, Expression, Predicate};
+    use crate::kuadrant::{MockWasmHost, ReqRespCtx};
+    use cel::Value;
+
+    fn eval(expression: &str) -> EvalResult {
+        let req_ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let mut cel_ctx = cel::Context::default();
+        Expression::new(expression)
+            .expect("This must be valid CEL")
+            .eval(&req_ctx, &mut cel_ctx)
+    }
+
+    fn bytes(b: &[u8]) -> Value {
+        Value::Bytes(b.to_vec().into())
+    }
+
+    #[test]
+    fn decodes_standard_base64() {
+        // RFC 4648, section 10
+        for (encoded, decoded) in [
+            ("", ""),
+            ("Zg==", "f"),
+            ("Zm8=", "fo"),
+            ("Zm9v", "foo"),
+            ("Zm9vYg==", "foob"),
+            ("Zm9vYmE=", "fooba"),
+            ("Zm9vYmFy", "foobar"),
+            ("Zm9vYg", "foob"),
+        ] {
+            assert_eq!(
+                eval(&format!("kuadrant.base64decode('{encoded}')")),
+                Ok(AttributeState::Available(bytes(decoded.as_bytes()))),
+                "{encoded:?}"
+            );
+        }
+        assert_eq!(
+            eval("kuadrant.base64decode('+/8=')"),
+            Ok(AttributeState::Available(bytes(&[0xfb, 0xff])))
+        );
+    }
+
+    #[test]
+    fn decodes_url_safe_base64() {
+        assert_eq!(
+            eval("kuadrant.base64decode('-_8=')"),
+            Ok(AttributeState::Available(bytes(&[0xfb, 0xff])))
+        );
+        assert_eq!(
+            eval("kuadrant.base64decode('-_8')"),
+            Ok(AttributeState::Available(bytes(&[0xfb, 0xff])))
+        );
+    }
+
+    #[test]
+    fn invalid_base64_is_an_error() {
+        assert!(eval("kuadrant.base64decode('Zm9v!')").is_err());
+        assert!(eval("kuadrant.base64decode('+_8=')").is_err());
+        assert!(eval("kuadrant.base64decode('Z')").is_err());
+    }
+
+    #[test]
+    fn encodes_bytes() {
+        assert_eq!(
+            eval("kuadrant.base64encode(b'foobar')"),
+            Ok(AttributeState::Available("Zm9vYmFy".into()))
+        );
+        assert_eq!(
+            eval("kuadrant.base64encode(kuadrant.base64decode('-_8'))"),
+            Ok(AttributeState::Available("+/8=".into()))
+        );
+    }
+
+    #[test]
+    fn available_to_predicates() {
+        let req_ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let predicate = Predicate::new("kuadrant.base64decode('YWRtaW4=') == b'admin'")
+            .expect("This is valid CEL!");
+        assert_eq!(
+            predicate
+                .test(&req_ctx)
+                .expect("This must evaluate properly!"),
+            AttributeState::Available(true)
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use cel::common::ast::{CallExpr, ComprehensionExpr, EntryExpr, Expr, IdedExpr, LiteralValue};
+
+/// Renders a parsed expression back to CEL source, with single spaces around
+/// binary operators and only the parentheses its structure needs. Parsing the
+/// result yields the same AST.
+pub fn to_cel_string(expr: &IdedExpr) -> String {
+    let mut out = String::new();
+    write_expr(&mut out, expr);
+    out
+}
+
+const TERNARY: u8 = 8;
+const UNARY: u8 = 2;
+
+/// How loosely an operator binds, a higher value binding looser; `0` for
+/// anything that never needs parentheses.
+fn operator_precedence(func_name: &str) -> u8 {
+    match func_name {
+        "_?_:_" => TERNARY,
+        "_||_" => 7,
+        "_&&_" => 6,
+        "_==_" | "_!=_" | "_<_" | "_<=_" | "_>_" | "_>=_" | "@in" => 5,
+        "_+_" | "_-_" => 4,
+        "_*_" | "_/_" | "_%_" => 3,
+        "!_" | "-_" => UNARY,
+        _ => 0,
+    }
+}
+
+fn precedence(expr: &IdedExpr) -> u8 {
+    match &expr.expr {
+        Expr::Call(call) if call.target.is_none() => operator_precedence(&call.func_name),
+        Expr::Literal(LiteralValue::Int(i)) if *i < 0 => UNARY,
+        Expr::Literal(LiteralValue::Double(d)) if d.is_sign_negative() => UNARY,
+        _ => 0,
+    }
+}
+
+fn binary_operator(func_name: &str) -> Option<&'static str> {
+    Some(match func_name {
+        "_||_" => "||",
+        "_&&_" => "&&",
+        "_==_" => "==",
+        "_!=_" => "!=",
+        "_<_" => "<",
+        "_<=_" => "<=",
+        "_>_" => ">",
+        "_>=_" => ">=",
+        "@in" => "in",
+        "_+_" => "+",
+        "_-_" => "-",
+        "_*_" => "*",
+        "_/_" => "/",
+        "_%_" => "%",
+        _ => return None,
+    })
+}
+
+fn write_nested(out: &mut String, expr: &IdedExpr, parenthesize: bool) {
+    if parenthesize {
+        out.push('(');
+        write_expr(out, expr);
+        out.push(')');
+    } else {
+        write_expr(out, expr);
+    }
+}
+
+fn write_list<'e>(out: &mut String, exprs: impl IntoIterator<Item = &'e IdedExpr>) {
+    for (i, expr) in exprs.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_expr(out, expr);
+    }
+}
+
+fn write_entries<'e>(out: &mut String, entries: impl Iterator<Item = &'e EntryExpr>) {
+    out.push('{');
+    for (i, entry) in entries.enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        match entry {
+            EntryExpr::MapEntry(map_entry) => {
+                write_expr(out, &map_entry.key);
+                out.push_str(": ");
+                write_expr(out, &map_entry.value);
+            }
+            EntryExpr::StructField(field) => {
+                out.push_str(&field.field);
+                out.push_str(": ");
+                write_expr(out, &field.value);
+            }
+        }
+    }
+    out.push('}');
+}
+
+fn write_expr(out: &mut String, expr: &IdedExpr) {
+    match &expr.expr {
+        Expr::Call(call) => write_call(out, call),
+        Expr::Comprehension(comp) => write_comprehension(out, comp),
+        Expr::Ident(name) => out.push_str(name),
+        Expr::List(list) => {
+            out.push('[');
+            write_list(out, &list.elements);
+            out.push(']');
+        }
+        Expr::Literal(literal) => write_literal(out, literal),
+        Expr::Map(map) => write_entries(out, map.entries.iter().map(|entry| &entry.expr)),
+        Expr::Select(select) => {
+            if select.test {
+                out.push_str("has(");
+            }
+            write_nested(out, &select.operand, precedence(&select.operand) >= UNARY);
+            out.push('.');
+            out.push_str(&select.field);
+            if select.test {
+                out.push(')');
+            }
+        }
+        Expr::Struct(struct_expr) => {
+            out.push_str(&struct_expr.type_name);
+            write_entries(out, struct_expr.entries.iter().map(|entry| &entry.expr));
+        }
+        Expr::Unspecified => {}
+    }
+}
+
+fn write_call(out: &mut String, call: &CallExpr) {
+    let target = call.target.as_deref();
+    if let (None, Some(operator), [lhs, rhs]) = (
+        target,
+        binary_operator(&call.func_name),
+        call.args.as_slice(),
+    ) {
+        let binding = operator_precedence(&call.func_name);
+        write_nested(out, lhs, precedence(lhs) > binding);
+        out.push(' ');
+        out.push_str(operator);
+        out.push(' ');
+        write_nested(out, rhs, precedence(rhs) >= binding);
+        return;
+    }
+    match (target, call.func_name.as_str(), call.args.as_slice()) {
+        (None, "_?_:_", [condition, then, otherwise]) => {
+            write_nested(out, condition, precedence(condition) >= TERNARY);
+            out.push_str(" ? ");
+            write_nested(out, then, precedence(then) >= TERNARY);
+            out.push_str(" : ");
+            write_nested(out, otherwise, precedence(otherwise) >= TERNARY);
+        }
+        (None, "!_" | "-_", [operand]) => {
+            out.push_str(if call.func_name == "!_" { "!" } else { "-" });
+            write_nested(out, operand, precedence(operand) >= UNARY);
+        }
+        (None, "_[_]" | "_[?_]", [operand, index]) => {
+            write_nested(out, operand, precedence(operand) >= UNARY);
+            out.push_str(if call.func_name == "_[_]" { "[" } else { "[?" });
+            write_expr(out, index);
+            out.push(']');
+        }
+        (None, "_?._", [operand, field])
+            if matches!(field.expr, Expr::Literal(LiteralValue::String(_))) =>
+        {
+            write_nested(out, operand, precedence(operand) >= UNARY);
+            out.push_str(".?");
+            if let Expr::Literal(LiteralValue::String(field)) = &field.expr {
+                out.push_str(field.as_str());
+            }
+        }
+        (target, func_name, args) => {
+            if let Some(target) = target {
+                write_nested(out, target, precedence(target) >= UNARY);
+                out.push('.');
+            }
+            out.push_str(func_name);
+            out.push('(');
+            write_list(out, args);
+            out.push(')');
+        }
+    }
+}
+
+fn call_args<'e>(expr: &'e IdedExpr, func_name: &str) -> Option<&'e [IdedExpr]> {
+    match &expr.expr {
+        Expr::Call(call) if call.target.is_none() && call.func_name == func_name => {
+            Some(&call.args)
+        }
+        _ => None,
+    }
+}
+
+fn is_ident(expr: &IdedExpr, name: &str) -> bool {
+    matches!(&expr.expr, Expr::Ident(ident) if ident == name)
+}
+
+/// The element of `accu + [element]`
+fn appended<'e>(expr: &'e IdedExpr, accu: &str) -> Option<&'e IdedExpr> {
+    match call_args(expr, "_+_")? {
+        [lhs, rhs] if is_ident(lhs, accu) => match &rhs.expr {
+            Expr::List(list) if list.elements.len() == 1 => list.elements.first(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The macro, and its arguments after the iteration variable, that the
+/// parser expanded into `comp`
+fn macro_call(comp: &ComprehensionExpr) -> Option<(&'static str, Vec<&IdedExpr>)> {
+    let accu = comp.accu_var.as_str();
+    let step = &comp.loop_step;
+    if let Some([lhs, predicate]) = call_args(step, "_&&_") {
+        return is_ident(lhs, accu).then(|| ("all", vec![predicate]));
+    }
+    if let Some([lhs, predicate]) = call_args(step, "_||_") {
+        return is_ident(lhs, accu).then(|| ("exists", vec![predicate]));
+    }
+    if let Some(element) = appended(step, accu) {
+        return Some(("map", vec![element]));
+    }
+    let [predicate, then, otherwise] = call_args(step, "_?_:_")? else {
+        return None;
+    };
+    if !is_ident(otherwise, accu) {
+        return None;
+    }
+    match appended(then, accu) {
+        Some(element) if is_ident(element, &comp.iter_var) => Some(("filter", vec![predicate])),
+        Some(element) => Some(("map", vec![predicate, element])),
+        None => call_args(then, "_+_").map(|_| ("exists_one", vec![predicate])),
+    }
+}
+
+fn write_comprehension(out: &mut String, comp: &ComprehensionExpr) {
+    match macro_call(comp) {
+        Some((name, args)) => {
+            write_nested(out, &comp.iter_range, precedence(&comp.iter_range) >= UNARY);
+            out.push('.');
+            out.push_str(name);
+            out.push('(');
+            out.push_str(&comp.iter_var);
+            for arg in args {
+                out.push_str(", ");
+                write_expr(out, arg);
+            }
+            out.push(')');
+        }
+        // Not the expansion of a standard macro, so there is no source to
+        // print it as; this is only meant for reading.
+        None => {
+            out.push_str("__comprehension__(");
+            out.push_str(&comp.iter_var);
+            out.push_str(", ");
+            write_expr(out, &comp.iter_range);
+            out.push_str(", ");
+            out.push_str(&comp.accu_var);
+            out.push_str(", ");
+            write_list(
+                out,
+                [
+                    &comp.accu_init,
+                    &comp.loop_cond,
+                    &comp.loop_step,
+                    &comp.result,
+                ],
+            );
+            out.push(')');
+        }
+    }
+}
+
+fn write_literal(out: &mut String, literal: &LiteralValue) {
+    match literal {
+        LiteralValue::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        LiteralValue::Bytes(bytes) => {
+            out.push_str("b\"");
+            for &byte in bytes.iter() {
+                match byte {
+                    b'"' => out.push_str("\\\""),
+                    b'\\' => out.push_str("\\\\"),
+                    0x20..=0x7e => out.push(char::from(byte)),
+                    _ => out.push_str(&format!("\\x{byte:02x}")),
+                }
+            }
+            out.push('"');
+        }
+        LiteralValue::Double(d) => out.push_str(&format!("{d:?}")),
+        LiteralValue::Int(i) => out.push_str(&i.to_string()),
+        LiteralValue::Null => out.push_str("null"),
+        LiteralValue::String(s) => {
+            out.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    '\t' => out.push_str("\\t"),
+                    c if c.is_control() => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+        }
+        LiteralValue::UInt(u) => out.push_str(&format!("{u}u")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::cel::Expression;
+
+    fn round_trip(source: &str) -> String {
+        let parsed = Expression::new(source).expect("This must be valid CEL");
+        let printed = parsed.to_cel_string();
+        let reparsed = Expression::new(&printed)
+            .unwrap_or_else(|e| unreachable!("`{source}` printed as invalid `{printed}`: {e:?}"));
+        assert_eq!(reparsed, parsed, "`{source}` printed as `{printed}`");
+        printed
+    }
+
+    #[test]
+    fn prints_operators_with_the_parentheses_they_need() {
+        assert_eq!(round_trip("a&&(b||c) && d"), "a && (b || c) && d");
+        assert_eq!(round_trip("(1 + 2) * 3 - -x"), "(1 + 2) * 3 - -x");
+        assert_eq!(round_trip("1 - (2 - 3)"), "1 - (2 - 3)");
+        assert_eq!(round_trip("!(a == b) || a in [b]"), "!(a == b) || a in [b]");
+        assert_eq!(round_trip("a ? b : (c ? d : e)"), "a ? b : (c ? d : e)");
+        assert_eq!(round_trip("(a ? b : c) ? d : e"), "(a ? b : c) ? d : e");
+        assert_eq!(round_trip("(-1).string()"), "(-1).string()");
+    }
+
+    #[test]
+    fn prints_nested_calls_and_members() {
+        for source in [
+            "size(request.headers) > 2 && request.method == 'GET'",
+            "request.headers['x-user'].split(',').size() == 2",
+            "request.path.startsWith(\"/api\") || !(source.address in [\"127.0.0.1\"])",
+            "has(request.headers.x) && timestamp(request.time) < now",
+            "requestBodyJSON('/model') == 'gpt-4' && responseBodyJSON('/usage/total_tokens') > 0",
+            "kuadrant.base64decode(auth.identity.token).size()",
+            "envoy.extensions.common.ratelimit.v3.RateLimitDescriptor.Entry { key: \"k\", value: string(1) }",
+        ] {
+            round_trip(source);
+        }
+    }
+
+    #[test]
+    fn prints_literals() {
+        assert_eq!(
+            round_trip(r#"{'a': [1u, 2.5, 1.0, null, true], "b": {}, 3: -4}"#),
+            r#"{"a": [1u, 2.5, 1.0, null, true], "b": {}, 3: -4}"#
+        );
+        assert_eq!(
+            round_trip(r#"'tab\t"quoted" \\ new\nline ünïcode \u0001'"#),
+            r#""tab\t\"quoted\" \\ new\nline ünïcode \u0001""#
+        );
+        assert_eq!(round_trip(r#"b'\x00a\xff"'"#), r#"b"\x00a\xff\"""#);
+    }
+
+    #[test]
+    fn prints_macros() {
+        for source in [
+            "[1, 2].all(x, x > 0) && [1].exists(y, y == 1) && [1].exists_one(z, z == 1)",
+            "[1, 2].map(x, x * 2).filter(y, y > 2)",
+            "[1, 2].map(x, x > 1, x * 2)",
+        ] {
+            assert_eq!(round_trip(source), source);
+        }
+    }
+
+    #[test]
+    fn printing_is_idempotent() {
+        let source = "a  &&(b||c)?{'k':[ 1,2 ]}:  f( x ,y )";
+        let printed = round_trip(source);
+        assert_eq!(round_trip(&printed), printed);
+    }
+}
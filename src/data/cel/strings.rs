@@ -2,6 +2,8 @@ use cel::extractors::{Arguments, This};
 use cel::{ExecutionError, ResolveResult, Value};
 use std::sync::Arc;
 
+use crate::data::path;
+
 pub fn char_at(This(this): This<Arc<String>>, arg: i64) -> ResolveResult {
     match this.chars().nth(arg as usize) {
         None => Err(ExecutionError::FunctionError {
@@ -267,6 +269,11 @@ pub fn substring(This(this): This<Arc<String>>, Arguments(args): Arguments) -> R
     }
 }
 
+/// RFC 3986 normalization of a URL path, see [`path::normalize`]
+pub fn normalize_path(This(this): This<Arc<String>>) -> ResolveResult {
+    Ok(path::normalize(&this).into())
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -474,4 +481,34 @@ mod tests {
             Ok(AttributeState::Available("©o©α".into()))
         );
     }
+
+    #[test]
+    fn normalize_path_blocks_traversal_bypasses() {
+        let req_ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let mut cel_ctx = cel::Context::default();
+
+        for (path, normalized) in [
+            ("/api//v1", "/api/v1"),
+            ("/api/./v1", "/api/v1"),
+            ("/api/v1/../admin", "/api/admin"),
+            ("/api/%2e%2e/admin", "/admin"),
+            ("/api/%2E%2E/%2e/admin", "/admin"),
+            ("/../../etc/passwd", "/"),
+            ("/api/%2e%2e/%2e%2e/%2e%2e/etc/passwd", "/"),
+            ("/api/%2Fadmin", "/api/%2Fadmin"),
+            ("/api/%2fadmin", "/api/%2Fadmin"),
+            ("/%7Euser/%41pi", "/~user/Api"),
+            ("/api/v1/", "/api/v1/"),
+            ("/api/v1/..?q=a/../b", "/api/?q=a/../b"),
+            ("/caf%C3%A9", "/caf%C3%A9"),
+        ] {
+            let e = Expression::new(&format!("'{path}'.normalizePath()"))
+                .expect("This must be valid CEL");
+            assert_eq!(
+                e.eval(&req_ctx, &mut cel_ctx),
+                Ok(AttributeState::Available(normalized.into())),
+                "normalizing {path}"
+            );
+        }
+    }
 }
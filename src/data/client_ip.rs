@@ -0,0 +1,107 @@
+use std::net::{IpAddr, SocketAddr};
+
+/// Resolves the client address exposed to CEL as `kuadrant.client_ip`, from
+/// `x-real-ip`, then the first `x-forwarded-for` entry, then Envoy's
+/// `source.address`. A malformed source is skipped for the next one.
+pub fn client_ip(
+    x_real_ip: Option<&str>,
+    x_forwarded_for: Option<&str>,
+    source_address: Option<&str>,
+) -> Option<IpAddr> {
+    x_real_ip
+        .and_then(parse_address)
+        .or_else(|| x_forwarded_for.and_then(parse_x_forwarded_for))
+        .or_else(|| source_address.and_then(parse_address))
+}
+
+/// The originating client of an `x-forwarded-for` header, its leftmost entry
+pub fn parse_x_forwarded_for(header: &str) -> Option<IpAddr> {
+    header.split(',').next().and_then(parse_address)
+}
+
+/// Parses an address, with or without a port, IPv6 literals possibly in brackets
+pub fn parse_address(address: &str) -> Option<IpAddr> {
+    let address = address.trim();
+    if let Some(bracketed) = address.strip_prefix('[') {
+        let (ip, rest) = bracketed.split_once(']')?;
+        if !rest.is_empty() && !rest.starts_with(':') {
+            return None;
+        }
+        return ip.parse().ok().filter(IpAddr::is_ipv6);
+    }
+    address
+        .parse::<IpAddr>()
+        .or_else(|_| address.parse::<SocketAddr>().map(|socket| socket.ip()))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn parses_the_leftmost_forwarded_address() {
+        assert_eq!(
+            parse_x_forwarded_for("203.0.113.7, 10.0.0.1, 10.0.0.2"),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)))
+        );
+        assert_eq!(
+            parse_x_forwarded_for("203.0.113.7:51234"),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)))
+        );
+    }
+
+    #[test]
+    fn parses_ipv6_forwarded_addresses() {
+        let expected = Some(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)));
+        assert_eq!(parse_x_forwarded_for("2001:db8::1, 10.0.0.1"), expected);
+        assert_eq!(parse_x_forwarded_for("[2001:db8::1]"), expected);
+        assert_eq!(
+            parse_x_forwarded_for("[2001:db8::1]:443, 10.0.0.1"),
+            expected
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_forwarded_addresses() {
+        for header in [
+            "",
+            "unknown",
+            "203.0.113",
+            "[203.0.113.7]",
+            "[2001:db8::1",
+            "[2001:db8::1]443",
+            "not-an-ip, 10.0.0.1",
+        ] {
+            assert_eq!(parse_x_forwarded_for(header), None, "{header}");
+        }
+    }
+
+    #[test]
+    fn prefers_real_ip_then_forwarded_for_then_source() {
+        let real_ip = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+        let forwarded = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let source = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+        assert_eq!(
+            client_ip(
+                Some("198.51.100.1"),
+                Some("203.0.113.7"),
+                Some("[::1]:8080")
+            ),
+            Some(real_ip)
+        );
+        assert_eq!(
+            client_ip(None, Some("203.0.113.7"), Some("[::1]:8080")),
+            Some(forwarded)
+        );
+        assert_eq!(
+            client_ip(Some("garbage"), Some("203.0.113.7"), None),
+            Some(forwarded)
+        );
+        assert_eq!(client_ip(None, None, Some("[::1]:8080")), Some(source));
+        assert_eq!(client_ip(None, Some("garbage"), Some("::1")), Some(source));
+        assert_eq!(client_ip(None, None, None), None);
+    }
+}
@@ -1,3 +1,126 @@
+use base64::Engine;
+use prost::Message;
+
+pub(crate) const GRPC_STATUS_DETAILS_TRAILER: &str = "grpc-status-details-bin";
+
+const LOCALIZED_MESSAGE_TYPE_URL: &str = "type.googleapis.com/google.rpc.LocalizedMessage";
+
+/// `google.rpc.Status`, as carried by the `grpc-status-details-bin` trailer.
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<prost_types::Any>,
+}
+
+/// `google.rpc.LocalizedMessage`, the detail services use for readable reasons.
+#[derive(Clone, PartialEq, Message)]
+struct LocalizedMessage {
+    #[prost(string, tag = "1")]
+    locale: String,
+    #[prost(string, tag = "2")]
+    message: String,
+}
+
+/// The local reply built from the rich error a service attached to a failed call.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GrpcErrResponse {
+    pub status_code: u32,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// A status code outside of the `100..=599` range HTTP allows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct InvalidHttpStatus(pub u16);
+
+impl std::fmt::Display for InvalidHttpStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid HTTP status code: {}", self.0)
+    }
+}
+
+impl GrpcErrResponse {
+    /// Decodes the base64 `grpc-status-details-bin` trailer. The body is the
+    /// first detail when it is a `LocalizedMessage`, the status message otherwise.
+    pub fn from_status_details(trailer: &str) -> Option<Self> {
+        let bytes = base64::engine::general_purpose::STANDARD_NO_PAD
+            .decode(trailer.trim_end_matches('='))
+            .ok()?;
+        let status = RpcStatus::decode(bytes.as_slice()).ok()?;
+        let body = status
+            .details
+            .first()
+            .filter(|detail| detail.type_url == LOCALIZED_MESSAGE_TYPE_URL)
+            .and_then(|detail| LocalizedMessage::decode(detail.value.as_slice()).ok())
+            .map(|localized| localized.message)
+            .unwrap_or(status.message);
+        Some(Self {
+            status_code: http_status_of(status.code),
+            headers: Vec::new(),
+            body,
+        })
+    }
+
+    /// A plain text reply for `code`, with the reason phrase of the well-known
+    /// statuses as body and a generic one otherwise.
+    pub fn from_http_status(code: u16) -> Self {
+        let reason = match code {
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            429 => "Too Many Requests",
+            500 => "Internal Server Error",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            _ => "Request Failed",
+        };
+        let mut headers = vec![("content-type".to_string(), "text/plain".to_string())];
+        if code == 401 {
+            headers.push(("www-authenticate".to_string(), "Bearer".to_string()));
+        }
+        Self {
+            status_code: u32::from(code),
+            headers,
+            body: format!("{reason}.\n"),
+        }
+    }
+}
+
+impl TryFrom<u16> for GrpcErrResponse {
+    type Error = InvalidHttpStatus;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        if (100..=599).contains(&code) {
+            Ok(Self::from_http_status(code))
+        } else {
+            Err(InvalidHttpStatus(code))
+        }
+    }
+}
+
+/// The HTTP status of a gRPC status code, as mapped by Envoy's ext_authz.
+fn http_status_of(grpc_code: i32) -> u32 {
+    match grpc_code {
+        3 | 9 | 11 => 400,
+        16 => 401,
+        7 => 403,
+        5 => 404,
+        6 | 10 => 409,
+        8 => 429,
+        1 => 499,
+        12 => 501,
+        14 => 503,
+        4 => 504,
+        _ => 500,
+    }
+}
+
 /// Returns `true` if the content-type indicates a gRPC request.
 pub(crate) fn is_grpc_content_type(content_type: &str) -> bool {
     content_type.starts_with("application/grpc")
@@ -60,4 +183,102 @@ mod tests {
             assert_eq!(result, expected, "parse_grpc_path({input:?})");
         }
     }
+
+    fn encoded_status(code: i32, message: &str, details: Vec<prost_types::Any>) -> String {
+        let status = RpcStatus {
+            code,
+            message: message.to_string(),
+            details,
+        };
+        base64::engine::general_purpose::STANDARD.encode(status.encode_to_vec())
+    }
+
+    #[test]
+    fn status_details_expose_first_localized_message() {
+        let detail = prost_types::Any {
+            type_url: LOCALIZED_MESSAGE_TYPE_URL.to_string(),
+            value: LocalizedMessage {
+                locale: "en-US".to_string(),
+                message: "Too many requests for tenant acme".to_string(),
+            }
+            .encode_to_vec(),
+        };
+        let trailer = encoded_status(8, "OVER_LIMIT", vec![detail]);
+
+        assert_eq!(
+            GrpcErrResponse::from_status_details(&trailer),
+            Some(GrpcErrResponse {
+                status_code: 429,
+                headers: Vec::new(),
+                body: "Too many requests for tenant acme".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn status_details_fall_back_to_status_message() {
+        let detail = prost_types::Any {
+            type_url: "type.googleapis.com/google.rpc.ErrorInfo".to_string(),
+            value: Vec::new(),
+        };
+        let trailer = encoded_status(7, "identity not allowed", vec![detail]);
+        let unpadded = trailer.trim_end_matches('=');
+
+        assert_eq!(
+            GrpcErrResponse::from_status_details(unpadded),
+            Some(GrpcErrResponse {
+                status_code: 403,
+                headers: Vec::new(),
+                body: "identity not allowed".to_string(),
+            })
+        );
+        assert_eq!(GrpcErrResponse::from_status_details("not base64!"), None);
+    }
+
+    #[test]
+    fn well_known_statuses_have_their_reason_as_body() {
+        for (code, body) in [
+            (400, "Bad Request.\n"),
+            (401, "Unauthorized.\n"),
+            (403, "Forbidden.\n"),
+            (404, "Not Found.\n"),
+            (429, "Too Many Requests.\n"),
+            (500, "Internal Server Error.\n"),
+            (502, "Bad Gateway.\n"),
+            (503, "Service Unavailable.\n"),
+            (504, "Gateway Timeout.\n"),
+        ] {
+            let response = GrpcErrResponse::from_http_status(code);
+            assert_eq!(response.status_code, u32::from(code));
+            assert_eq!(response.body, body);
+            assert!(response
+                .headers
+                .contains(&("content-type".to_string(), "text/plain".to_string())));
+        }
+    }
+
+    #[test]
+    fn only_unauthorized_challenges_the_client() {
+        let challenge = ("www-authenticate".to_string(), "Bearer".to_string());
+        assert!(GrpcErrResponse::from_http_status(401)
+            .headers
+            .contains(&challenge));
+        assert!(!GrpcErrResponse::from_http_status(403)
+            .headers
+            .contains(&challenge));
+    }
+
+    #[test]
+    fn unmapped_statuses_keep_their_code_with_a_generic_body() {
+        let response = GrpcErrResponse::from_http_status(418);
+        assert_eq!(response.status_code, 418);
+        assert_eq!(response.body, "Request Failed.\n");
+
+        assert_eq!(
+            GrpcErrResponse::try_from(451).map(|r| r.status_code),
+            Ok(451)
+        );
+        assert_eq!(GrpcErrResponse::try_from(99), Err(InvalidHttpStatus(99)));
+        assert_eq!(GrpcErrResponse::try_from(600), Err(InvalidHttpStatus(600)));
+    }
 }
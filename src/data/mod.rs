@@ -1,7 +1,11 @@
 pub mod attribute;
 pub mod cel;
-mod grpc;
+pub(crate) mod client_ip;
+pub(crate) mod grpc;
 mod headers;
+pub(crate) mod path;
+pub(crate) mod tls;
+pub(crate) mod trace;
 
 pub use cel::Expression;
 pub use headers::Headers;
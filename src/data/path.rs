@@ -0,0 +1,89 @@
+/// RFC 3986 normalization of a URL path: percent-encoded unreserved characters
+/// are decoded, empty and `.` segments dropped and `..` segments resolved. A path
+/// escaping the root normalizes to `/`. Any query string is left untouched.
+///
+/// Every match on the request path goes through it, so that a path spelled
+/// differently cannot reach what its normalized form would not.
+pub fn normalize(path: &str) -> String {
+    let (path, query) = match path.find('?') {
+        Some(idx) => path.split_at(idx),
+        None => (path, ""),
+    };
+    let decoded = decode_unreserved(path);
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if segments.pop().is_none() {
+                    return format!("/{query}");
+                }
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    if !segments.is_empty() && matches!(decoded.rsplit('/').next(), Some("" | "." | "..")) {
+        normalized.push('/');
+    }
+    normalized.push_str(query);
+    normalized
+}
+
+/// Whether `prefix` is made of whole leading segments of `path`, so that
+/// `/api` prefixes `/api` and `/api/v1` but not `/apis`
+pub fn has_prefix(path: &str, prefix: &str) -> bool {
+    if prefix.ends_with('/') {
+        return path.starts_with(prefix);
+    }
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn decode_unreserved(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(idx) = rest.find('%') {
+        out.push_str(&rest[..idx]);
+        let escaped = &rest[idx..];
+        let byte = escaped
+            .get(1..3)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(b) if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') => {
+                out.push(b as char);
+            }
+            Some(_) => {
+                out.push('%');
+                out.push_str(&escaped[1..3].to_ascii_uppercase());
+            }
+            None => {
+                out.push('%');
+                rest = &escaped[1..];
+                continue;
+            }
+        }
+        rest = &escaped[3..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_match_whole_segments() {
+        assert!(has_prefix("/api", "/api"));
+        assert!(has_prefix("/api/v1", "/api"));
+        assert!(has_prefix("/api/v1", "/api/"));
+        assert!(has_prefix("/api/v1", "/"));
+        assert!(!has_prefix("/apis", "/api"));
+        assert!(!has_prefix("/api", "/api/"));
+        assert!(!has_prefix("/ap", "/api"));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::data::attribute::{AttributeError, AttributeValue, Path};
+use serde::{Deserialize, Serialize};
+
+/// The peer certificate of an mTLS connection, as reported by Envoy's
+/// `connection.*` properties, exposed to CEL under `kuadrant.tls`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TlsCertificateAttributes {
+    pub version: Option<String>,
+    pub subject: Option<String>,
+    pub common_name: Option<String>,
+    pub uri_san: Option<String>,
+    pub fingerprint: Option<String>,
+}
+
+impl TlsCertificateAttributes {
+    /// Reads the connection properties through `get`, `None` when the
+    /// connection carries no TLS information at all.
+    pub fn read<F>(mut get: F) -> Result<Option<Self>, AttributeError>
+    where
+        F: FnMut(&Path) -> Result<Option<Vec<u8>>, AttributeError>,
+    {
+        let mut property = |name: &str| -> Result<Option<String>, AttributeError> {
+            get(&Path::new(vec!["connection", name]))?
+                .map(<String as AttributeValue>::parse)
+                .transpose()
+        };
+        let version = property("tls_version")?;
+        let subject = property("subject_peer_certificate")?;
+        let uri_san = property("uri_san_peer_certificate")?;
+        let fingerprint = property("sha256_peer_certificate_digest")?;
+
+        if version.is_none() && subject.is_none() && uri_san.is_none() && fingerprint.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            version,
+            common_name: subject.as_deref().and_then(common_name_of),
+            subject,
+            uri_san,
+            fingerprint,
+        }))
+    }
+
+    /// The JSON encoding of the attribute at `field`, or of all of them when empty
+    pub fn field_json(&self, field: &[&str]) -> Option<Vec<u8>> {
+        let json = serde_json::to_value(self).ok()?;
+        let value = match field {
+            [] => json,
+            [name] => json.get(name)?.clone(),
+            _ => return None,
+        };
+        if value.is_null() {
+            return None;
+        }
+        serde_json::to_vec(&value).ok()
+    }
+}
+
+impl AttributeValue for TlsCertificateAttributes {
+    fn parse(raw_attribute: Vec<u8>) -> Result<Self, AttributeError> {
+        serde_json::from_slice(&raw_attribute).map_err(|err| {
+            AttributeError::Parse(format!(
+                "parse: failed to parse TLS certificate attributes, error: {err}"
+            ))
+        })
+    }
+}
+
+/// Splits an RFC 2253 distinguished name on its unescaped commas and returns
+/// the unescaped value of the first `CN` attribute.
+fn common_name_of(subject: &str) -> Option<String> {
+    let mut attributes = Vec::new();
+    let mut current = String::new();
+    let mut chars = subject.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            ',' => attributes.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    attributes.push(current);
+
+    attributes.into_iter().find_map(|attribute| {
+        let (name, value) = attribute.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("CN")
+            .then(|| value.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<Path, Vec<u8>> {
+        pairs
+            .iter()
+            .map(|(path, value)| ((*path).into(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_peer_certificate_properties() {
+        let properties = properties(&[
+            ("connection.tls_version", "TLSv1.3"),
+            (
+                "connection.subject_peer_certificate",
+                "CN=client.example.com,OU=Engineering,O=Acme\\, Inc.,C=US",
+            ),
+            (
+                "connection.uri_san_peer_certificate",
+                "spiffe://acme/client",
+            ),
+            ("connection.sha256_peer_certificate_digest", "9f86d081"),
+        ]);
+        let tls = TlsCertificateAttributes::read(|path| Ok(properties.get(path).cloned()))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(tls.version.as_deref(), Some("TLSv1.3"));
+        assert_eq!(tls.common_name.as_deref(), Some("client.example.com"));
+        assert_eq!(tls.uri_san.as_deref(), Some("spiffe://acme/client"));
+        assert_eq!(tls.fingerprint.as_deref(), Some("9f86d081"));
+        assert_eq!(
+            tls.field_json(&["subject"]),
+            Some(br#""CN=client.example.com,OU=Engineering,O=Acme\\, Inc.,C=US""#.to_vec())
+        );
+        assert_eq!(
+            TlsCertificateAttributes::parse(tls.field_json(&[]).unwrap()).unwrap(),
+            tls
+        );
+    }
+
+    #[test]
+    fn common_name_is_unescaped_wherever_it_appears() {
+        assert_eq!(
+            common_name_of("O=Acme\\, Inc., cn = Jane\\, Doe ,C=US").as_deref(),
+            Some("Jane, Doe")
+        );
+        assert_eq!(common_name_of("O=Acme,C=US"), None);
+    }
+
+    #[test]
+    fn plaintext_connections_have_no_attributes() {
+        assert_eq!(TlsCertificateAttributes::read(|_| Ok(None)).unwrap(), None);
+    }
+}
@@ -0,0 +1,90 @@
+/// The W3C trace id of a `traceparent` header, exposed to CEL as
+/// `kuadrant.trace_id`: `{version}-{trace-id}-{parent-id}-{flags}`. Versions
+/// after `00` may append fields, which are ignored.
+pub fn extract_trace_id(traceparent: &str) -> Option<String> {
+    let mut fields = traceparent.trim().split('-');
+    let version = fields.next()?;
+    let trace_id = fields.next()?;
+    let parent_id = fields.next()?;
+    let flags = fields.next()?;
+
+    if !is_lower_hex(version, 2) || version == "ff" {
+        return None;
+    }
+    if version == "00" && fields.next().is_some() {
+        return None;
+    }
+    let is_set = |id: &str| id.bytes().any(|b| b != b'0');
+    if !is_lower_hex(trace_id, 32) || !is_set(trace_id) {
+        return None;
+    }
+    if !is_lower_hex(parent_id, 16) || !is_set(parent_id) || !is_lower_hex(flags, 2) {
+        return None;
+    }
+    Some(trace_id.to_string())
+}
+
+fn is_lower_hex(field: &str, len: usize) -> bool {
+    field.len() == len
+        && field
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+
+    #[test]
+    fn extracts_the_trace_id() {
+        assert_eq!(
+            extract_trace_id("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"),
+            Some(TRACE_ID.to_string())
+        );
+        assert_eq!(
+            extract_trace_id(" 00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00 "),
+            Some(TRACE_ID.to_string())
+        );
+    }
+
+    #[test]
+    fn later_versions_may_append_fields() {
+        assert_eq!(
+            extract_trace_id("01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra"),
+            Some(TRACE_ID.to_string())
+        );
+        assert_eq!(
+            extract_trace_id("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra"),
+            None
+        );
+        assert_eq!(
+            extract_trace_id("ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_trace_parents() {
+        for malformed in [
+            "",
+            "00",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+            "00-0af7651916cd43dd8448eb211c80319c",
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319g-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c8031-b7ad6b7169203331-01",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-1",
+            "0-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        ] {
+            assert_eq!(
+                extract_trace_id(malformed),
+                None,
+                "{malformed:?} was accepted"
+            );
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime};
+
+/// Shared between the root context and its HTTP contexts, so shutdown can stop
+/// taking on new requests while the gRPC calls already dispatched complete.
+#[derive(Default)]
+pub struct DrainState {
+    started_at: Cell<Option<SystemTime>>,
+    in_flight_tokens: RefCell<BTreeSet<u32>>,
+}
+
+impl DrainState {
+    pub fn start(&self, now: SystemTime) {
+        if self.started_at.get().is_none() {
+            self.started_at.set(Some(now));
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.started_at.get().is_some()
+    }
+
+    pub fn track(&self, tokens: impl Iterator<Item = u32>) {
+        self.in_flight_tokens.borrow_mut().extend(tokens);
+    }
+
+    pub fn complete(&self, token_id: u32) {
+        self.in_flight_tokens.borrow_mut().remove(&token_id);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.is_draining() && self.in_flight_tokens.borrow().is_empty()
+    }
+
+    pub fn has_timed_out(&self, now: SystemTime, timeout: Duration) -> bool {
+        self.started_at
+            .get()
+            .and_then(|started_at| now.duration_since(started_at).ok())
+            .is_some_and(|elapsed| elapsed >= timeout)
+    }
+
+    pub fn in_flight_tokens(&self) -> Vec<u32> {
+        self.in_flight_tokens.borrow().iter().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drain_completes_once_in_flight_calls_respond() {
+        let drain = DrainState::default();
+        drain.track([3, 7].into_iter());
+        assert!(!drain.is_complete());
+
+        drain.start(SystemTime::UNIX_EPOCH);
+        assert!(drain.is_draining());
+        assert!(!drain.is_complete());
+
+        drain.complete(3);
+        assert_eq!(drain.in_flight_tokens(), vec![7]);
+        drain.complete(7);
+        assert!(drain.is_complete());
+    }
+
+    #[test]
+    fn drain_times_out_from_first_signal() {
+        let drain = DrainState::default();
+        let start = SystemTime::UNIX_EPOCH;
+        assert!(!drain.has_timed_out(start, Duration::ZERO));
+
+        drain.start(start);
+        drain.start(start + Duration::from_secs(5));
+        assert!(!drain.has_timed_out(start + Duration::from_secs(9), Duration::from_secs(10)));
+        assert!(drain.has_timed_out(start + Duration::from_secs(10), Duration::from_secs(10)));
+    }
+}
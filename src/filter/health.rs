@@ -0,0 +1,139 @@
+use crate::services::{GrpcRequest, HealthCheck};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use tracing::info;
+
+/// The upstreams the root context found down, by service name, along with the
+/// checks awaiting a response.
+#[derive(Default)]
+pub struct UpstreamHealth {
+    pending: RefCell<HashMap<u32, String>>,
+    unhealthy: RefCell<BTreeSet<String>>,
+}
+
+impl UpstreamHealth {
+    /// Dispatches each check through `dispatch`, a failure to dispatch counting
+    /// as a failed check.
+    pub fn dispatch<F, E>(&self, checks: Vec<HealthCheck>, mut dispatch: F)
+    where
+        F: FnMut(&GrpcRequest) -> Result<u32, E>,
+        E: std::fmt::Debug,
+    {
+        for check in checks {
+            match dispatch(&check.request) {
+                Ok(token_id) => {
+                    self.pending.borrow_mut().insert(token_id, check.service);
+                }
+                Err(e) => {
+                    info!(
+                        "health check of {} could not be dispatched: {:?}",
+                        check.service, e
+                    );
+                    self.record(&check.service, false);
+                }
+            }
+        }
+    }
+
+    pub fn is_pending(&self, token_id: u32) -> bool {
+        self.pending.borrow().contains_key(&token_id)
+    }
+
+    /// The service the pending check `token_id` is for
+    pub fn service_of(&self, token_id: u32) -> Option<String> {
+        self.pending.borrow().get(&token_id).cloned()
+    }
+
+    /// Whether a check of `service` awaits its response
+    pub fn is_checking(&self, service: &str) -> bool {
+        self.pending
+            .borrow()
+            .values()
+            .any(|pending| pending == service)
+    }
+
+    pub fn complete(&self, token_id: u32, serving: bool) {
+        let Some(service) = self.pending.borrow_mut().remove(&token_id) else {
+            return;
+        };
+        info!(
+            "health check of {}: {}",
+            service,
+            if serving { "serving" } else { "not serving" }
+        );
+        self.record(&service, serving);
+    }
+
+    fn record(&self, service: &str, serving: bool) {
+        if serving {
+            self.unhealthy.borrow_mut().remove(service);
+        } else {
+            self.unhealthy.borrow_mut().insert(service.to_string());
+        }
+    }
+
+    /// Whether any upstream is down
+    pub fn is_degraded(&self) -> bool {
+        !self.unhealthy.borrow().is_empty()
+    }
+
+    pub fn is_unhealthy(&self, service: &str) -> bool {
+        self.unhealthy.borrow().contains(service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::GrpcRequestBuilder;
+
+    fn check(service: &str) -> HealthCheck {
+        HealthCheck {
+            service: service.to_string(),
+            request: GrpcRequestBuilder::new(format!("{service}-cluster"))
+                .service("grpc.health.v1.Health")
+                .method("Check")
+                .build()
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn failed_dispatch_marks_the_upstream_unhealthy() {
+        let health = UpstreamHealth::default();
+        let mut dispatch = |request: &GrpcRequest| match request.upstream_name() {
+            "authorino-cluster" => Err("cluster not found"),
+            _ => Ok(1),
+        };
+        health.dispatch(vec![check("authorino"), check("limitador")], &mut dispatch);
+
+        assert!(health.is_degraded());
+        assert!(health.is_unhealthy("authorino"));
+        assert!(!health.is_unhealthy("limitador"));
+        assert!(health.is_checking("limitador"));
+    }
+
+    #[test]
+    fn recovers_once_the_upstream_serves() {
+        let health = UpstreamHealth::default();
+        let mut next_token = 0;
+        let mut dispatch = |_: &GrpcRequest| -> Result<u32, ()> {
+            next_token += 1;
+            Ok(next_token)
+        };
+
+        health.dispatch(vec![check("authorino")], &mut dispatch);
+        assert!(health.is_pending(1));
+        assert!(health.is_checking("authorino"));
+        assert!(!health.is_degraded());
+
+        health.complete(1, false);
+        assert!(health.is_degraded());
+        assert!(!health.is_checking("authorino"));
+
+        health.dispatch(vec![check("authorino")], &mut dispatch);
+        health.complete(2, true);
+        assert!(!health.is_degraded());
+        assert!(!health.is_pending(2));
+    }
+}
@@ -1,107 +1,107 @@
-use crate::kuadrant::{Pipeline, PipelineFactory, PipelineState, ReqRespCtx};
+use super::drain::DrainState;
+use super::local_reply::send_local_reply;
+use super::logger::FilterLogger;
+use super::retry_queue::RetryQueue;
+use super::watchdog::{CallPolicy, CallWatchdog};
+use crate::configuration::{InternalRequestPolicy, OverloadMode};
+use crate::data::Headers;
+use crate::kuadrant::{
+    AccessLogEntry, Pipeline, PipelineFactory, PipelineState, ReqRespCtx, SharedAccessLog,
+};
 use crate::metrics::METRICS;
-use proxy_wasm::hostcalls;
+use crate::{flog_debug, flog_error, flog_trace, flog_warn};
 use proxy_wasm::traits::{Context, HttpContext};
 use proxy_wasm::types::Action;
+use std::cell::RefCell;
 use std::ops::Not;
 use std::rc::Rc;
-use tracing::{debug, error, trace, warn};
+use std::time::SystemTime;
+use tracing::info;
+
+const DRY_RUN_HEADER: &str = "x-kuadrant-dry-run";
+const OVERLOADED_HEADER: &str = "x-envoy-overloaded";
+const INTERNAL_HEADER: &str = "x-envoy-internal";
+/// gRPC status the calls the watchdog gives up on are digested with
+const DEADLINE_EXCEEDED: u32 = 4;
 
 pub struct KuadrantFilter {
-    context_id: u32,
+    log: FilterLogger,
     factory: Rc<PipelineFactory>,
+    drain: Rc<DrainState>,
+    watchdog: Rc<CallWatchdog>,
+    retry_queue: Rc<RetryQueue>,
     pipeline: Option<Pipeline>,
     in_response_phase: bool,
     force_resume: bool,
+    access_log: Option<SharedAccessLog>,
+    request_start: Option<SystemTime>,
+    /// Set when the pipeline is to be started from the request trailers,
+    /// holding the action set internal requests are pinned to
+    deferred_start: Option<Option<String>>,
 }
 
 impl KuadrantFilter {
-    pub fn new(context_id: u32, factory: Rc<PipelineFactory>) -> Self {
+    pub fn new(
+        context_id: u32,
+        factory: Rc<PipelineFactory>,
+        drain: Rc<DrainState>,
+        watchdog: Rc<CallWatchdog>,
+        retry_queue: Rc<RetryQueue>,
+    ) -> Self {
         Self {
-            context_id,
+            log: FilterLogger::new(context_id),
             factory,
+            drain,
+            watchdog,
+            retry_queue,
             pipeline: None,
             in_response_phase: false,
             force_resume: false,
+            access_log: None,
+            request_start: None,
+            deferred_start: None,
         }
     }
 
-    fn should_pause(&self) -> bool {
-        self.pipeline.as_ref().is_some_and(|p| p.requires_pause())
+    fn new_ctx(&self) -> ReqRespCtx {
+        ReqRespCtx::default()
+            .with_logger(self.log)
+            .with_access_log(self.access_log.clone())
+            .with_retry_queue(self.log.context_id(), Rc::clone(&self.retry_queue))
     }
 
-    #[allow(clippy::expect_used)]
-    fn should_resume(&self) -> bool {
-        let pipeline = self.pipeline.as_ref().expect("pipeline must be present");
-
-        if pipeline.is_terminated() && self.in_response_phase {
-            return self.should_pause().not();
+    fn capture_request_trailers(&self, ctx: &mut ReqRespCtx) {
+        let trailers: Headers = self.get_http_request_trailers().into();
+        if let Err(e) = ctx.set_request_trailers(trailers) {
+            flog_warn!(self.log, "failed to store request trailers: {:?}", e);
         }
-
-        pipeline.is_terminated().not() && self.should_pause().not()
-    }
-}
-
-impl Context for KuadrantFilter {
-    fn on_grpc_call_response(&mut self, token_id: u32, status_code: u32, response_size: usize) {
-        debug!(
-            "#{} on_grpc_call_response: received gRPC call response: token: {}, status: {}",
-            self.context_id, token_id, status_code
-        );
-        if let Some(pipeline) = self.pipeline.take() {
-            let should_resume = match pipeline.digest(token_id, status_code, response_size) {
-                PipelineState::InProgress(p) => {
-                    self.pipeline = Some(*p);
-                    self.should_resume()
-                }
-                PipelineState::Completed { should_resume } => {
-                    self.pipeline = None;
-                    trace!(
-                        "#{} PipelineState::Completed: should_resume={}",
-                        self.context_id,
-                        should_resume
-                    );
-                    should_resume || self.force_resume || self.in_response_phase
-                }
-            };
-
-            if should_resume {
-                let result = if self.in_response_phase {
-                    trace!("on_grpc_call_response: resume_http_response");
-                    self.resume_http_response()
-                } else {
-                    trace!("on_grpc_call_response: resume_http_request");
-                    self.resume_http_request()
-                };
-
-                if let Err(e) = result {
-                    error!(
-                        "#{} failed to resume filter processing: {:?}",
-                        self.context_id, e
-                    );
-                }
-            }
-        } else {
-            warn!("#{} received response without a pipeline", self.context_id);
+        let forwarded_trailers = self.factory.forwarded_trailers();
+        if !forwarded_trailers.is_empty() {
+            ctx.forward_request_trailers(
+                forwarded_trailers,
+                self.get_http_request_trailers_bytes(),
+            );
         }
     }
-}
-
-impl HttpContext for KuadrantFilter {
-    fn on_http_request_headers(&mut self, _num_headers: usize, _end_of_stream: bool) -> Action {
-        debug!("#{} on_http_request_headers", self.context_id);
-
-        #[cfg(feature = "debug-host-behaviour")]
-        crate::data::debug_all_well_known_attributes();
-
-        let ctx = ReqRespCtx::default();
 
-        match self.factory.build(ctx) {
+    fn start_pipeline(
+        &mut self,
+        ctx: ReqRespCtx,
+        internal_action_set: Option<&str>,
+        hook: &str,
+    ) -> Action {
+        let factory = Rc::clone(&self.factory);
+        let built = match internal_action_set {
+            Some(name) => factory.build_for_action_set(ctx, name),
+            None => factory.build(ctx),
+        };
+        match built {
             Ok(Some(pipeline)) => {
-                debug!("#{} pipeline built successfully", self.context_id);
+                flog_debug!(self.log, "pipeline built successfully");
                 METRICS.hits().increment();
                 match pipeline.eval() {
                     PipelineState::InProgress(p) => {
+                        self.track_pending(&p);
                         self.pipeline = Some(*p);
                     }
                     PipelineState::Completed { .. } => {
@@ -109,28 +109,25 @@ impl HttpContext for KuadrantFilter {
                     }
                 }
                 if self.should_pause() {
-                    trace!("on_http_request_headers: pause");
+                    flog_trace!(self.log, "{}: pause", hook);
                     Action::Pause
                 } else {
-                    trace!("on_http_request_headers: continue");
+                    flog_trace!(self.log, "{}: continue", hook);
                     Action::Continue
                 }
             }
             Ok(None) => {
-                debug!("#{} no matching route found", self.context_id);
+                flog_debug!(self.log, "no matching route found");
                 METRICS.misses().increment();
                 Action::Continue
             }
             Err(e) => {
-                error!("#{} failed to build pipeline: {:?}", self.context_id, e);
+                flog_error!(self.log, "failed to build pipeline: {:?}", e);
                 METRICS.errors().increment();
                 #[allow(clippy::panic)]
-                hostcalls::send_http_response(500, Default::default(), Some(b"Internal Server Error.\n"))
+                send_local_reply(self.log, self.factory.dry_run(), 500, b"Internal Server Error.\n")
                     .unwrap_or_else(|err| {
-                               error!(
-                                   "#{} CRITICAL: Failed to send error response: {:?}. WASM runtime is in an invalid state",
-                                   self.context_id, err
-                               );
+                               flog_error!(self.log, "CRITICAL: Failed to send error response: {:?}. WASM runtime is in an invalid state", err);
                                panic!("CRITICAL: Failed to send HTTP reply after pipeline build failure");
                            });
                 Action::Continue
@@ -138,14 +135,320 @@ impl HttpContext for KuadrantFilter {
         }
     }
 
+    fn track_pending(&self, pipeline: &Pipeline) {
+        self.drain.track(pipeline.pending_tokens());
+        self.watchdog.track(
+            self.log.context_id(),
+            pipeline.pending_tokens().map(|token_id| {
+                let policy = CallPolicy {
+                    holds_request: pipeline.is_guarded_by(token_id),
+                    fails_open: pipeline.fails_open_on(token_id),
+                    response_phase: self.in_response_phase,
+                };
+                (token_id, policy)
+            }),
+            self.get_current_time(),
+        );
+    }
+
+    fn complete(&self, token_id: u32) {
+        self.drain.complete(token_id);
+        self.watchdog.complete(token_id);
+    }
+
+    /// Only to be relied upon when Envoy has `use_remote_address: true`, it
+    /// leaves the header clients send alone otherwise
+    fn is_internal_request(&self) -> bool {
+        self.get_http_request_header(INTERNAL_HEADER)
+            .is_some_and(|internal| internal == "true")
+    }
+
+    fn should_pause(&self) -> bool {
+        self.pipeline.as_ref().is_some_and(|p| p.requires_pause())
+    }
+
+    #[allow(clippy::expect_used)]
+    fn should_resume(&self) -> bool {
+        let pipeline = self.pipeline.as_ref().expect("pipeline must be present");
+
+        if pipeline.is_terminated() && self.in_response_phase {
+            return self.should_pause().not();
+        }
+
+        pipeline.is_terminated().not() && self.should_pause().not()
+    }
+
+    /// Feeds a response to the pipeline, telling whether the filter is to
+    /// resume processing
+    #[allow(clippy::expect_used)]
+    fn digest(&mut self, token_id: u32, status_code: u32, response_size: usize) -> bool {
+        let pipeline = self.pipeline.take().expect("pipeline must be present");
+        match pipeline.digest(token_id, status_code, response_size) {
+            PipelineState::InProgress(p) => {
+                self.track_pending(&p);
+                self.pipeline = Some(*p);
+                self.should_resume()
+            }
+            PipelineState::Completed { should_resume } => {
+                self.pipeline = None;
+                flog_trace!(
+                    self.log,
+                    "PipelineState::Completed: should_resume={}",
+                    should_resume
+                );
+                should_resume || self.force_resume || self.in_response_phase
+            }
+        }
+    }
+
+    /// Gives up on the calls the watchdog abandoned as if they failed with
+    /// `DEADLINE_EXCEEDED`, so the pipeline follows the failure mode of their
+    /// services. The root context resumes the request once nothing else holds
+    /// it back.
+    fn digest_abandoned(&mut self) {
+        for token_id in self.watchdog.take_abandoned(self.log.context_id()) {
+            if self.awaits(token_id) {
+                flog_warn!(self.log, "giving up on gRPC call {}", token_id);
+                self.digest(token_id, DEADLINE_EXCEEDED, 0);
+            }
+        }
+    }
+
+    fn awaits(&self, token_id: u32) -> bool {
+        self.pipeline
+            .as_ref()
+            .is_some_and(|p| p.pending_tokens().any(|pending| pending == token_id))
+    }
+
+    fn digest_response(&mut self, token_id: u32, status_code: u32, response_size: usize) {
+        self.complete(token_id);
+        self.digest_abandoned();
+        if self.pipeline.is_none() {
+            flog_warn!(self.log, "received response without a pipeline");
+            return;
+        }
+        if !self.awaits(token_id) {
+            flog_debug!(self.log, "ignoring response to abandoned call {}", token_id);
+            return;
+        }
+
+        if self.digest(token_id, status_code, response_size) {
+            let result = if self.in_response_phase {
+                flog_trace!(self.log, "digest_response: resume_http_response");
+                self.resume_http_response()
+            } else {
+                flog_trace!(self.log, "digest_response: resume_http_request");
+                self.resume_http_request()
+            };
+
+            if let Err(e) = result {
+                flog_error!(self.log, "failed to resume filter processing: {:?}", e);
+            }
+        }
+    }
+}
+
+impl Context for KuadrantFilter {
+    fn on_grpc_call_response(&mut self, token_id: u32, status_code: u32, response_size: usize) {
+        flog_debug!(
+            self.log,
+            "on_grpc_call_response: received gRPC call response: token: {}, status: {}",
+            token_id,
+            status_code
+        );
+        let token_id = self.retry_queue.resolve(token_id);
+        self.digest_response(token_id, status_code, response_size);
+    }
+
+    #[cfg(feature = "http-callout")]
+    fn on_http_call_response(
+        &mut self,
+        token_id: u32,
+        _num_headers: usize,
+        body_size: usize,
+        _num_trailers: usize,
+    ) {
+        let status_code = self
+            .get_http_call_response_header(":status")
+            .and_then(|status| status.parse().ok())
+            .unwrap_or_default();
+        flog_debug!(
+            self.log,
+            "on_http_call_response: received HTTP call response: token: {}, status: {}",
+            token_id,
+            status_code
+        );
+        self.digest_response(token_id, status_code, body_size);
+    }
+
+    fn on_done(&mut self) -> bool {
+        if let Some(pipeline) = &self.pipeline {
+            pipeline
+                .pending_tokens()
+                .for_each(|token_id| self.complete(token_id));
+        }
+        self.watchdog.take_abandoned(self.log.context_id());
+        self.retry_queue.cancel(self.log.context_id());
+        true
+    }
+}
+
+impl HttpContext for KuadrantFilter {
+    fn on_http_request_headers(&mut self, _num_headers: usize, end_of_stream: bool) -> Action {
+        flog_debug!(self.log, "on_http_request_headers");
+
+        if self.factory.access_log() {
+            let request_start = self.get_current_time();
+            let mut entry = AccessLogEntry::new(self.log.context_id());
+            entry.set_start(request_start);
+            entry.method = self.get_http_request_header(":method");
+            entry.path = self.get_http_request_header(":path");
+            self.access_log = Some(Rc::new(RefCell::new(entry)));
+            self.request_start = Some(request_start);
+        }
+
+        if self.drain.is_draining() {
+            flog_debug!(self.log, "draining, skipping new request");
+            return Action::Continue;
+        }
+
+        if let Some(overload_mode) = self.factory.overload_mode() {
+            // Clients can set the header themselves, it is only trusted on the
+            // requests Envoy flags as internal, having stripped it from the others
+            if self
+                .get_http_request_header(OVERLOADED_HEADER)
+                .is_some_and(|overloaded| overloaded == "true")
+                && self.is_internal_request()
+            {
+                match overload_mode {
+                    OverloadMode::Allow => {
+                        flog_warn!(self.log, "envoy is overloaded, skipping request");
+                        return Action::Continue;
+                    }
+                    OverloadMode::Deny => {
+                        flog_warn!(self.log, "envoy is overloaded, denying request");
+                        return match send_local_reply(
+                            self.log,
+                            self.factory.dry_run(),
+                            503,
+                            b"Service Unavailable.\n",
+                        ) {
+                            Ok(true) => {
+                                METRICS.denied().increment();
+                                Action::Pause
+                            }
+                            Ok(false) => Action::Continue,
+                            Err(e) => {
+                                flog_error!(self.log, "failed to send overload response: {:?}", e);
+                                Action::Continue
+                            }
+                        };
+                    }
+                }
+            }
+        }
+
+        if self.factory.has_bypass_paths()
+            && self
+                .get_http_request_header(":path")
+                .is_some_and(|path| self.factory.bypasses(&path))
+        {
+            flog_debug!(self.log, "path bypasses action sets");
+            return Action::Continue;
+        }
+
+        let mut internal_action_set = None;
+        let factory = Rc::clone(&self.factory);
+        if let Some(policy) = factory.internal_request_policy() {
+            if self.is_internal_request() {
+                match policy {
+                    InternalRequestPolicy::Skip => {
+                        flog_debug!(self.log, "internal request, skipping");
+                        self.access_log = None;
+                        return Action::Continue;
+                    }
+                    InternalRequestPolicy::Allow => {
+                        flog_debug!(self.log, "internal request, allowing");
+                        return Action::Continue;
+                    }
+                    InternalRequestPolicy::UseActionSet(name) => {
+                        flog_debug!(self.log, "internal request, using action set {}", name);
+                        internal_action_set = Some(name.as_str());
+                    }
+                }
+            }
+        }
+
+        #[cfg(feature = "debug-host-behaviour")]
+        crate::data::debug_all_well_known_attributes();
+
+        if self.factory.trigger_on_trailers() && !end_of_stream {
+            flog_debug!(self.log, "starting the pipeline from the request trailers");
+            self.deferred_start = Some(internal_action_set.map(str::to_string));
+            return Action::Continue;
+        }
+
+        let mut ctx = self.new_ctx();
+        ctx.set_current_request_body_buffer_size(0, end_of_stream);
+        self.start_pipeline(ctx, internal_action_set, "on_http_request_headers")
+    }
+
     fn on_http_request_body(&mut self, buffer_size: usize, end_of_stream: bool) -> Action {
-        debug!("#{} on_http_request_body", self.context_id);
+        flog_debug!(self.log, "on_http_request_body");
+        self.digest_abandoned();
+        if end_of_stream {
+            // No trailers follow, the pipeline cannot wait for them any longer
+            if let Some(internal_action_set) = self.deferred_start.take() {
+                let mut ctx = self.new_ctx();
+                ctx.set_current_request_body_buffer_size(buffer_size, end_of_stream);
+                return self.start_pipeline(
+                    ctx,
+                    internal_action_set.as_deref(),
+                    "on_http_request_body",
+                );
+            }
+        }
         if let Some(mut pipeline) = self.pipeline.take() {
             pipeline
                 .ctx
                 .set_current_request_body_buffer_size(buffer_size, end_of_stream);
             match pipeline.eval() {
                 PipelineState::InProgress(p) => {
+                    self.track_pending(&p);
+                    self.pipeline = Some(*p);
+                }
+                PipelineState::Completed { .. } => {
+                    self.pipeline = None;
+                }
+            }
+        }
+        if self.should_pause() {
+            flog_trace!(self.log, "on_http_request_body: pause");
+            Action::Pause
+        } else {
+            flog_trace!(self.log, "on_http_request_body: continue");
+            Action::Continue
+        }
+    }
+
+    fn on_http_request_trailers(&mut self, _num_trailers: usize) -> Action {
+        flog_debug!(self.log, "on_http_request_trailers");
+        self.digest_abandoned();
+        if let Some(internal_action_set) = self.deferred_start.take() {
+            let mut ctx = self.new_ctx();
+            ctx.set_current_request_body_buffer_size(0, true);
+            self.capture_request_trailers(&mut ctx);
+            return self.start_pipeline(
+                ctx,
+                internal_action_set.as_deref(),
+                "on_http_request_trailers",
+            );
+        }
+        if let Some(mut pipeline) = self.pipeline.take() {
+            self.capture_request_trailers(&mut pipeline.ctx);
+            match pipeline.eval() {
+                PipelineState::InProgress(p) => {
+                    self.track_pending(&p);
                     self.pipeline = Some(*p);
                 }
                 PipelineState::Completed { .. } => {
@@ -154,21 +457,31 @@ impl HttpContext for KuadrantFilter {
             }
         }
         if self.should_pause() {
-            trace!("on_http_request_body: pause");
+            flog_trace!(self.log, "on_http_request_trailers: pause");
             Action::Pause
         } else {
-            trace!("on_http_request_body: continue");
+            flog_trace!(self.log, "on_http_request_trailers: continue");
             Action::Continue
         }
     }
 
     fn on_http_response_headers(&mut self, _num_headers: usize, _end_of_stream: bool) -> Action {
-        debug!("#{} on_http_response_headers", self.context_id);
+        flog_debug!(self.log, "on_http_response_headers");
+        self.digest_abandoned();
         METRICS.allowed().increment();
         self.in_response_phase = true;
+        if let Some(access_log) = &self.access_log {
+            access_log.borrow_mut().status = self
+                .get_http_response_header(":status")
+                .and_then(|status| status.parse().ok());
+        }
+        if self.factory.dry_run() {
+            self.set_http_response_header(DRY_RUN_HEADER, Some("true"));
+        }
         if let Some(pipeline) = self.pipeline.take() {
             match pipeline.eval() {
                 PipelineState::InProgress(p) => {
+                    self.track_pending(&p);
                     self.pipeline = Some(*p);
                 }
                 PipelineState::Completed { .. } => {
@@ -176,23 +489,29 @@ impl HttpContext for KuadrantFilter {
                 }
             }
         }
+        let factory = Rc::clone(&self.factory);
+        for name in factory.response_headers_to_remove() {
+            self.set_http_response_header(name, None);
+        }
         if self.should_pause() {
-            trace!("on_http_response_headers: pause");
+            flog_trace!(self.log, "on_http_response_headers: pause");
             Action::Pause
         } else {
-            trace!("on_http_response_headers: continue");
+            flog_trace!(self.log, "on_http_response_headers: continue");
             Action::Continue
         }
     }
 
     fn on_http_response_body(&mut self, buffer_size: usize, end_of_stream: bool) -> Action {
-        debug!("#{} on_http_response_body", self.context_id);
+        flog_debug!(self.log, "on_http_response_body");
+        self.digest_abandoned();
         if let Some(mut pipeline) = self.pipeline.take() {
             pipeline
                 .ctx
                 .set_current_response_body_buffer_size(buffer_size, end_of_stream);
             match pipeline.eval() {
                 PipelineState::InProgress(p) => {
+                    self.track_pending(&p);
                     self.pipeline = Some(*p);
                 }
                 PipelineState::Completed { .. } => {
@@ -201,17 +520,35 @@ impl HttpContext for KuadrantFilter {
             }
         }
         if self.should_pause() {
-            trace!("on_http_response_body: pause");
+            flog_trace!(self.log, "on_http_response_body: pause");
             Action::Pause
         } else {
             if self.pipeline.is_some() && end_of_stream {
-                trace!("on_http_response_body: pipeline is some, pause");
+                flog_trace!(self.log, "on_http_response_body: pipeline is some, pause");
                 self.force_resume = true;
                 Action::Pause
             } else {
-                trace!("on_http_response_body: continue");
+                flog_trace!(self.log, "on_http_response_body: continue");
                 Action::Continue
             }
         }
     }
+
+    fn on_log(&mut self) {
+        let Some(access_log) = self.access_log.take() else {
+            return;
+        };
+        let mut entry = access_log.borrow_mut();
+        if let Some(request_start) = self.request_start {
+            entry.set_duration(
+                self.get_current_time()
+                    .duration_since(request_start)
+                    .unwrap_or_default(),
+            );
+        }
+        match entry.to_json() {
+            Ok(line) => info!("{line}"),
+            Err(e) => flog_warn!(self.log, "failed to serialize the access log entry: {e}"),
+        }
+    }
 }
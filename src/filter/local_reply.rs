@@ -0,0 +1,23 @@
+use proxy_wasm::hostcalls;
+use proxy_wasm::types::Status;
+
+use super::logger::FilterLogger;
+use crate::flog_warn;
+
+/// Replies to a request on behalf of the filter rather than of a pipeline, e.g.
+/// with a 503 while Envoy is overloaded. In dry run the reply is only logged,
+/// as the pipeline does with its denials, and `Ok(false)` tells the request is
+/// to go on.
+pub fn send_local_reply(
+    log: FilterLogger,
+    dry_run: bool,
+    status_code: u32,
+    body: &[u8],
+) -> Result<bool, Status> {
+    if dry_run {
+        flog_warn!(log, "dry run, not replying with status {}", status_code);
+        return Ok(false);
+    }
+    hostcalls::send_http_response(status_code, vec![], Some(body))?;
+    Ok(true)
+}
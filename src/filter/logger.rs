@@ -0,0 +1,104 @@
+/// Prefixes the log lines of an HTTP context with `#{context_id}`, so those of
+/// a single request can be correlated, through the `flog_*!` macros.
+#[derive(Clone, Copy, Debug)]
+pub struct FilterLogger {
+    context_id: u32,
+}
+
+impl FilterLogger {
+    pub fn new(context_id: u32) -> Self {
+        Self { context_id }
+    }
+
+    pub fn context_id(&self) -> u32 {
+        self.context_id
+    }
+}
+
+#[macro_export]
+macro_rules! flog_trace {
+    ($logger:expr, $($arg:tt)+) => {
+        tracing::trace!("#{} {}", $logger.context_id(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! flog_debug {
+    ($logger:expr, $($arg:tt)+) => {
+        tracing::debug!("#{} {}", $logger.context_id(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! flog_warn {
+    ($logger:expr, $($arg:tt)+) => {
+        tracing::warn!("#{} {}", $logger.context_id(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! flog_error {
+    ($logger:expr, $($arg:tt)+) => {
+        tracing::error!("#{} {}", $logger.context_id(), format_args!($($arg)+))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Layer;
+
+    #[derive(Clone, Default)]
+    struct CapturedLines(Arc<Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> Layer<S> for CapturedLines {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct MessageVisitor(String);
+
+            impl tracing::field::Visit for MessageVisitor {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    if field.name() == "message" {
+                        self.0 = format!("{:?}", value);
+                    }
+                }
+            }
+
+            let mut visitor = MessageVisitor(String::new());
+            event.record(&mut visitor);
+            self.0.lock().unwrap().push(visitor.0);
+        }
+    }
+
+    #[test]
+    fn every_line_carries_the_context_id() {
+        let lines = CapturedLines::default();
+        let subscriber = tracing_subscriber::registry().with(lines.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let log = FilterLogger::new(7);
+            flog_trace!(log, "on_http_request_headers: pause");
+            flog_debug!(log, "on_http_request_headers");
+            flog_warn!(log, "failed to store request trailers: {:?}", "oops");
+            flog_error!(log, "failed to build pipeline: {}", 42);
+        });
+
+        assert_eq!(
+            *lines.0.lock().unwrap(),
+            vec![
+                "#7 on_http_request_headers: pause",
+                "#7 on_http_request_headers",
+                "#7 failed to store request trailers: \"oops\"",
+                "#7 failed to build pipeline: 42",
+            ]
+        );
+    }
+}
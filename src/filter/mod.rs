@@ -1,6 +1,14 @@
 mod descriptor_manager;
+mod drain;
+mod health;
 mod kuadrant_filter;
+mod local_reply;
+mod logger;
+mod retry_queue;
 mod root_context;
+mod watchdog;
 
 pub use descriptor_manager::{DescriptorKey, DescriptorManager};
+pub use logger::FilterLogger;
+pub use retry_queue::{DelayedCall, RetryQueue};
 pub use root_context::FilterRoot;
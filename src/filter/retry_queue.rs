@@ -0,0 +1,155 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+
+use crate::services::GrpcRequest;
+
+/// A gRPC call to dispatch again once its back-off delay has elapsed
+#[derive(Debug)]
+pub struct DelayedCall {
+    pub context_id: u32,
+    pub due: SystemTime,
+    pub request: GrpcRequest,
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub timeout: Duration,
+}
+
+/// Shared between the root context and its HTTP contexts, so a retry can wait
+/// out its delay on the root tick, HTTP contexts having no timer of their own.
+///
+/// The pipeline awaits a scheduled retry under a placeholder token, counting
+/// down from `u32::MAX` so as not to collide with the ones of the host. Once
+/// the call is dispatched, its response is mapped back to the placeholder.
+pub struct RetryQueue {
+    next_placeholder: Cell<u32>,
+    delayed: RefCell<BTreeMap<u32, DelayedCall>>,
+    /// Placeholders of the dispatched retries, with their context, by token
+    dispatched: RefCell<BTreeMap<u32, (u32, u32)>>,
+}
+
+impl Default for RetryQueue {
+    fn default() -> Self {
+        Self {
+            next_placeholder: Cell::new(u32::MAX),
+            delayed: RefCell::default(),
+            dispatched: RefCell::default(),
+        }
+    }
+}
+
+impl RetryQueue {
+    /// Queues `call`, returning the placeholder token its response is to be
+    /// awaited under
+    pub fn schedule(&self, call: DelayedCall) -> u32 {
+        let placeholder = self.next_placeholder.get();
+        self.next_placeholder.set(placeholder.wrapping_sub(1));
+        self.delayed.borrow_mut().insert(placeholder, call);
+        placeholder
+    }
+
+    /// Dequeues the calls due at `now`, along with their placeholder
+    pub fn take_due(&self, now: SystemTime) -> Vec<(u32, DelayedCall)> {
+        let mut delayed = self.delayed.borrow_mut();
+        let due: Vec<u32> = delayed
+            .iter()
+            .filter(|(_, call)| call.due <= now)
+            .map(|(placeholder, _)| *placeholder)
+            .collect();
+        due.into_iter()
+            .filter_map(|placeholder| Some((placeholder, delayed.remove(&placeholder)?)))
+            .collect()
+    }
+
+    pub fn next_due(&self) -> Option<SystemTime> {
+        self.delayed.borrow().values().map(|call| call.due).min()
+    }
+
+    /// Records the token the host dispatched the call queued as `placeholder` with
+    pub fn dispatched(&self, token_id: u32, context_id: u32, placeholder: u32) {
+        self.dispatched
+            .borrow_mut()
+            .insert(token_id, (context_id, placeholder));
+    }
+
+    /// The token the pipeline awaits the response to `token_id` under
+    pub fn resolve(&self, token_id: u32) -> u32 {
+        self.dispatched
+            .borrow_mut()
+            .remove(&token_id)
+            .map_or(token_id, |(_, placeholder)| placeholder)
+    }
+
+    /// Forgets the retries of a context that is done
+    pub fn cancel(&self, context_id: u32) {
+        self.delayed
+            .borrow_mut()
+            .retain(|_, call| call.context_id != context_id);
+        self.dispatched
+            .borrow_mut()
+            .retain(|_, (context, _)| *context != context_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::GrpcRequestBuilder;
+
+    fn call(context_id: u32, due: SystemTime) -> DelayedCall {
+        DelayedCall {
+            context_id,
+            due,
+            request: GrpcRequestBuilder::new("limitador-cluster")
+                .service("envoy.service.ratelimit.v3.RateLimitService")
+                .method("ShouldRateLimit")
+                .build()
+                .unwrap(),
+            headers: vec![],
+            timeout: Duration::from_millis(20),
+        }
+    }
+
+    #[test]
+    fn hands_out_calls_once_due() {
+        let queue = RetryQueue::default();
+        let start = SystemTime::UNIX_EPOCH;
+        let first = queue.schedule(call(2, start + Duration::from_millis(50)));
+        let second = queue.schedule(call(3, start + Duration::from_millis(10)));
+        assert_eq!(first, u32::MAX);
+        assert_eq!(second, u32::MAX - 1);
+        assert_eq!(queue.next_due(), Some(start + Duration::from_millis(10)));
+
+        let due = queue.take_due(start + Duration::from_millis(20));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, second);
+        assert_eq!(due[0].1.context_id, 3);
+        assert_eq!(queue.next_due(), Some(start + Duration::from_millis(50)));
+        assert!(queue.take_due(start + Duration::from_millis(20)).is_empty());
+    }
+
+    #[test]
+    fn maps_responses_back_to_their_placeholder() {
+        let queue = RetryQueue::default();
+        let placeholder = queue.schedule(call(2, SystemTime::UNIX_EPOCH));
+        queue.take_due(SystemTime::UNIX_EPOCH);
+        queue.dispatched(7, 2, placeholder);
+
+        assert_eq!(queue.resolve(7), placeholder);
+        assert_eq!(queue.resolve(7), 7);
+        assert_eq!(queue.resolve(8), 8);
+    }
+
+    #[test]
+    fn forgets_the_retries_of_done_contexts() {
+        let queue = RetryQueue::default();
+        queue.schedule(call(2, SystemTime::UNIX_EPOCH));
+        let kept = queue.schedule(call(3, SystemTime::UNIX_EPOCH));
+        queue.dispatched(7, 2, u32::MAX - 5);
+
+        queue.cancel(2);
+        assert_eq!(queue.resolve(7), 7);
+        let due = queue.take_due(SystemTime::UNIX_EPOCH);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, kept);
+    }
+}
@@ -1,23 +1,53 @@
+use super::drain::DrainState;
+use super::health::UpstreamHealth;
 use super::kuadrant_filter::KuadrantFilter;
+use super::local_reply::send_local_reply;
+use super::logger::FilterLogger;
+use super::retry_queue::RetryQueue;
+use super::watchdog::{CallWatchdog, ExpiredCall};
 use super::DescriptorManager;
-use crate::configuration::PluginConfiguration;
+use crate::configuration::{ConfigDelta, ConfigValidator, DynamicActionSpec, PluginConfiguration};
 use crate::kuadrant::PipelineFactory;
 use crate::metrics::METRICS;
+use crate::services::{is_serving, HealthCheck};
 use crate::{WASM_SHIM_FEATURES, WASM_SHIM_GIT_HASH, WASM_SHIM_PROFILE, WASM_SHIM_VERSION};
 use const_format::formatcp;
+use proxy_wasm::hostcalls;
 use proxy_wasm::traits::{Context, HttpContext, RootContext};
 use proxy_wasm::types::ContextType;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
 use std::rc::Rc;
 use std::time::Duration;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 const WASM_SHIM_HEADER: &str = "Kuadrant wasm module";
+const CONFIG_HASH_KEY: &str = "kuadrant.config.hash";
+const DRAIN_TICK_PERIOD: Duration = Duration::from_millis(100);
+const MIN_TICK_PERIOD: Duration = Duration::from_millis(10);
+/// Set through the `vm_config.environment_variables` of the filter, a plugin
+/// configuration overriding the one from Envoy in `dev` builds. Filters cannot
+/// read the host filesystem, so it holds the JSON itself rather than a path.
+const DEV_CONFIG_ENV: &str = "DEV_WASM_CONFIG";
+#[cfg(feature = "gzip-config")]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Past which a gzipped configuration is refused rather than inflated further,
+/// as a tiny archive can inflate to more than the VM has memory for
+#[cfg(feature = "gzip-config")]
+const MAX_DECOMPRESSED_CONFIG_SIZE: u64 = 16 * 1024 * 1024;
 
 pub struct FilterRoot {
     pub context_id: u32,
     pub pipeline_factory: Rc<PipelineFactory>,
     pub descriptor_manager: Rc<DescriptorManager>,
+    drain: Rc<DrainState>,
+    drain_timeout: Duration,
+    watchdog: Rc<CallWatchdog>,
+    retry_queue: Rc<RetryQueue>,
+    health: UpstreamHealth,
     tick_enabled: bool,
+    /// Whether a full configuration was applied, which config deltas build on
+    configured: bool,
 }
 
 impl FilterRoot {
@@ -26,13 +56,19 @@ impl FilterRoot {
             context_id,
             pipeline_factory: Rc::new(PipelineFactory::default()),
             descriptor_manager: Rc::new(DescriptorManager::default()),
+            drain: Rc::new(DrainState::default()),
+            drain_timeout: Duration::ZERO,
+            watchdog: Rc::new(CallWatchdog::default()),
+            retry_queue: Rc::new(RetryQueue::default()),
+            health: UpstreamHealth::default(),
             tick_enabled: false,
+            configured: false,
         }
     }
 
     fn set_tick_enabled(&mut self, enable: bool) {
         if enable && !self.tick_enabled {
-            if let Err(e) = self.set_tick_period(self.descriptor_manager.tick_period()) {
+            if let Err(e) = self.set_tick_period(self.tick_period()) {
                 error!("Failed to enable tick: {:?}", e);
             } else {
                 self.tick_enabled = true;
@@ -46,8 +82,261 @@ impl FilterRoot {
         }
     }
 
+    fn tick_period(&self) -> Duration {
+        let period = self.descriptor_manager.tick_period();
+        [
+            self.watchdog.timeout(),
+            self.pipeline_factory.retry_tick_period(),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(period, Duration::min)
+    }
+
+    /// Ticks often enough to notice the drain completing, yet no less often
+    /// than the watched calls and the retries need
+    fn drain_tick_period(&self) -> Duration {
+        DRAIN_TICK_PERIOD
+            .min(self.tick_period())
+            .max(MIN_TICK_PERIOD)
+    }
+
+    /// Answers with a 504 the requests held back by a gRPC call past its
+    /// deadline. Calls to services failing open, those not holding back the
+    /// request, and any in dry run are instead given up on, the request
+    /// resuming once it awaits nothing else.
+    fn check_watchdog(&self) {
+        let now = self.get_current_time();
+        let expired = self.watchdog.take_expired(now);
+        let mut answered = BTreeSet::new();
+        let mut given_up = BTreeMap::new();
+        for call in &expired {
+            let ExpiredCall {
+                token_id,
+                context_id,
+                policy,
+            } = *call;
+            self.drain.complete(token_id);
+            METRICS.errors().increment();
+            warn!(
+                "#{} gRPC call {} got no response in time",
+                context_id, token_id
+            );
+            if policy.holds_request && !policy.fails_open {
+                if answered.contains(&context_id) {
+                    continue;
+                }
+                let reply = hostcalls::set_effective_context(context_id).and_then(|_| {
+                    send_local_reply(
+                        FilterLogger::new(context_id),
+                        self.pipeline_factory.dry_run(),
+                        504,
+                        b"Gateway Timeout",
+                    )
+                });
+                match reply {
+                    Ok(false) => {}
+                    Ok(true) => {
+                        answered.insert(context_id);
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("#{} failed to send timeout response: {:?}", context_id, e);
+                        answered.insert(context_id);
+                        continue;
+                    }
+                }
+            }
+            self.watchdog.abandon(call);
+            if policy.holds_request {
+                given_up.insert(context_id, policy.response_phase);
+            }
+        }
+        for (context_id, response_phase) in given_up {
+            if answered.contains(&context_id) || self.watchdog.holds_request(context_id) {
+                continue;
+            }
+            let resumed = hostcalls::set_effective_context(context_id).and_then(|_| {
+                if response_phase {
+                    hostcalls::resume_http_response()
+                } else {
+                    hostcalls::resume_http_request()
+                }
+            });
+            if let Err(e) = resumed {
+                error!("#{} failed to resume: {:?}", context_id, e);
+            }
+        }
+        if !expired.is_empty() {
+            if let Err(e) = hostcalls::set_effective_context(self.context_id) {
+                error!("Failed to restore the root context: {:?}", e);
+            }
+        }
+    }
+
+    /// Dispatches the retries whose back-off delay has elapsed on behalf of
+    /// the HTTP context awaiting them. One failing to be dispatched is left to
+    /// the watchdog.
+    fn dispatch_due_retries(&self) {
+        let due = self.retry_queue.take_due(self.get_current_time());
+        if due.is_empty() {
+            return;
+        }
+        for (placeholder, call) in due {
+            let headers = call
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_slice()))
+                .collect();
+            let dispatched = hostcalls::set_effective_context(call.context_id).and_then(|_| {
+                hostcalls::dispatch_grpc_call(
+                    call.request.grpc_service(),
+                    call.request.service_name(),
+                    call.request.method(),
+                    headers,
+                    Some(call.request.message()),
+                    call.timeout,
+                )
+            });
+            match dispatched {
+                Ok(token_id) => self
+                    .retry_queue
+                    .dispatched(token_id, call.context_id, placeholder),
+                Err(e) => {
+                    METRICS.errors().increment();
+                    error!(
+                        "#{} failed to retry gRPC call to {}: {:?}",
+                        call.context_id,
+                        call.request.upstream_name(),
+                        e
+                    );
+                }
+            }
+        }
+        if let Err(e) = hostcalls::set_effective_context(self.context_id) {
+            error!("Failed to restore the root context: {:?}", e);
+        }
+    }
+
+    /// Ticks again in time for the next watched call to expire or the next
+    /// retry to be due
+    fn reschedule_tick(&self) {
+        let now = self.get_current_time();
+        let next = [self.watchdog.next_deadline(), self.retry_queue.next_due()]
+            .into_iter()
+            .flatten()
+            .min();
+        let period = match next {
+            Some(next) => next
+                .duration_since(now)
+                .unwrap_or(Duration::ZERO)
+                .max(MIN_TICK_PERIOD)
+                .min(self.tick_period()),
+            None => self.tick_period(),
+        };
+        if let Err(e) = self.set_tick_period(period) {
+            error!("Failed to reschedule tick: {:?}", e);
+        }
+    }
+
+    fn dispatch_health_checks(&self, checks: Vec<HealthCheck>) {
+        let services: Vec<String> = checks.iter().map(|check| check.service.clone()).collect();
+        self.health.dispatch(checks, |request| {
+            self.dispatch_grpc_call(
+                request.grpc_service(),
+                request.service_name(),
+                request.method(),
+                vec![],
+                Some(request.message()),
+                request.timeout(),
+            )
+        });
+        for service in services {
+            self.sync_service_health(&service);
+        }
+    }
+
+    /// Lets the services of the pipeline factory know whether their upstream
+    /// is down, so each of their actions follows its own failure mode.
+    fn sync_service_health(&self, service: &str) {
+        self.pipeline_factory
+            .set_service_health(service, !self.health.is_unhealthy(service));
+    }
+
+    /// Checks again the upstreams found down, so requests are processed once
+    /// they recover.
+    fn recheck_unhealthy_upstreams(&self) {
+        let checks = self
+            .pipeline_factory
+            .health_checks()
+            .into_iter()
+            .filter(|check| {
+                self.health.is_unhealthy(&check.service) && !self.health.is_checking(&check.service)
+            })
+            .collect();
+        self.dispatch_health_checks(checks);
+    }
+
+    fn handle_health_response(&self, token_id: u32, status_code: u32, response_size: usize) {
+        let Some(service) = self.health.service_of(token_id) else {
+            return;
+        };
+        let serving = status_code == 0
+            && match self.get_grpc_call_response_body(0, response_size) {
+                Ok(body) => is_serving(&body.unwrap_or_default()).unwrap_or_else(|e| {
+                    warn!("Failed to read health check response: {}", e);
+                    false
+                }),
+                Err(e) => {
+                    warn!("Failed to get health check response: {:?}", e);
+                    false
+                }
+            };
+        self.health.complete(token_id, serving);
+        self.sync_service_health(&service);
+    }
+
+    /// Shares the hash of the active configuration, so divergence across clusters
+    /// can be detected by comparing it.
+    fn publish_config_hash(&self, hash: [u8; 32]) {
+        let (previous, cas) = self.get_shared_data(CONFIG_HASH_KEY);
+        if previous.as_deref() != Some(hash.as_slice()) {
+            info!(
+                "config hash changed: {} -> {}",
+                previous.as_deref().map(to_hex).unwrap_or_default(),
+                to_hex(&hash)
+            );
+        }
+        if let Err(e) = self.set_shared_data(CONFIG_HASH_KEY, Some(&hash), cas) {
+            error!("Failed to publish config hash: {:?}", e);
+        }
+    }
+
+    /// Finishes the drain once no gRPC call is in flight, or gives up on the
+    /// remaining ones after the drain timeout.
+    fn check_drain(&self) {
+        if self.drain.is_complete() {
+            info!("#{} drain complete", self.context_id);
+            self.done();
+        } else if self
+            .drain
+            .has_timed_out(self.get_current_time(), self.drain_timeout)
+        {
+            warn!(
+                "#{} drain timed out, abandoning in-flight gRPC calls: {:?}",
+                self.context_id,
+                self.drain.in_flight_tokens()
+            );
+            self.done();
+        }
+    }
+
     fn process_config(&mut self, config: PluginConfiguration) -> bool {
         let descriptor_service = config.descriptor_service.clone();
+        self.drain_timeout = config.drain_timeout.0;
+        self.watchdog
+            .set_timeout(config.grpc_watchdog_timeout.map(|timeout| timeout.0));
+        let dynamic_actions_queue = config.dynamic_actions_queue.clone();
 
         let factory = match PipelineFactory::try_from(config, &self.descriptor_manager) {
             Ok(f) => f,
@@ -58,9 +347,15 @@ impl FilterRoot {
         };
 
         self.pipeline_factory = Rc::new(factory);
+        self.configured = true;
         self.descriptor_manager
             .set_descriptor_service(&descriptor_service);
 
+        self.health = UpstreamHealth::default();
+        let health_checks = self.pipeline_factory.health_checks();
+        let has_health_checks = !health_checks.is_empty();
+        self.dispatch_health_checks(health_checks);
+
         let has_dynamic_services = self.descriptor_manager.has_expected();
         if has_dynamic_services {
             if let Err(e) = self.descriptor_manager.fetch_missing(self) {
@@ -68,8 +363,50 @@ impl FilterRoot {
             }
         }
 
-        self.set_tick_enabled(has_dynamic_services);
+        let has_dynamic_actions = match dynamic_actions_queue {
+            Some(queue_name) => match hostcalls::register_shared_queue(&queue_name) {
+                Ok(queue_id) => {
+                    info!("listening for dynamic actions on queue {queue_name} ({queue_id})");
+                    true
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to register dynamic actions queue {queue_name}: {:?}",
+                        e
+                    );
+                    false
+                }
+            },
+            None => false,
+        };
+
+        self.set_tick_enabled(
+            has_dynamic_services
+                || has_dynamic_actions
+                || has_health_checks
+                || self.watchdog.timeout().is_some()
+                || self.pipeline_factory.retry_tick_period().is_some(),
+        );
+
+        true
+    }
 
+    /// Updates the action sets of the running configuration in place, which
+    /// the requests already in flight keep using as they were
+    fn apply_config_delta(&self, delta: ConfigDelta) -> bool {
+        if let Err(e) = self.pipeline_factory.apply_delta(&delta) {
+            error!(
+                "config delta rejected, action sets left as they were: {}",
+                e
+            );
+            return false;
+        }
+        info!(
+            "config delta applied: {} action set(s) removed, {} upserted",
+            delta.remove_action_sets.len(),
+            delta.upsert_action_sets.len()
+        );
+        self.pipeline_factory.reset_index_stats();
         true
     }
 
@@ -121,35 +458,86 @@ impl RootContext for FilterRoot {
         Some(Box::new(KuadrantFilter::new(
             context_id,
             Rc::clone(&self.pipeline_factory),
+            Rc::clone(&self.drain),
+            Rc::clone(&self.watchdog),
+            Rc::clone(&self.retry_queue),
         )))
     }
 
     fn on_configure(&mut self, _config_size: usize) -> bool {
         log::info!("#{} on_configure", self.context_id);
         METRICS.configs().increment();
-        let configuration: Vec<u8> = match self.get_plugin_configuration() {
-            Ok(cfg) => match cfg {
-                Some(c) => c,
-                None => return false,
+        let configuration: Vec<u8> = match dev_configuration(|name| std::env::var(name).ok()) {
+            Some(c) => {
+                log::warn!(
+                    "#{} on_configure: using the configuration from {}",
+                    self.context_id,
+                    DEV_CONFIG_ENV
+                );
+                c
+            }
+            None => match self.get_plugin_configuration() {
+                Ok(cfg) => match cfg {
+                    Some(c) => c,
+                    None => return false,
+                },
+                Err(status) => {
+                    log::error!("#{} on_configure: {:?}", self.context_id, status);
+                    return false;
+                }
             },
-            Err(status) => {
-                log::error!("#{} on_configure: {:?}", self.context_id, status);
+        };
+        #[cfg(feature = "gzip-config")]
+        let configuration =
+            match decompress_configuration(configuration, MAX_DECOMPRESSED_CONFIG_SIZE) {
+                Ok(configuration) => configuration,
+                Err(e) => {
+                    log::error!("#{} on_configure: invalid gzip: {}", self.context_id, e);
+                    return false;
+                }
+            };
+        if ConfigDelta::is_delta(&configuration) {
+            if !self.configured {
+                log::error!("plugin config delta received before any full configuration");
                 return false;
             }
-        };
-        match serde_json::from_slice::<PluginConfiguration>(&configuration) {
+            return match ConfigValidator::validate_delta(
+                &configuration,
+                self.pipeline_factory.rate_limit_services(),
+            ) {
+                Ok(delta) => self.apply_config_delta(delta),
+                Err(errors) => {
+                    log::error!("invalid plugin config delta, {} error(s):", errors.len());
+                    for e in errors {
+                        log::error!("  {}", e);
+                    }
+                    false
+                }
+            };
+        }
+        match ConfigValidator::validate(&configuration) {
             Ok(config) => {
+                let config = config.into_inner();
                 let use_tracing_exporter = config.observability.tracing.is_some();
                 crate::tracing::init_observability(
                     use_tracing_exporter,
                     config.observability.default_level.as_deref(),
                 );
 
-                info!("plugin config parsed: {:?}", config);
+                info!(
+                    "plugin config parsed, version {}: {:?}",
+                    config.version, config
+                );
+                if config.observability.publish_config_hash {
+                    self.publish_config_hash(config_hash(&configuration));
+                }
                 self.process_config(config)
             }
-            Err(e) => {
-                log::error!("failed to parse plugin config: {}", e);
+            Err(errors) => {
+                log::error!("invalid plugin config, {} error(s):", errors.len());
+                for e in errors {
+                    log::error!("  {}", e);
+                }
                 false
             }
         }
@@ -160,19 +548,136 @@ impl RootContext for FilterRoot {
     }
 
     fn on_tick(&mut self) {
+        self.dispatch_due_retries();
+        // Ahead of the drain check, as answering the expired calls is what
+        // lets a drain waiting on them complete
+        if self.watchdog.timeout().is_some() {
+            self.check_watchdog();
+        }
+        if self.drain.is_draining() {
+            self.check_drain();
+            return;
+        }
         if let Err(e) = self.descriptor_manager.fetch_missing(self) {
             error!("Failed to fetch missing descriptors on tick: {}", e);
         }
+        let evicted = self
+            .pipeline_factory
+            .evict_expired_dynamic_actions(self.get_current_time());
+        if evicted > 0 {
+            debug!("evicted {} expired dynamic actions", evicted);
+        }
+        if self.watchdog.timeout().is_some() || self.pipeline_factory.retry_tick_period().is_some()
+        {
+            self.reschedule_tick();
+        }
+        if self.health.is_degraded() {
+            self.recheck_unhealthy_upstreams();
+        }
+    }
+
+    fn on_queue_ready(&mut self, queue_id: u32) {
+        loop {
+            let message = match self.dequeue_shared_queue(queue_id) {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Failed to dequeue dynamic action: {:?}", e);
+                    break;
+                }
+            };
+            let spec = match serde_json::from_slice::<DynamicActionSpec>(&message) {
+                Ok(spec) => spec,
+                Err(e) => {
+                    warn!("Discarding malformed dynamic action: {}", e);
+                    continue;
+                }
+            };
+            match self.pipeline_factory.inject_dynamic_action(&spec) {
+                Ok(()) => info!(
+                    "injected dynamic action into {} until {}",
+                    spec.target_action_set, spec.expires_at_ms
+                ),
+                Err(e) => warn!(
+                    "Discarding dynamic action for {}: {}",
+                    spec.target_action_set, e
+                ),
+            }
+        }
     }
 }
 
 impl Context for FilterRoot {
     fn on_grpc_call_response(&mut self, token_id: u32, status_code: u32, response_size: usize) {
+        if self.health.is_pending(token_id) {
+            self.handle_health_response(token_id, status_code, response_size);
+            return;
+        }
         if let Err(e) = self.handle_descriptor_response(token_id, status_code, response_size) {
             error!("Failed to handle descriptor response: {}", e);
         }
         self.descriptor_manager.reset_pending(token_id);
     }
+
+    fn on_done(&mut self) -> bool {
+        self.drain.start(self.get_current_time());
+        if self.drain.is_complete() {
+            info!("#{} drain complete", self.context_id);
+            return true;
+        }
+        info!(
+            "#{} draining {} in-flight gRPC calls",
+            self.context_id,
+            self.drain.in_flight_tokens().len()
+        );
+        if let Err(e) = self.set_tick_period(self.drain_tick_period()) {
+            error!("Failed to enable drain tick: {:?}", e);
+        } else {
+            self.tick_enabled = true;
+        }
+        false
+    }
+}
+
+/// The configuration `lookup` finds under [`DEV_CONFIG_ENV`], never looked up
+/// outside of `dev` builds; blank falls back to the one from Envoy.
+fn dev_configuration(lookup: impl FnOnce(&str) -> Option<String>) -> Option<Vec<u8>> {
+    if !cfg!(feature = "dev") {
+        return None;
+    }
+    lookup(DEV_CONFIG_ENV)
+        .filter(|configuration| !configuration.trim().is_empty())
+        .map(String::into_bytes)
+}
+
+/// Inflates `configuration` when it starts with the gzip magic number, for
+/// configurations larger than Envoy accepts inline, up to `max_size` bytes
+#[cfg(feature = "gzip-config")]
+fn decompress_configuration(configuration: Vec<u8>, max_size: u64) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    if !configuration.starts_with(&GZIP_MAGIC) {
+        return Ok(configuration);
+    }
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(configuration.as_slice())
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() as u64 > max_size {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("configuration inflates past {max_size} bytes"),
+        ));
+    }
+    Ok(decompressed)
+}
+
+fn config_hash(configuration: &[u8]) -> [u8; 32] {
+    Sha256::digest(configuration).into()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
 }
 
 #[cfg(test)]
@@ -305,4 +810,95 @@ mod tests {
         let result = PipelineFactory::try_from(config, &descriptor_manager);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn dev_configuration_is_only_looked_up_in_dev_builds() {
+        let looked_up = std::cell::Cell::new(false);
+        let configuration = dev_configuration(|name| {
+            looked_up.set(true);
+            assert_eq!(name, DEV_CONFIG_ENV);
+            Some(r#"{"services": {}, "actionSets": []}"#.to_string())
+        });
+        assert_eq!(looked_up.get(), cfg!(feature = "dev"));
+        assert_eq!(configuration.is_some(), cfg!(feature = "dev"));
+    }
+
+    #[test]
+    fn missing_or_blank_dev_configuration_falls_back() {
+        assert_eq!(dev_configuration(|_| None), None);
+        assert_eq!(dev_configuration(|_| Some(" \n".to_string())), None);
+    }
+
+    #[cfg(feature = "gzip-config")]
+    #[test]
+    fn gzipped_configuration_builds_the_same_factory() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        // A single capability, as the order of a set varies between instances
+        let configuration = serde_json::json!({
+            "services": {
+                "limitador": {
+                    "type": "ratelimit",
+                    "endpoint": "limitador-cluster",
+                    "failureMode": "deny",
+                    "timeout": "5s"
+                }
+            },
+            "registeredCapabilities": ["rate_limit"],
+            "actionSets": [{
+                "name": "toystore",
+                "routeRuleConditions": {"hostnames": ["*.toystore.com"]},
+                "actions": [{"service": "limitador", "scope": "toystore"}]
+            }]
+        })
+        .to_string()
+        .into_bytes();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&configuration).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(compressed[..2], GZIP_MAGIC);
+
+        let max_size = configuration.len() as u64;
+        let decompressed = decompress_configuration(compressed.clone(), max_size).unwrap();
+        assert_eq!(decompressed, configuration);
+        assert_eq!(
+            decompress_configuration(configuration.clone(), max_size).unwrap(),
+            configuration
+        );
+        assert!(decompress_configuration(vec![0x1f, 0x8b, 0x00], max_size).is_err());
+        assert_eq!(
+            decompress_configuration(compressed, max_size - 1)
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+
+        let gzipped = ConfigValidator::validate(&decompressed)
+            .unwrap()
+            .into_inner();
+        let plain = ConfigValidator::validate(&configuration)
+            .unwrap()
+            .into_inner();
+        assert_eq!(format!("{gzipped:?}"), format!("{plain:?}"));
+        assert!(PipelineFactory::try_from(gzipped, &Rc::new(DescriptorManager::default())).is_ok());
+    }
+
+    #[test]
+    fn config_hash_is_deterministic() {
+        let config = br#"{"services": {}, "actionSets": []}"#;
+        assert_eq!(config_hash(config), config_hash(config));
+        assert_eq!(
+            to_hex(&config_hash(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn config_hash_differs_for_different_configs() {
+        let a = br#"{"services": {}, "actionSets": []}"#;
+        let b = br#"{"services": {}, "actionSets": [{"name": "b"}]}"#;
+        assert_ne!(config_hash(a), config_hash(b));
+    }
 }
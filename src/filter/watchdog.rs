@@ -0,0 +1,204 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, SystemTime};
+
+/// How the request awaiting a call is to be handled once the call is given up on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CallPolicy {
+    /// The request is paused until the call gets a response
+    pub holds_request: bool,
+    /// The call is to a service whose `failureMode` is `allow`
+    pub fails_open: bool,
+    /// The call was made while the response, rather than the request, was
+    /// being processed
+    pub response_phase: bool,
+}
+
+struct Watched {
+    context_id: u32,
+    deadline: SystemTime,
+    policy: CallPolicy,
+}
+
+/// A call past its deadline, no longer watched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExpiredCall {
+    pub token_id: u32,
+    pub context_id: u32,
+    pub policy: CallPolicy,
+}
+
+/// Shared between the root context and its HTTP contexts, so a request whose
+/// gRPC call never gets a response from the host can be answered on tick
+/// rather than staying paused forever.
+#[derive(Default)]
+pub struct CallWatchdog {
+    timeout: Cell<Option<Duration>>,
+    calls: RefCell<BTreeMap<u32, Watched>>,
+    /// Expired calls the HTTP contexts are to give up on, by context
+    abandoned: RefCell<BTreeMap<u32, BTreeSet<u32>>>,
+}
+
+impl CallWatchdog {
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.timeout.set(timeout);
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.get()
+    }
+
+    /// Starts watching the calls not watched yet, a call keeps the deadline it
+    /// got when first seen.
+    pub fn track(
+        &self,
+        context_id: u32,
+        calls: impl Iterator<Item = (u32, CallPolicy)>,
+        now: SystemTime,
+    ) {
+        let Some(timeout) = self.timeout.get() else {
+            return;
+        };
+        let mut watched = self.calls.borrow_mut();
+        for (token_id, policy) in calls {
+            watched.entry(token_id).or_insert(Watched {
+                context_id,
+                deadline: now + timeout,
+                policy,
+            });
+        }
+    }
+
+    pub fn complete(&self, token_id: u32) {
+        self.calls.borrow_mut().remove(&token_id);
+    }
+
+    /// Stops watching the calls past their deadline at `now`, returning them
+    /// along with the HTTP context awaiting them.
+    pub fn take_expired(&self, now: SystemTime) -> Vec<ExpiredCall> {
+        let mut calls = self.calls.borrow_mut();
+        let expired: Vec<ExpiredCall> = calls
+            .iter()
+            .filter(|(_, watched)| watched.deadline <= now)
+            .map(|(token_id, watched)| ExpiredCall {
+                token_id: *token_id,
+                context_id: watched.context_id,
+                policy: watched.policy,
+            })
+            .collect();
+        for call in &expired {
+            calls.remove(&call.token_id);
+        }
+        expired
+    }
+
+    /// Whether the HTTP context is still paused on a watched call
+    pub fn holds_request(&self, context_id: u32) -> bool {
+        self.calls
+            .borrow()
+            .values()
+            .any(|watched| watched.context_id == context_id && watched.policy.holds_request)
+    }
+
+    /// Leaves the expired call for its HTTP context to give up on, the next
+    /// time it is called back
+    pub fn abandon(&self, call: &ExpiredCall) {
+        self.abandoned
+            .borrow_mut()
+            .entry(call.context_id)
+            .or_default()
+            .insert(call.token_id);
+    }
+
+    pub fn take_abandoned(&self, context_id: u32) -> BTreeSet<u32> {
+        self.abandoned
+            .borrow_mut()
+            .remove(&context_id)
+            .unwrap_or_default()
+    }
+
+    pub fn next_deadline(&self) -> Option<SystemTime> {
+        self.calls
+            .borrow()
+            .values()
+            .map(|watched| watched.deadline)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    const GUARD: CallPolicy = CallPolicy {
+        holds_request: true,
+        fails_open: false,
+        response_phase: false,
+    };
+
+    fn guards(tokens: &[u32]) -> impl Iterator<Item = (u32, CallPolicy)> + '_ {
+        tokens.iter().map(|token_id| (*token_id, GUARD))
+    }
+
+    #[test]
+    fn disabled_without_timeout() {
+        let watchdog = CallWatchdog::default();
+        watchdog.track(1, guards(&[3]), SystemTime::UNIX_EPOCH);
+        assert_eq!(watchdog.next_deadline(), None);
+    }
+
+    #[test]
+    fn expires_calls_past_their_deadline() {
+        let start = SystemTime::UNIX_EPOCH;
+        let watchdog = CallWatchdog::default();
+        watchdog.set_timeout(Some(TIMEOUT));
+
+        watchdog.track(1, guards(&[3]), start);
+        let later = start + Duration::from_secs(1);
+        watchdog.track(2, guards(&[3, 7]), later);
+        assert_eq!(watchdog.next_deadline(), Some(start + TIMEOUT));
+
+        assert!(watchdog.take_expired(later).is_empty());
+        assert_eq!(
+            watchdog.take_expired(start + TIMEOUT),
+            vec![ExpiredCall {
+                token_id: 3,
+                context_id: 1,
+                policy: GUARD
+            }]
+        );
+        assert_eq!(watchdog.next_deadline(), Some(later + TIMEOUT));
+
+        watchdog.complete(7);
+        assert!(watchdog.take_expired(later + TIMEOUT).is_empty());
+        assert_eq!(watchdog.next_deadline(), None);
+    }
+
+    #[test]
+    fn hands_abandoned_calls_to_their_context() {
+        let start = SystemTime::UNIX_EPOCH;
+        let watchdog = CallWatchdog::default();
+        watchdog.set_timeout(Some(TIMEOUT));
+
+        let report = CallPolicy {
+            holds_request: false,
+            ..GUARD
+        };
+        watchdog.track(1, [(3, GUARD), (5, report)].into_iter(), start);
+        watchdog.track(1, guards(&[7]), start + Duration::from_secs(1));
+        assert!(watchdog.holds_request(1));
+
+        for call in watchdog.take_expired(start + TIMEOUT) {
+            watchdog.abandon(&call);
+        }
+        assert!(watchdog.holds_request(1), "call 7 is still awaited");
+        watchdog.complete(7);
+        assert!(!watchdog.holds_request(1));
+
+        assert_eq!(watchdog.take_abandoned(1), BTreeSet::from([3, 5]));
+        assert!(watchdog.take_abandoned(1).is_empty());
+        assert!(watchdog.take_abandoned(2).is_empty());
+    }
+}
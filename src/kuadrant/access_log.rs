@@ -0,0 +1,139 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::metrics::{CallOutcome, CallService};
+
+/// The access log entry of a request, shared between its HTTP context and its
+/// pipeline so the decisions taken outlive the latter.
+pub type SharedAccessLog = Rc<RefCell<AccessLogEntry>>;
+
+/// What the filter decided for a request, logged as a JSON line once the
+/// exchange completes.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct AccessLogEntry {
+    pub context_id: u32,
+    /// When the request headers were received, in RFC 3339
+    pub ts: Option<String>,
+    pub method: Option<String>,
+    pub authority: Option<String>,
+    pub path: Option<String>,
+    /// The status of the response sent downstream, local replies included
+    pub status: Option<u32>,
+    pub matched_action_set: Option<String>,
+    pub auth_decision: AuthDecision,
+    pub rl_decision: RateLimitDecision,
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthDecision {
+    Allow,
+    Deny,
+    Error,
+    #[default]
+    Skip,
+}
+
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitDecision {
+    Allowed,
+    Limited,
+    Error,
+    #[default]
+    Skip,
+}
+
+impl AccessLogEntry {
+    pub fn new(context_id: u32) -> Self {
+        Self {
+            context_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn record_call(&mut self, service: CallService, outcome: CallOutcome) {
+        match service {
+            CallService::Auth => {
+                self.auth_decision = match outcome {
+                    CallOutcome::Ok => AuthDecision::Allow,
+                    CallOutcome::Rejected => AuthDecision::Deny,
+                    CallOutcome::Error => AuthDecision::Error,
+                }
+            }
+            CallService::RateLimit => {
+                self.rl_decision = match outcome {
+                    CallOutcome::Ok => RateLimitDecision::Allowed,
+                    CallOutcome::Rejected => RateLimitDecision::Limited,
+                    CallOutcome::Error => RateLimitDecision::Error,
+                }
+            }
+        }
+    }
+
+    pub fn set_start(&mut self, start: SystemTime) {
+        self.ts = Some(DateTime::<Utc>::from(start).to_rfc3339_opts(SecondsFormat::Micros, true));
+    }
+
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration_ms = Some(duration.as_millis() as u64);
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_is_a_json_line_with_every_field() {
+        let mut entry = AccessLogEntry::new(2);
+        entry.set_start(SystemTime::UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456));
+        entry.method = Some("GET".to_string());
+        entry.authority = Some("api.toystore.com".to_string());
+        entry.path = Some("/toys?page=2".to_string());
+        entry.status = Some(429);
+        entry.matched_action_set = Some("toystore".to_string());
+        entry.record_call(CallService::Auth, CallOutcome::Ok);
+        entry.record_call(CallService::RateLimit, CallOutcome::Rejected);
+        entry.set_duration(Duration::from_micros(12_500));
+
+        let line = entry.to_json().unwrap();
+        assert!(!line.contains('\n'));
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "context_id": 2,
+                "ts": "2023-11-14T22:13:20.123456Z",
+                "method": "GET",
+                "authority": "api.toystore.com",
+                "path": "/toys?page=2",
+                "status": 429,
+                "matched_action_set": "toystore",
+                "auth_decision": "allow",
+                "rl_decision": "limited",
+                "duration_ms": 12,
+            })
+        );
+    }
+
+    #[test]
+    fn services_not_called_are_skipped() {
+        let mut entry = AccessLogEntry::new(7);
+        entry.record_call(CallService::Auth, CallOutcome::Error);
+
+        let json: serde_json::Value = serde_json::from_str(&entry.to_json().unwrap()).unwrap();
+        assert_eq!(json["auth_decision"], "error");
+        assert_eq!(json["rl_decision"], "skip");
+        assert_eq!(json["matched_action_set"], serde_json::Value::Null);
+    }
+}
@@ -1,18 +1,35 @@
 use cel::Value;
-use std::cell::OnceCell;
+use std::cell::{OnceCell, RefCell};
 use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tracing::{debug, warn};
 
-use crate::data::attribute::{wasm_prop, AttributeError, AttributeState, AttributeValue, Path};
+use crate::configuration::TracingHeaderStyle;
+use crate::data::attribute::{
+    encode_list, get_metadata_generation, wasm_prop, AttributeError, AttributeState,
+    AttributeValue, ListElement, Path, METADATA_GENERATION_PATH,
+};
+use crate::data::client_ip::client_ip;
+use crate::data::tls::TlsCertificateAttributes;
+use crate::data::trace::extract_trace_id;
 use crate::data::{Expression, Headers};
+use crate::filter::{DelayedCall, FilterLogger, RetryQueue};
+use crate::kuadrant::access_log::SharedAccessLog;
 use crate::kuadrant::cache::{AttributeCache, CachedValue};
 use crate::kuadrant::resolver::{AttributeResolver, ProxyWasmHost};
-use crate::services::ServiceError;
+use crate::kuadrant::IndexStats;
+use crate::metrics::{CallOutcome, CallService, MetricsCollector};
+#[cfg(feature = "http-callout")]
+use crate::services::HttpCalloutRequest;
+use crate::services::{GrpcRequest, ServiceError};
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 use uuid::Uuid;
 
 const X_REQUEST_ID_HEADER: &str = "x-request-id";
+const X_ENVOY_EXPECTED_RQ_TIMEOUT_MS_HEADER: &str = "x-envoy-expected-rq-timeout-ms";
 
 type RequestData = ((String, String), Expression);
 
@@ -31,6 +48,20 @@ pub struct ReqRespCtx {
     request_body_values: HashMap<String, Value>,
     response_body_values: HashMap<String, Value>,
     stored_values: BTreeMap<String, Value>,
+    default_header_values: Arc<HashMap<String, String>>,
+    deadline: Option<SystemTime>,
+    computed_properties: Arc<HashMap<String, Expression>>,
+    computed_values: RefCell<HashMap<String, Value>>,
+    dry_run: bool,
+    logs_sampled: bool,
+    tracing_header_style: TracingHeaderStyle,
+    static_forwarded_headers: Arc<Vec<(String, String)>>,
+    metrics: Option<Rc<MetricsCollector>>,
+    index_stats: Option<IndexStats>,
+    access_log: Option<SharedAccessLog>,
+    forwarded_trailers: Vec<(String, Vec<u8>)>,
+    retry_queue: Option<(u32, Rc<RetryQueue>)>,
+    log: FilterLogger,
     pub barrier: Barrier,
 }
 
@@ -56,6 +87,20 @@ impl ReqRespCtx {
             request_body_values: HashMap::new(),
             response_body_values: HashMap::new(),
             stored_values: BTreeMap::new(),
+            default_header_values: Arc::new(HashMap::new()),
+            deadline: None,
+            computed_properties: Arc::new(HashMap::new()),
+            computed_values: RefCell::new(HashMap::new()),
+            dry_run: false,
+            logs_sampled: true,
+            tracing_header_style: TracingHeaderStyle::default(),
+            static_forwarded_headers: Arc::new(Vec::new()),
+            metrics: None,
+            index_stats: None,
+            access_log: None,
+            forwarded_trailers: Vec::new(),
+            retry_queue: None,
+            log: FilterLogger::new(0),
             barrier: Barrier::default(),
         }
     }
@@ -65,11 +110,150 @@ impl ReqRespCtx {
         self
     }
 
+    pub fn with_default_header_values(
+        mut self,
+        default_header_values: Arc<HashMap<String, String>>,
+    ) -> Self {
+        self.default_header_values = default_header_values;
+        self
+    }
+
+    pub fn with_logger(mut self, log: FilterLogger) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// The logger of the HTTP context this request is processed in
+    pub fn log(&self) -> FilterLogger {
+        self.log
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Whether decisions are only logged, never enforced
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn set_logs_sampled(&mut self, sampled: bool) {
+        self.logs_sampled = sampled;
+    }
+
+    /// Whether this request was sampled to emit its debug logs
+    pub fn logs_sampled(&self) -> bool {
+        self.logs_sampled
+    }
+
+    pub fn with_metrics(mut self, metrics: Option<Rc<MetricsCollector>>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// The hostname index counters as of this request's lookup, read as the
+    /// JSON `kuadrant.index.stats`
+    pub fn with_index_stats(mut self, index_stats: IndexStats) -> Self {
+        self.index_stats = Some(index_stats);
+        self
+    }
+
+    /// The collector of per action set metrics, when enabled
+    pub fn metrics(&self) -> Option<Rc<MetricsCollector>> {
+        self.metrics.clone()
+    }
+
+    pub fn with_access_log(mut self, access_log: Option<SharedAccessLog>) -> Self {
+        self.access_log = access_log;
+        self
+    }
+
+    /// Whether the outcome of auth and rate limit calls is recorded for the access log
+    pub fn records_calls(&self) -> bool {
+        self.access_log.is_some()
+    }
+
+    pub fn record_call(&self, service: CallService, outcome: CallOutcome) {
+        if let Some(access_log) = &self.access_log {
+            access_log.borrow_mut().record_call(service, outcome);
+        }
+    }
+
+    /// Lets the calls of the HTTP context `context_id` be retried after a
+    /// delay, from the root context sharing `retry_queue`
+    pub fn with_retry_queue(mut self, context_id: u32, retry_queue: Rc<RetryQueue>) -> Self {
+        self.retry_queue = Some((context_id, retry_queue));
+        self
+    }
+
+    pub fn with_tracing_header_style(mut self, tracing_header_style: TracingHeaderStyle) -> Self {
+        self.tracing_header_style = tracing_header_style;
+        self
+    }
+
+    pub fn with_static_forwarded_headers(
+        mut self,
+        static_forwarded_headers: Arc<Vec<(String, String)>>,
+    ) -> Self {
+        self.static_forwarded_headers = static_forwarded_headers;
+        self
+    }
+
+    pub fn with_computed_properties(
+        mut self,
+        computed_properties: Arc<HashMap<String, Expression>>,
+    ) -> Self {
+        self.computed_properties = computed_properties;
+        self
+    }
+
+    /// Bounds every gRPC call made on behalf of this request by the timeout Envoy
+    /// expects for the request itself. Keeps an already set deadline.
+    pub fn inherit_deadline(&mut self) {
+        if self.deadline.is_some() {
+            return;
+        }
+        let timeout_ms = self
+            .get_request_header(X_ENVOY_EXPECTED_RQ_TIMEOUT_MS_HEADER)
+            .and_then(|value| value.parse::<u64>().ok());
+        if let Some(timeout_ms) = timeout_ms {
+            self.set_deadline(Duration::from_millis(timeout_ms));
+        }
+    }
+
+    pub fn current_time(&self) -> SystemTime {
+        self.backend.get_current_time()
+    }
+
+    pub fn set_deadline(&mut self, timeout: Duration) {
+        self.deadline = Some(self.backend.get_current_time() + timeout);
+    }
+
+    fn remaining_time(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| {
+            deadline
+                .duration_since(self.backend.get_current_time())
+                .unwrap_or(Duration::ZERO)
+        })
+    }
+
     pub fn extract_trace_context(&mut self) {
         let request_headers: Result<AttributeState<Option<Headers>>, _> =
             self.get_attribute("request.headers");
 
-        if let Ok(AttributeState::Available(Some(header_map))) = request_headers {
+        if let Ok(AttributeState::Available(Some(mut header_map))) = request_headers {
+            for name in self.default_header_values.keys() {
+                if let Some((value, source)) =
+                    resolve_header(&header_map, &self.default_header_values, name)
+                {
+                    debug!("header {name} resolved from {source:?}");
+                    if source == HeaderSource::Default {
+                        let value = value.to_string();
+                        header_map.append(name.clone(), value);
+                    }
+                }
+            }
             let extractor = crate::tracing::HeadersExtractor::new(&header_map);
             self.tracing.otel_context =
                 opentelemetry::global::get_text_map_propagator(|propagator| {
@@ -78,6 +262,34 @@ impl ReqRespCtx {
         }
     }
 
+    /// Starts a new trace when none was extracted from the request, so the gRPC
+    /// calls made on its behalf carry a `traceparent` all the same.
+    pub fn generate_trace_context(&mut self, sampled: bool) {
+        if self.tracing.otel_context.span().span_context().is_valid() {
+            return;
+        }
+        let trace_id = TraceId::from_bytes(*Uuid::new_v4().as_bytes());
+        let mut span_id = [0u8; 8];
+        span_id.copy_from_slice(&Uuid::new_v4().as_bytes()[..8]);
+        let flags = if sampled {
+            TraceFlags::SAMPLED
+        } else {
+            TraceFlags::default()
+        };
+        let span_context = SpanContext::new(
+            trace_id,
+            SpanId::from_bytes(span_id),
+            flags,
+            true,
+            TraceState::default(),
+        );
+        debug!("generated trace context: {}", trace_id);
+        self.tracing.otel_context = self
+            .tracing
+            .otel_context
+            .with_remote_span_context(span_context);
+    }
+
     pub fn enter_request_span(&mut self) {
         let span = tracing::info_span!(
             "kuadrant_filter",
@@ -105,15 +317,27 @@ impl ReqRespCtx {
     }
 
     pub fn set_action_set_name(&mut self, name: String) {
+        if let Some(access_log) = &self.access_log {
+            access_log.borrow_mut().matched_action_set = Some(name.clone());
+        }
         self.tracing.action_set_name = Some(name);
     }
 
     pub fn set_hostname(&mut self, hostname: String) {
+        if let Some(access_log) = &self.access_log {
+            access_log.borrow_mut().authority = Some(hostname.clone());
+        }
         self.tracing.hostname = Some(hostname);
     }
 
     pub fn set_current_request_body_buffer_size(&mut self, body_size: usize, end_of_stream: bool) {
         self.request_body_size = body_size;
+        if end_of_stream && !self.request_end_of_stream {
+            // A request ending with its headers or body has no trailers to wait for
+            if let Err(e) = self.set_request_trailers(Headers::default()) {
+                warn!("Failed to record the absence of request trailers: {}", e);
+            }
+        }
         self.request_end_of_stream = end_of_stream;
     }
 
@@ -136,6 +360,10 @@ impl ReqRespCtx {
         Ok(())
     }
 
+    pub fn grpc_response_status(&self) -> Option<u32> {
+        self.grpc_response_data.map(|(status_code, _)| status_code)
+    }
+
     pub fn get_grpc_response_data(&mut self) -> Result<(u32, usize), ServiceError> {
         self.grpc_response_data
             .take()
@@ -149,6 +377,25 @@ impl ReqRespCtx {
         self.get_attribute_ref(&path.into())
     }
 
+    /// The attribute at `path`, or `default` when the host has none; a value
+    /// that fails to parse is still an error
+    pub fn get_attribute_or<T: AttributeValue>(
+        &self,
+        path: impl Into<Path>,
+        default: T,
+    ) -> Result<AttributeState<T>, AttributeError> {
+        Ok(self
+            .get_attribute(path)?
+            .map(|value| value.unwrap_or(default)))
+    }
+
+    pub fn get_attribute_or_default<T: AttributeValue + Default>(
+        &self,
+        path: impl Into<Path>,
+    ) -> Result<AttributeState<T>, AttributeError> {
+        Ok(self.get_attribute(path)?.map(Option::unwrap_or_default))
+    }
+
     pub fn get_request_header(&self, key: &str) -> Option<String> {
         match self
             .backend
@@ -172,6 +419,10 @@ impl ReqRespCtx {
 
     fn fetch_attribute(&self, path: &Path) -> Result<CachedValue, AttributeError> {
         match *path.tokens() {
+            // Trailers are only known once the filter receives them, see `set_request_trailers`
+            ["request", "trailers"] => Err(AttributeError::NotAvailable(
+                "request trailers not received yet".to_string(),
+            )),
             ["request", "headers"] => {
                 match self
                     .backend
@@ -195,6 +446,37 @@ impl ReqRespCtx {
                 let bytes = self.remote_address()?;
                 Ok(CachedValue::Bytes(bytes))
             }
+            ["kuadrant", "metadata", "generation"] => Ok(CachedValue::Bytes(None)),
+            ["kuadrant", "index", "stats"] => match self.index_stats {
+                Some(stats) => serde_json::to_vec(&stats)
+                    .map(|json| CachedValue::Bytes(Some(json)))
+                    .map_err(|e| AttributeError::Parse(format!("index stats: {e}"))),
+                None => Ok(CachedValue::Bytes(None)),
+            },
+            ["kuadrant", "client_ip"] => {
+                let source_address = self
+                    .backend
+                    .get_attribute(&"source.address".into())?
+                    .and_then(|bytes| String::from_utf8(bytes).ok());
+                let ip = client_ip(
+                    self.get_request_header("x-real-ip").as_deref(),
+                    self.get_request_header("x-forwarded-for").as_deref(),
+                    source_address.as_deref(),
+                );
+                Ok(CachedValue::Bytes(ip.map(|ip| ip.to_string().into_bytes())))
+            }
+            ["kuadrant", "trace_id"] => {
+                let trace_id = self
+                    .get_request_header("traceparent")
+                    .and_then(|traceparent| extract_trace_id(&traceparent));
+                Ok(CachedValue::Bytes(trace_id.map(String::into_bytes)))
+            }
+            ["kuadrant", "tls", ref field @ ..] => {
+                let tls = TlsCertificateAttributes::read(|path| self.backend.get_attribute(path))?;
+                Ok(CachedValue::Bytes(
+                    tls.and_then(|tls| tls.field_json(field)),
+                ))
+            }
             ["auth", ..] => {
                 let bytes = self.backend.get_attribute(&wasm_prop(&path.tokens()))?;
                 Ok(CachedValue::Bytes(bytes))
@@ -248,6 +530,13 @@ impl ReqRespCtx {
         }
     }
 
+    pub fn set_request_trailers(&self, trailers: Headers) -> Result<(), AttributeError> {
+        self.cache.insert(
+            Path::new(vec!["request", "trailers"]),
+            CachedValue::Headers(trailers),
+        )
+    }
+
     pub fn ensure_attributes(&self, paths: &[Path]) {
         for path in paths {
             if let Err(e) = self.cache.populate(path, || self.fetch_attribute(path)) {
@@ -289,6 +578,26 @@ impl ReqRespCtx {
         }
     }
 
+    /// Stores `values` as a protobuf `ListValue`, read back with
+    /// `get_attribute::<Vec<T>>`
+    pub fn set_property_list<T: ListElement>(
+        &self,
+        attribute_path: &str,
+        values: Vec<T>,
+    ) -> Result<AttributeState<()>, AttributeError> {
+        self.set_attribute(attribute_path, &encode_list(&values))
+    }
+
+    /// The generation only lives in the request's attribute cache, bumping it
+    /// never reaches the host.
+    pub fn bump_metadata_generation(&self) -> Result<(), AttributeError> {
+        let generation = get_metadata_generation(self).wrapping_add(1);
+        self.cache.insert(
+            METADATA_GENERATION_PATH.into(),
+            CachedValue::Bytes(Some(generation.to_le_bytes().to_vec())),
+        )
+    }
+
     /// Sets header maps for request or response headers
     pub fn set_attribute_map(
         &self,
@@ -306,6 +615,26 @@ impl ReqRespCtx {
         self.response_end_of_stream
     }
 
+    pub fn is_request_end_of_stream(&self) -> bool {
+        self.request_end_of_stream
+    }
+
+    pub fn request_body_buffer_size(&self) -> usize {
+        self.request_body_size
+    }
+
+    pub(crate) fn get_http_request_body(
+        &self,
+        start: usize,
+        body_size: usize,
+    ) -> Result<AttributeState<Option<Vec<u8>>>, AttributeError> {
+        match self.backend.get_http_request_body(start, body_size) {
+            Ok(maybe_bytes) => Ok(AttributeState::Available(maybe_bytes)),
+            Err(AttributeError::NotAvailable(_)) => Ok(AttributeState::Pending),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn response_body_buffer_size(&self) -> usize {
         self.response_body_size
     }
@@ -322,36 +651,140 @@ impl ReqRespCtx {
         }
     }
 
-    pub fn dispatch_grpc_call(
+    /// Replaces the response body buffered so far with `body`.
+    pub(crate) fn set_http_response_body(
         &self,
-        upstream_name: &str,
-        service_name: &str,
-        method: &str,
-        message: Vec<u8>,
-        timeout: std::time::Duration,
-    ) -> Result<u32, ServiceError> {
-        let tracing_headers = self.get_tracing_headers();
-        let mut headers: Vec<(&str, &[u8])> = tracing_headers
+        body: &[u8],
+    ) -> Result<AttributeState<()>, AttributeError> {
+        match self
+            .backend
+            .set_http_response_body(0, self.response_body_size, body)
+        {
+            Ok(()) => Ok(AttributeState::Available(())),
+            Err(AttributeError::NotAvailable(_)) => Ok(AttributeState::Pending),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn dispatch_grpc_call(&self, request: GrpcRequest) -> Result<u32, ServiceError> {
+        let timeout = match self.remaining_time() {
+            Some(Duration::ZERO) => return Err(ServiceError::DeadlineExceeded),
+            Some(remaining) => request.timeout().min(remaining),
+            None => request.timeout(),
+        };
+
+        let forwarded_headers = self.get_forwarded_headers();
+        let mut headers: Vec<(&str, &[u8])> = forwarded_headers
             .iter()
             .map(|(name, value)| (name.as_str(), value.as_slice()))
             .collect();
 
         headers.push((X_REQUEST_ID_HEADER, self.request_id().as_bytes()));
+        headers.extend(
+            request
+                .metadata()
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_slice())),
+        );
 
         self.backend.dispatch_grpc_call(
-            upstream_name,
-            service_name,
-            method,
+            request.grpc_service(),
+            request.service_name(),
+            request.method(),
             headers,
-            message,
+            request.message(),
             timeout,
         )
     }
 
+    /// Queues `request` to be dispatched by the root context once `delay` has
+    /// elapsed, returning the placeholder token its response is awaited under
+    pub fn schedule_grpc_call(
+        &self,
+        request: GrpcRequest,
+        delay: Duration,
+    ) -> Result<u32, ServiceError> {
+        let Some((context_id, retry_queue)) = &self.retry_queue else {
+            return Err(ServiceError::Dispatch(
+                "no retry queue to schedule the call on".to_string(),
+            ));
+        };
+        let timeout = match self.remaining_time() {
+            Some(remaining) if remaining <= delay => return Err(ServiceError::DeadlineExceeded),
+            Some(remaining) => request.timeout().min(remaining - delay),
+            None => request.timeout(),
+        };
+
+        let mut headers = self.get_forwarded_headers();
+        headers.push((
+            X_REQUEST_ID_HEADER.to_string(),
+            self.request_id().as_bytes().to_vec(),
+        ));
+        headers.extend(request.metadata().iter().cloned());
+
+        Ok(retry_queue.schedule(DelayedCall {
+            context_id: *context_id,
+            due: self.current_time() + delay,
+            request,
+            headers,
+            timeout,
+        }))
+    }
+
+    #[cfg(feature = "http-callout")]
+    pub fn dispatch_http_call(&self, request: HttpCalloutRequest) -> Result<u32, ServiceError> {
+        let timeout = match self.remaining_time() {
+            Some(Duration::ZERO) => return Err(ServiceError::DeadlineExceeded),
+            Some(remaining) => request.timeout().min(remaining),
+            None => request.timeout(),
+        };
+
+        let mut headers: Vec<(&str, &str)> = request
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        headers.push((X_REQUEST_ID_HEADER, self.request_id()));
+
+        self.backend
+            .dispatch_http_call(request.upstream_name(), headers, request.body(), timeout)
+    }
+
+    #[cfg(feature = "http-callout")]
+    pub fn get_http_call_response_body(&self, body_size: usize) -> Result<Vec<u8>, ServiceError> {
+        self.backend.get_http_call_response_body(body_size)
+    }
+
+    /// Keeps the `forwarded` request trailers, for the services sending them
+    /// along their calls to attach them to their requests.
+    pub fn forward_request_trailers(
+        &mut self,
+        forwarded: &[String],
+        trailers: Vec<(String, Vec<u8>)>,
+    ) {
+        self.forwarded_trailers
+            .extend(trailers.into_iter().filter(|(name, _)| {
+                forwarded
+                    .iter()
+                    .any(|forwarded| forwarded.eq_ignore_ascii_case(name))
+            }));
+    }
+
+    pub fn forwarded_trailers(&self) -> &[(String, Vec<u8>)] {
+        &self.forwarded_trailers
+    }
+
     pub fn get_grpc_response(&self, response_size: usize) -> Result<Vec<u8>, ServiceError> {
         self.backend.get_grpc_response(response_size)
     }
 
+    pub fn get_grpc_response_trailer(&self, name: &str) -> Result<Option<String>, AttributeError> {
+        self.backend.get_attribute_map_value(
+            proxy_wasm::types::MapType::GrpcReceiveTrailingMetadata,
+            name,
+        )
+    }
+
     pub fn send_http_reply(
         &self,
         status_code: u32,
@@ -361,6 +794,24 @@ impl ReqRespCtx {
         self.backend.send_http_reply(status_code, headers, body)
     }
 
+    /// The trace context headers, extended with the static forwarded headers
+    /// not named like any of them
+    fn get_forwarded_headers(&self) -> Vec<(String, Vec<u8>)> {
+        let mut headers = self.get_tracing_headers();
+        let static_headers: Vec<(String, Vec<u8>)> = self
+            .static_forwarded_headers
+            .iter()
+            .filter(|(name, _)| {
+                !headers
+                    .iter()
+                    .any(|(tracing, _)| tracing.eq_ignore_ascii_case(name))
+            })
+            .map(|(name, value)| (name.clone(), value.as_bytes().to_vec()))
+            .collect();
+        headers.extend(static_headers);
+        headers
+    }
+
     fn get_tracing_headers(&self) -> Vec<(String, Vec<u8>)> {
         let mut headers = Vec::new();
 
@@ -370,10 +821,25 @@ impl ReqRespCtx {
             &self.tracing.otel_context
         };
 
-        opentelemetry::global::get_text_map_propagator(|propagator| {
-            let mut injector = crate::tracing::HeadersInjector::new(&mut headers);
-            propagator.inject_context(context, &mut injector);
-        });
+        if self.tracing_header_style.includes_w3c() {
+            opentelemetry::global::get_text_map_propagator(|propagator| {
+                let mut injector = crate::tracing::HeadersInjector::new(&mut headers);
+                propagator.inject_context(context, &mut injector);
+            });
+        }
+
+        let forwarded = self.tracing_header_style.forwarded_headers();
+        if !forwarded.is_empty() {
+            if let Ok(AttributeState::Available(Some(request_headers))) =
+                self.get_attribute::<Headers>("request.headers")
+            {
+                for name in forwarded {
+                    if let Some(value) = request_headers.get(name) {
+                        headers.push((name.to_string(), value.as_bytes().to_vec()));
+                    }
+                }
+            }
+        }
 
         headers
     }
@@ -422,6 +888,39 @@ impl ReqRespCtx {
         self.stored_values.keys().map(|s| s.as_str())
     }
 
+    /// Evaluates the computed property `name` on first use, `None` if no such property
+    /// is configured.
+    pub fn get_computed_value(
+        &self,
+        name: &str,
+    ) -> Result<Option<AttributeState<Value>>, AttributeError> {
+        let Some(expression) = self.computed_properties.get(name) else {
+            return Ok(None);
+        };
+        if let Some(value) = self.computed_values.borrow().get(name) {
+            return Ok(Some(AttributeState::Available(value.clone())));
+        }
+        let mut cel_ctx = cel::Context::default();
+        match expression.eval(self, &mut cel_ctx) {
+            Ok(AttributeState::Available(value)) => {
+                self.computed_values
+                    .borrow_mut()
+                    .insert(name.to_string(), value.clone());
+                Ok(Some(AttributeState::Available(value)))
+            }
+            Ok(AttributeState::Pending) => Ok(Some(AttributeState::Pending)),
+            Err(e) => Err(AttributeError::Retrieval(format!(
+                "failed to compute property {name}: {e}"
+            ))),
+        }
+    }
+
+    pub fn has_computed_prefix(&self, prefix: &str) -> bool {
+        self.computed_properties
+            .keys()
+            .any(|name| name.starts_with(prefix))
+    }
+
     pub fn has_stored_prefix(&self, prefix: &str) -> bool {
         self.stored_values
             .range::<String, _>(prefix.to_string()..)
@@ -430,6 +929,28 @@ impl ReqRespCtx {
     }
 }
 
+/// Where the value of a request header read by the filter came from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HeaderSource {
+    Host,
+    Default,
+}
+
+/// Resolves `name` from the request headers, falling back to the configured
+/// default when the host does not provide it.
+fn resolve_header<'a>(
+    headers: &'a Headers,
+    defaults: &'a HashMap<String, String>,
+    name: &str,
+) -> Option<(&'a str, HeaderSource)> {
+    match headers.get(name) {
+        Some(value) => Some((value, HeaderSource::Host)),
+        None => defaults
+            .get(name)
+            .map(|value| (value.as_str(), HeaderSource::Default)),
+    }
+}
+
 struct Tracker {
     id: OnceCell<String>,
     downstream_identifier: Option<String>,
@@ -520,6 +1041,7 @@ mod tests {
     use super::*;
     use crate::data::attribute::AttributeState;
     use crate::kuadrant::resolver::MockWasmHost;
+    use crate::services::GrpcRequestBuilder;
     use std::sync::Arc;
 
     #[test]
@@ -707,6 +1229,109 @@ mod tests {
         assert!(tracing_headers.is_empty());
     }
 
+    fn mixed_trace_headers() -> Vec<(String, String)> {
+        vec![
+            (
+                "traceparent".to_string(),
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string(),
+            ),
+            (
+                "x-b3-traceid".to_string(),
+                "80f198ee56343ba864fe8b2a57d3eff7".to_string(),
+            ),
+            ("x-b3-spanid".to_string(), "e457b5a2e4d86bd1".to_string()),
+            ("x-b3-sampled".to_string(), "1".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_tracing_headers_zipkin_only() {
+        let mock_host =
+            MockWasmHost::new().with_map("request.headers".to_string(), mixed_trace_headers());
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host))
+            .with_tracing_header_style(TracingHeaderStyle::Zipkin);
+        ctx.extract_trace_context();
+
+        let names: Vec<String> = ctx
+            .get_tracing_headers()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["x-b3-traceid", "x-b3-spanid", "x-b3-sampled"]);
+    }
+
+    #[test]
+    fn test_tracing_headers_both_styles() {
+        let mock_host =
+            MockWasmHost::new().with_map("request.headers".to_string(), mixed_trace_headers());
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host))
+            .with_tracing_header_style(TracingHeaderStyle::Both);
+        ctx.extract_trace_context();
+
+        let tracing_headers = ctx.get_tracing_headers();
+        assert_eq!(tracing_headers.len(), 4);
+        assert!(tracing_headers
+            .iter()
+            .any(|(name, value)| *name == "traceparent"
+                && value.as_slice() == b"00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"));
+        assert!(tracing_headers
+            .iter()
+            .any(|(name, value)| *name == "x-b3-traceid"
+                && value.as_slice() == b"80f198ee56343ba864fe8b2a57d3eff7"));
+    }
+
+    #[test]
+    fn test_tracing_headers_w3c_ignores_zipkin() {
+        let mock_host =
+            MockWasmHost::new().with_map("request.headers".to_string(), mixed_trace_headers());
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+        ctx.extract_trace_context();
+
+        let tracing_headers = ctx.get_tracing_headers();
+        assert_eq!(tracing_headers.len(), 1);
+        assert_eq!(tracing_headers[0].0, "traceparent");
+    }
+
+    #[test]
+    fn test_static_forwarded_headers_extend_tracing_headers() {
+        let mock_host = Arc::new(
+            MockWasmHost::new().with_map("request.headers".to_string(), mixed_trace_headers()),
+        );
+        let mut ctx = ReqRespCtx::new(mock_host.clone())
+            .with_tracing_header_style(TracingHeaderStyle::Zipkin)
+            .with_static_forwarded_headers(Arc::new(vec![
+                ("x-region".to_string(), "eu-west-1".to_string()),
+                ("X-B3-TraceId".to_string(), "overridden".to_string()),
+            ]));
+        ctx.extract_trace_context();
+
+        let forwarded_headers = ctx.get_forwarded_headers();
+        assert_eq!(forwarded_headers.len(), 4);
+        assert!(forwarded_headers.contains(&(
+            "x-b3-traceid".to_string(),
+            b"80f198ee56343ba864fe8b2a57d3eff7".to_vec()
+        )));
+        assert!(forwarded_headers.contains(&("x-region".to_string(), b"eu-west-1".to_vec())));
+        assert!(!forwarded_headers
+            .iter()
+            .any(|(_, value)| value.as_slice() == b"overridden"));
+
+        ctx.dispatch_grpc_call(
+            GrpcRequestBuilder::new("upstream")
+                .service("service")
+                .method("method")
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        let dispatched_headers = mock_host.last_dispatched_headers();
+        assert!(dispatched_headers.contains(&("x-region".to_string(), b"eu-west-1".to_vec())));
+        assert!(dispatched_headers.contains(&(
+            X_REQUEST_ID_HEADER.to_string(),
+            ctx.request_id().as_bytes().to_vec()
+        )));
+    }
+
     #[test]
     fn test_set_attribute_cache_consistency() {
         let mock_host = MockWasmHost::new();
@@ -728,6 +1353,66 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_set_property_list_round_trips() {
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+
+        ctx.set_property_list::<String>("auth.identity.roles", vec![])
+            .unwrap();
+        assert_eq!(
+            ctx.get_attribute::<Vec<String>>("auth.identity.roles"),
+            Ok(AttributeState::Available(Some(vec![])))
+        );
+
+        ctx.set_property_list("auth.identity.quota", vec![-42i64])
+            .unwrap();
+        assert_eq!(
+            ctx.get_attribute::<Vec<i64>>("auth.identity.quota"),
+            Ok(AttributeState::Available(Some(vec![-42])))
+        );
+
+        let groups = vec![
+            "admins".to_string(),
+            "ops, \"on-call\" \u{1f4df}".to_string(),
+            "a\\b\nc\u{0}".to_string(),
+        ];
+        ctx.set_property_list("auth.identity.groups", groups.clone())
+            .unwrap();
+        assert_eq!(
+            ctx.get_attribute::<Vec<String>>("auth.identity.groups"),
+            Ok(AttributeState::Available(Some(groups)))
+        );
+    }
+
+    #[test]
+    fn test_get_attribute_falls_back_only_when_absent() {
+        let mock_host = MockWasmHost::new()
+            .with_property("request.method".into(), b"GET".to_vec())
+            .with_property("request.size".into(), b"not eight bytes".to_vec());
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+
+        assert_eq!(
+            ctx.get_attribute_or("request.method", "POST".to_string()),
+            Ok(AttributeState::Available("GET".to_string()))
+        );
+        assert_eq!(
+            ctx.get_attribute_or("request.protocol", "HTTP/1.1".to_string()),
+            Ok(AttributeState::Available("HTTP/1.1".to_string()))
+        );
+        assert_eq!(
+            ctx.get_attribute_or_default::<String>("request.scheme"),
+            Ok(AttributeState::Available(String::new()))
+        );
+        assert!(matches!(
+            ctx.get_attribute_or("request.size", 0i64),
+            Err(AttributeError::Parse(_))
+        ));
+        assert!(matches!(
+            ctx.get_attribute_or_default::<i64>("request.size"),
+            Err(AttributeError::Parse(_))
+        ));
+    }
+
     #[test]
     fn test_get_attribute_from_host_when_not_in_cache() {
         let mock_host = MockWasmHost::new().with_property(
@@ -755,4 +1440,244 @@ mod tests {
             Ok(AttributeState::Available(Some(ref s))) if s == "external-user-id"
         ));
     }
+
+    #[test]
+    fn test_metadata_generation_increments() {
+        let mock_host = MockWasmHost::new();
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+
+        assert_eq!(get_metadata_generation(&ctx), 0);
+        ctx.bump_metadata_generation().unwrap();
+        assert_eq!(get_metadata_generation(&ctx), 1);
+        ctx.bump_metadata_generation().unwrap();
+        assert_eq!(get_metadata_generation(&ctx), 2);
+    }
+
+    #[test]
+    fn test_resolve_header_sources() {
+        let host: Headers = vec![("traceparent".to_string(), "from-host".to_string())].into();
+        let empty = Headers::new();
+        let defaults = HashMap::from([("traceparent".to_string(), "from-default".to_string())]);
+        let no_defaults = HashMap::new();
+
+        assert_eq!(
+            resolve_header(&host, &defaults, "traceparent"),
+            Some(("from-host", HeaderSource::Host))
+        );
+        assert_eq!(
+            resolve_header(&host, &no_defaults, "traceparent"),
+            Some(("from-host", HeaderSource::Host))
+        );
+        assert_eq!(
+            resolve_header(&empty, &defaults, "traceparent"),
+            Some(("from-default", HeaderSource::Default))
+        );
+        assert_eq!(resolve_header(&empty, &no_defaults, "traceparent"), None);
+    }
+
+    #[test]
+    fn test_tracing_headers_use_default_values() {
+        let mock_host = MockWasmHost::new().with_map("request.headers".to_string(), vec![]);
+        let defaults = HashMap::from([(
+            "traceparent".to_string(),
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string(),
+        )]);
+        let mut ctx =
+            ReqRespCtx::new(Arc::new(mock_host)).with_default_header_values(Arc::new(defaults));
+        ctx.extract_trace_context();
+
+        let tracing_headers = ctx.get_tracing_headers();
+
+        assert_eq!(tracing_headers.len(), 1);
+        assert_eq!(tracing_headers[0].0, "traceparent");
+    }
+
+    #[test]
+    fn test_generated_traceparent_is_w3c_compliant() {
+        let mock_host = MockWasmHost::new().with_map("request.headers".to_string(), vec![]);
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+        ctx.extract_trace_context();
+        ctx.generate_trace_context(true);
+
+        let tracing_headers = ctx.get_tracing_headers();
+        assert_eq!(tracing_headers.len(), 1);
+        assert_eq!(tracing_headers[0].0, "traceparent");
+
+        let traceparent = String::from_utf8(tracing_headers[0].1.clone()).unwrap();
+        let parts: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(
+            parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            vec![2, 32, 16, 2]
+        );
+        assert!(parts.iter().all(|p| p
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())));
+        assert_eq!(parts[0], "00");
+        assert_eq!(parts[3], "01");
+    }
+
+    #[test]
+    fn test_generation_keeps_inbound_trace_context() {
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let mock_host = MockWasmHost::new().with_map(
+            "request.headers".to_string(),
+            vec![("traceparent".to_string(), traceparent.to_string())],
+        );
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+        ctx.extract_trace_context();
+        ctx.generate_trace_context(false);
+
+        let tracing_headers = ctx.get_tracing_headers();
+        assert_eq!(tracing_headers[0].1, traceparent.as_bytes());
+    }
+
+    #[test]
+    fn test_forwarded_trailers_are_sent_with_the_calls_carrying_them() {
+        let mock_host = Arc::new(MockWasmHost::new());
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        let token = vec![0xff, 0x00, b'a', 0xc3];
+
+        ctx.forward_request_trailers(
+            &["X-Auth-Token".to_string()],
+            vec![
+                ("x-auth-token".to_string(), token.clone()),
+                ("grpc-status".to_string(), b"0".to_vec()),
+            ],
+        );
+        assert_eq!(
+            ctx.forwarded_trailers(),
+            &[("x-auth-token".to_string(), token.clone())]
+        );
+
+        let request = |metadata| {
+            GrpcRequestBuilder::new("upstream")
+                .service("service")
+                .method("method")
+                .metadata(metadata)
+                .build()
+                .unwrap()
+        };
+        ctx.dispatch_grpc_call(request(Vec::new())).unwrap();
+        assert!(!mock_host
+            .last_dispatched_headers()
+            .iter()
+            .any(|(name, _)| name == "x-auth-token"));
+
+        ctx.dispatch_grpc_call(request(ctx.forwarded_trailers().to_vec()))
+            .unwrap();
+        let headers = mock_host.last_dispatched_headers();
+        assert!(headers.contains(&("x-auth-token".to_string(), token)));
+        assert!(!headers.iter().any(|(name, _)| name == "grpc-status"));
+    }
+
+    #[test]
+    fn test_dispatch_stops_once_deadline_is_exceeded() {
+        let mock_host = Arc::new(MockWasmHost::new());
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        ctx.set_deadline(Duration::from_millis(100));
+
+        let timeout = Duration::from_millis(80);
+        let request = || {
+            GrpcRequestBuilder::new("upstream")
+                .service("service")
+                .method("method")
+                .timeout(timeout)
+                .build()
+                .unwrap()
+        };
+        assert!(ctx.dispatch_grpc_call(request()).is_ok());
+
+        mock_host.advance_time(timeout);
+        mock_host.advance_time(Duration::from_millis(30));
+        assert!(matches!(
+            ctx.dispatch_grpc_call(request()),
+            Err(ServiceError::DeadlineExceeded)
+        ));
+        assert_eq!(mock_host.dispatched_calls(), 1);
+    }
+
+    #[test]
+    fn test_access_log_records_request_decisions() {
+        use crate::kuadrant::AccessLogEntry;
+
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        assert!(!ctx.records_calls());
+        ctx.record_call(CallService::Auth, CallOutcome::Rejected);
+
+        let access_log = Rc::new(RefCell::new(AccessLogEntry::new(3)));
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()))
+            .with_access_log(Some(Rc::clone(&access_log)));
+        assert!(ctx.records_calls());
+        ctx.set_hostname("api.toystore.com".to_string());
+        ctx.set_action_set_name("toystore".to_string());
+        ctx.record_call(CallService::RateLimit, CallOutcome::Ok);
+        drop(ctx);
+
+        let mut expected = AccessLogEntry::new(3);
+        expected.authority = Some("api.toystore.com".to_string());
+        expected.matched_action_set = Some("toystore".to_string());
+        expected.record_call(CallService::RateLimit, CallOutcome::Ok);
+        assert_eq!(*access_log.borrow(), expected);
+    }
+
+    #[test]
+    fn test_inherit_deadline_from_request_header() {
+        let mock_host = Arc::new(
+            MockWasmHost::new()
+                .with_current_time(SystemTime::UNIX_EPOCH + Duration::from_secs(10))
+                .with_map(
+                    "request.headers".to_string(),
+                    vec![(
+                        X_ENVOY_EXPECTED_RQ_TIMEOUT_MS_HEADER.to_string(),
+                        "250".to_string(),
+                    )],
+                ),
+        );
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        ctx.inherit_deadline();
+        assert_eq!(ctx.remaining_time(), Some(Duration::from_millis(250)));
+
+        mock_host.advance_time(Duration::from_millis(100));
+        ctx.inherit_deadline();
+        assert_eq!(ctx.remaining_time(), Some(Duration::from_millis(150)));
+    }
+
+    #[test]
+    fn test_request_trailers_pending_until_received() {
+        let mock_host = MockWasmHost::new();
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+
+        let result: Result<AttributeState<Option<Headers>>, _> =
+            ctx.get_attribute("request.trailers");
+        assert_eq!(result, Ok(AttributeState::Pending));
+
+        let trailers: Headers = vec![("grpc-status".to_string(), "0".to_string())].into();
+        ctx.set_request_trailers(trailers).unwrap();
+
+        let result: Result<AttributeState<Option<Headers>>, _> =
+            ctx.get_attribute("request.trailers");
+        assert!(matches!(
+            result,
+            Ok(AttributeState::Available(Some(ref t))) if t.get("grpc-status") == Some("0")
+        ));
+    }
+
+    #[test]
+    fn test_request_trailers_empty_when_the_stream_ends_without_them() {
+        let mock_host = MockWasmHost::new();
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+
+        ctx.set_current_request_body_buffer_size(0, false);
+        let result: Result<AttributeState<Option<Headers>>, _> =
+            ctx.get_attribute("request.trailers");
+        assert_eq!(result, Ok(AttributeState::Pending));
+
+        ctx.set_current_request_body_buffer_size(42, true);
+        let result: Result<AttributeState<Option<Headers>>, _> =
+            ctx.get_attribute("request.trailers");
+        assert_eq!(
+            result,
+            Ok(AttributeState::Available(Some(Headers::default())))
+        );
+    }
 }
@@ -1,3 +1,4 @@
+mod access_log;
 mod cache;
 mod context;
 mod pipeline;
@@ -6,6 +7,7 @@ mod resolver;
 #[cfg(test)]
 pub use resolver::MockWasmHost;
 
+pub(crate) use access_log::{AccessLogEntry, SharedAccessLog};
 pub(crate) use cache::CachedValue;
 pub(crate) use context::ReqRespCtx;
-pub(crate) use pipeline::{Pipeline, PipelineFactory, PipelineState};
+pub(crate) use pipeline::{IndexStats, Pipeline, PipelineFactory, PipelineState};
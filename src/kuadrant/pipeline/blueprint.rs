@@ -1,26 +1,41 @@
 #[allow(deprecated)]
 use crate::configuration::{
     self, translate_legacy_auth_to_typed, translate_legacy_ratelimit_to_typed,
-    translate_legacy_report_to_typed,
+    translate_legacy_report_to_typed, PredicateComposition, RoutePredicates,
+};
+use crate::data::{
+    cel::{CompoundPredicate, Predicate},
+    path, Expression,
 };
-use crate::data::{cel::Predicate, Expression};
 use crate::kuadrant::pipeline::tasks::{
-    DynamicTask, ExportTracesTask, FailureModeTask, HeaderOperation, HeadersType,
-    ModifyHeadersTask, Task, TeardownAction, TokenUsageTask, TracingDecoratorTask,
+    CallMetricsTask, DynamicTask, ExportTracesTask, FailureModeTask, HeaderOperation, HeadersType,
+    ModifyHeadersTask, RequestBodyTask, Task, TeardownAction, TokenUsageTask, TracingDecoratorTask,
 };
 use crate::kuadrant::ReqRespCtx;
+use crate::metrics::CallService;
 use crate::services::ServiceInstance;
 use cel::ParseErrors;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::rc::Rc;
+use std::time::{Duration, SystemTime};
 
 pub type RequestData = ((String, String), Expression);
 
 pub(crate) struct Blueprint {
     pub name: String,
-    pub route_predicates: Vec<Predicate>,
+    pub route_predicates: Vec<CompoundPredicate>,
     pub actions: Vec<Action>,
+    /// Actions injected at runtime, each with its expiry in milliseconds since the Unix epoch
+    pub dynamic_actions: RefCell<Vec<(Action, u64)>>,
+    pub dynamic_action_count: Cell<usize>,
+    pub log_sample_rate: Option<f32>,
+    pub priority: i32,
+    /// See [`configuration::ActionSet::namespace`]
+    pub namespace: Option<String>,
+    /// See [`configuration::ActionSet::path_prefix`]
+    pub path_prefix: Option<String>,
 }
 
 #[derive(Clone)]
@@ -40,6 +55,8 @@ pub(crate) enum Operation {
         service: ServiceInstance,
         var: String,
         message_builder: Expression,
+        /// Overrides the timeout of `service` when set
+        timeout: Option<Duration>,
         on_reply: Vec<Action>,
     },
     Deny {
@@ -49,6 +66,11 @@ pub(crate) enum Operation {
         target: HeadersType,
         headers: Expression,
     },
+    /// Removes the headers named by the list `headers` evaluates to
+    RemoveHeaders {
+        target: HeadersType,
+        headers: Expression,
+    },
     Store {
         path: String,
         expression: Expression,
@@ -57,20 +79,53 @@ pub(crate) enum Operation {
     Fail {
         log_message: String,
     },
+    RequestHeaderMutation {
+        set: Vec<(String, String)>,
+        append: Vec<(String, String)>,
+        remove: Vec<String>,
+    },
+}
+
+impl Operation {
+    pub fn request_header_mutation(
+        set: &[(String, String)],
+        append: &[(String, String)],
+        remove: &[String],
+    ) -> HeaderOperation {
+        HeaderOperation::Mutate {
+            set: set.to_vec().into(),
+            append: append.to_vec().into(),
+            remove: remove.to_vec(),
+        }
+    }
 }
 
 impl Action {
     pub fn collect_body_values(&self, request_data: &[RequestData]) -> Vec<String> {
-        use std::collections::HashSet;
+        self.collect_fields(request_data, Expression::response_body_values)
+            .into_iter()
+            .collect()
+    }
+
+    pub fn collect_request_body_values(&self, request_data: &[RequestData]) -> Vec<String> {
+        self.collect_fields(request_data, Expression::request_body_values)
+            .into_iter()
+            .collect()
+    }
 
+    fn collect_fields(
+        &self,
+        request_data: &[RequestData],
+        fields_of: fn(&Expression) -> &[String],
+    ) -> HashSet<String> {
         let mut fields = HashSet::new();
 
-        fields.extend(self.predicate.response_body_values().iter().cloned());
+        fields.extend(fields_of(self.predicate.expression()).iter().cloned());
 
         fields.extend(
             request_data
                 .iter()
-                .flat_map(|(_, expr)| expr.response_body_values().iter().cloned()),
+                .flat_map(|(_, expr)| fields_of(expr).iter().cloned()),
         );
 
         match &self.operation {
@@ -79,51 +134,51 @@ impl Action {
                 on_reply,
                 ..
             } => {
-                fields.extend(message_builder.response_body_values().iter().cloned());
+                fields.extend(fields_of(message_builder).iter().cloned());
                 fields.extend(on_reply.iter().flat_map(|action| {
                     let mut reply_fields = Vec::new();
-                    reply_fields.extend(action.predicate.response_body_values().iter().cloned());
+                    reply_fields.extend(fields_of(action.predicate.expression()).iter().cloned());
                     match &action.operation {
                         Operation::Grpc {
                             message_builder,
                             on_reply: nested_reply,
                             ..
                         } => {
-                            reply_fields
-                                .extend(message_builder.response_body_values().iter().cloned());
+                            reply_fields.extend(fields_of(message_builder).iter().cloned());
                             reply_fields.extend(
                                 nested_reply
                                     .iter()
-                                    .flat_map(|nested| nested.collect_body_values(&[])),
+                                    .flat_map(|nested| nested.collect_fields(&[], fields_of)),
                             );
                         }
                         Operation::Deny { deny_with } => {
-                            reply_fields.extend(deny_with.response_body_values().iter().cloned());
+                            reply_fields.extend(fields_of(deny_with).iter().cloned());
                         }
-                        Operation::Headers { headers, .. } => {
-                            reply_fields.extend(headers.response_body_values().iter().cloned());
+                        Operation::Headers { headers, .. }
+                        | Operation::RemoveHeaders { headers, .. } => {
+                            reply_fields.extend(fields_of(headers).iter().cloned());
                         }
                         Operation::Store { expression, .. } => {
-                            reply_fields.extend(expression.response_body_values().iter().cloned());
+                            reply_fields.extend(fields_of(expression).iter().cloned());
                         }
-                        Operation::Fail { .. } => {}
+                        Operation::Fail { .. } | Operation::RequestHeaderMutation { .. } => {}
                     }
                     reply_fields
                 }));
             }
             Operation::Deny { deny_with } => {
-                fields.extend(deny_with.response_body_values().iter().cloned());
+                fields.extend(fields_of(deny_with).iter().cloned());
             }
-            Operation::Headers { headers, .. } => {
-                fields.extend(headers.response_body_values().iter().cloned());
+            Operation::Headers { headers, .. } | Operation::RemoveHeaders { headers, .. } => {
+                fields.extend(fields_of(headers).iter().cloned());
             }
             Operation::Store { expression, .. } => {
-                fields.extend(expression.response_body_values().iter().cloned());
+                fields.extend(fields_of(expression).iter().cloned());
             }
-            Operation::Fail { .. } => {}
+            Operation::Fail { .. } | Operation::RequestHeaderMutation { .. } => {}
         }
 
-        fields.into_iter().collect()
+        fields
     }
 }
 
@@ -134,6 +189,10 @@ pub enum CompileError {
     InvalidDataExpression(String),
     UnknownService(String),
     ServiceCreationFailed(String),
+    CyclicProperty { cycle: Vec<String> },
+    UnknownActionSet(String),
+    InvalidLogSampleRate { action_set: String, rate: f32 },
+    ActionSetInUse(String),
 }
 
 impl From<ParseErrors> for CompileError {
@@ -158,6 +217,18 @@ impl Display for CompileError {
             CompileError::ServiceCreationFailed(srv) => {
                 write!(f, "Service creation failed: {}", srv)
             }
+            CompileError::CyclicProperty { cycle } => {
+                write!(f, "Cyclic computed property: {}", cycle.join(" -> "))
+            }
+            CompileError::UnknownActionSet(name) => write!(f, "Unknown action set: {}", name),
+            CompileError::InvalidLogSampleRate { action_set, rate } => write!(
+                f,
+                "Invalid log sample rate on {}: {} is not between 0.0 and 1.0",
+                action_set, rate
+            ),
+            CompileError::ActionSetInUse(name) => {
+                write!(f, "Action set {} is used by internalRequestPolicy", name)
+            }
         }
     }
 }
@@ -168,16 +239,44 @@ impl Blueprint {
         services: &HashMap<String, ServiceInstance>,
         request_data: &[RequestData],
     ) -> Result<Self, CompileError> {
-        let route_predicates: Vec<Predicate> = config
+        let predicates = config
             .route_rule_conditions
             .predicates
+            .expressions()
             .iter()
-            .map(|p| Predicate::new(p))
-            .collect::<Result<_, _>>()
-            .map_err(|e| CompileError::InvalidRoutePredicate {
+            .map(|p| {
+                let predicate = Predicate::new(p).map_err(|e| e.to_string())?;
+                predicate.compile_check().map_err(|e| e.to_string())?;
+                Ok(CompoundPredicate::from(predicate))
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(|error| CompileError::InvalidRoutePredicate {
                 action_set: config.name.clone(),
-                error: e.to_string(),
+                error,
             })?;
+        let route_predicates: Vec<CompoundPredicate> =
+            match &config.route_rule_conditions.predicates {
+                RoutePredicates::All(_) => predicates,
+                RoutePredicates::Composed(PredicateComposition::And(_)) => predicates
+                    .into_iter()
+                    .reduce(|acc, p| acc & p)
+                    .into_iter()
+                    .collect(),
+                RoutePredicates::Composed(PredicateComposition::Or(_)) => predicates
+                    .into_iter()
+                    .reduce(|acc, p| acc | p)
+                    .into_iter()
+                    .collect(),
+            };
+
+        if let Some(rate) = config.log_sample_rate {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(CompileError::InvalidLogSampleRate {
+                    action_set: config.name.clone(),
+                    rate,
+                });
+            }
+        }
 
         let actions: Vec<Action> = config
             .actions
@@ -185,23 +284,19 @@ impl Blueprint {
             .enumerate()
             .map(|(i, action_config)| {
                 let id = i.to_string();
-                let dependencies = if i > 0 {
+                let dependencies = if i > 0 && !config.parallel {
                     vec![(i - 1).to_string()]
                 } else {
                     vec![]
                 };
-                match action_config {
-                    configuration::ActionConfig::Legacy(action) => {
-                        let legacy_request_data: Vec<((String, String), String)> = request_data
-                            .iter()
-                            .map(|(key, expr)| (key.clone(), expr.source().to_string()))
-                            .collect();
-                        Action::compile(action, services, id, dependencies, &legacy_request_data)
-                    }
-                    configuration::ActionConfig::Typed(typed) => {
-                        Action::compile_typed(typed, services, id, dependencies)
-                    }
-                }
+                Action::compile_config(
+                    action_config,
+                    services,
+                    id,
+                    dependencies,
+                    request_data,
+                    config.namespace.as_deref(),
+                )
             })
             .collect::<Result<_, _>>()?;
 
@@ -209,8 +304,87 @@ impl Blueprint {
             name: config.name.clone(),
             route_predicates,
             actions,
+            dynamic_actions: RefCell::default(),
+            dynamic_action_count: Cell::default(),
+            log_sample_rate: config.log_sample_rate,
+            priority: config.priority,
+            namespace: config.namespace.clone(),
+            path_prefix: config.path_prefix.clone(),
         })
     }
+
+    /// Whether requests to `path`, normalized and ignoring its query, match the
+    /// path prefix
+    pub fn matches_path(&self, path: &str) -> bool {
+        let path = path::normalize(path.split_once('?').map_or(path, |(path, _)| path));
+        self.path_prefix
+            .as_ref()
+            .is_none_or(|prefix| path::has_prefix(&path, prefix))
+    }
+
+    /// Compiles an action pushed at runtime and serves it after the configured
+    /// actions until `expires_at_ms`.
+    pub fn inject_dynamic_action(
+        &self,
+        action_config: &configuration::ActionConfig,
+        expires_at_ms: u64,
+        services: &HashMap<String, ServiceInstance>,
+        request_data: &[RequestData],
+    ) -> Result<(), CompileError> {
+        let count = self.dynamic_action_count.get();
+        let id = format!("dynamic.{count}");
+        let action = Action::compile_config(
+            action_config,
+            services,
+            id,
+            vec![],
+            request_data,
+            self.namespace.as_deref(),
+        )?;
+        self.dynamic_action_count.set(count + 1);
+        self.dynamic_actions
+            .borrow_mut()
+            .push((action, expires_at_ms));
+        Ok(())
+    }
+
+    /// Drops the dynamic actions expired by `now`, returning how many were removed.
+    pub fn evict_expired_dynamic_actions(&self, now: SystemTime) -> usize {
+        let now_ms = unix_millis(now);
+        let mut dynamic_actions = self.dynamic_actions.borrow_mut();
+        let before = dynamic_actions.len();
+        dynamic_actions.retain(|(_, expires_at_ms)| *expires_at_ms >= now_ms);
+        before - dynamic_actions.len()
+    }
+
+    /// Whether any of the static actions calls a rate limit service.
+    pub fn calls_rate_limit_service(&self) -> bool {
+        self.actions.iter().any(|action| {
+            matches!(
+                &action.operation,
+                Operation::Grpc {
+                    service: ServiceInstance::RateLimit(_) | ServiceInstance::RateLimitCheck(_),
+                    ..
+                }
+            )
+        })
+    }
+
+    fn live_dynamic_actions(&self, now: SystemTime) -> Vec<Action> {
+        let now_ms = unix_millis(now);
+        self.dynamic_actions
+            .borrow()
+            .iter()
+            .filter(|(_, expires_at_ms)| *expires_at_ms >= now_ms)
+            .map(|(action, _)| action.clone())
+            .collect()
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
 }
 
 type TaskList = Vec<Box<dyn Task>>;
@@ -221,6 +395,7 @@ impl Blueprint {
         &self,
         ctx: &mut ReqRespCtx,
         request_data: &[RequestData],
+        max_request_body_size: usize,
     ) -> (TaskList, TeardownList) {
         let mut tasks: TaskList = Vec::new();
         let mut teardown_tasks: TeardownList = Vec::new();
@@ -232,12 +407,28 @@ impl Blueprint {
             )
         });
 
-        for action in &self.actions {
+        let dynamic_actions = self.live_dynamic_actions(ctx.current_time());
+
+        let request_body_fields: HashSet<String> = self
+            .actions
+            .iter()
+            .chain(&dynamic_actions)
+            .flat_map(|action| action.collect_request_body_values(request_data))
+            .collect();
+        if !request_body_fields.is_empty() {
+            tasks.push(Box::new(RequestBodyTask::new(
+                request_body_fields.into_iter().collect(),
+                max_request_body_size,
+            )));
+        }
+
+        for action in self.actions.iter().chain(&dynamic_actions) {
             match &action.operation {
                 Operation::Grpc {
                     service,
                     var,
                     message_builder,
+                    timeout,
                     on_reply,
                 } => {
                     let abort_on_failure =
@@ -269,26 +460,55 @@ impl Blueprint {
                                 ));
                             }
 
-                            let task: Box<dyn Task> = Box::new(DynamicTask::new_with_attributes(
-                                ctx,
-                                action.id.clone(),
-                                Rc::clone(dynamic_service),
-                                var.clone(),
-                                message_builder.clone(),
-                                on_reply.clone(),
-                                vec![action.predicate.clone()],
-                                action.dependencies.clone(),
-                                action.is_guard,
-                            ));
-                            let task = Box::new(FailureModeTask::new(task, abort_on_failure));
+                            let task: Box<dyn Task> = Box::new(
+                                DynamicTask::new_with_attributes(
+                                    ctx,
+                                    action.id.clone(),
+                                    Rc::clone(dynamic_service),
+                                    var.clone(),
+                                    message_builder.clone(),
+                                    on_reply.clone(),
+                                    vec![action.predicate.clone()],
+                                    action.dependencies.clone(),
+                                    action.is_guard,
+                                )
+                                .with_timeout(*timeout),
+                            );
+                            let span_label = match service {
+                                ServiceInstance::Auth(_) => "auth",
+                                ServiceInstance::RateLimit(_)
+                                | ServiceInstance::RateLimitCheck(_) => "ratelimit",
+                                ServiceInstance::RateLimitReport(_) => "ratelimit_report",
+                                _ => "dynamic",
+                            };
+                            let call_service = match service {
+                                ServiceInstance::Auth(_) => Some(CallService::Auth),
+                                ServiceInstance::RateLimit(_)
+                                | ServiceInstance::RateLimitCheck(_) => {
+                                    Some(CallService::RateLimit)
+                                }
+                                _ => None,
+                            };
+                            let task: Box<dyn Task> = match call_service {
+                                Some(call_service)
+                                    if ctx.metrics().is_some() || ctx.records_calls() =>
+                                {
+                                    Box::new(CallMetricsTask::new(
+                                        task,
+                                        ctx.metrics(),
+                                        self.name.clone(),
+                                        call_service,
+                                    ))
+                                }
+                                _ => task,
+                            };
+                            let task = Box::new(
+                                FailureModeTask::new(task, abort_on_failure).with_error_response(
+                                    service.error_response().cloned(),
+                                    span_label,
+                                ),
+                            );
                             if tracing_enabled {
-                                let span_label = match service {
-                                    ServiceInstance::Auth(_) => "auth",
-                                    ServiceInstance::RateLimit(_)
-                                    | ServiceInstance::RateLimitCheck(_) => "ratelimit",
-                                    ServiceInstance::RateLimitReport(_) => "ratelimit_report",
-                                    _ => "dynamic",
-                                };
                                 tasks.push(Box::new(TracingDecoratorTask::new(
                                     span_label,
                                     task,
@@ -298,6 +518,26 @@ impl Blueprint {
                                 tasks.push(task);
                             }
                         }
+                        #[cfg(feature = "http-callout")]
+                        ServiceInstance::HttpCallout(http_service) => {
+                            use crate::kuadrant::pipeline::tasks::HttpCalloutTask;
+                            let task = HttpCalloutTask::new(
+                                action.id.clone(),
+                                Rc::clone(http_service),
+                                message_builder.clone(),
+                                action.predicate.clone(),
+                                action.dependencies.clone(),
+                                action.is_guard,
+                            )
+                            .with_timeout(*timeout);
+                            tasks.push(Box::new(
+                                FailureModeTask::new(Box::new(task), abort_on_failure)
+                                    .with_error_response(
+                                        service.error_response().cloned(),
+                                        "http_callout",
+                                    ),
+                            ));
+                        }
                     }
                 }
                 Operation::Deny { deny_with } => {
@@ -321,6 +561,18 @@ impl Blueprint {
                     );
                     tasks.push(Box::new(task));
                 }
+                Operation::RemoveHeaders {
+                    target,
+                    headers: names_expr,
+                } => {
+                    let task = ModifyHeadersTask::new_deferred_removal(
+                        action.predicate.clone(),
+                        names_expr.clone(),
+                        target.clone(),
+                        action.terminal,
+                    );
+                    tasks.push(Box::new(task));
+                }
                 Operation::Store {
                     path,
                     expression,
@@ -343,6 +595,18 @@ impl Blueprint {
                         log_message
                     );
                 }
+                Operation::RequestHeaderMutation {
+                    set,
+                    append,
+                    remove,
+                } => {
+                    tasks.push(Box::new(ModifyHeadersTask::new_conditional(
+                        action.predicate.clone(),
+                        Operation::request_header_mutation(set, append, remove),
+                        HeadersType::HttpRequestHeaders,
+                        action.terminal,
+                    )));
+                }
             }
         }
 
@@ -351,6 +615,35 @@ impl Blueprint {
 }
 
 impl Action {
+    fn compile_config(
+        action_config: &configuration::ActionConfig,
+        services: &HashMap<String, ServiceInstance>,
+        id: String,
+        dependencies: Vec<String>,
+        request_data: &[RequestData],
+        namespace: Option<&str>,
+    ) -> Result<Self, CompileError> {
+        match action_config {
+            configuration::ActionConfig::Legacy(action) => {
+                let legacy_request_data: Vec<((String, String), String)> = request_data
+                    .iter()
+                    .map(|(key, expr)| (key.clone(), expr.source().to_string()))
+                    .collect();
+                Action::compile(
+                    action,
+                    services,
+                    id,
+                    dependencies,
+                    &legacy_request_data,
+                    namespace,
+                )
+            }
+            configuration::ActionConfig::Typed(typed) => {
+                Action::compile_typed(typed, services, id, dependencies)
+            }
+        }
+    }
+
     #[allow(deprecated)]
     fn compile(
         config: &configuration::Action,
@@ -358,6 +651,7 @@ impl Action {
         id: String,
         dependencies: Vec<String>,
         request_data: &[((String, String), String)],
+        namespace: Option<&str>,
     ) -> Result<Self, CompileError> {
         let service = services
             .get(&config.service)
@@ -366,10 +660,10 @@ impl Action {
         let typed_config = match service {
             ServiceInstance::Auth(_) => translate_legacy_auth_to_typed(config, request_data),
             ServiceInstance::RateLimit(_) | ServiceInstance::RateLimitCheck(_) => {
-                translate_legacy_ratelimit_to_typed(config, request_data)
+                translate_legacy_ratelimit_to_typed(config, request_data, namespace)
             }
             ServiceInstance::RateLimitReport(_) => {
-                translate_legacy_report_to_typed(config, request_data)
+                translate_legacy_report_to_typed(config, request_data, namespace)
             }
             _ => {
                 return Err(CompileError::ServiceCreationFailed(format!(
@@ -388,16 +682,30 @@ impl Action {
         id: String,
         dependencies: Vec<String>,
     ) -> Result<Self, CompileError> {
-        let predicate =
-            Predicate::new(&typed.predicate).map_err(|e| CompileError::InvalidActionPredicate {
+        let parsed = match &typed.request_body_field {
+            None => Predicate::new(&typed.predicate),
+            Some(field) => Predicate::from_json_pointer(&field.pointer, field.equals.clone())
+                .and_then(|body_check| body_check.requiring(&typed.predicate)),
+        };
+        let predicate = parsed
+            .map_err(|e| e.to_string())
+            .and_then(|predicate| {
+                predicate.compile_check().map_err(|e| e.to_string())?;
+                Ok(predicate)
+            })
+            .map_err(|error| CompileError::InvalidActionPredicate {
                 service: match &typed.operation {
                     configuration::Operation::Grpc(grpc) => grpc.service.clone(),
                     configuration::Operation::Deny(_) => "deny".to_string(),
                     configuration::Operation::Headers(_) => "headers".to_string(),
                     configuration::Operation::Store(_) => "store".to_string(),
                     configuration::Operation::Fail(_) => "fail".to_string(),
+                    configuration::Operation::RequestHeaderMutation(_) => {
+                        "requestHeaderMutation".to_string()
+                    }
+                    configuration::Operation::RemoveHeaders(_) => "removeHeaders".to_string(),
                 },
-                error: e.to_string(),
+                error,
             })?;
 
         let operation = match &typed.operation {
@@ -406,19 +714,32 @@ impl Action {
                     .get(&grpc.service)
                     .ok_or_else(|| CompileError::UnknownService(grpc.service.clone()))?;
 
-                if !matches!(
+                let callable = matches!(
                     service_instance,
                     ServiceInstance::Dynamic(_)
                         | ServiceInstance::Auth(_)
                         | ServiceInstance::RateLimit(_)
                         | ServiceInstance::RateLimitCheck(_)
                         | ServiceInstance::RateLimitReport(_)
-                ) {
+                );
+                #[cfg(feature = "http-callout")]
+                let callable =
+                    callable || matches!(service_instance, ServiceInstance::HttpCallout(_));
+                if !callable {
                     return Err(CompileError::ServiceCreationFailed(format!(
                         "Service '{}' cannot be used with gRPC action",
                         grpc.service
                     )));
                 }
+                #[cfg(feature = "http-callout")]
+                if matches!(service_instance, ServiceInstance::HttpCallout(_))
+                    && !grpc.on_reply.is_empty()
+                {
+                    return Err(CompileError::ServiceCreationFailed(format!(
+                        "Service '{}' answers with a decision, onReply is not supported",
+                        grpc.service
+                    )));
+                }
 
                 let on_reply: Vec<Action> = grpc
                     .on_reply
@@ -445,6 +766,7 @@ impl Action {
                     service: service_instance.clone(),
                     var: grpc.var.clone(),
                     message_builder,
+                    timeout: grpc.timeout_ms.map(Duration::from_millis),
                     on_reply,
                 }
             }
@@ -463,6 +785,16 @@ impl Action {
                     headers: headers_expr,
                 }
             }
+            configuration::Operation::RemoveHeaders(headers) => {
+                let target = match headers.target {
+                    configuration::HeadersTarget::Request => HeadersType::HttpRequestHeaders,
+                    configuration::HeadersTarget::Response => HeadersType::HttpResponseHeaders,
+                };
+                Operation::RemoveHeaders {
+                    target,
+                    headers: Expression::new(&headers.headers)?,
+                }
+            }
             configuration::Operation::Store(store) => {
                 let expression = Expression::new(&store.value)?;
                 Operation::Store {
@@ -474,6 +806,13 @@ impl Action {
             configuration::Operation::Fail(fail) => Operation::Fail {
                 log_message: fail.log_message.clone(),
             },
+            configuration::Operation::RequestHeaderMutation(mutation) => {
+                Operation::RequestHeaderMutation {
+                    set: mutation.set.clone(),
+                    append: mutation.append.clone(),
+                    remove: mutation.remove.clone(),
+                }
+            }
         };
 
         Ok(Action {
@@ -494,14 +833,17 @@ mod tests {
     use crate::configuration::{
         Action as ConfigAction, ActionConfig, ActionSet, ConditionalData as ConfigConditionalData,
         DataItem as ConfigDataItem, DataType, DenyOperation, ExpressionItem, GrpcOperation,
-        HeadersOperation, HeadersTarget, Operation as ConfigOperation, RouteRuleConditions,
-        StaticItem, StoreOperation, TypedAction as ConfigTypedAction,
+        HeadersOperation, HeadersTarget, Operation as ConfigOperation, RequestBodyField,
+        RouteRuleConditions, StaticItem, StoreOperation, TypedAction as ConfigTypedAction,
     };
     use crate::configuration::{FailOperation, FailureMode};
+    use crate::data::attribute::AttributeState;
     use crate::filter::DescriptorManager;
+    use crate::kuadrant::MockWasmHost;
     use crate::services::{DynamicService, ServiceInstance};
     use std::collections::HashMap;
     use std::rc::Rc;
+    use std::sync::Arc;
 
     fn build_test_service(name: &str) -> (String, ServiceInstance) {
         let descriptor_manager = Rc::new(DescriptorManager::default());
@@ -539,9 +881,13 @@ mod tests {
 
         let config = ActionSet {
             name: "test-action-set".to_string(),
+            required_capabilities: vec![],
+            parallel: false,
+            log_sample_rate: None,
+            priority: 0,
             route_rule_conditions: RouteRuleConditions {
                 hostnames: vec!["example.com".to_string()],
-                predicates: vec![],
+                predicates: vec![].into(),
             },
             actions: vec![],
         };
@@ -560,9 +906,13 @@ mod tests {
 
         let config = ActionSet {
             name: "test-action-set".to_string(),
+            required_capabilities: vec![],
+            parallel: false,
+            log_sample_rate: None,
+            priority: 0,
             route_rule_conditions: RouteRuleConditions {
                 hostnames: vec!["example.com".to_string()],
-                predicates: vec!["true".to_string(), "request.method == 'GET'".to_string()],
+                predicates: vec!["true".to_string(), "request.method == 'GET'".to_string()].into(),
             },
             actions: vec![],
         };
@@ -573,15 +923,67 @@ mod tests {
         assert_eq!(blueprint.route_predicates.len(), 2);
     }
 
+    #[test]
+    fn blueprint_rejects_out_of_range_log_sample_rate() {
+        let services = HashMap::from([build_test_service("test-service")]);
+
+        let config = ActionSet {
+            name: "test-action-set".to_string(),
+            log_sample_rate: Some(1.5),
+            route_rule_conditions: RouteRuleConditions {
+                hostnames: vec!["example.com".to_string()],
+                predicates: vec![].into(),
+            },
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            Blueprint::compile(&config, &services, &[]),
+            Err(CompileError::InvalidLogSampleRate { rate, .. }) if rate == 1.5
+        ));
+    }
+
+    #[test]
+    fn blueprint_composes_or_route_predicates() {
+        let services = HashMap::from([build_test_service("test-service")]);
+
+        let config = ActionSet {
+            name: "test-action-set".to_string(),
+            required_capabilities: vec![],
+            parallel: false,
+            log_sample_rate: None,
+            priority: 0,
+            route_rule_conditions: RouteRuleConditions {
+                hostnames: vec!["example.com".to_string()],
+                predicates: RoutePredicates::Composed(PredicateComposition::Or(vec![
+                    "request.method == 'GET'".to_string(),
+                    "request.method == 'HEAD'".to_string(),
+                ])),
+            },
+            actions: vec![],
+        };
+
+        let blueprint = Blueprint::compile(&config, &services, &[]).unwrap();
+        assert_eq!(blueprint.route_predicates.len(), 1);
+        assert!(matches!(
+            blueprint.route_predicates[0],
+            CompoundPredicate::Or(..)
+        ));
+    }
+
     #[test]
     fn blueprint_fails_on_invalid_route_predicate() {
         let services = HashMap::from([build_test_service("test-service")]);
 
         let config = ActionSet {
             name: "test-action-set".to_string(),
+            required_capabilities: vec![],
+            parallel: false,
+            log_sample_rate: None,
+            priority: 0,
             route_rule_conditions: RouteRuleConditions {
                 hostnames: vec!["example.com".to_string()],
-                predicates: vec!["invalid syntax !!@@".to_string()],
+                predicates: vec!["invalid syntax !!@@".to_string()].into(),
             },
             actions: vec![],
         };
@@ -660,9 +1062,13 @@ mod tests {
 
         let config = ActionSet {
             name: "complete-test".to_string(),
+            required_capabilities: vec![],
+            parallel: false,
+            log_sample_rate: None,
+            priority: 0,
             route_rule_conditions: RouteRuleConditions {
                 hostnames: vec!["*.example.com".to_string()],
-                predicates: vec!["request.path.startsWith('/api')".to_string()],
+                predicates: vec!["request.path.startsWith('/api')".to_string()].into(),
             },
             actions: vec![ActionConfig::Legacy(ConfigAction {
                 service: "auth-service".to_string(),
@@ -710,16 +1116,19 @@ mod tests {
             terminal: false,
             is_guard: true,
             sources: vec![],
+            request_body_field: None,
             operation: ConfigOperation::Grpc(GrpcOperation {
                 var: "rl_check".to_string(),
                 service: "my-dynamic".to_string(),
                 message_builder: "envoy.service.ratelimit.v3.RateLimitRequest{}".to_string(),
+                timeout_ms: Some(250),
                 on_reply: vec![
                     ConfigTypedAction {
                         predicate: "rl_check.overall_code == 2".to_string(),
                         terminal: true,
                         is_guard: false,
                         sources: vec![],
+                        request_body_field: None,
                         operation: ConfigOperation::Deny(DenyOperation {
                             deny_with: "DenyResponse{status: 429u}".to_string(),
                         }),
@@ -729,6 +1138,7 @@ mod tests {
                         terminal: true,
                         is_guard: false,
                         sources: vec![],
+                        request_body_field: None,
                         operation: ConfigOperation::Fail(FailOperation {
                             log_message: "Received UNKNOWN from rate limiting service".to_string(),
                         }),
@@ -739,6 +1149,7 @@ mod tests {
                         terminal: true,
                         is_guard: false,
                         sources: vec![],
+                        request_body_field: None,
                         operation: ConfigOperation::Fail(FailOperation {
                             log_message:
                                 "Received invalid response code from rate limiting service"
@@ -750,6 +1161,7 @@ mod tests {
                         terminal: false,
                         is_guard: false,
                         sources: vec![],
+                        request_body_field: None,
                         operation: ConfigOperation::Headers(HeadersOperation {
                             target: HeadersTarget::Request,
                             headers: "result.headers".to_string(),
@@ -760,6 +1172,7 @@ mod tests {
                         terminal: false,
                         is_guard: false,
                         sources: vec![],
+                        request_body_field: None,
                         operation: ConfigOperation::Store(StoreOperation {
                             path: "rl.remaining".to_string(),
                             value: "result.remaining".to_string(),
@@ -780,12 +1193,14 @@ mod tests {
         if let Operation::Grpc {
             ref service,
             ref var,
+            ref timeout,
             ref on_reply,
             ..
         } = action.operation
         {
             assert_eq!(var, "rl_check");
             assert!(matches!(service, ServiceInstance::Dynamic(_)));
+            assert_eq!(*timeout, Some(Duration::from_millis(250)));
             assert_eq!(on_reply.len(), 5);
         }
     }
@@ -799,10 +1214,12 @@ mod tests {
             terminal: false,
             is_guard: true,
             sources: vec![],
+            request_body_field: None,
             operation: ConfigOperation::Grpc(GrpcOperation {
                 var: "check".to_string(),
                 service: "nonexistent".to_string(),
                 message_builder: "test.Request{}".to_string(),
+                timeout_ms: None,
                 on_reply: vec![],
             }),
         };
@@ -827,10 +1244,12 @@ mod tests {
             terminal: false,
             is_guard: true,
             sources: vec![],
+            request_body_field: None,
             operation: ConfigOperation::Grpc(GrpcOperation {
                 var: "check".to_string(),
                 service: "tracing-svc".to_string(),
                 message_builder: "test.Request{}".to_string(),
+                timeout_ms: None,
                 on_reply: vec![],
             }),
         };
@@ -851,10 +1270,12 @@ mod tests {
             terminal: false,
             is_guard: false,
             sources: vec![],
+            request_body_field: None,
             operation: ConfigOperation::Grpc(GrpcOperation {
                 var: "nested".to_string(),
                 service: "svc".to_string(),
                 message_builder: "test.Request{}".to_string(),
+                timeout_ms: None,
                 on_reply: vec![],
             }),
         };
@@ -866,6 +1287,41 @@ mod tests {
         assert!(matches!(action.operation, Operation::Grpc { .. }));
     }
 
+    #[test]
+    fn typed_actions_require_their_request_body_field() {
+        let services = HashMap::new();
+        let config = |predicate: &str| ConfigTypedAction {
+            predicate: predicate.to_string(),
+            terminal: true,
+            is_guard: true,
+            sources: vec![],
+            request_body_field: Some(RequestBodyField {
+                pointer: "/model".to_string(),
+                equals: serde_json::json!("gpt-4"),
+            }),
+            operation: ConfigOperation::Deny(DenyOperation {
+                deny_with: "DenyResponse{status: 403u}".to_string(),
+            }),
+        };
+
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        ctx.set_request_body_value("/model", "gpt-4");
+        for (predicate, expected) in [("true", true), ("false", false)] {
+            let action =
+                Action::compile_typed(&config(predicate), &services, "0".to_string(), vec![])
+                    .expect("compiles");
+            assert_eq!(
+                action.collect_request_body_values(&[]),
+                vec!["/model".to_string()]
+            );
+            assert_eq!(
+                action.predicate.test(&ctx),
+                Ok(AttributeState::Available(expected)),
+                "{predicate}"
+            );
+        }
+    }
+
     #[test]
     fn typed_actions_compile() {
         let services = HashMap::new();
@@ -875,6 +1331,7 @@ mod tests {
             terminal: true,
             is_guard: false,
             sources: vec![],
+            request_body_field: None,
             operation: ConfigOperation::Deny(DenyOperation {
                 deny_with: "DenyResponse{status: 429u}".to_string(),
             }),
@@ -890,6 +1347,7 @@ mod tests {
             terminal: false,
             is_guard: false,
             sources: vec![],
+            request_body_field: None,
             operation: ConfigOperation::Headers(HeadersOperation {
                 target: HeadersTarget::Response,
                 headers: "result.resp_headers".to_string(),
@@ -913,6 +1371,7 @@ mod tests {
             terminal: false,
             is_guard: true,
             sources: vec![],
+            request_body_field: None,
             operation: ConfigOperation::Store(StoreOperation {
                 path: "a.b".to_string(),
                 value: "result.x".to_string(),
@@ -938,6 +1397,7 @@ mod tests {
             terminal: true,
             is_guard: true,
             sources: vec![],
+            request_body_field: None,
             operation: ConfigOperation::Deny(DenyOperation {
                 deny_with: "DenyResponse{status: 429u}".to_string(),
             }),
@@ -958,9 +1418,13 @@ mod tests {
 
         let config = ActionSet {
             name: "mixed-set".to_string(),
+            required_capabilities: vec![],
+            parallel: false,
+            log_sample_rate: None,
+            priority: 0,
             route_rule_conditions: RouteRuleConditions {
                 hostnames: vec!["example.com".to_string()],
-                predicates: vec![],
+                predicates: vec![].into(),
             },
             actions: vec![
                 ActionConfig::Legacy(ConfigAction {
@@ -975,15 +1439,18 @@ mod tests {
                     terminal: false,
                     is_guard: true,
                     sources: vec![],
+                    request_body_field: None,
                     operation: ConfigOperation::Grpc(GrpcOperation {
                         var: "rl_check".to_string(),
                         service: "dyn-svc".to_string(),
                         message_builder: "test.Request{}".to_string(),
+                        timeout_ms: None,
                         on_reply: vec![ConfigTypedAction {
                             predicate: "rl_check.code == 2".to_string(),
                             terminal: true,
                             is_guard: false,
                             sources: vec![],
+                            request_body_field: None,
                             operation: ConfigOperation::Deny(DenyOperation {
                                 deny_with: "DenyResponse{status: 429u}".to_string(),
                             }),
@@ -1020,4 +1487,41 @@ mod tests {
         }
         assert_eq!(blueprint.actions[1].dependencies, vec!["0"]);
     }
+
+    #[test]
+    fn parallel_action_set_has_no_dependencies() {
+        let services = HashMap::from([
+            build_test_service("auth-svc"),
+            build_test_service("other-auth-svc"),
+        ]);
+        let legacy_action = |service: &str| {
+            ActionConfig::Legacy(ConfigAction {
+                service: service.to_string(),
+                scope: "scope".to_string(),
+                predicates: vec![],
+                conditional_data: vec![],
+                sources: vec![],
+            })
+        };
+
+        let config = ActionSet {
+            name: "parallel-set".to_string(),
+            required_capabilities: vec![],
+            parallel: true,
+            log_sample_rate: None,
+            priority: 0,
+            route_rule_conditions: RouteRuleConditions {
+                hostnames: vec!["example.com".to_string()],
+                predicates: vec![].into(),
+            },
+            actions: vec![legacy_action("auth-svc"), legacy_action("other-auth-svc")],
+        };
+
+        let blueprint = Blueprint::compile(&config, &services, &[]).unwrap();
+        assert_eq!(blueprint.actions.len(), 2);
+        assert!(blueprint
+            .actions
+            .iter()
+            .all(|action| action.dependencies.is_empty()));
+    }
 }
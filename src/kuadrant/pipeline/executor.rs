@@ -1,12 +1,14 @@
-use tracing::error;
+use tracing::{debug, error};
 
+use crate::data::attribute::{list_attributes, AttributeState};
 use crate::kuadrant::{
     pipeline::tasks::{
         noop_response_processor, PendingTask, Task, TaskOutcome, TeardownAction, TeardownOutcome,
     },
     ReqRespCtx,
 };
-use std::collections::{BTreeMap, HashSet};
+use crate::tracing::SampledLogger;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::ops::Not;
 
 pub enum PipelineState {
@@ -16,7 +18,9 @@ pub enum PipelineState {
 
 pub struct Pipeline {
     pub ctx: ReqRespCtx,
-    task_queue: Vec<Box<dyn Task>>,
+    /// Tasks left to apply; those a response requeues go first, ahead of the
+    /// ones still waiting on dependencies or data
+    task_queue: VecDeque<Box<dyn Task>>,
     deferred_tasks: BTreeMap<u32, Box<dyn Task>>,
     completed_tasks: HashSet<String>,
     teardown_tasks: Vec<Box<dyn TeardownAction>>,
@@ -39,7 +43,7 @@ impl Pipeline {
     pub fn new(ctx: ReqRespCtx) -> Self {
         Self {
             ctx,
-            task_queue: Vec::new(),
+            task_queue: VecDeque::new(),
             deferred_tasks: BTreeMap::new(),
             completed_tasks: HashSet::new(),
             teardown_tasks: Vec::new(),
@@ -48,7 +52,7 @@ impl Pipeline {
     }
 
     pub fn with_tasks(mut self, tasks: Vec<Box<dyn Task>>) -> Self {
-        self.task_queue = tasks;
+        self.task_queue = tasks.into();
         self
     }
 
@@ -57,6 +61,25 @@ impl Pipeline {
         self
     }
 
+    /// Tokens of the gRPC calls this pipeline is still awaiting a response for.
+    pub fn pending_tokens(&self) -> impl Iterator<Item = u32> + '_ {
+        self.deferred_tasks.keys().copied()
+    }
+
+    /// Whether the request is paused until the call `token_id` gets a response
+    pub fn is_guarded_by(&self, token_id: u32) -> bool {
+        self.deferred_tasks
+            .get(&token_id)
+            .is_some_and(|task| task.is_guard())
+    }
+
+    /// Whether the request goes on should the call `token_id` fail
+    pub fn fails_open_on(&self, token_id: u32) -> bool {
+        self.deferred_tasks
+            .get(&token_id)
+            .is_some_and(|task| task.fails_open())
+    }
+
     pub fn is_terminated(&self) -> bool {
         self.terminated
     }
@@ -101,7 +124,8 @@ impl Pipeline {
     }
 
     pub fn eval(mut self) -> PipelineState {
-        let tasks_to_process: Vec<_> = self.task_queue.drain(..).collect();
+        let _sampling = SampledLogger::scope(self.ctx.logs_sampled());
+        let tasks_to_process = std::mem::take(&mut self.task_queue);
 
         for task in tasks_to_process {
             if task
@@ -109,7 +133,7 @@ impl Pipeline {
                 .iter()
                 .any(|dep| !self.completed_tasks.contains(dep))
             {
-                self.task_queue.push(task);
+                self.task_queue.push_back(task);
                 continue;
             }
 
@@ -131,9 +155,17 @@ impl Pipeline {
                 TaskOutcome::Failed => {
                     // todo(refactor): error handling
                     error!("Task failed: {:?}", task_id);
+                    self.log_stored_attributes();
                 }
                 TaskOutcome::Terminate(terminal_task) => {
+                    self.log_stored_attributes();
                     terminal_task.apply(&mut self.ctx);
+                    if self.ctx.is_dry_run() {
+                        if let Some(id) = task_id {
+                            self.completed_tasks.insert(id);
+                        }
+                        continue;
+                    }
                     self.task_queue.clear();
                     self.terminated = true;
                     self.execute_teardown();
@@ -151,12 +183,30 @@ impl Pipeline {
         self.into()
     }
 
+    /// Logs the `kuadrant` attributes set so far, telling what a request that
+    /// fails or is denied was decided on
+    fn log_stored_attributes(&self) {
+        if !tracing::enabled!(tracing::Level::DEBUG) {
+            return;
+        }
+        match list_attributes(&self.ctx, "kuadrant") {
+            Ok(AttributeState::Available(attributes)) => {
+                for (path, value) in attributes {
+                    debug!("{path} = {:?}", value.kind);
+                }
+            }
+            Ok(AttributeState::Pending) => {}
+            Err(e) => debug!("Failed to list the stored attributes: {e:?}"),
+        }
+    }
+
     pub fn digest(
         mut self,
         token_id: u32,
         status_code: u32,
         response_size: usize,
     ) -> PipelineState {
+        let _sampling = SampledLogger::scope(self.ctx.logs_sampled());
         if let Some(pending) = self.deferred_tasks.remove(&token_id) {
             match self.ctx.set_grpc_response_data(status_code, response_size) {
                 Ok(_) => {}
@@ -174,7 +224,7 @@ impl Pipeline {
                         self.completed_tasks.insert(id);
                     }
                     for task in tasks.into_iter().rev() {
-                        self.task_queue.insert(0, task);
+                        self.task_queue.push_front(task);
                     }
                 }
                 TaskOutcome::Deferred { token_id, pending } => {
@@ -185,14 +235,22 @@ impl Pipeline {
                 TaskOutcome::Failed => {
                     // todo(refactor): error handling
                     error!("Failed to process response for token_id: {}", token_id);
+                    self.log_stored_attributes();
                 }
                 TaskOutcome::Terminate(terminal_task) => {
+                    self.log_stored_attributes();
                     terminal_task.apply(&mut self.ctx);
-                    self.task_queue.clear();
-                    self.terminated = true;
-                    self.execute_teardown();
+                    if self.ctx.is_dry_run() {
+                        if let Some(id) = task_id {
+                            self.completed_tasks.insert(id);
+                        }
+                    } else {
+                        self.task_queue.clear();
+                        self.terminated = true;
+                        self.execute_teardown();
 
-                    return self.eval();
+                        return self.eval();
+                    }
                 }
             }
         } else {
@@ -210,7 +268,10 @@ impl Pipeline {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kuadrant::pipeline::tasks::FailureModeTask;
     use crate::kuadrant::MockWasmHost;
+    use std::cell::RefCell;
+    use std::rc::Rc;
     use std::sync::Arc;
 
     fn create_test_context() -> ReqRespCtx {
@@ -449,6 +510,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn scenario_concurrent_guard_failure_terminates() {
+        use crate::kuadrant::pipeline::tasks::SendReplyTask;
+
+        let ctx = create_test_context();
+        let mut auth_task = MockGuardTask::new("auth", vec![], true);
+        auth_task.complete_outcome =
+            TaskOutcome::Terminate(Box::new(SendReplyTask::new(500, vec![], None)));
+        let ratelimit_task = MockGuardTask::new("ratelimit", vec![], true);
+
+        let pipeline =
+            Pipeline::new(ctx).with_tasks(vec![Box::new(auth_task), Box::new(ratelimit_task)]);
+
+        let PipelineState::InProgress(pipeline) = pipeline.eval() else {
+            unreachable!("Expected InProgress after both guards dispatch");
+        };
+        assert_eq!(pipeline.ctx.barrier.count(), 2, "Both guards dispatched");
+
+        let PipelineState::InProgress(pipeline) = pipeline.digest(token_id_for("auth"), 0, 0)
+        else {
+            unreachable!("Expected the ratelimit response to still be awaited");
+        };
+        assert!(pipeline.is_terminated());
+
+        let state = pipeline.digest(token_id_for("ratelimit"), 0, 0);
+        assert!(
+            matches!(
+                state,
+                PipelineState::Completed {
+                    should_resume: false
+                }
+            ),
+            "A single failed guard must stop the request"
+        );
+    }
+
     #[test]
     fn scenario_mixed_guard_and_non_guard() {
         let ctx = create_test_context();
@@ -482,4 +579,189 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn scenario_dry_run_never_terminates() {
+        use crate::kuadrant::pipeline::tasks::SendReplyTask;
+
+        let ctx = create_test_context().with_dry_run(true);
+        let mut auth_task = MockGuardTask::new("auth", vec![], true);
+        auth_task.complete_outcome =
+            TaskOutcome::Terminate(Box::new(SendReplyTask::new(403, vec![], None)));
+        let ratelimit_task = MockGuardTask::new("ratelimit", vec!["auth"], true);
+
+        let pipeline =
+            Pipeline::new(ctx).with_tasks(vec![Box::new(auth_task), Box::new(ratelimit_task)]);
+
+        let PipelineState::InProgress(pipeline) = pipeline.eval() else {
+            unreachable!("Expected InProgress after auth dispatch");
+        };
+        let PipelineState::InProgress(pipeline) = pipeline.digest(token_id_for("auth"), 0, 0)
+        else {
+            unreachable!("Expected ratelimit to run after the denial was logged");
+        };
+        assert!(!pipeline.is_terminated());
+
+        let state = pipeline.digest(token_id_for("ratelimit"), 0, 0);
+        assert!(
+            matches!(
+                state,
+                PipelineState::Completed {
+                    should_resume: true
+                }
+            ),
+            "Dry run must resume the request after a would-be denial"
+        );
+    }
+
+    /// Records its position once applied, after awaiting a response when it
+    /// carries a token, then requeues `next`
+    struct ChainTask {
+        position: usize,
+        token_id: Option<u32>,
+        dependencies: Vec<String>,
+        next: Vec<ChainTask>,
+        applied: Rc<RefCell<Vec<usize>>>,
+    }
+
+    impl ChainTask {
+        fn new(position: usize, applied: &Rc<RefCell<Vec<usize>>>) -> Self {
+            Self {
+                position,
+                token_id: None,
+                dependencies: Vec::new(),
+                next: Vec::new(),
+                applied: Rc::clone(applied),
+            }
+        }
+    }
+
+    impl Task for ChainTask {
+        fn apply(self: Box<Self>, _ctx: &mut ReqRespCtx) -> TaskOutcome {
+            let ChainTask {
+                position,
+                token_id,
+                next,
+                applied,
+                ..
+            } = *self;
+            let complete = move || {
+                applied.borrow_mut().push(position);
+                if next.is_empty() {
+                    TaskOutcome::Done
+                } else {
+                    TaskOutcome::Requeued(
+                        next.into_iter()
+                            .map(|task| Box::new(task) as Box<dyn Task>)
+                            .collect(),
+                    )
+                }
+            };
+            match token_id {
+                Some(token_id) => TaskOutcome::Deferred {
+                    token_id,
+                    pending: Box::new(PendingTask::new(
+                        position.to_string(),
+                        Box::new(move |_ctx| complete()),
+                        false,
+                    )),
+                },
+                None => complete(),
+            }
+        }
+
+        fn id(&self) -> Option<String> {
+            Some(self.position.to_string())
+        }
+
+        fn dependencies(&self) -> &[String] {
+            &self.dependencies
+        }
+    }
+
+    #[test]
+    fn long_chains_of_calls_complete() {
+        const CALLS: usize = 10_000;
+        let applied = Rc::new(RefCell::new(Vec::new()));
+        let chain = (0..CALLS)
+            .rev()
+            .fold(None, |next: Option<ChainTask>, position| {
+                let mut task = ChainTask::new(position, &applied);
+                task.token_id = Some(position as u32);
+                task.next = next.into_iter().collect();
+                Some(task)
+            });
+        let mut state = Pipeline::new(create_test_context())
+            .with_tasks(
+                chain
+                    .into_iter()
+                    .map(|t| Box::new(t) as Box<dyn Task>)
+                    .collect(),
+            )
+            .eval();
+
+        for token_id in 0..CALLS as u32 {
+            state = match state {
+                PipelineState::InProgress(pipeline) => {
+                    assert_eq!(pipeline.pending_tokens().collect::<Vec<_>>(), [token_id]);
+                    pipeline.digest(token_id, 0, 0)
+                }
+                PipelineState::Completed { .. } => {
+                    unreachable!("Completed before call {token_id}")
+                }
+            };
+        }
+
+        assert!(matches!(
+            state,
+            PipelineState::Completed {
+                should_resume: true
+            }
+        ));
+        assert_eq!(*applied.borrow(), (0..CALLS).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn tells_how_pending_calls_hold_the_request() {
+        let guard = MockGuardTask::new("guard", vec![], true);
+        let report = MockGuardTask::new("report", vec![], false);
+        let allowed =
+            FailureModeTask::new(Box::new(MockGuardTask::new("allowed", vec![], true)), false);
+        let pipeline = match Pipeline::new(create_test_context())
+            .with_tasks(vec![Box::new(guard), Box::new(report), Box::new(allowed)])
+            .eval()
+        {
+            PipelineState::InProgress(pipeline) => pipeline,
+            PipelineState::Completed { .. } => unreachable!("Expected InProgress after eval"),
+        };
+
+        assert!(pipeline.is_guarded_by(token_id_for("guard")));
+        assert!(!pipeline.fails_open_on(token_id_for("guard")));
+        assert!(!pipeline.is_guarded_by(token_id_for("report")));
+        assert!(pipeline.is_guarded_by(token_id_for("allowed")));
+        assert!(pipeline.fails_open_on(token_id_for("allowed")));
+        assert!(!pipeline.is_guarded_by(token_id_for("unknown")));
+    }
+
+    #[test]
+    fn requeued_tasks_run_in_order_ahead_of_waiting_ones() {
+        let applied = Rc::new(RefCell::new(Vec::new()));
+        let mut call = ChainTask::new(0, &applied);
+        call.token_id = Some(7);
+        call.next = (1..=3)
+            .map(|position| ChainTask::new(position, &applied))
+            .collect();
+        let mut waiting = ChainTask::new(4, &applied);
+        waiting.dependencies = vec!["0".to_string()];
+
+        let pipeline = Pipeline::new(create_test_context())
+            .with_tasks(vec![Box::new(call), Box::new(waiting)]);
+        let state = match pipeline.eval() {
+            PipelineState::InProgress(pipeline) => pipeline.digest(7, 0, 0),
+            PipelineState::Completed { .. } => unreachable!("Expected InProgress after eval"),
+        };
+
+        assert!(matches!(state, PipelineState::Completed { .. }));
+        assert_eq!(*applied.borrow(), vec![0, 1, 2, 3, 4]);
+    }
 }
@@ -1,31 +1,77 @@
 #[allow(deprecated)]
 use crate::configuration::{
     translate_legacy_auth_to_typed, translate_legacy_ratelimit_to_typed,
-    translate_legacy_report_to_typed, ActionConfig, PluginConfiguration,
+    translate_legacy_report_to_typed, ActionConfig, ActionSet, ComputedProperty, ConfigDelta,
+    DynamicActionSpec, InternalRequestPolicy, OverloadMode, PluginConfiguration, TraceGeneration,
+    TracingHeaderStyle,
 };
 use crate::data::{
-    attribute::AttributeState,
-    cel::{Predicate, PredicateVec},
-    Expression,
+    attribute::{AttributeState, Path},
+    cel::{CompoundPredicate, Predicate, PredicateVec},
+    path, Expression,
 };
 use crate::filter::DescriptorManager;
 use crate::kuadrant::pipeline::blueprint::{Action, Blueprint, CompileError, Operation};
 use crate::kuadrant::pipeline::executor::Pipeline;
+use crate::kuadrant::pipeline::tasks::QuotaBodyTask;
 
 use crate::kuadrant::ReqRespCtx;
-use crate::services::ServiceInstance;
-use radix_trie::Trie;
-use std::collections::HashMap;
+use crate::metrics::MetricsCollector;
+use crate::services::{HealthCheck, ServiceInstance};
+use crate::tracing::{HostRandom, SampledLogger};
+use radix_trie::{Trie, TrieCommon};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::rc::Rc;
 use std::sync::Arc;
-use tracing::debug;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
 
 type RequestData = ((String, String), Expression);
 
+/// How the hostname index of the action sets has been used, which requests
+/// read as `kuadrant.index.stats`. A lookup is a hit when some
+/// action set is indexed under the hostname, whether or not its predicates
+/// then match.
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct IndexStats {
+    pub entries: usize,
+    pub lookups: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
 pub struct PipelineFactory {
-    index: Trie<String, Vec<Rc<Blueprint>>>,
+    index: RefCell<Trie<String, Vec<Rc<Blueprint>>>>,
+    index_lookups: Cell<u64>,
+    index_hits: Cell<u64>,
+    index_misses: Cell<u64>,
+    blueprints: RefCell<HashMap<String, Rc<Blueprint>>>,
+    services: HashMap<String, ServiceInstance>,
+    registered_capabilities: HashSet<String>,
     request_data: Arc<Vec<RequestData>>,
+    default_header_values: Arc<HashMap<String, String>>,
+    trace_generation: Option<TraceGeneration>,
+    tracing_header_style: TracingHeaderStyle,
+    static_forwarded_headers: Arc<Vec<(String, String)>>,
+    metrics: Option<Rc<MetricsCollector>>,
+    inherit_deadline_from_request: bool,
+    trigger_on_trailers: bool,
+    max_request_body_size: usize,
+    response_body_injection: bool,
+    max_response_body_size: usize,
+    dry_run: bool,
+    wildcard_match: bool,
+    bypass_paths: Vec<String>,
+    response_headers_to_remove: Vec<String>,
+    access_log: bool,
+    forwarded_trailers: Vec<String>,
+    overload_mode: Option<OverloadMode>,
+    internal_request_policy: Option<InternalRequestPolicy>,
+    computed_properties: Arc<HashMap<String, Expression>>,
     fallback_blueprint: Option<Rc<Blueprint>>,
 }
 
@@ -33,6 +79,8 @@ pub struct PipelineFactory {
 pub enum BuildError {
     DataPending(String),
     EvaluationError(String),
+    /// The action set internal requests are to go through is gone
+    UnknownActionSet(String),
 }
 
 impl Display for BuildError {
@@ -40,6 +88,7 @@ impl Display for BuildError {
         match self {
             BuildError::DataPending(msg) => write!(f, "Data pending: {}", msg),
             BuildError::EvaluationError(msg) => write!(f, "Evaluation error: {}", msg),
+            BuildError::UnknownActionSet(name) => write!(f, "No action set named {}", name),
         }
     }
 }
@@ -47,8 +96,33 @@ impl Display for BuildError {
 impl Default for PipelineFactory {
     fn default() -> Self {
         Self {
-            index: Trie::new(),
+            index: RefCell::new(Trie::new()),
+            index_lookups: Cell::new(0),
+            index_hits: Cell::new(0),
+            index_misses: Cell::new(0),
+            blueprints: RefCell::default(),
+            services: HashMap::new(),
+            registered_capabilities: HashSet::new(),
             request_data: Arc::new(Vec::new()),
+            default_header_values: Arc::new(HashMap::new()),
+            trace_generation: None,
+            tracing_header_style: TracingHeaderStyle::default(),
+            static_forwarded_headers: Arc::new(Vec::new()),
+            metrics: None,
+            inherit_deadline_from_request: false,
+            trigger_on_trailers: false,
+            max_request_body_size: 0,
+            response_body_injection: false,
+            max_response_body_size: 0,
+            dry_run: false,
+            wildcard_match: false,
+            bypass_paths: Vec::new(),
+            response_headers_to_remove: Vec::new(),
+            access_log: false,
+            forwarded_trailers: Vec::new(),
+            overload_mode: None,
+            internal_request_policy: None,
+            computed_properties: Arc::new(HashMap::new()),
             fallback_blueprint: None,
         }
     }
@@ -77,6 +151,8 @@ impl PipelineFactory {
             .cloned()
             .unwrap_or(ServiceInstance::Tracing(None));
 
+        let computed_properties = compile_computed_properties(&config.computed_properties)?;
+
         let request_data_raw: Vec<((String, String), String)> = config
             .request_data
             .iter()
@@ -87,6 +163,7 @@ impl PipelineFactory {
             .collect();
 
         for action_set in &mut config.action_sets {
+            let namespace = action_set.namespace.as_deref();
             for action in &mut action_set.actions {
                 if let ActionConfig::Legacy(legacy) = action {
                     if let Some(
@@ -94,7 +171,11 @@ impl PipelineFactory {
                     ) = services.get(&legacy.service)
                     {
                         #[allow(deprecated)]
-                        let typed = translate_legacy_ratelimit_to_typed(legacy, &request_data_raw);
+                        let typed = translate_legacy_ratelimit_to_typed(
+                            legacy,
+                            &request_data_raw,
+                            namespace,
+                        );
                         *action = ActionConfig::Typed(typed);
                     } else if let Some(ServiceInstance::Auth(_)) = services.get(&legacy.service) {
                         #[allow(deprecated)]
@@ -104,7 +185,8 @@ impl PipelineFactory {
                         services.get(&legacy.service)
                     {
                         #[allow(deprecated)]
-                        let typed = translate_legacy_report_to_typed(legacy, &request_data_raw);
+                        let typed =
+                            translate_legacy_report_to_typed(legacy, &request_data_raw, namespace);
                         *action = ActionConfig::Typed(typed);
                     }
                 }
@@ -132,6 +214,7 @@ impl PipelineFactory {
                     service: tracing_service.clone(),
                     var: header,
                     message_builder: Expression::new("true").expect("Valid expression"),
+                    timeout: None,
                     on_reply: vec![],
                 },
                 dependencies: Default::default(),
@@ -139,13 +222,26 @@ impl PipelineFactory {
                 is_guard: true,
             });
         let mut index = Trie::new();
+        let mut blueprints = HashMap::new();
         for config_action_set in &config.action_sets {
+            if let Some(missing) = config_action_set
+                .required_capabilities
+                .iter()
+                .find(|capability| !config.registered_capabilities.contains(*capability))
+            {
+                info!(
+                    "Skipping action set {}: required capability `{}` is not registered",
+                    config_action_set.name, missing
+                );
+                continue;
+            }
             let mut blueprint = Blueprint::compile(config_action_set, &services, &request_data)?;
             if let Some(dev_mode) = &dev_mode_action {
                 blueprint.actions.push(dev_mode.clone());
             }
 
             let blueprint = Rc::new(blueprint);
+            blueprints.insert(blueprint.name.clone(), Rc::clone(&blueprint));
             for hostname in &config_action_set.route_rule_conditions.hostnames {
                 let key = reverse_subdomain(hostname);
                 index.map_with_default(
@@ -156,25 +252,287 @@ impl PipelineFactory {
             }
         }
 
+        if let Some(InternalRequestPolicy::UseActionSet(name)) = &config.internal_request_policy {
+            if !blueprints.contains_key(name) {
+                return Err(CompileError::UnknownActionSet(name.clone()));
+            }
+        }
+
+        let default_header_values = Arc::new(std::mem::take(
+            &mut config.observability.default_header_values,
+        ));
+
         Ok(Self {
-            index,
+            index: RefCell::new(index),
+            index_lookups: Cell::new(0),
+            index_hits: Cell::new(0),
+            index_misses: Cell::new(0),
+            blueprints: RefCell::new(blueprints),
+            services,
+            registered_capabilities: config.registered_capabilities,
             request_data: Arc::new(request_data),
+            default_header_values,
+            trace_generation: config.observability.trace_generation,
+            tracing_header_style: config.observability.tracing_header_style,
+            static_forwarded_headers: Arc::new(config.static_forwarded_headers),
+            metrics: config
+                .observability
+                .action_set_metrics
+                .then(|| Rc::new(MetricsCollector::default())),
+            inherit_deadline_from_request: config.inherit_deadline_from_request,
+            trigger_on_trailers: config.trigger_on_trailers,
+            max_request_body_size: config.max_request_body_size,
+            response_body_injection: config.response_body_injection,
+            max_response_body_size: config.max_response_body_size,
+            dry_run: config.dry_run,
+            wildcard_match: config.wildcard_match,
+            bypass_paths: config.bypass_paths,
+            response_headers_to_remove: config.response_headers_to_remove,
+            access_log: config.observability.access_log,
+            forwarded_trailers: config.forwarded_trailers,
+            overload_mode: config.overload_mode,
+            internal_request_policy: config.internal_request_policy,
+            computed_properties: Arc::new(computed_properties),
             fallback_blueprint: dev_mode_action.map(|action| {
                 Blueprint {
                     name: "kuadrant.devMode".to_string(),
                     route_predicates: vec![],
                     actions: vec![action],
+                    dynamic_actions: Default::default(),
+                    dynamic_action_count: Default::default(),
+                    log_sample_rate: None,
+                    priority: 0,
+                    namespace: None,
+                    path_prefix: None,
                 }
                 .into()
             }),
         })
     }
 
+    /// Whether pipelines start from the request trailers rather than the headers,
+    /// a running pipeline is evaluated again on the trailers regardless
+    pub fn trigger_on_trailers(&self) -> bool {
+        self.trigger_on_trailers
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn overload_mode(&self) -> Option<OverloadMode> {
+        self.overload_mode
+    }
+
+    pub fn internal_request_policy(&self) -> Option<&InternalRequestPolicy> {
+        self.internal_request_policy.as_ref()
+    }
+
+    pub fn has_bypass_paths(&self) -> bool {
+        !self.bypass_paths.is_empty()
+    }
+
+    /// The health checks of the services configured with `healthCheck`
+    pub fn health_checks(&self) -> Vec<HealthCheck> {
+        self.services
+            .iter()
+            .filter_map(|(name, service)| match service.health_check_request()? {
+                Ok(request) => Some(HealthCheck {
+                    service: name.clone(),
+                    request,
+                }),
+                Err(e) => {
+                    warn!("Skipping health check of {}: {}", name, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The shortest delay any service waits before retrying a call, which
+    /// the root context is to tick at least as often as
+    pub fn retry_tick_period(&self) -> Option<Duration> {
+        self.services
+            .values()
+            .filter_map(ServiceInstance::retry_base_delay)
+            .min()
+    }
+
+    /// Marks `service` as passing or failing its health check; the actions
+    /// calling a failing one follow its failure mode without calling it
+    pub fn set_service_health(&self, service: &str, healthy: bool) {
+        if let Some(service) = self.services.get(service) {
+            service.set_healthy(healthy);
+        }
+    }
+
+    /// Whether requests to `path`, normalized and ignoring its query, skip every
+    /// action set
+    pub fn bypasses(&self, path: &str) -> bool {
+        let path = path::normalize(path.split_once('?').map_or(path, |(path, _)| path));
+        self.bypass_paths
+            .iter()
+            .any(|bypass| match bypass.strip_suffix('*') {
+                Some(prefix) => path::has_prefix(&path, prefix),
+                None => path == *bypass,
+            })
+    }
+
+    pub fn access_log(&self) -> bool {
+        self.access_log
+    }
+
+    pub fn response_headers_to_remove(&self) -> &[String] {
+        &self.response_headers_to_remove
+    }
+
+    pub fn forwarded_trailers(&self) -> &[String] {
+        &self.forwarded_trailers
+    }
+
+    pub fn index_stats(&self) -> IndexStats {
+        IndexStats {
+            entries: self.index.borrow().len(),
+            lookups: self.index_lookups.get(),
+            hits: self.index_hits.get(),
+            misses: self.index_misses.get(),
+        }
+    }
+
+    /// Zeroes the counters, for them to describe the index as it is now
+    pub fn reset_index_stats(&self) {
+        self.index_lookups.set(0);
+        self.index_hits.set(0);
+        self.index_misses.set(0);
+    }
+
+    /// Removes then upserts the action sets of `delta`, for the requests that
+    /// follow. Every upserted action set is compiled before any change is
+    /// made, so that a delta failing to compile leaves the running one as is.
+    pub fn apply_delta(&self, delta: &ConfigDelta) -> Result<(), CompileError> {
+        if let Some(InternalRequestPolicy::UseActionSet(name)) = &self.internal_request_policy {
+            if delta.remove_action_sets.contains(name) {
+                return Err(CompileError::ActionSetInUse(name.clone()));
+            }
+        }
+        let mut index = self.index.borrow().clone();
+        let mut blueprints = self.blueprints.borrow().clone();
+        for name in &delta.remove_action_sets {
+            if !remove_blueprint(&mut index, &mut blueprints, name) {
+                warn!("config delta removes unknown action set {}", name);
+            }
+        }
+        for action_set in &delta.upsert_action_sets {
+            remove_blueprint(&mut index, &mut blueprints, &action_set.name);
+            let Some(blueprint) = self.compile_upserted(action_set)? else {
+                continue;
+            };
+            let blueprint = Rc::new(blueprint);
+            for hostname in &action_set.route_rule_conditions.hostnames {
+                index.map_with_default(
+                    reverse_subdomain(hostname),
+                    |blueprints| blueprints.push(Rc::clone(&blueprint)),
+                    vec![Rc::clone(&blueprint)],
+                );
+            }
+            blueprints.insert(blueprint.name.clone(), blueprint);
+        }
+        *self.index.borrow_mut() = index;
+        *self.blueprints.borrow_mut() = blueprints;
+        Ok(())
+    }
+
+    /// The blueprint of an upserted `action_set`, none when it requires a
+    /// capability that is not registered
+    fn compile_upserted(&self, action_set: &ActionSet) -> Result<Option<Blueprint>, CompileError> {
+        if let Some(missing) = action_set
+            .required_capabilities
+            .iter()
+            .find(|capability| !self.registered_capabilities.contains(*capability))
+        {
+            info!(
+                "Skipping action set {}: required capability `{}` is not registered",
+                action_set.name, missing
+            );
+            return Ok(None);
+        }
+        let mut blueprint = Blueprint::compile(action_set, &self.services, &self.request_data)?;
+        if let Some(dev_mode) = &self.fallback_blueprint {
+            blueprint.actions.extend(dev_mode.actions.iter().cloned());
+        }
+        Ok(Some(blueprint))
+    }
+
+    /// The names of the rate limit services, whose actions are held to the
+    /// rate limit domain rules by the validation of config deltas
+    pub fn rate_limit_services(&self) -> HashSet<String> {
+        self.services
+            .iter()
+            .filter(|(_, service)| {
+                matches!(
+                    service,
+                    ServiceInstance::RateLimit(_)
+                        | ServiceInstance::RateLimitCheck(_)
+                        | ServiceInstance::RateLimitReport(_)
+                )
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Adds an action pushed at runtime to the action set it targets
+    pub fn inject_dynamic_action(&self, spec: &DynamicActionSpec) -> Result<(), CompileError> {
+        let blueprint = self
+            .blueprints
+            .borrow()
+            .get(&spec.target_action_set)
+            .cloned()
+            .ok_or_else(|| CompileError::UnknownActionSet(spec.target_action_set.clone()))?;
+        blueprint.inject_dynamic_action(
+            &spec.action,
+            spec.expires_at_ms,
+            &self.services,
+            &self.request_data,
+        )
+    }
+
+    pub fn evict_expired_dynamic_actions(&self, now: SystemTime) -> usize {
+        self.blueprints
+            .borrow()
+            .values()
+            .map(|blueprint| blueprint.evict_expired_dynamic_actions(now))
+            .sum()
+    }
+
     pub fn build(&self, mut ctx: ReqRespCtx) -> Result<Option<Pipeline>, BuildError> {
         let blueprint = match self.select_blueprint(&mut ctx)? {
             Some(bp) => bp,
             None => return Ok(None),
         };
+        self.build_from(ctx, blueprint)
+    }
+
+    /// Builds the pipeline of the action set `name`, whatever the hostname
+    /// and route predicates of the request. Its absence is an error rather
+    /// than a miss, so as not to let the request through unchecked.
+    pub fn build_for_action_set(
+        &self,
+        mut ctx: ReqRespCtx,
+        name: &str,
+    ) -> Result<Option<Pipeline>, BuildError> {
+        let hostname = self.get_hostname(&ctx)?;
+        ctx.set_hostname(hostname);
+        let Some(blueprint) = self.blueprints.borrow().get(name).cloned() else {
+            return Err(BuildError::UnknownActionSet(name.to_string()));
+        };
+        self.build_from(ctx, blueprint)
+    }
+
+    fn build_from(
+        &self,
+        mut ctx: ReqRespCtx,
+        blueprint: Rc<Blueprint>,
+    ) -> Result<Option<Pipeline>, BuildError> {
         ctx.set_action_set_name(blueprint.name.clone());
 
         // Clone request_data with fresh expressions for this request
@@ -185,13 +543,34 @@ impl PipelineFactory {
             .map(|((domain, field), expr)| ((domain.clone(), field.clone()), expr.clone()))
             .collect();
 
-        let mut ctx = ctx.with_request_data(request_data.clone());
+        let mut ctx = ctx
+            .with_request_data(request_data.clone())
+            .with_default_header_values(Arc::clone(&self.default_header_values))
+            .with_computed_properties(Arc::clone(&self.computed_properties))
+            .with_dry_run(self.dry_run)
+            .with_tracing_header_style(self.tracing_header_style)
+            .with_static_forwarded_headers(Arc::clone(&self.static_forwarded_headers))
+            .with_metrics(self.metrics.clone())
+            .with_index_stats(self.index_stats());
         ctx.extract_trace_context();
+        if let Some(trace_generation) = self.trace_generation {
+            ctx.generate_trace_context(trace_generation.sampled);
+        }
+        if self.inherit_deadline_from_request {
+            ctx.inherit_deadline();
+        }
+        if let Some(rate) = blueprint.log_sample_rate {
+            ctx.set_logs_sampled(SampledLogger::new(rate).should_log(&mut HostRandom));
+        }
 
-        let (tasks, teardown_tasks) = blueprint.to_tasks(&mut ctx, &request_data);
+        let (mut tasks, teardown_tasks) =
+            blueprint.to_tasks(&mut ctx, &request_data, self.max_request_body_size);
         if tasks.is_empty() {
             return Ok(None);
         }
+        if self.response_body_injection && blueprint.calls_rate_limit_service() {
+            tasks.push(Box::new(QuotaBodyTask::new(self.max_response_body_size)));
+        }
 
         Ok(Some(
             Pipeline::new(ctx)
@@ -204,13 +583,41 @@ impl PipelineFactory {
         let hostname = self.get_hostname(ctx)?;
         ctx.set_hostname(hostname.clone());
 
-        let candidates = match self.index.get_ancestor_value(&reverse_subdomain(&hostname)) {
-            Some(blueprints) => blueprints,
-            None => {
-                debug!("No matching blueprint found for hostname: {}", hostname);
-                return Ok(None);
-            }
+        let mut candidates = if self.wildcard_match {
+            self.get_all_matching_blueprints(&hostname)
+        } else {
+            self.index
+                .borrow()
+                .get_ancestor_value(&reverse_subdomain(&hostname))
+                .cloned()
+                .unwrap_or_default()
         };
+        self.index_lookups.set(self.index_lookups.get() + 1);
+        let counter = if candidates.is_empty() {
+            &self.index_misses
+        } else {
+            &self.index_hits
+        };
+        counter.set(counter.get() + 1);
+        if candidates
+            .iter()
+            .any(|blueprint| blueprint.path_prefix.is_some())
+        {
+            let path = self.get_path(ctx)?;
+            candidates.retain(|blueprint| blueprint.matches_path(&path));
+        }
+        // Stable, so action sets of equal priority and path prefix length keep
+        // their configured order
+        candidates.sort_by_key(|blueprint| {
+            (
+                blueprint.priority,
+                Reverse(blueprint.path_prefix.as_ref().map_or(0, String::len)),
+            )
+        });
+        if candidates.is_empty() {
+            debug!("No matching blueprint found for hostname: {}", hostname);
+            return Ok(None);
+        }
 
         for blueprint in candidates {
             if self.route_predicates_match(&blueprint.route_predicates, &blueprint.name, ctx)? {
@@ -218,7 +625,7 @@ impl PipelineFactory {
                     "Selected blueprint {} for hostname: {}",
                     blueprint.name, hostname
                 );
-                return Ok(Some(Rc::clone(blueprint)));
+                return Ok(Some(blueprint));
             }
         }
 
@@ -226,6 +633,29 @@ impl PipelineFactory {
         Ok(self.fallback_blueprint.clone())
     }
 
+    /// The blueprints of every entry matching `hostname`, the exact match first and
+    /// then each wildcard from the most to the least specific, without repeats.
+    fn get_all_matching_blueprints(&self, hostname: &str) -> Vec<Rc<Blueprint>> {
+        let key = reverse_subdomain(hostname);
+        let wildcard_keys = key
+            .char_indices()
+            .rev()
+            .filter(|(_, c)| *c == '.')
+            .map(|(i, _)| &key[..=i]);
+        let index = self.index.borrow();
+        let mut matching: Vec<Rc<Blueprint>> = Vec::new();
+        for blueprint in std::iter::once(key.as_str())
+            .chain(wildcard_keys)
+            .filter_map(|key| index.get(key))
+            .flatten()
+        {
+            if !matching.iter().any(|known| Rc::ptr_eq(known, blueprint)) {
+                matching.push(Rc::clone(blueprint));
+            }
+        }
+        matching
+    }
+
     fn get_hostname(&self, ctx: &ReqRespCtx) -> Result<String, BuildError> {
         match ctx.get_attribute::<String>("request.host") {
             Ok(AttributeState::Available(Some(host))) => {
@@ -240,9 +670,20 @@ impl PipelineFactory {
         }
     }
 
+    fn get_path(&self, ctx: &ReqRespCtx) -> Result<String, BuildError> {
+        match ctx.get_attribute::<String>("request.path") {
+            Ok(AttributeState::Available(Some(path))) => Ok(path),
+            Ok(AttributeState::Available(None)) => {
+                Err(BuildError::EvaluationError("path not found".to_string()))
+            }
+            Ok(AttributeState::Pending) => Err(BuildError::DataPending("path".to_string())),
+            Err(e) => Err(BuildError::EvaluationError(e.to_string())),
+        }
+    }
+
     fn route_predicates_match(
         &self,
-        predicates: &Vec<Predicate>,
+        predicates: &Vec<CompoundPredicate>,
         blueprint_name: &str,
         ctx: &ReqRespCtx,
     ) -> Result<bool, BuildError> {
@@ -260,6 +701,31 @@ impl PipelineFactory {
     }
 }
 
+/// Stops matching the action set `name`, returning whether there was one
+fn remove_blueprint(
+    index: &mut Trie<String, Vec<Rc<Blueprint>>>,
+    blueprints: &mut HashMap<String, Rc<Blueprint>>,
+    name: &str,
+) -> bool {
+    let Some(removed) = blueprints.remove(name) else {
+        return false;
+    };
+    let keys: Vec<String> = index
+        .iter()
+        .filter(|(_, blueprints)| blueprints.iter().any(|bp| Rc::ptr_eq(bp, &removed)))
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in keys {
+        if let Some(blueprints) = index.get_mut(&key) {
+            blueprints.retain(|bp| !Rc::ptr_eq(bp, &removed));
+            if blueprints.is_empty() {
+                index.remove(&key);
+            }
+        }
+    }
+    true
+}
+
 fn reverse_subdomain(subdomain: &str) -> String {
     let mut s = subdomain.to_string();
     s.push('.');
@@ -289,6 +755,72 @@ fn domain_and_field_name(name: &str) -> (&str, &str) {
         .unwrap_or(("", name))
 }
 
+fn compile_computed_properties(
+    properties: &[ComputedProperty],
+) -> Result<HashMap<String, Expression>, CompileError> {
+    let compiled = properties
+        .iter()
+        .map(|property| {
+            Ok((
+                property.name.clone(),
+                Expression::new(&property.expression)?,
+            ))
+        })
+        .collect::<Result<HashMap<String, Expression>, CompileError>>()?;
+
+    let names: Vec<(&str, Path)> = compiled
+        .keys()
+        .map(|name| (name.as_str(), name.as_str().into()))
+        .collect();
+    let dependencies: HashMap<&str, Vec<&str>> = compiled
+        .iter()
+        .map(|(name, expression)| {
+            let mut deps: Vec<&str> = names
+                .iter()
+                .filter(|(_, path)| {
+                    expression
+                        .attribute_paths()
+                        .any(|attr| attr.tokens().starts_with(&path.tokens()))
+                })
+                .map(|(dep, _)| *dep)
+                .collect();
+            deps.sort();
+            (name.as_str(), deps)
+        })
+        .collect();
+
+    let mut roots: Vec<&str> = dependencies.keys().copied().collect();
+    roots.sort();
+    let mut visited = HashSet::new();
+    for root in roots {
+        find_property_cycle(root, &dependencies, &mut visited, &mut Vec::new())?;
+    }
+    Ok(compiled)
+}
+
+fn find_property_cycle<'a>(
+    name: &'a str,
+    dependencies: &HashMap<&'a str, Vec<&'a str>>,
+    visited: &mut HashSet<&'a str>,
+    stack: &mut Vec<&'a str>,
+) -> Result<(), CompileError> {
+    if let Some(start) = stack.iter().position(|entry| *entry == name) {
+        let mut cycle: Vec<String> = stack[start..].iter().map(|s| s.to_string()).collect();
+        cycle.push(name.to_string());
+        return Err(CompileError::CyclicProperty { cycle });
+    }
+    if visited.contains(name) {
+        return Ok(());
+    }
+    stack.push(name);
+    for dep in dependencies.get(name).into_iter().flatten() {
+        find_property_cycle(dep, dependencies, visited, stack)?;
+    }
+    stack.pop();
+    visited.insert(name);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -309,11 +841,20 @@ mod tests {
             service_name.to_string(),
             Service {
                 service_type: ServiceType::Auth,
-                endpoint: "test-cluster".to_string(),
+                endpoint: "test-cluster".to_string().into(),
                 failure_mode: FailureMode::Deny,
                 timeout: Timeout::default(),
                 grpc_service: None,
                 grpc_method: None,
+                circuit_breaker: None,
+                retry_policy: None,
+                response_cache: None,
+                error_response: None,
+                use_grpc_status_details: false,
+                health_check: false,
+                dynamic_metadata_key: None,
+                #[cfg(feature = "http-callout")]
+                http_callout: None,
             },
         );
 
@@ -321,9 +862,15 @@ mod tests {
             services,
             vec![ActionSet {
                 name: "test-action-set".to_string(),
+                required_capabilities: vec![],
+                parallel: false,
+                log_sample_rate: None,
+                priority: 0,
+                namespace: None,
+                path_prefix: None,
                 route_rule_conditions: RouteRuleConditions {
                     hostnames,
-                    predicates,
+                    predicates: predicates.into(),
                 },
                 actions: vec![ActionConfig::Legacy(Action {
                     service: service_name.to_string(),
@@ -378,11 +925,20 @@ mod tests {
             "test-service".to_string(),
             Service {
                 service_type: ServiceType::Auth,
-                endpoint: "test-cluster".to_string(),
+                endpoint: "test-cluster".to_string().into(),
                 failure_mode: FailureMode::Deny,
                 timeout: Timeout::default(),
                 grpc_service: None,
                 grpc_method: None,
+                circuit_breaker: None,
+                retry_policy: None,
+                response_cache: None,
+                error_response: None,
+                use_grpc_status_details: false,
+                health_check: false,
+                dynamic_metadata_key: None,
+                #[cfg(feature = "http-callout")]
+                http_callout: None,
             },
         );
 
@@ -390,9 +946,15 @@ mod tests {
             services,
             vec![ActionSet {
                 name: "test-action-set".to_string(),
+                required_capabilities: vec![],
+                parallel: false,
+                log_sample_rate: None,
+                priority: 0,
+                namespace: None,
+                path_prefix: None,
                 route_rule_conditions: RouteRuleConditions {
                     hostnames: vec!["example.com".to_string()],
-                    predicates: vec!["invalid syntax !!!".to_string()],
+                    predicates: vec!["invalid syntax !!!".to_string()].into(),
                 },
                 actions: vec![],
             }],
@@ -402,6 +964,22 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn factory_fails_on_predicate_that_cannot_evaluate_to_bool() {
+        for predicate in ["1 + 'a' == 2", "'not a bool'"] {
+            let config = build_test_config(
+                vec!["example.com".to_string()],
+                vec![predicate.to_string()],
+                "test-service",
+            );
+            let result = PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default()));
+            assert!(
+                matches!(result, Err(CompileError::InvalidRoutePredicate { .. })),
+                "{predicate} was accepted"
+            );
+        }
+    }
+
     #[test]
     fn build_returns_none_when_hostname_does_not_match() {
         let config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
@@ -482,6 +1060,232 @@ mod tests {
         assert!(result.unwrap().is_some());
     }
 
+    #[test]
+    fn lower_priority_action_sets_are_tried_first() {
+        let mut config = build_test_config(
+            vec!["example.com".to_string()],
+            vec!["request.method == 'GET'".to_string()],
+            "test-service",
+        );
+        config.action_sets[0].priority = 10;
+        let mut preferred = config.action_sets[0].clone();
+        preferred.name = "preferred".to_string();
+        preferred.priority = -1;
+        preferred.route_rule_conditions.predicates = vec![].into();
+        config.action_sets.push(preferred);
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+
+        // evaluating the first configured action set's predicate would fail the build
+        let mock_host = MockWasmHost::new()
+            .with_property("request.host".into(), "example.com".as_bytes().to_vec())
+            .with_pending_property("request.method".into());
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+
+        let selected = factory.select_blueprint(&mut ctx).unwrap().unwrap();
+        assert_eq!(selected.name, "preferred");
+    }
+
+    #[test]
+    fn path_prefixes_narrow_the_action_sets_of_an_authority() {
+        let mut config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
+        config.action_sets[0].name = "any-path".to_string();
+        for (name, hostname, path_prefix) in [
+            ("api", "example.com", "/api/"),
+            ("api-v1", "example.com", "/api/v1/"),
+            ("other-api-v2", "other.com", "/api/v2/"),
+        ] {
+            let mut action_set = config.action_sets[0].clone();
+            action_set.name = name.to_string();
+            action_set.route_rule_conditions.hostnames = vec![hostname.to_string()];
+            action_set.path_prefix = Some(path_prefix.to_string());
+            config.action_sets.push(action_set);
+        }
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+
+        let selected = |host: &str, path: &str| {
+            let mock_host = MockWasmHost::new()
+                .with_property("request.host".into(), host.as_bytes().to_vec())
+                .with_property("request.path".into(), path.as_bytes().to_vec());
+            let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+            factory
+                .select_blueprint(&mut ctx)
+                .unwrap()
+                .map(|blueprint| blueprint.name.clone())
+        };
+
+        assert_eq!(
+            selected("example.com", "/api/v1/toys"),
+            Some("api-v1".to_string())
+        );
+        assert_eq!(
+            selected("example.com", "/api/v2/toys"),
+            Some("api".to_string())
+        );
+        assert_eq!(
+            selected("example.com", "/api?v1/"),
+            Some("any-path".to_string())
+        );
+        assert_eq!(
+            selected("example.com", "/toys"),
+            Some("any-path".to_string())
+        );
+        assert_eq!(
+            selected("other.com", "/api/v2/toys"),
+            Some("other-api-v2".to_string())
+        );
+        assert_eq!(selected("other.com", "/api/v1/toys"), None);
+    }
+
+    #[test]
+    fn path_prefixes_match_whole_segments_after_priority() {
+        let mut config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
+        config.action_sets[0].name = "api".to_string();
+        config.action_sets[0].path_prefix = Some("/api".to_string());
+        config.action_sets[0].priority = 1;
+        let mut preferred = config.action_sets[0].clone();
+        preferred.name = "preferred-api".to_string();
+        preferred.priority = 0;
+        config.action_sets.push(preferred);
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+
+        let selected = |path: &str| {
+            let mock_host = MockWasmHost::new()
+                .with_property("request.host".into(), "example.com".as_bytes().to_vec())
+                .with_property("request.path".into(), path.as_bytes().to_vec());
+            let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+            factory
+                .select_blueprint(&mut ctx)
+                .unwrap()
+                .map(|blueprint| blueprint.name.clone())
+        };
+
+        assert_eq!(selected("/api/toys"), Some("preferred-api".to_string()));
+        assert_eq!(selected("/api"), Some("preferred-api".to_string()));
+        assert_eq!(selected("/toys/../api/"), Some("preferred-api".to_string()));
+        assert_eq!(selected("/apis"), None);
+        assert_eq!(selected("/api/../apis"), None);
+    }
+
+    #[test]
+    fn builds_the_named_action_set_whatever_the_hostname() {
+        let mut config = build_test_config(
+            vec!["mesh.internal".to_string()],
+            vec!["request.method == 'GET'".to_string()],
+            "test-service",
+        );
+        config.internal_request_policy = Some(InternalRequestPolicy::UseActionSet(
+            "test-action-set".to_string(),
+        ));
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+        let request = || {
+            let mock_host = MockWasmHost::new()
+                .with_property("request.host".into(), "example.com".as_bytes().to_vec())
+                .with_property("request.method".into(), "POST".as_bytes().to_vec());
+            ReqRespCtx::new(Arc::new(mock_host))
+        };
+
+        assert!(factory.build(request()).unwrap().is_none());
+        assert!(factory
+            .build_for_action_set(request(), "test-action-set")
+            .unwrap()
+            .is_some());
+        assert!(matches!(
+            factory.build_for_action_set(request(), "unknown"),
+            Err(BuildError::UnknownActionSet(name)) if name == "unknown"
+        ));
+    }
+
+    #[test]
+    fn rejects_an_internal_request_policy_naming_an_unknown_action_set() {
+        let mut config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
+        config.internal_request_policy =
+            Some(InternalRequestPolicy::UseActionSet("unknown".to_string()));
+        assert!(matches!(
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())),
+            Err(CompileError::UnknownActionSet(name)) if name == "unknown"
+        ));
+    }
+
+    #[test]
+    fn index_stats_count_the_hostname_lookups() {
+        let config = build_test_config(
+            vec!["example.com".to_string(), "*.toystore.com".to_string()],
+            vec!["request.method == 'GET'".to_string()],
+            "test-service",
+        );
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+        let lookup = |host: &str| {
+            let mock_host = MockWasmHost::new()
+                .with_property("request.host".into(), host.as_bytes().to_vec())
+                .with_property("request.method".into(), "GET".as_bytes().to_vec());
+            factory.build(ReqRespCtx::new(Arc::new(mock_host))).unwrap()
+        };
+
+        assert_eq!(
+            factory.index_stats(),
+            IndexStats {
+                entries: 2,
+                ..IndexStats::default()
+            }
+        );
+        for host in [
+            "example.com",
+            "api.toystore.com",
+            "other.com",
+            "example.com",
+        ] {
+            lookup(host);
+        }
+        assert_eq!(
+            factory.index_stats(),
+            IndexStats {
+                entries: 2,
+                lookups: 4,
+                hits: 3,
+                misses: 1,
+            }
+        );
+
+        factory.reset_index_stats();
+        assert_eq!(
+            factory.index_stats(),
+            IndexStats {
+                entries: 2,
+                ..IndexStats::default()
+            }
+        );
+    }
+
+    #[test]
+    fn requests_read_the_index_stats() {
+        let config = build_test_config(
+            vec!["example.com".to_string()],
+            vec!["request.method == 'GET'".to_string()],
+            "test-service",
+        );
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+        let mock_host = MockWasmHost::new()
+            .with_property("request.host".into(), "example.com".as_bytes().to_vec())
+            .with_property("request.method".into(), "GET".as_bytes().to_vec());
+
+        let pipeline = factory
+            .build(ReqRespCtx::new(Arc::new(mock_host)))
+            .unwrap()
+            .expect("pipeline");
+        assert_eq!(
+            pipeline.ctx.get_attribute::<String>("kuadrant.index.stats"),
+            Ok(AttributeState::Available(Some(
+                r#"{"entries":1,"lookups":1,"hits":1,"misses":0}"#.to_string()
+            )))
+        );
+    }
+
     #[test]
     fn build_returns_none_when_route_predicates_do_not_match() {
         let config = build_test_config(
@@ -616,11 +1420,20 @@ mod tests {
             "test-service".to_string(),
             Service {
                 service_type: ServiceType::Auth,
-                endpoint: "test-cluster".to_string(),
+                endpoint: "test-cluster".to_string().into(),
                 failure_mode: FailureMode::Deny,
                 timeout: Timeout::default(),
                 grpc_service: None,
                 grpc_method: None,
+                circuit_breaker: None,
+                retry_policy: None,
+                response_cache: None,
+                error_response: None,
+                use_grpc_status_details: false,
+                health_check: false,
+                dynamic_metadata_key: None,
+                #[cfg(feature = "http-callout")]
+                http_callout: None,
             },
         );
 
@@ -640,6 +1453,64 @@ mod tests {
         assert_eq!(factory.request_data.len(), 1);
     }
 
+    #[test]
+    fn dynamic_actions_are_served_until_they_expire() {
+        let config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+
+        let message = br#"{
+            "targetActionSet": "test-action-set",
+            "action": {"service": "test-service", "scope": "dynamic-scope"},
+            "expiresAtMs": 2000
+        }"#;
+        let spec: DynamicActionSpec = serde_json::from_slice(message).unwrap();
+        factory.inject_dynamic_action(&spec).unwrap();
+
+        let unknown = DynamicActionSpec {
+            target_action_set: "missing".to_string(),
+            ..spec
+        };
+        assert!(matches!(
+            factory.inject_dynamic_action(&unknown),
+            Err(CompileError::UnknownActionSet(_))
+        ));
+
+        let blueprint = Rc::clone(&factory.blueprints["test-action-set"]);
+        let start = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        let mock_host = Arc::new(MockWasmHost::new().with_current_time(start));
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        assert_eq!(blueprint.to_tasks(&mut ctx, &[], 1024).0.len(), 2);
+
+        mock_host.advance_time(std::time::Duration::from_secs(2));
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        assert_eq!(blueprint.to_tasks(&mut ctx, &[], 1024).0.len(), 1);
+
+        assert_eq!(factory.evict_expired_dynamic_actions(start), 0);
+        assert_eq!(factory.evict_expired_dynamic_actions(ctx.current_time()), 1);
+        assert!(blueprint.dynamic_actions.borrow().is_empty());
+    }
+
+    #[test]
+    fn factory_skips_action_sets_with_unregistered_capabilities() {
+        let mut config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
+        config.action_sets[0].required_capabilities = vec!["ai_guard".to_string()];
+
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+        assert!(factory.index.borrow().is_empty());
+    }
+
+    #[test]
+    fn factory_keeps_action_sets_with_registered_capabilities() {
+        let mut config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
+        config.action_sets[0].required_capabilities = vec!["auth".to_string()];
+
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+        assert!(!factory.index.borrow().is_empty());
+    }
+
     #[test]
     fn factory_handles_multiple_hostnames_for_same_action_set() {
         let config = build_test_config(
@@ -664,4 +1535,238 @@ mod tests {
         let ctx2 = ReqRespCtx::new(Arc::new(mock_host2));
         assert!(factory.build(ctx2).unwrap().is_some());
     }
+
+    fn delta(remove: &[&str], upsert: Vec<ActionSet>) -> ConfigDelta {
+        ConfigDelta {
+            delta: true,
+            remove_action_sets: remove.iter().map(|name| name.to_string()).collect(),
+            upsert_action_sets: upsert,
+        }
+    }
+
+    #[test]
+    fn upserted_action_sets_replace_those_of_the_same_name() {
+        let config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
+        let mut upserted = config.action_sets[0].clone();
+        upserted.route_rule_conditions.hostnames = vec!["example.org".to_string()];
+        let mut added = config.action_sets[0].clone();
+        added.name = "added".to_string();
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+        let selected = |hostname: &str| {
+            let mock_host = MockWasmHost::new()
+                .with_property("request.host".into(), hostname.as_bytes().to_vec());
+            let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+            factory
+                .select_blueprint(&mut ctx)
+                .unwrap()
+                .map(|blueprint| blueprint.name.clone())
+        };
+
+        assert_eq!(selected("example.com"), Some("test-action-set".to_string()));
+        factory.apply_delta(&delta(&[], vec![upserted])).unwrap();
+        assert_eq!(selected("example.org"), Some("test-action-set".to_string()));
+        assert_eq!(selected("example.com"), None);
+
+        factory.apply_delta(&delta(&[], vec![added])).unwrap();
+        assert_eq!(selected("example.com"), Some("added".to_string()));
+        assert_eq!(factory.blueprints.borrow().len(), 2);
+    }
+
+    #[test]
+    fn removed_action_sets_are_no_longer_matched() {
+        let mut config = build_test_config(
+            vec!["example.com".to_string(), "*.example.com".to_string()],
+            vec![],
+            "test-service",
+        );
+        let mut other = config.action_sets[0].clone();
+        other.name = "other".to_string();
+        other.route_rule_conditions.hostnames = vec!["*.example.com".to_string()];
+        config.action_sets.push(other);
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+
+        factory
+            .apply_delta(&delta(&["test-action-set", "unknown"], vec![]))
+            .unwrap();
+        assert!(factory
+            .get_all_matching_blueprints("example.com")
+            .is_empty());
+        let names: Vec<_> = factory
+            .get_all_matching_blueprints("api.example.com")
+            .iter()
+            .map(|blueprint| blueprint.name.clone())
+            .collect();
+        assert_eq!(names, vec!["other"]);
+
+        factory.apply_delta(&delta(&["other"], vec![])).unwrap();
+        assert!(factory.index.borrow().is_empty());
+    }
+
+    #[test]
+    fn failing_deltas_leave_the_action_sets_untouched() {
+        let mut config = build_test_config(vec!["example.com".to_string()], vec![], "test-service");
+        let mut invalid = config.action_sets[0].clone();
+        invalid.name = "invalid".to_string();
+        invalid.route_rule_conditions.predicates = vec!["not valid CEL (".to_string()].into();
+        let mut internal = config.action_sets[0].clone();
+        internal.name = "internal".to_string();
+        internal.route_rule_conditions.hostnames = vec!["mesh.internal".to_string()];
+        config.action_sets.push(internal);
+        config.internal_request_policy =
+            Some(InternalRequestPolicy::UseActionSet("internal".to_string()));
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+
+        assert!(matches!(
+            factory.apply_delta(&delta(&["test-action-set"], vec![invalid])),
+            Err(CompileError::InvalidRoutePredicate { .. })
+        ));
+        assert!(matches!(
+            factory.apply_delta(&delta(&["internal"], vec![])),
+            Err(CompileError::ActionSetInUse(name)) if name == "internal"
+        ));
+
+        assert_eq!(factory.blueprints.borrow().len(), 2);
+        assert_eq!(factory.get_all_matching_blueprints("example.com").len(), 1);
+        assert_eq!(
+            factory.get_all_matching_blueprints("mesh.internal").len(),
+            1
+        );
+    }
+
+    fn build_overlapping_config(wildcard_match: bool) -> PluginConfiguration {
+        let mut config = build_test_config(
+            vec!["api.example.com".to_string()],
+            vec!["request.method == 'GET'".to_string()],
+            "test-service",
+        );
+        let mut catch_all = config.action_sets[0].clone();
+        catch_all.name = "catch-all".to_string();
+        catch_all.route_rule_conditions = RouteRuleConditions {
+            hostnames: vec!["*.example.com".to_string(), "*".to_string()],
+            predicates: vec![].into(),
+        };
+        config.action_sets.push(catch_all);
+        config.wildcard_match = wildcard_match;
+        config
+    }
+
+    #[test]
+    fn get_all_matching_blueprints_orders_specific_before_wildcards() {
+        let factory = PipelineFactory::try_from(
+            build_overlapping_config(true),
+            &Rc::new(DescriptorManager::default()),
+        )
+        .unwrap();
+
+        let names = |hostname| {
+            factory
+                .get_all_matching_blueprints(hostname)
+                .iter()
+                .map(|blueprint| blueprint.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names("api.example.com"),
+            vec!["test-action-set", "catch-all"]
+        );
+        assert_eq!(names("www.example.com"), vec!["catch-all"]);
+        assert_eq!(names("example.org"), vec!["catch-all"]);
+    }
+
+    #[test]
+    fn bypass_paths_match_exactly_or_by_prefix() {
+        let mut config = build_test_config(vec!["*".to_string()], vec![], "test-service");
+        config.bypass_paths = vec![
+            "/healthz".to_string(),
+            "/livez/*".to_string(),
+            "/ready*".to_string(),
+        ];
+        let factory =
+            PipelineFactory::try_from(config, &Rc::new(DescriptorManager::default())).unwrap();
+
+        assert!(factory.has_bypass_paths());
+        assert!(factory.bypasses("/healthz"));
+        assert!(factory.bypasses("/healthz?verbose=1"));
+        assert!(factory.bypasses("/livez/ping"));
+        assert!(!factory.bypasses("/healthz/extra"));
+        assert!(!factory.bypasses("/livez"));
+        assert!(!factory.bypasses("/api/healthz"));
+        assert!(factory.bypasses("//healthz"));
+        assert!(factory.bypasses("/api/../healthz"));
+        assert!(factory.bypasses("/%68ealthz"));
+        assert!(!factory.bypasses("/livez/../admin"));
+        assert!(factory.bypasses("/ready"));
+        assert!(factory.bypasses("/ready/db"));
+        assert!(!factory.bypasses("/readyz-admin"));
+    }
+
+    #[test]
+    fn wildcard_match_falls_back_to_broader_action_sets() {
+        let request = || {
+            let mock_host = MockWasmHost::new()
+                .with_property("request.host".into(), "api.example.com".as_bytes().to_vec())
+                .with_property("request.method".into(), "POST".as_bytes().to_vec());
+            ReqRespCtx::new(Arc::new(mock_host))
+        };
+
+        let factory = PipelineFactory::try_from(
+            build_overlapping_config(false),
+            &Rc::new(DescriptorManager::default()),
+        )
+        .unwrap();
+        assert!(factory.build(request()).unwrap().is_none());
+
+        let factory = PipelineFactory::try_from(
+            build_overlapping_config(true),
+            &Rc::new(DescriptorManager::default()),
+        )
+        .unwrap();
+        assert!(factory.build(request()).unwrap().is_some());
+    }
+
+    fn computed(name: &str, expression: &str) -> ComputedProperty {
+        ComputedProperty {
+            name: name.to_string(),
+            expression: expression.to_string(),
+        }
+    }
+
+    #[test]
+    fn computed_properties_resolve_through_a_chain() {
+        let compiled = compile_computed_properties(&[
+            computed("derived.needs_auth", "derived.is_write && true"),
+            computed("derived.is_write", "derived.method == 'POST'"),
+            computed("derived.method", "request.method"),
+        ])
+        .unwrap();
+
+        let mock_host =
+            MockWasmHost::new().with_property("request.method".into(), b"POST".to_vec());
+        let ctx = ReqRespCtx::new(Arc::new(mock_host)).with_computed_properties(Arc::new(compiled));
+
+        let expression = Expression::new("derived.needs_auth").unwrap();
+        let mut cel_ctx = cel::Context::default();
+        assert_eq!(
+            expression.eval(&ctx, &mut cel_ctx).unwrap(),
+            AttributeState::Available(cel::Value::Bool(true))
+        );
+    }
+
+    #[test]
+    fn computed_properties_reject_cycles() {
+        let result = compile_computed_properties(&[
+            computed("derived.a", "derived.c"),
+            computed("derived.b", "derived.a"),
+            computed("derived.c", "derived.b"),
+        ]);
+
+        assert!(matches!(
+            result,
+            Err(CompileError::CyclicProperty { ref cycle })
+                if *cycle == ["derived.a", "derived.c", "derived.b", "derived.a"]
+        ));
+    }
 }
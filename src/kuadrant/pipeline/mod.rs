@@ -4,4 +4,4 @@ mod factory;
 mod tasks;
 
 pub(crate) use executor::{Pipeline, PipelineState};
-pub(crate) use factory::PipelineFactory;
+pub(crate) use factory::{IndexStats, PipelineFactory};
@@ -0,0 +1,97 @@
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use crate::kuadrant::pipeline::tasks::{Task, TaskOutcome};
+use crate::kuadrant::ReqRespCtx;
+use crate::metrics::{CallOutcome, CallService, MetricsCollector};
+
+/// Records how the gRPC call of the wrapped task ended, and how long it took,
+/// against the action set the task belongs to. The outcome alone also goes to
+/// the access log of the request, when enabled.
+pub struct CallMetricsTask {
+    task: Box<dyn Task>,
+    metrics: Option<Rc<MetricsCollector>>,
+    action_set: String,
+    service: CallService,
+    dispatched_at: Option<SystemTime>,
+}
+
+impl CallMetricsTask {
+    pub fn new(
+        task: Box<dyn Task>,
+        metrics: Option<Rc<MetricsCollector>>,
+        action_set: String,
+        service: CallService,
+    ) -> Self {
+        Self {
+            task,
+            metrics,
+            action_set,
+            service,
+            dispatched_at: None,
+        }
+    }
+}
+
+impl Task for CallMetricsTask {
+    fn apply(self: Box<Self>, ctx: &mut ReqRespCtx) -> TaskOutcome {
+        let CallMetricsTask {
+            task,
+            metrics,
+            action_set,
+            service,
+            dispatched_at,
+        } = *self;
+        let wrap = |task: Box<dyn Task>, dispatched_at: Option<SystemTime>| -> Box<dyn Task> {
+            Box::new(CallMetricsTask {
+                task,
+                metrics: metrics.clone(),
+                action_set: action_set.clone(),
+                service,
+                dispatched_at,
+            })
+        };
+
+        let now = ctx.current_time();
+        match (dispatched_at, task.apply(ctx)) {
+            (dispatched_at, TaskOutcome::Deferred { token_id, pending }) => TaskOutcome::Deferred {
+                token_id,
+                // A retry dispatches the call again, keep timing from the first one
+                pending: wrap(pending, dispatched_at.or(Some(now))),
+            },
+            (dispatched_at, TaskOutcome::Requeued(tasks)) => TaskOutcome::Requeued(
+                tasks
+                    .into_iter()
+                    .map(|task| wrap(task, dispatched_at))
+                    .collect(),
+            ),
+            // Nothing was dispatched, so there is no call to record
+            (None, outcome) => outcome,
+            (Some(dispatched_at), outcome) => {
+                let call_outcome = match &outcome {
+                    TaskOutcome::Failed => CallOutcome::Error,
+                    TaskOutcome::Terminate(_) => CallOutcome::Rejected,
+                    _ => CallOutcome::Ok,
+                };
+                ctx.record_call(service, call_outcome);
+                if let Some(metrics) = &metrics {
+                    let duration = now.duration_since(dispatched_at).unwrap_or_default();
+                    metrics.record_call(&action_set, service, call_outcome, duration);
+                }
+                outcome
+            }
+        }
+    }
+
+    fn id(&self) -> Option<String> {
+        self.task.id()
+    }
+
+    fn dependencies(&self) -> &[String] {
+        self.task.dependencies()
+    }
+
+    fn is_guard(&self) -> bool {
+        self.task.is_guard()
+    }
+}
@@ -1,18 +1,22 @@
 use std::rc::Rc;
+use std::time::Duration;
 
 use cel::Value;
-use tracing::{debug, error};
 
 use crate::data::attribute::AttributeState;
 use crate::data::cel::{Predicate, PredicateVec};
+use crate::data::grpc::GrpcErrResponse;
 use crate::data::Expression;
 use crate::kuadrant::pipeline::blueprint::{Action, Operation};
 use crate::kuadrant::pipeline::tasks::{
-    HeaderOperation, ModifyHeadersTask, PendingTask, SendReplyTask, StoreTask, Task, TaskOutcome,
+    HeaderOperation, HeadersType, ModifyHeadersTask, PendingTask, SendReplyTask, StoreTask, Task,
+    TaskOutcome,
 };
 use crate::kuadrant::ReqRespCtx;
-use crate::record_error;
-use crate::services::{cel_value_to_header_pairs, DynamicService};
+use crate::services::{
+    cel_value_to_header_names, cel_value_to_header_pairs, DynamicService, ServiceError,
+};
+use crate::{flog_debug, flog_error, flog_warn, record_error};
 
 pub struct DynamicTask {
     task_id: String,
@@ -23,6 +27,21 @@ pub struct DynamicTask {
     predicates: Vec<Predicate>,
     dependencies: Vec<String>,
     is_guard: bool,
+    timeout: Option<Duration>,
+}
+
+/// What the response to a dispatched call is handled with
+struct DispatchedCall {
+    task_id: String,
+    service: Rc<DynamicService>,
+    name: String,
+    on_reply: Vec<Action>,
+    is_guard: bool,
+    timeout: Option<Duration>,
+    cache_key: Option<Vec<u8>>,
+    /// The request, kept only when the service retries calls
+    message: Option<Vec<u8>>,
+    attempt: u32,
 }
 
 impl DynamicTask {
@@ -60,13 +79,14 @@ impl DynamicTask {
                     Operation::Deny { deny_with } => {
                         let _ = deny_with.eval(ctx, &mut cel_ctx);
                     }
-                    Operation::Headers { headers, .. } => {
+                    Operation::Headers { headers, .. }
+                    | Operation::RemoveHeaders { headers, .. } => {
                         let _ = headers.eval(ctx, &mut cel_ctx);
                     }
                     Operation::Store { expression, .. } => {
                         let _ = expression.eval(ctx, &mut cel_ctx);
                     }
-                    Operation::Fail { .. } => {}
+                    Operation::Fail { .. } | Operation::RequestHeaderMutation { .. } => {}
                 }
             }
         }
@@ -80,8 +100,15 @@ impl DynamicTask {
             predicates,
             dependencies,
             is_guard,
+            timeout: None,
         }
     }
+
+    /// Overrides the timeout of the service for this call
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
 }
 
 impl Task for DynamicTask {
@@ -109,12 +136,12 @@ impl Task for DynamicTask {
             Ok(AttributeState::Available(false)) => return TaskOutcome::Done,
             Ok(AttributeState::Available(true)) => {}
             Err(e) => {
-                error!("Failed to apply predicates: {e:?}");
+                flog_error!(ctx.log(), "Failed to apply predicates: {e:?}");
                 return TaskOutcome::Failed;
             }
         }
 
-        let token_id = {
+        let (token_id, cache_key, message) = {
             let _span =
                 tracing::debug_span!("dynamic_request", task_id = self.task_id, name = self.name)
                     .entered();
@@ -122,7 +149,7 @@ impl Task for DynamicTask {
             let env = match self.service.cel_env() {
                 Ok(env) => env,
                 Err(e) => {
-                    error!("Failed to get CEL environment: {e}");
+                    flog_error!(ctx.log(), "Failed to get CEL environment: {e}");
                     return TaskOutcome::Failed;
                 }
             };
@@ -138,26 +165,86 @@ impl Task for DynamicTask {
                 }
                 Ok(AttributeState::Available(val)) => val,
                 Err(e) => {
-                    error!("Failed to evaluate message builder: {e}");
+                    flog_error!(ctx.log(), "Failed to evaluate message builder: {e}");
+                    return TaskOutcome::Failed;
+                }
+            };
+
+            let message = match self.service.encode_value(&cel_value) {
+                Ok(message) => message,
+                Err(e) => {
+                    flog_error!(ctx.log(), "Failed to encode dynamic service request: {e}");
                     return TaskOutcome::Failed;
                 }
             };
+            if let Some(response) = self.service.cached_response(ctx, &message) {
+                flog_debug!(ctx.log(), "Serving {} from the response cache", self.name);
+                return apply_on_reply(ctx, &self.service, &self.name, &self.on_reply, response);
+            }
+            let cache_key = self.service.caches_responses().then(|| message.clone());
+            let retained = self.service.retry_base_delay().map(|_| message.clone());
 
-            match self.service.dispatch_value(ctx, &cel_value) {
-                Ok(id) => id,
+            match self.service.dispatch_message(ctx, message, self.timeout) {
+                Ok(id) => (id, cache_key, retained),
+                Err(ServiceError::DeadlineExceeded) => {
+                    flog_error!(
+                        ctx.log(),
+                        "Request deadline exceeded before dispatching {}",
+                        self.name
+                    );
+                    return TaskOutcome::Terminate(Box::new(SendReplyTask::from(
+                        GrpcErrResponse::from_http_status(504),
+                    )));
+                }
+                Err(ServiceError::CircuitOpen) => {
+                    flog_debug!(ctx.log(), "Circuit open, skipping {}", self.name);
+                    return TaskOutcome::Done;
+                }
+                Err(ServiceError::Unhealthy) => {
+                    flog_error!(
+                        ctx.log(),
+                        "{} failed its health check, not calling it",
+                        self.name
+                    );
+                    return TaskOutcome::Failed;
+                }
                 Err(e) => {
-                    error!("Failed to dispatch dynamic service: {e}");
+                    flog_error!(ctx.log(), "Failed to dispatch dynamic service: {e}");
                     return TaskOutcome::Failed;
                 }
             }
         };
 
-        let service = self.service.clone();
+        let DynamicTask {
+            task_id,
+            service,
+            name,
+            on_reply,
+            is_guard,
+            timeout,
+            ..
+        } = *self;
+        DispatchedCall {
+            task_id,
+            service,
+            name,
+            on_reply,
+            is_guard,
+            timeout,
+            cache_key,
+            message,
+            attempt: 1,
+        }
+        .deferred(ctx, token_id)
+    }
+}
+
+impl DispatchedCall {
+    /// Awaits the response to `token_id`, holding back the request meanwhile
+    /// if the call guards it
+    fn deferred(self, ctx: &mut ReqRespCtx, token_id: u32) -> TaskOutcome {
         let task_id = self.task_id.clone();
-        let name = self.name.clone();
-        let on_reply = self.on_reply.clone();
         let is_guard = self.is_guard;
-
         if is_guard {
             ctx.barrier.raise();
         }
@@ -165,11 +252,16 @@ impl Task for DynamicTask {
         TaskOutcome::Deferred {
             token_id,
             pending: Box::new(PendingTask::new(
-                self.task_id,
+                task_id,
                 Box::new(move |ctx| {
-                    let outcome = process_dynamic_response(
-                        ctx, &service, &task_id, token_id, &name, &on_reply,
-                    );
+                    let outcome = match ctx.grpc_response_status() {
+                        Some(status_code)
+                            if self.service.should_retry(status_code, self.attempt) =>
+                        {
+                            self.retry(ctx, token_id, status_code)
+                        }
+                        _ => self.process(ctx, token_id),
+                    };
                     if is_guard {
                         ctx.barrier.lower();
                     }
@@ -179,6 +271,48 @@ impl Task for DynamicTask {
             )),
         }
     }
+
+    /// Dispatches the call again once the back-off delay of this attempt has
+    /// elapsed, handling the failed response as any other if it cannot be
+    fn retry(mut self, ctx: &mut ReqRespCtx, token_id: u32, status_code: u32) -> TaskOutcome {
+        let Some(message) = self.message.clone() else {
+            return self.process(ctx, token_id);
+        };
+        let delay = self.service.retry_delay(self.attempt);
+        match self
+            .service
+            .schedule_message(ctx, message, self.timeout, delay)
+        {
+            Ok(placeholder) => {
+                flog_warn!(
+                    ctx.log(),
+                    "Retrying {} in {delay:?} after status {status_code}, attempt {}",
+                    self.name,
+                    self.attempt
+                );
+                self.service.record_outcome(ctx, false);
+                let _ = ctx.get_grpc_response_data();
+                self.attempt += 1;
+                self.deferred(ctx, placeholder)
+            }
+            Err(e) => {
+                flog_warn!(ctx.log(), "Not retrying {}: {e}", self.name);
+                self.process(ctx, token_id)
+            }
+        }
+    }
+
+    fn process(self, ctx: &mut ReqRespCtx, token_id: u32) -> TaskOutcome {
+        process_dynamic_response(
+            ctx,
+            &self.service,
+            &self.task_id,
+            token_id,
+            &self.name,
+            &self.on_reply,
+            self.cache_key,
+        )
+    }
 }
 
 fn process_dynamic_response(
@@ -188,6 +322,7 @@ fn process_dynamic_response(
     token_id: u32,
     name: &str,
     on_reply: &[Action],
+    cache_key: Option<Vec<u8>>,
 ) -> TaskOutcome {
     let span = tracing::debug_span!(
         "dynamic_response",
@@ -202,26 +337,70 @@ fn process_dynamic_response(
     let (status_code, response_size) = match ctx.get_grpc_response_data() {
         Ok(data) => data,
         Err(e) => {
-            record_error!("Failed to get gRPC response: {e:?}");
+            service.record_outcome(ctx, false);
+            record_error!(ctx.log(), "Failed to get gRPC response: {e:?}");
             return TaskOutcome::Failed;
         }
     };
     span.record("grpc_status_code", status_code);
 
     if status_code != proxy_wasm::types::Status::Ok as u32 {
-        record_error!("gRPC status code is not OK");
+        service.record_outcome(ctx, false);
+        if let Some(response) = service.status_details_response(ctx) {
+            flog_debug!(
+                ctx.log(),
+                "Denying with the reason attached to the gRPC status"
+            );
+            return TaskOutcome::Terminate(Box::new(SendReplyTask::from(response)));
+        }
+        record_error!(ctx.log(), "gRPC status code is not OK");
         return TaskOutcome::Failed;
     }
+    service.record_outcome(ctx, true);
+
+    if on_reply.is_empty() && cache_key.is_none() {
+        flog_debug!(ctx.log(), "No onReply actions, completing");
+        return TaskOutcome::Done;
+    }
+
+    let response = match ctx.get_grpc_response(response_size) {
+        Ok(response) => response,
+        Err(e) => {
+            record_error!(ctx.log(), "Failed to get gRPC response: {e:?}");
+            return TaskOutcome::Failed;
+        }
+    };
+    if let Some(cache_key) = cache_key {
+        service.cache_response(ctx, cache_key, response.clone());
+    }
 
+    apply_on_reply(ctx, service, name, on_reply, response)
+}
+
+fn apply_on_reply(
+    ctx: &mut ReqRespCtx,
+    service: &DynamicService,
+    name: &str,
+    on_reply: &[Action],
+    response: Vec<u8>,
+) -> TaskOutcome {
     if on_reply.is_empty() {
-        debug!("No onReply actions, completing");
+        flog_debug!(ctx.log(), "No onReply actions, completing");
         return TaskOutcome::Done;
     }
 
-    let mut cel_ctx = match service.response_cel_context(ctx, response_size, name) {
+    let response = match service.with_host_dynamic_metadata(ctx, response) {
+        Ok(response) => response,
+        Err(e) => {
+            record_error!(ctx.log(), "Failed to merge dynamic metadata: {e:?}");
+            return TaskOutcome::Failed;
+        }
+    };
+
+    let mut cel_ctx = match service.response_cel_context(response, name) {
         Ok(c) => c,
         Err(e) => {
-            record_error!("Failed to build response context: {e:?}");
+            record_error!(ctx.log(), "Failed to build response context: {e:?}");
             return TaskOutcome::Failed;
         }
     };
@@ -236,7 +415,7 @@ fn process_dynamic_response(
                 //todo(@adam-cattermole): if we requeue here, we lose predicates as headers/store/sendreply are not modelled with predicates
             }
             Err(e) => {
-                error!("Failed to apply predicates: {e:?}");
+                flog_error!(ctx.log(), "Failed to apply predicates: {e:?}");
                 return TaskOutcome::Failed;
             }
         }
@@ -244,7 +423,7 @@ fn process_dynamic_response(
         match &action.operation {
             Operation::Deny { deny_with } => match deny_with.eval(ctx, &mut cel_ctx) {
                 Ok(AttributeState::Pending) => {
-                    error!("Unexpected pending state in onReply deny");
+                    flog_error!(ctx.log(), "Unexpected pending state in onReply deny");
                     return TaskOutcome::Failed;
                 }
                 Ok(AttributeState::Available(val @ Value::Struct(_))) => {
@@ -256,17 +435,20 @@ fn process_dynamic_response(
                             tasks.push(Box::new(task));
                         }
                         Err(e) => {
-                            error!("Invalid DenyResponse: {e}");
+                            flog_error!(ctx.log(), "Invalid DenyResponse: {e}");
                             return TaskOutcome::Failed;
                         }
                     }
                 }
                 Ok(AttributeState::Available(other)) => {
-                    error!("denyWith must return DenyResponse, got: {other:?}");
+                    flog_error!(
+                        ctx.log(),
+                        "denyWith must return DenyResponse, got: {other:?}"
+                    );
                     return TaskOutcome::Failed;
                 }
                 Err(e) => {
-                    error!("Failed to evaluate denyWith expression: {e}");
+                    flog_error!(ctx.log(), "Failed to evaluate denyWith expression: {e}");
                     return TaskOutcome::Failed;
                 }
             },
@@ -281,11 +463,33 @@ fn process_dynamic_response(
                     }
                 }
                 Ok(AttributeState::Pending) => {
-                    error!("Unexpected pending state in onReply headers");
+                    flog_error!(ctx.log(), "Unexpected pending state in onReply headers");
+                    return TaskOutcome::Failed;
+                }
+                Err(e) => {
+                    flog_error!(ctx.log(), "Failed to evaluate headers expression: {e}");
+                    return TaskOutcome::Failed;
+                }
+            },
+            Operation::RemoveHeaders { target, headers } => match headers.eval(ctx, &mut cel_ctx) {
+                Ok(AttributeState::Available(ref val)) => {
+                    let names = cel_value_to_header_names(val);
+                    if !names.is_empty() {
+                        tasks.push(Box::new(ModifyHeadersTask::new(
+                            HeaderOperation::Remove(names),
+                            target.clone(),
+                        )));
+                    }
+                }
+                Ok(AttributeState::Pending) => {
+                    flog_error!(
+                        ctx.log(),
+                        "Unexpected pending state in onReply removeHeaders"
+                    );
                     return TaskOutcome::Failed;
                 }
                 Err(e) => {
-                    error!("Failed to evaluate headers expression: {e}");
+                    flog_error!(ctx.log(), "Failed to evaluate header names expression: {e}");
                     return TaskOutcome::Failed;
                 }
             },
@@ -298,22 +502,39 @@ fn process_dynamic_response(
                     tasks.push(Box::new(StoreTask::new(path.clone(), val, *export_to_host)));
                 }
                 Ok(AttributeState::Pending) => {
-                    error!("Unexpected pending state in onReply store for '{path}'");
+                    flog_error!(
+                        ctx.log(),
+                        "Unexpected pending state in onReply store for '{path}'"
+                    );
                     return TaskOutcome::Failed;
                 }
                 Err(e) => {
-                    error!("Failed to evaluate store expression for '{path}': {e}");
+                    flog_error!(
+                        ctx.log(),
+                        "Failed to evaluate store expression for '{path}': {e}"
+                    );
                     return TaskOutcome::Failed;
                 }
             },
             Operation::Fail { log_message } => {
-                error!("Action failure: {log_message}");
+                flog_error!(ctx.log(), "Action failure: {log_message}");
                 return TaskOutcome::Failed;
             }
+            Operation::RequestHeaderMutation {
+                set,
+                append,
+                remove,
+            } => {
+                tasks.push(Box::new(ModifyHeadersTask::new(
+                    Operation::request_header_mutation(set, append, remove),
+                    HeadersType::HttpRequestHeaders,
+                )));
+            }
             Operation::Grpc {
                 service,
                 var,
                 message_builder,
+                timeout,
                 on_reply: nested_on_reply,
             } => match service {
                 crate::services::ServiceInstance::Dynamic(dynamic_service)
@@ -321,24 +542,30 @@ fn process_dynamic_response(
                 | crate::services::ServiceInstance::RateLimit(dynamic_service)
                 | crate::services::ServiceInstance::RateLimitCheck(dynamic_service)
                 | crate::services::ServiceInstance::RateLimitReport(dynamic_service) => {
-                    let task = Box::new(DynamicTask::new_with_attributes(
-                        ctx,
-                        action.id.clone(),
-                        Rc::clone(dynamic_service),
-                        var.clone(),
-                        message_builder.clone(),
-                        nested_on_reply.clone(),
-                        vec![action.predicate.clone()],
-                        action.dependencies.clone(),
-                        action.is_guard,
-                    ));
+                    let task = Box::new(
+                        DynamicTask::new_with_attributes(
+                            ctx,
+                            action.id.clone(),
+                            Rc::clone(dynamic_service),
+                            var.clone(),
+                            message_builder.clone(),
+                            nested_on_reply.clone(),
+                            vec![action.predicate.clone()],
+                            action.dependencies.clone(),
+                            action.is_guard,
+                        )
+                        .with_timeout(*timeout),
+                    );
                     if action.terminal {
                         return TaskOutcome::Terminate(task);
                     }
                     tasks.push(task);
                 }
                 _ => {
-                    error!("Unsupported service type for nested gRPC operation");
+                    flog_error!(
+                        ctx.log(),
+                        "Unsupported service type for nested gRPC operation"
+                    );
                     return TaskOutcome::Failed;
                 }
             },
@@ -351,3 +578,198 @@ fn process_dynamic_response(
         TaskOutcome::Requeued(tasks)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{FailureMode, RetryPolicy, Timeout};
+    use crate::data::attribute::Path;
+    use crate::data::cel::Predicate;
+    use crate::data::Headers;
+    use crate::filter::{DescriptorManager, RetryQueue};
+    use crate::kuadrant::{MockWasmHost, Pipeline, PipelineState};
+    use prost::Message;
+    use prost_types::value::Kind;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    const UNAVAILABLE: u32 = 14;
+
+    fn task(ctx: &ReqRespCtx) -> Box<DynamicTask> {
+        let service = DynamicService::new(
+            "limitador-cluster".to_string(),
+            "envoy.service.ratelimit.v3.RateLimitService".to_string(),
+            "ShouldRateLimit".to_string(),
+            Duration::from_millis(100),
+            FailureMode::Deny,
+            Rc::new(DescriptorManager::default()),
+        )
+        .with_retry_policy(Some(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Timeout(Duration::from_millis(10)),
+            multiplier: 2.0,
+        }));
+        Box::new(DynamicTask::new_with_attributes(
+            ctx,
+            "0".to_string(),
+            Rc::new(service),
+            "ratelimit".to_string(),
+            Expression::new("envoy.service.ratelimit.v3.RateLimitRequest { domain: 'toystore' }")
+                .expect("valid expression"),
+            vec![],
+            vec![],
+            vec![],
+            true,
+        ))
+    }
+
+    #[test]
+    fn guards_reading_the_request_trailers_run_once_they_arrive() {
+        let mock_host = Arc::new(MockWasmHost::new());
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        ctx.set_current_request_body_buffer_size(0, false);
+        let service = DynamicService::new(
+            "limitador-cluster".to_string(),
+            "envoy.service.ratelimit.v3.RateLimitService".to_string(),
+            "ShouldRateLimit".to_string(),
+            Duration::from_millis(100),
+            FailureMode::Deny,
+            Rc::new(DescriptorManager::default()),
+        );
+        let guard = DynamicTask::new_with_attributes(
+            &ctx,
+            "0".to_string(),
+            Rc::new(service),
+            "ratelimit".to_string(),
+            Expression::new("envoy.service.ratelimit.v3.RateLimitRequest { domain: 'toystore' }")
+                .expect("valid expression"),
+            vec![],
+            vec![Predicate::new("request.trailers['x-tenant'] == 'acme'").expect("valid predicate")],
+            vec![],
+            true,
+        );
+        let pipeline = Pipeline::new(ctx).with_tasks(vec![Box::new(guard)]);
+
+        let PipelineState::InProgress(mut pipeline) = pipeline.eval() else {
+            unreachable!("expected the guard to wait for the trailers");
+        };
+        assert_eq!(mock_host.dispatched_calls(), 0);
+        assert!(!pipeline.requires_pause());
+
+        let trailers: Headers = vec![("x-tenant".to_string(), "acme".to_string())].into();
+        pipeline
+            .ctx
+            .set_request_trailers(trailers)
+            .expect("trailers set once");
+        let PipelineState::InProgress(pipeline) = pipeline.eval() else {
+            unreachable!("expected the guard to await its call");
+        };
+        assert_eq!(mock_host.dispatched_calls(), 1);
+        assert!(pipeline.requires_pause());
+    }
+
+    #[test]
+    fn retries_unavailable_calls_after_a_growing_delay() {
+        let start = SystemTime::UNIX_EPOCH;
+        let mock_host = Arc::new(MockWasmHost::new().with_current_time(start));
+        let queue = Rc::new(RetryQueue::default());
+        let mut ctx = ReqRespCtx::new(mock_host.clone()).with_retry_queue(2, Rc::clone(&queue));
+
+        let TaskOutcome::Deferred { token_id, pending } = task(&ctx).apply(&mut ctx) else {
+            unreachable!("expected the call to be deferred");
+        };
+        assert_eq!(token_id, 42);
+        assert_eq!(mock_host.dispatched_calls(), 1);
+
+        ctx.set_grpc_response_data(UNAVAILABLE, 0)
+            .expect("response data set once");
+        let TaskOutcome::Deferred { token_id, pending } = pending.apply(&mut ctx) else {
+            unreachable!("expected the call to be retried");
+        };
+        assert_eq!(token_id, u32::MAX);
+        assert_eq!(ctx.barrier.count(), 1);
+        assert_eq!(mock_host.dispatched_calls(), 1);
+        let first = queue.take_due(start + Duration::from_millis(10));
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].0, u32::MAX);
+        assert_eq!(first[0].1.context_id, 2);
+        assert!(!first[0].1.request.message().is_empty());
+
+        ctx.set_grpc_response_data(UNAVAILABLE, 0)
+            .expect("response data set once");
+        let TaskOutcome::Deferred { token_id, pending } = pending.apply(&mut ctx) else {
+            unreachable!("expected the call to be retried");
+        };
+        assert_eq!(token_id, u32::MAX - 1);
+        assert_eq!(queue.next_due(), Some(start + Duration::from_millis(20)));
+        let second = queue.take_due(start + Duration::from_millis(20));
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].1.request.message(), first[0].1.request.message());
+
+        ctx.set_grpc_response_data(UNAVAILABLE, 0)
+            .expect("response data set once");
+        assert!(matches!(pending.apply(&mut ctx), TaskOutcome::Failed));
+        assert_eq!(ctx.barrier.count(), 0);
+        assert_eq!(queue.next_due(), None);
+    }
+
+    #[test]
+    fn stores_the_host_dynamic_metadata_of_replies_carrying_none() {
+        let metadata = prost_types::Struct {
+            fields: [(
+                "user".to_string(),
+                prost_types::Value {
+                    kind: Some(Kind::StringValue("alice".to_string())),
+                },
+            )]
+            .into(),
+        };
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new().with_property(
+            Path::from_parts([
+                "metadata",
+                "filter_metadata",
+                "envoy.filters.http.ext_authz",
+            ]),
+            metadata.encode_to_vec(),
+        )));
+        let service = DynamicService::new(
+            "authorino-cluster".to_string(),
+            "envoy.service.auth.v3.Authorization".to_string(),
+            "Check".to_string(),
+            Duration::from_millis(100),
+            FailureMode::Deny,
+            Rc::new(DescriptorManager::default()),
+        )
+        .with_dynamic_metadata_key(Some("envoy.filters.http.ext_authz".to_string()));
+        let on_reply = vec![Action {
+            id: "0".to_string(),
+            predicate: Predicate::new("has(auth_check.dynamic_metadata)").expect("valid predicate"),
+            terminal: false,
+            operation: Operation::Store {
+                path: "auth".to_string(),
+                expression: Expression::new("auth_check.dynamic_metadata")
+                    .expect("valid expression"),
+                export_to_host: false,
+            },
+            dependencies: vec![],
+            sources: vec![],
+            is_guard: true,
+        }];
+
+        // an OK CheckResponse with an ok_response, yet no dynamic_metadata
+        let response = vec![10, 0, 18, 0];
+        let TaskOutcome::Requeued(tasks) =
+            apply_on_reply(&mut ctx, &service, "auth_check", &on_reply, response)
+        else {
+            unreachable!("expected the metadata to be stored");
+        };
+        for task in tasks {
+            assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        }
+        assert_eq!(
+            ctx.get_stored_value("auth"),
+            Some(&Value::from(HashMap::from([("user", "alice")])))
+        );
+    }
+}
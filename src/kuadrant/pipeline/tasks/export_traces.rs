@@ -1,8 +1,8 @@
 use crate::kuadrant::pipeline::tasks::{TeardownAction, TeardownOutcome};
 use crate::kuadrant::ReqRespCtx;
 use crate::services::TracingService;
+use crate::{flog_debug, flog_warn};
 use std::rc::Rc;
-use tracing::{debug, warn};
 
 pub struct ExportTracesTask {
     service: Rc<TracingService>,
@@ -24,21 +24,25 @@ impl TeardownAction for ExportTracesTask {
         let spans = processor.take_pending_spans();
 
         if spans.is_empty() {
-            debug!("No spans to export");
+            flog_debug!(ctx.log(), "No spans to export");
             return TeardownOutcome::Done;
         }
 
-        debug!("Exporting {} spans", spans.len());
+        flog_debug!(ctx.log(), "Exporting {} spans", spans.len());
 
         let token_id = match self.service.dispatch_export(ctx, &spans) {
             Ok(id) => id,
             Err(e) => {
-                warn!("Failed to dispatch trace export: {:?}", e);
+                flog_warn!(ctx.log(), "Failed to dispatch trace export: {:?}", e);
                 return TeardownOutcome::Done;
             }
         };
 
-        debug!("Trace export dispatched with token_id: {}", token_id);
+        flog_debug!(
+            ctx.log(),
+            "Trace export dispatched with token_id: {}",
+            token_id
+        );
 
         TeardownOutcome::Deferred(token_id)
     }
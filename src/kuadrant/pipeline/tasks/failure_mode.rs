@@ -1,3 +1,4 @@
+use crate::configuration::ErrorResponse;
 use crate::kuadrant::{
     pipeline::tasks::{SendReplyTask, Task, TaskOutcome},
     ReqRespCtx,
@@ -7,11 +8,43 @@ use crate::metrics::METRICS;
 pub struct FailureModeTask {
     task: Box<dyn Task>,
     abort: bool,
+    error_response: Option<ErrorResponse>,
+    service_label: &'static str,
 }
 
 impl FailureModeTask {
     pub fn new(task: Box<dyn Task>, abort: bool) -> Self {
-        Self { task, abort }
+        Self {
+            task,
+            abort,
+            error_response: None,
+            service_label: "dynamic",
+        }
+    }
+
+    pub fn with_error_response(
+        mut self,
+        error_response: Option<ErrorResponse>,
+        service_label: &'static str,
+    ) -> Self {
+        self.error_response = error_response;
+        self.service_label = service_label;
+        self
+    }
+}
+
+fn error_reply(error_response: Option<&ErrorResponse>, service_label: &str) -> SendReplyTask {
+    match error_response {
+        Some(error_response) => SendReplyTask::new(
+            error_response.status,
+            error_response
+                .headers
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            error_response.render_body(&format!("{service_label} service failed")),
+        ),
+        None => SendReplyTask::default(),
     }
 }
 
@@ -23,7 +56,10 @@ impl Task for FailureModeTask {
                 if self.abort {
                     let span = tracing::Span::current();
                     span.record("otel.status_code", "ERROR");
-                    TaskOutcome::Terminate(Box::new(SendReplyTask::default()))
+                    TaskOutcome::Terminate(Box::new(error_reply(
+                        self.error_response.as_ref(),
+                        self.service_label,
+                    )))
                 } else {
                     TaskOutcome::Done
                 }
@@ -33,6 +69,8 @@ impl Task for FailureModeTask {
                 pending: Box::new(FailureModeTask {
                     task: pending,
                     abort: self.abort,
+                    error_response: self.error_response,
+                    service_label: self.service_label,
                 }),
             },
             outcome => outcome,
@@ -46,4 +84,12 @@ impl Task for FailureModeTask {
     fn dependencies(&self) -> &[String] {
         self.task.dependencies()
     }
+
+    fn is_guard(&self) -> bool {
+        self.task.is_guard()
+    }
+
+    fn fails_open(&self) -> bool {
+        !self.abort
+    }
 }
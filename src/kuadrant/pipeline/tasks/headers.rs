@@ -1,12 +1,15 @@
 use crate::data::attribute::{AttributeState, Path};
 use crate::data::cel::Predicate;
 use crate::data::{Expression, Headers};
-use crate::kuadrant::pipeline::tasks::{SendReplyTask, Task, TaskOutcome};
+use crate::filter::FilterLogger;
+use crate::kuadrant::pipeline::tasks::{
+    ActionInput, ActionOutput, HostOperation, SendReplyTask, Task, TaskOutcome,
+};
 use crate::kuadrant::ReqRespCtx;
-use crate::services::cel_value_to_header_pairs;
-use tracing::{debug, error};
+use crate::services::{cel_value_to_header_names, cel_value_to_header_pairs};
+use crate::{flog_debug, flog_error};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum HeadersType {
     HttpRequestHeaders,
     HttpResponseHeaders,
@@ -18,6 +21,63 @@ pub enum HeaderOperation {
     Append(Headers),
     Set(Headers),
     Remove(Vec<String>),
+    /// Sets, then appends, then removes, so a removal always wins
+    Mutate {
+        set: Headers,
+        append: Headers,
+        remove: Vec<String>,
+    },
+}
+
+impl HeaderOperation {
+    pub fn transform(
+        &self,
+        log: FilterLogger,
+        target: &HeadersType,
+        mut input: ActionInput,
+    ) -> ActionOutput {
+        let headers = input.headers_mut(target);
+        match self {
+            HeaderOperation::Append(new_headers) => {
+                flog_debug!(log, "Appending {} headers", new_headers.len());
+                headers.extend(new_headers.clone());
+            }
+            HeaderOperation::Set(new_headers) => {
+                flog_debug!(log, "Setting {} headers", new_headers.len());
+                for (key, value) in new_headers.clone().into_inner() {
+                    headers.set(key, value);
+                }
+            }
+            HeaderOperation::Remove(keys) => {
+                flog_debug!(log, "Removing {} headers", keys.len());
+                for key in keys {
+                    headers.remove(key);
+                }
+            }
+            HeaderOperation::Mutate {
+                set,
+                append,
+                remove,
+            } => {
+                flog_debug!(
+                    log,
+                    "Setting {} headers, appending {} and removing {}",
+                    set.len(),
+                    append.len(),
+                    remove.len()
+                );
+                for (key, value) in set.clone().into_inner() {
+                    headers.set(key, value);
+                }
+                headers.extend(append.clone());
+                for key in remove {
+                    headers.remove(key);
+                }
+            }
+        }
+        let operation = HostOperation::SetHeaders(target.clone(), headers.clone());
+        ActionOutput::new(input).with_operation(operation)
+    }
 }
 
 impl From<&HeadersType> for Path {
@@ -32,6 +92,7 @@ impl From<&HeadersType> for Path {
 enum HeadersMode {
     Concrete { operation: HeaderOperation },
     Deferred { headers_expr: Expression },
+    DeferredRemoval { names_expr: Expression },
 }
 
 #[derive(Clone)]
@@ -51,6 +112,9 @@ impl Clone for HeadersMode {
             HeadersMode::Deferred { headers_expr } => HeadersMode::Deferred {
                 headers_expr: headers_expr.clone(),
             },
+            HeadersMode::DeferredRemoval { names_expr } => HeadersMode::DeferredRemoval {
+                names_expr: names_expr.clone(),
+            },
         }
     }
 }
@@ -65,6 +129,20 @@ impl ModifyHeadersTask {
         }
     }
 
+    pub fn new_conditional(
+        predicate: Predicate,
+        operation: HeaderOperation,
+        target: HeadersType,
+        terminal: bool,
+    ) -> Self {
+        Self {
+            predicate: Some(predicate),
+            mode: HeadersMode::Concrete { operation },
+            target,
+            terminal,
+        }
+    }
+
     pub fn new_deferred(
         predicate: Predicate,
         headers_expr: Expression,
@@ -78,6 +156,21 @@ impl ModifyHeadersTask {
             terminal,
         }
     }
+
+    /// Removes the headers named by the list of strings `names_expr` evaluates to
+    pub fn new_deferred_removal(
+        predicate: Predicate,
+        names_expr: Expression,
+        target: HeadersType,
+        terminal: bool,
+    ) -> Self {
+        Self {
+            predicate: Some(predicate),
+            mode: HeadersMode::DeferredRemoval { names_expr },
+            target,
+            terminal,
+        }
+    }
 }
 
 impl Task for ModifyHeadersTask {
@@ -90,7 +183,7 @@ impl Task for ModifyHeadersTask {
                     return TaskOutcome::Requeued(vec![self]);
                 }
                 Err(e) => {
-                    error!("Failed to evaluate predicate: {e:?}");
+                    flog_error!(ctx.log(), "Failed to evaluate predicate: {e:?}");
                     return TaskOutcome::Failed;
                 }
             }
@@ -102,7 +195,7 @@ impl Task for ModifyHeadersTask {
                 let mut cel_ctx = cel::Context::default();
                 match headers_expr.eval(ctx, &mut cel_ctx) {
                     Ok(AttributeState::Pending) => {
-                        error!("Unexpected pending state in headers expression");
+                        flog_error!(ctx.log(), "Unexpected pending state in headers expression");
                         return TaskOutcome::Failed;
                     }
                     Ok(AttributeState::Available(ref val)) => {
@@ -113,36 +206,45 @@ impl Task for ModifyHeadersTask {
                         HeaderOperation::Set(pairs.into())
                     }
                     Err(e) => {
-                        error!("Failed to evaluate headers expression: {e}");
+                        flog_error!(ctx.log(), "Failed to evaluate headers expression: {e}");
                         return TaskOutcome::Failed;
                     }
                 }
             }
-        };
-
-        let path: Path = (&self.target).into();
-        let result: Result<AttributeState<Option<Headers>>, _> = ctx.get_attribute_ref(&path);
-        match result {
-            Ok(AttributeState::Available(Some(mut existing_headers))) => {
-                match &operation {
-                    HeaderOperation::Append(headers) => {
-                        debug!("Appending {} headers", headers.len());
-                        existing_headers.extend(headers.clone());
+            HeadersMode::DeferredRemoval { names_expr } => {
+                let mut cel_ctx = cel::Context::default();
+                match names_expr.eval(ctx, &mut cel_ctx) {
+                    Ok(AttributeState::Pending) => {
+                        flog_error!(
+                            ctx.log(),
+                            "Unexpected pending state in header names expression"
+                        );
+                        return TaskOutcome::Failed;
                     }
-                    HeaderOperation::Set(headers) => {
-                        debug!("Setting {} headers", headers.len());
-                        for (key, value) in headers.clone().into_inner() {
-                            existing_headers.set(key, value);
+                    Ok(AttributeState::Available(ref val)) => {
+                        let names = cel_value_to_header_names(val);
+                        if names.is_empty() {
+                            return TaskOutcome::Done;
                         }
+                        HeaderOperation::Remove(names)
                     }
-                    HeaderOperation::Remove(keys) => {
-                        debug!("Removing {} headers", keys.len());
-                        for key in keys {
-                            existing_headers.remove(key);
-                        }
+                    Err(e) => {
+                        flog_error!(ctx.log(), "Failed to evaluate header names expression: {e}");
+                        return TaskOutcome::Failed;
                     }
                 }
-                match ctx.set_attribute_map(&path, existing_headers) {
+            }
+        };
+
+        let path: Path = (&self.target).into();
+        let result: Result<AttributeState<Option<Headers>>, _> = ctx.get_attribute_ref(&path);
+        match result {
+            Ok(AttributeState::Available(Some(existing_headers))) => {
+                let input = ActionInput::with_headers(&self.target, existing_headers);
+                match operation
+                    .transform(ctx.log(), &self.target, input)
+                    .commit(ctx)
+                {
                     Ok(AttributeState::Available(_)) => {
                         if self.terminal {
                             TaskOutcome::Terminate(Box::new(SendReplyTask::default()))
@@ -152,20 +254,21 @@ impl Task for ModifyHeadersTask {
                     }
                     Ok(AttributeState::Pending) => TaskOutcome::Requeued(vec![self]),
                     Err(e) => {
-                        error!("Failed to set attribute map: {e:?}");
+                        flog_error!(ctx.log(), "Failed to set attribute map: {e:?}");
                         TaskOutcome::Failed
                     }
                 }
             }
             Ok(AttributeState::Available(None)) => {
-                error!(
+                flog_error!(
+                    ctx.log(),
                     "Unexpected state: getting headers returned AttributeState::Available(None)"
                 );
                 TaskOutcome::Failed
             }
             Ok(AttributeState::Pending) => TaskOutcome::Requeued(vec![self]),
             Err(e) => {
-                error!("Failed to get attribute reference: {e:?}");
+                flog_error!(ctx.log(), "Failed to get attribute reference: {e:?}");
                 TaskOutcome::Failed
             }
         }
@@ -240,6 +343,110 @@ mod tests {
         }
     }
 
+    #[test]
+    fn transform_append_keeps_existing_headers() {
+        let input = ActionInput::with_headers(
+            &HeadersType::HttpRequestHeaders,
+            vec![("x-a".to_string(), "1".to_string())].into(),
+        );
+        let operation = HeaderOperation::Append(vec![("x-a".to_string(), "2".to_string())].into());
+
+        let output = operation.transform(
+            FilterLogger::new(1),
+            &HeadersType::HttpRequestHeaders,
+            input,
+        );
+
+        let expected: Headers = vec![
+            ("x-a".to_string(), "1".to_string()),
+            ("x-a".to_string(), "2".to_string()),
+        ]
+        .into();
+        assert_eq!(output.next_input.request_headers, expected);
+        assert!(output.next_input.response_headers.is_empty());
+        assert_eq!(
+            output.operations,
+            vec![HostOperation::SetHeaders(
+                HeadersType::HttpRequestHeaders,
+                expected
+            )]
+        );
+    }
+
+    #[test]
+    fn transform_set_replaces_header() {
+        let input = ActionInput::with_headers(
+            &HeadersType::HttpResponseHeaders,
+            vec![
+                ("x-a".to_string(), "1".to_string()),
+                ("x-b".to_string(), "1".to_string()),
+            ]
+            .into(),
+        );
+        let operation = HeaderOperation::Set(vec![("x-a".to_string(), "2".to_string())].into());
+
+        let output = operation.transform(
+            FilterLogger::new(1),
+            &HeadersType::HttpResponseHeaders,
+            input,
+        );
+
+        assert_eq!(output.next_input.response_headers.get("x-a"), Some("2"));
+        assert_eq!(output.next_input.response_headers.get("x-b"), Some("1"));
+        assert_eq!(output.operations.len(), 1);
+    }
+
+    #[test]
+    fn transform_remove_drops_header() {
+        let input = ActionInput::with_headers(
+            &HeadersType::HttpResponseHeaders,
+            vec![
+                ("x-a".to_string(), "1".to_string()),
+                ("x-b".to_string(), "1".to_string()),
+            ]
+            .into(),
+        );
+        let operation = HeaderOperation::Remove(vec!["x-a".to_string()]);
+
+        let output = operation.transform(
+            FilterLogger::new(1),
+            &HeadersType::HttpResponseHeaders,
+            input,
+        );
+
+        assert_eq!(output.next_input.response_headers.len(), 1);
+        assert_eq!(output.next_input.response_headers.get("x-a"), None);
+    }
+
+    #[test]
+    fn transform_mutate_remove_wins_over_set() {
+        let input = ActionInput::with_headers(
+            &HeadersType::HttpRequestHeaders,
+            vec![("x-user-id".to_string(), "spoofed".to_string())].into(),
+        );
+        let operation = HeaderOperation::Mutate {
+            set: vec![
+                ("x-user-id".to_string(), "alice".to_string()),
+                ("x-tenant".to_string(), "acme".to_string()),
+            ]
+            .into(),
+            append: vec![("x-trace".to_string(), "1".to_string())].into(),
+            remove: vec!["x-user-id".to_string()],
+        };
+
+        let output = operation.transform(
+            FilterLogger::new(1),
+            &HeadersType::HttpRequestHeaders,
+            input,
+        );
+
+        let headers = &output.next_input.request_headers;
+        assert_eq!(headers.get("x-user-id"), None);
+        assert_eq!(headers.get("x-tenant"), Some("acme"));
+        assert_eq!(headers.get("x-trace"), Some("1"));
+        assert_eq!(output.operations.len(), 1);
+    }
+
     #[test]
     fn remove_headers_task() {
         let existing_headers = vec![
@@ -270,4 +477,32 @@ mod tests {
             assert_eq!(headers.get("X-Origin"), Some("Kuadrant"));
         }
     }
+
+    #[test]
+    fn deferred_removal_leaves_unlisted_headers() {
+        let existing_headers = vec![
+            ("x-internal-token".to_string(), "secret".to_string()),
+            ("x-backend-version".to_string(), "2".to_string()),
+            ("content-type".to_string(), "text/plain".to_string()),
+        ];
+        let mock_host =
+            MockWasmHost::new().with_map("response.headers".to_string(), existing_headers);
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+
+        let task = Box::new(ModifyHeadersTask::new_deferred_removal(
+            Predicate::new("true").unwrap(),
+            Expression::new("['x-internal-token', 'x-backend-version', 'x-absent']").unwrap(),
+            HeadersType::HttpResponseHeaders,
+            false,
+        ));
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+
+        let result: Result<AttributeState<Option<Headers>>, _> =
+            ctx.get_attribute_ref(&Path::from(&HeadersType::HttpResponseHeaders));
+        let Ok(AttributeState::Available(Some(headers))) = result else {
+            unreachable!("expected the response headers");
+        };
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers.get("content-type"), Some("text/plain"));
+    }
 }
@@ -0,0 +1,249 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::data::attribute::AttributeState;
+use crate::data::cel::Predicate;
+use crate::data::grpc::GrpcErrResponse;
+use crate::data::Expression;
+use crate::kuadrant::pipeline::tasks::{PendingTask, SendReplyTask, Task, TaskOutcome};
+use crate::kuadrant::ReqRespCtx;
+use crate::services::{HttpCalloutService, ServiceError};
+use crate::{flog_debug, flog_error, record_error};
+
+/// Calls an `http-callout` service with the JSON `message_builder` evaluates
+/// to, denying the request with a 403 unless the service allows it.
+pub struct HttpCalloutTask {
+    task_id: String,
+    service: Rc<HttpCalloutService>,
+    message_builder: Expression,
+    predicate: Predicate,
+    dependencies: Vec<String>,
+    is_guard: bool,
+    timeout: Option<Duration>,
+}
+
+impl HttpCalloutTask {
+    pub fn new(
+        task_id: String,
+        service: Rc<HttpCalloutService>,
+        message_builder: Expression,
+        predicate: Predicate,
+        dependencies: Vec<String>,
+        is_guard: bool,
+    ) -> Self {
+        Self {
+            task_id,
+            service,
+            message_builder,
+            predicate,
+            dependencies,
+            is_guard,
+            timeout: None,
+        }
+    }
+
+    /// Overrides the timeout of the service for this call
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn requeue_or_fail(self: Box<Self>, ctx: &ReqRespCtx) -> TaskOutcome {
+        if ctx.is_end_of_stream() {
+            TaskOutcome::Failed
+        } else {
+            TaskOutcome::Requeued(vec![self])
+        }
+    }
+}
+
+impl Task for HttpCalloutTask {
+    fn id(&self) -> Option<String> {
+        Some(self.task_id.clone())
+    }
+
+    fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    fn is_guard(&self) -> bool {
+        self.is_guard
+    }
+
+    fn apply(self: Box<Self>, ctx: &mut ReqRespCtx) -> TaskOutcome {
+        match self.predicate.test(ctx) {
+            Ok(AttributeState::Pending) => return self.requeue_or_fail(ctx),
+            Ok(AttributeState::Available(false)) => return TaskOutcome::Done,
+            Ok(AttributeState::Available(true)) => {}
+            Err(e) => {
+                flog_error!(ctx.log(), "Failed to apply predicate: {e:?}");
+                return TaskOutcome::Failed;
+            }
+        }
+
+        let mut cel_ctx = cel::Context::default();
+        let value = match self.message_builder.eval(ctx, &mut cel_ctx) {
+            Ok(AttributeState::Pending) => return self.requeue_or_fail(ctx),
+            Ok(AttributeState::Available(value)) => value,
+            Err(e) => {
+                flog_error!(ctx.log(), "Failed to evaluate message builder: {e}");
+                return TaskOutcome::Failed;
+            }
+        };
+
+        let token_id = match self.service.dispatch_value(ctx, &value, self.timeout) {
+            Ok(token_id) => token_id,
+            Err(ServiceError::DeadlineExceeded) => {
+                flog_error!(
+                    ctx.log(),
+                    "Request deadline exceeded before dispatching HTTP callout"
+                );
+                return TaskOutcome::Terminate(Box::new(SendReplyTask::from(
+                    GrpcErrResponse::from_http_status(504),
+                )));
+            }
+            Err(e) => {
+                flog_error!(ctx.log(), "Failed to dispatch HTTP callout: {e}");
+                return TaskOutcome::Failed;
+            }
+        };
+
+        let service = Rc::clone(&self.service);
+        let is_guard = self.is_guard;
+        if is_guard {
+            ctx.barrier.raise();
+        }
+
+        TaskOutcome::Deferred {
+            token_id,
+            pending: Box::new(PendingTask::new(
+                self.task_id,
+                Box::new(move |ctx| {
+                    let outcome = process_http_callout_response(ctx, &service, token_id);
+                    if is_guard {
+                        ctx.barrier.lower();
+                    }
+                    outcome
+                }),
+                is_guard,
+            )),
+        }
+    }
+}
+
+fn process_http_callout_response(
+    ctx: &mut ReqRespCtx,
+    service: &HttpCalloutService,
+    token_id: u32,
+) -> TaskOutcome {
+    let (status_code, body_size) = match ctx.get_grpc_response_data() {
+        Ok(data) => data,
+        Err(e) => {
+            record_error!(ctx.log(), "Failed to get HTTP callout response: {e:?}");
+            return TaskOutcome::Failed;
+        }
+    };
+    if !(200..300).contains(&status_code) {
+        record_error!(
+            ctx.log(),
+            "HTTP callout {token_id} answered with status {status_code}"
+        );
+        return TaskOutcome::Failed;
+    }
+
+    let allowed = ctx
+        .get_http_call_response_body(body_size)
+        .and_then(|body| service.decide(&body));
+    match allowed {
+        Ok(true) => {
+            flog_debug!(ctx.log(), "HTTP callout {token_id} allowed the request");
+            TaskOutcome::Done
+        }
+        Ok(false) => {
+            flog_debug!(ctx.log(), "HTTP callout {token_id} denied the request");
+            TaskOutcome::Terminate(Box::new(SendReplyTask::from(
+                GrpcErrResponse::from_http_status(403),
+            )))
+        }
+        Err(e) => {
+            record_error!(ctx.log(), "Failed to read HTTP callout response: {e}");
+            TaskOutcome::Failed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::{FailureMode, HttpCalloutConfig};
+    use crate::kuadrant::MockWasmHost;
+    use std::sync::Arc;
+
+    fn task() -> Box<HttpCalloutTask> {
+        let service = HttpCalloutService::new(
+            "opa-cluster".to_string(),
+            &HttpCalloutConfig {
+                path: "/v1/data/authz".to_string(),
+                decision_path: "$.result.allow".to_string(),
+            },
+            Duration::from_millis(100),
+            FailureMode::Deny,
+        )
+        .expect("valid decision path");
+        Box::new(HttpCalloutTask::new(
+            "0".to_string(),
+            Rc::new(service),
+            Expression::new(r#"{"path": "/toys"}"#).expect("valid expression"),
+            Predicate::new("true").expect("valid predicate"),
+            vec![],
+            false,
+        ))
+    }
+
+    fn call(mock_host: Arc<MockWasmHost>, status_code: u32, body_size: usize) -> TaskOutcome {
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        let TaskOutcome::Deferred { token_id, pending } = task().apply(&mut ctx) else {
+            unreachable!("expected the call to be deferred");
+        };
+        assert_eq!(token_id, 43);
+        assert_eq!(mock_host.dispatched_calls(), 1);
+        assert_eq!(
+            mock_host.last_http_call_body(),
+            Some(br#"{"path":"/toys"}"#.to_vec())
+        );
+
+        ctx.set_grpc_response_data(status_code, body_size)
+            .expect("response data set once");
+        pending.apply(&mut ctx)
+    }
+
+    #[test]
+    fn allows_when_the_decision_is_true() {
+        let body = br#"{"result": {"allow": true}}"#;
+        let mock_host = Arc::new(MockWasmHost::new().with_http_call_response(body));
+        assert!(matches!(
+            call(mock_host, 200, body.len()),
+            TaskOutcome::Done
+        ));
+    }
+
+    #[test]
+    fn denies_with_forbidden_otherwise() {
+        let body = br#"{"result": {"allow": false}}"#;
+        let mock_host = Arc::new(MockWasmHost::new().with_http_call_response(body));
+        assert!(matches!(
+            call(mock_host, 200, body.len()),
+            TaskOutcome::Terminate(_)
+        ));
+    }
+
+    #[test]
+    fn fails_on_an_error_status() {
+        let body = br#"{"result": {"allow": true}}"#;
+        let mock_host = Arc::new(MockWasmHost::new().with_http_call_response(body));
+        assert!(matches!(
+            call(mock_host, 503, body.len()),
+            TaskOutcome::Failed
+        ));
+    }
+}
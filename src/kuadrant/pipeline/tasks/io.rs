@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use crate::data::attribute::{AttributeError, AttributeState, Path};
+use crate::data::Headers;
+use crate::kuadrant::pipeline::tasks::HeadersType;
+use crate::kuadrant::ReqRespCtx;
+
+/// The data an action reads from, snapshotted from the request context so the
+/// transformation itself can be exercised without a host.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActionInput {
+    pub request_headers: Headers,
+    pub response_headers: Headers,
+    pub properties: HashMap<Path, Vec<u8>>,
+}
+
+impl ActionInput {
+    pub fn with_headers(target: &HeadersType, headers: Headers) -> Self {
+        let mut input = Self::default();
+        *input.headers_mut(target) = headers;
+        input
+    }
+
+    #[cfg(test)]
+    pub fn headers(&self, target: &HeadersType) -> &Headers {
+        match target {
+            HeadersType::HttpRequestHeaders => &self.request_headers,
+            HeadersType::HttpResponseHeaders => &self.response_headers,
+        }
+    }
+
+    pub fn headers_mut(&mut self, target: &HeadersType) -> &mut Headers {
+        match target {
+            HeadersType::HttpRequestHeaders => &mut self.request_headers,
+            HeadersType::HttpResponseHeaders => &mut self.response_headers,
+        }
+    }
+}
+
+/// A side effect on the host produced by an action.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostOperation {
+    SetHeaders(HeadersType, Headers),
+    SetProperty(Path, Vec<u8>),
+    /// Sets a property to a list, see [`ReqRespCtx::set_property_list`]
+    SetPropertyList(Path, Vec<prost_types::Value>),
+}
+
+/// The result of applying an action to an [`ActionInput`]: the input the next
+/// action observes, and the operations required to make it so on the host.
+#[derive(Debug, PartialEq)]
+pub struct ActionOutput {
+    pub next_input: ActionInput,
+    pub operations: Vec<HostOperation>,
+}
+
+impl ActionOutput {
+    pub fn new(next_input: ActionInput) -> Self {
+        Self {
+            next_input,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn with_operation(mut self, operation: HostOperation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    /// Applies the operations to the host, in order. Stops at the first operation
+    /// the host cannot serve yet and reports `Pending`.
+    pub fn commit(&self, ctx: &ReqRespCtx) -> Result<AttributeState<()>, AttributeError> {
+        for operation in &self.operations {
+            let state = match operation {
+                HostOperation::SetHeaders(target, headers) => {
+                    ctx.set_attribute_map(&target.into(), headers.clone())?
+                }
+                HostOperation::SetProperty(path, value) => {
+                    ctx.set_attribute(&path.to_string(), value)?
+                }
+                HostOperation::SetPropertyList(path, values) => {
+                    ctx.set_property_list(&path.to_string(), values.clone())?
+                }
+            };
+            if let AttributeState::Pending = state {
+                return Ok(AttributeState::Pending);
+            }
+        }
+        Ok(AttributeState::Available(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kuadrant::MockWasmHost;
+    use std::sync::Arc;
+
+    #[test]
+    fn input_selects_headers_by_target() {
+        let headers: Headers = vec![("a".to_string(), "1".to_string())].into();
+        let input = ActionInput::with_headers(&HeadersType::HttpResponseHeaders, headers.clone());
+
+        assert_eq!(input.headers(&HeadersType::HttpResponseHeaders), &headers);
+        assert!(input.headers(&HeadersType::HttpRequestHeaders).is_empty());
+    }
+
+    #[test]
+    fn commit_applies_operations_to_host() {
+        let mock_host = MockWasmHost::new().with_map("request.headers".to_string(), vec![]);
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+
+        let headers: Headers = vec![("x-user".to_string(), "alice".to_string())].into();
+        let output = ActionOutput::new(ActionInput::default())
+            .with_operation(HostOperation::SetHeaders(
+                HeadersType::HttpRequestHeaders,
+                headers,
+            ))
+            .with_operation(HostOperation::SetProperty(
+                "auth.identity.user".into(),
+                b"alice".to_vec(),
+            ));
+
+        assert_eq!(output.commit(&ctx), Ok(AttributeState::Available(())));
+
+        let stored: Result<AttributeState<Option<Headers>>, _> =
+            ctx.get_attribute("request.headers");
+        assert!(matches!(
+            stored,
+            Ok(AttributeState::Available(Some(ref h))) if h.get("x-user") == Some("alice")
+        ));
+        let user: Result<AttributeState<Option<String>>, _> =
+            ctx.get_attribute("auth.identity.user");
+        assert!(matches!(
+            user,
+            Ok(AttributeState::Available(Some(ref u))) if u == "alice"
+        ));
+    }
+}
@@ -1,22 +1,34 @@
+mod call_metrics;
 mod dynamic;
 mod export_traces;
 mod failure_mode;
 mod headers;
+#[cfg(feature = "http-callout")]
+mod http_callout;
+mod io;
+mod quota_body;
+mod request_body;
 mod send_reply;
 mod store;
 mod token_usage;
 mod tracing_decorator;
 
+pub use call_metrics::CallMetricsTask;
 pub use dynamic::DynamicTask;
 pub use export_traces::ExportTracesTask;
 pub use failure_mode::FailureModeTask;
 pub use headers::{HeaderOperation, HeadersType, ModifyHeadersTask};
+#[cfg(feature = "http-callout")]
+pub use http_callout::HttpCalloutTask;
+pub use io::{ActionInput, ActionOutput, HostOperation};
+pub use quota_body::QuotaBodyTask;
+pub use request_body::RequestBodyTask;
 pub use send_reply::SendReplyTask;
 pub use store::StoreTask;
 pub use token_usage::TokenUsageTask;
-use tracing::debug;
 pub use tracing_decorator::TracingDecoratorTask;
 
+use crate::flog_debug;
 use crate::kuadrant::ReqRespCtx;
 
 //todo(refactor): this now has the signature of a task; should it be one?
@@ -36,6 +48,12 @@ pub trait Task {
     fn is_guard(&self) -> bool {
         false
     }
+
+    /// Whether the request goes on when the task fails, as it does for the
+    /// services whose `failureMode` is `allow`
+    fn fails_open(&self) -> bool {
+        false
+    }
 }
 
 pub struct PendingTask {
@@ -97,16 +115,20 @@ pub fn noop_response_processor(
         match ctx.get_grpc_response_data() {
             Ok((status_code, _response_size)) => {
                 if status_code != 0 {
-                    debug!(
+                    flog_debug!(
+                        ctx.log(),
                         "gRPC request failed with status {} (token_id: {})",
-                        status_code, token_id
+                        status_code,
+                        token_id
                     );
                 }
             }
             Err(e) => {
-                debug!(
+                flog_debug!(
+                    ctx.log(),
                     "Failed to get gRPC response for token_id {}: {:?}",
-                    token_id, e
+                    token_id,
+                    e
                 );
             }
         }
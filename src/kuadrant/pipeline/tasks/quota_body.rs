@@ -0,0 +1,339 @@
+use crate::data::attribute::{AttributeError, AttributeState};
+use crate::data::Headers;
+use crate::kuadrant::pipeline::tasks::{Task, TaskOutcome};
+use crate::kuadrant::ReqRespCtx;
+use crate::{flog_debug, flog_error, flog_warn};
+use serde_json::Value;
+
+const QUOTA_HEADER_PREFIX: &str = "x-ratelimit-";
+
+#[derive(Default)]
+enum Stage {
+    #[default]
+    AwaitingHeaders,
+    AwaitingBody {
+        holds_barrier: bool,
+    },
+}
+
+/// Merges the `x-ratelimit-*` response headers into a JSON response body, for
+/// clients that read their quota from the payload rather than the headers.
+/// The body is only buffered when the response carries such headers, and up
+/// to `max_size` bytes.
+pub struct QuotaBodyTask {
+    stage: Stage,
+    max_size: usize,
+}
+
+impl QuotaBodyTask {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            stage: Stage::default(),
+            max_size,
+        }
+    }
+}
+
+fn response_headers(ctx: &ReqRespCtx) -> Result<AttributeState<Headers>, AttributeError> {
+    ctx.get_attribute_or("response.headers", Headers::new())
+}
+
+fn is_quota_header(name: &str) -> bool {
+    name.to_ascii_lowercase().starts_with(QUOTA_HEADER_PREFIX)
+}
+
+impl Task for QuotaBodyTask {
+    #[tracing::instrument(name = "quota_body", skip(self, ctx))]
+    fn apply(mut self: Box<Self>, ctx: &mut ReqRespCtx) -> TaskOutcome {
+        match self.stage {
+            Stage::AwaitingHeaders => {
+                let mut headers = match response_headers(ctx) {
+                    Ok(AttributeState::Available(headers)) => headers,
+                    Ok(AttributeState::Pending) => return TaskOutcome::Requeued(vec![self]),
+                    Err(e) => {
+                        flog_error!(ctx.log(), "Failed to get response headers: {e:?}");
+                        return TaskOutcome::Failed;
+                    }
+                };
+                if !headers
+                    .get("content-type")
+                    .is_some_and(|ct| ct.starts_with("application/json"))
+                {
+                    flog_debug!(
+                        ctx.log(),
+                        "Response is not JSON, leaving its body untouched"
+                    );
+                    return TaskOutcome::Done;
+                }
+                if !headers
+                    .inner()
+                    .iter()
+                    .any(|(name, _)| is_quota_header(name))
+                {
+                    flog_debug!(
+                        ctx.log(),
+                        "No rate limit headers to inject, leaving the body untouched"
+                    );
+                    return TaskOutcome::Done;
+                }
+                if let Some(length) = headers
+                    .get("content-length")
+                    .and_then(|length| length.parse::<usize>().ok())
+                    .filter(|length| *length > self.max_size)
+                {
+                    flog_warn!(
+                        ctx.log(),
+                        "Response body of {} bytes exceeds the {} bytes limit, not buffering",
+                        length,
+                        self.max_size
+                    );
+                    return TaskOutcome::Done;
+                }
+                // The body may grow, so its length is only known once rewritten
+                if headers.get("content-length").is_some() {
+                    headers.remove("content-length");
+                    if let Err(e) = ctx.set_attribute_map(&"response.headers".into(), headers) {
+                        flog_error!(ctx.log(), "Failed to remove content-length: {e:?}");
+                        return TaskOutcome::Failed;
+                    }
+                }
+                self.stage = Stage::AwaitingBody {
+                    holds_barrier: false,
+                };
+                TaskOutcome::Requeued(vec![self])
+            }
+            Stage::AwaitingBody { holds_barrier } => {
+                let body_size = ctx.response_body_buffer_size();
+                if body_size > self.max_size {
+                    flog_warn!(
+                        ctx.log(),
+                        "Response body of {} bytes exceeds the {} bytes limit, not buffering",
+                        body_size,
+                        self.max_size
+                    );
+                    if holds_barrier {
+                        ctx.barrier.lower();
+                    }
+                    return TaskOutcome::Done;
+                }
+                if !ctx.is_end_of_stream() {
+                    if !holds_barrier {
+                        ctx.barrier.raise();
+                        self.stage = Stage::AwaitingBody {
+                            holds_barrier: true,
+                        };
+                    }
+                    return TaskOutcome::Requeued(vec![self]);
+                }
+                if holds_barrier {
+                    ctx.barrier.lower();
+                }
+                inject_quota(ctx)
+            }
+        }
+    }
+}
+
+fn inject_quota(ctx: &mut ReqRespCtx) -> TaskOutcome {
+    let quota: serde_json::Map<String, Value> = match response_headers(ctx) {
+        Ok(AttributeState::Available(headers)) => headers
+            .into_inner()
+            .into_iter()
+            .filter(|(key, _)| is_quota_header(key))
+            .map(|(key, value)| (key.to_ascii_lowercase(), Value::String(value)))
+            .collect(),
+        Ok(AttributeState::Pending) => {
+            flog_error!(
+                ctx.log(),
+                "Response headers unexpectedly pending at end of stream"
+            );
+            return TaskOutcome::Failed;
+        }
+        Err(e) => {
+            flog_error!(ctx.log(), "Failed to get response headers: {e:?}");
+            return TaskOutcome::Failed;
+        }
+    };
+    if quota.is_empty() {
+        flog_debug!(ctx.log(), "No rate limit headers to inject");
+        return TaskOutcome::Done;
+    }
+
+    let body_size = ctx.response_body_buffer_size();
+    let mut json = match ctx.get_http_response_body(0, body_size) {
+        Ok(AttributeState::Available(Some(bytes))) => {
+            match serde_json::from_slice::<Value>(&bytes) {
+                Ok(json) => json,
+                Err(e) => {
+                    flog_warn!(ctx.log(), "Response body is not valid JSON: {e}");
+                    return TaskOutcome::Done;
+                }
+            }
+        }
+        Ok(AttributeState::Available(None)) | Ok(AttributeState::Pending) => {
+            flog_debug!(ctx.log(), "No response body available");
+            return TaskOutcome::Done;
+        }
+        Err(e) => {
+            flog_error!(ctx.log(), "Failed to get response body: {e:?}");
+            return TaskOutcome::Failed;
+        }
+    };
+    let Some(object) = json.as_object_mut() else {
+        flog_debug!(
+            ctx.log(),
+            "Response body is not a JSON object, leaving it untouched"
+        );
+        return TaskOutcome::Done;
+    };
+    object.extend(quota);
+
+    let body = match serde_json::to_vec(&json) {
+        Ok(body) => body,
+        Err(e) => {
+            flog_error!(ctx.log(), "Failed to serialise response body: {e}");
+            return TaskOutcome::Failed;
+        }
+    };
+    match ctx.set_http_response_body(&body) {
+        Ok(_) => TaskOutcome::Done,
+        Err(e) => {
+            flog_error!(ctx.log(), "Failed to set response body: {e:?}");
+            TaskOutcome::Failed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kuadrant::MockWasmHost;
+    use std::sync::Arc;
+
+    fn json_response(mock_host: MockWasmHost) -> MockWasmHost {
+        mock_host.with_map(
+            "response.headers".to_string(),
+            vec![
+                ("content-type".to_string(), "application/json".to_string()),
+                ("content-length".to_string(), "14".to_string()),
+                ("x-ratelimit-limit".to_string(), "10".to_string()),
+                ("x-ratelimit-remaining".to_string(), "7".to_string()),
+            ],
+        )
+    }
+
+    #[test]
+    fn merges_quota_headers_into_json_body() {
+        let body = br#"{"items": [1]}"#;
+        let mock_host = Arc::new(json_response(MockWasmHost::new()).with_response_body(body));
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+
+        let task = Box::new(QuotaBodyTask::new(1024));
+        let TaskOutcome::Requeued(mut tasks) = task.apply(&mut ctx) else {
+            unreachable!("expected the task to wait for the body")
+        };
+        let task = tasks.remove(0);
+        let TaskOutcome::Requeued(mut tasks) = task.apply(&mut ctx) else {
+            unreachable!("expected the task to wait for the end of the body")
+        };
+        assert!(ctx.barrier.is_tripped());
+
+        ctx.set_current_response_body_buffer_size(body.len(), true);
+        let task = tasks.remove(0);
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        assert!(!ctx.barrier.is_tripped());
+
+        let merged: Value = serde_json::from_slice(&mock_host.response_body().unwrap()).unwrap();
+        assert_eq!(
+            merged,
+            serde_json::json!({
+                "items": [1],
+                "x-ratelimit-limit": "10",
+                "x-ratelimit-remaining": "7",
+            })
+        );
+        let headers = ctx
+            .get_attribute_ref::<Headers>(&"response.headers".into())
+            .unwrap();
+        assert!(matches!(
+            headers,
+            AttributeState::Available(Some(h)) if h.get("content-length").is_none()
+        ));
+    }
+
+    #[test]
+    fn does_not_buffer_responses_without_quota_headers() {
+        let mock_host = Arc::new(
+            MockWasmHost::new()
+                .with_map(
+                    "response.headers".to_string(),
+                    vec![("content-type".to_string(), "application/json".to_string())],
+                )
+                .with_response_body(br#"{"items": [1]}"#),
+        );
+        let mut ctx = ReqRespCtx::new(mock_host);
+
+        let task = Box::new(QuotaBodyTask::new(1024));
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        assert!(!ctx.barrier.is_tripped());
+    }
+
+    #[test]
+    fn stops_buffering_bodies_over_the_limit() {
+        let body = br#"{"items": [1]}"#;
+        let mock_host = Arc::new(json_response(MockWasmHost::new()).with_response_body(body));
+        let mut ctx = ReqRespCtx::new(mock_host);
+
+        let task = Box::new(QuotaBodyTask::new(8));
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+
+        // Without a content-length, the limit is only known to be exceeded once buffered
+        let mock_host = Arc::new(
+            MockWasmHost::new()
+                .with_map(
+                    "response.headers".to_string(),
+                    vec![
+                        ("content-type".to_string(), "application/json".to_string()),
+                        ("x-ratelimit-limit".to_string(), "10".to_string()),
+                    ],
+                )
+                .with_response_body(body),
+        );
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        let task = Box::new(QuotaBodyTask::new(8));
+        let TaskOutcome::Requeued(mut tasks) = task.apply(&mut ctx) else {
+            unreachable!("expected the task to wait for the body")
+        };
+        let task = tasks.remove(0);
+        let TaskOutcome::Requeued(mut tasks) = task.apply(&mut ctx) else {
+            unreachable!("expected the task to wait for the end of the body")
+        };
+        assert!(ctx.barrier.is_tripped());
+
+        ctx.set_current_response_body_buffer_size(body.len(), false);
+        let task = tasks.remove(0);
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        assert!(!ctx.barrier.is_tripped());
+        assert_eq!(mock_host.response_body(), Some(body.to_vec()));
+    }
+
+    #[test]
+    fn leaves_non_json_responses_untouched() {
+        let mock_host = Arc::new(
+            MockWasmHost::new()
+                .with_map(
+                    "response.headers".to_string(),
+                    vec![
+                        ("content-type".to_string(), "text/plain".to_string()),
+                        ("x-ratelimit-limit".to_string(), "10".to_string()),
+                    ],
+                )
+                .with_response_body(b"plain"),
+        );
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+
+        let task = Box::new(QuotaBodyTask::new(1024));
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        assert_eq!(mock_host.response_body(), Some(b"plain".to_vec()));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::data::attribute::AttributeState;
+use crate::kuadrant::pipeline::tasks::token_usage::json_to_value;
+use crate::kuadrant::pipeline::tasks::{Task, TaskOutcome};
+use crate::kuadrant::ReqRespCtx;
+use crate::{flog_debug, flog_error, flog_warn};
+use serde_json::Value;
+
+/// Holds the request upstream until its body is complete, then exposes the
+/// fields read through `requestBodyJSON` to the expressions of the pipeline.
+pub struct RequestBodyTask {
+    expected_fields: Vec<String>,
+    max_size: usize,
+    holds_barrier: bool,
+}
+
+impl RequestBodyTask {
+    pub fn new(expected_fields: Vec<String>, max_size: usize) -> Self {
+        Self {
+            expected_fields,
+            max_size,
+            holds_barrier: false,
+        }
+    }
+
+    fn release(&mut self, ctx: &mut ReqRespCtx) {
+        if self.holds_barrier {
+            ctx.barrier.lower();
+            self.holds_barrier = false;
+        }
+    }
+}
+
+impl Task for RequestBodyTask {
+    #[tracing::instrument(name = "request_body", skip(self, ctx))]
+    fn apply(mut self: Box<Self>, ctx: &mut ReqRespCtx) -> TaskOutcome {
+        let body_size = ctx.request_body_buffer_size();
+        if body_size > self.max_size {
+            flog_warn!(
+                ctx.log(),
+                "Request body of {} bytes exceeds the {} bytes limit, not buffering",
+                body_size,
+                self.max_size
+            );
+            self.release(ctx);
+            return TaskOutcome::Done;
+        }
+
+        if !ctx.is_request_end_of_stream() {
+            if !self.holds_barrier {
+                ctx.barrier.raise();
+                self.holds_barrier = true;
+            }
+            return TaskOutcome::Requeued(vec![self]);
+        }
+        self.release(ctx);
+
+        if body_size == 0 {
+            flog_debug!(ctx.log(), "Empty request body");
+            return TaskOutcome::Done;
+        }
+
+        let json = match ctx.get_http_request_body(0, body_size) {
+            Ok(AttributeState::Available(Some(bytes))) => {
+                match serde_json::from_slice::<Value>(&bytes) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        flog_warn!(ctx.log(), "Request body is not valid JSON: {e}");
+                        return TaskOutcome::Done;
+                    }
+                }
+            }
+            Ok(AttributeState::Available(None)) => {
+                flog_debug!(ctx.log(), "No buffer available");
+                return TaskOutcome::Done;
+            }
+            Ok(AttributeState::Pending) => return TaskOutcome::Requeued(vec![self]),
+            Err(e) => {
+                flog_error!(ctx.log(), "Failed to get request body: {e:?}");
+                return TaskOutcome::Failed;
+            }
+        };
+
+        for field in &self.expected_fields {
+            match json.pointer(field) {
+                Some(json_value) => match json_to_value(json_value) {
+                    Some(value) => ctx.set_request_body_value(field, value),
+                    None => flog_warn!(ctx.log(), "Unsupported json value type: {:?}", json_value),
+                },
+                None => flog_warn!(ctx.log(), "Missing json property: {}", field),
+            }
+        }
+        TaskOutcome::Done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::cel::{Predicate, PredicateResult};
+    use crate::kuadrant::MockWasmHost;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn test_pointer(body: &[u8], pointer: &str, expected: Value) -> PredicateResult {
+        let predicate =
+            Predicate::from_json_pointer(pointer, expected).expect("generated CEL is valid");
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new().with_request_body(body)));
+        ctx.set_current_request_body_buffer_size(body.len(), true);
+
+        let task = Box::new(RequestBodyTask::new(
+            predicate.expression().request_body_values().to_vec(),
+            1024,
+        ));
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        predicate.test(&ctx)
+    }
+
+    #[test]
+    fn holds_request_until_body_is_complete() {
+        let body = br#"{"user": {"id": "alice"}, "tokens": 42}"#;
+        let mock_host = MockWasmHost::new().with_request_body(body);
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+
+        let task = Box::new(RequestBodyTask::new(
+            vec!["/user/id".to_string(), "/tokens".to_string()],
+            1024,
+        ));
+        let TaskOutcome::Requeued(mut tasks) = task.apply(&mut ctx) else {
+            unreachable!("expected the task to wait for the body")
+        };
+        assert!(ctx.barrier.is_tripped());
+
+        ctx.set_current_request_body_buffer_size(body.len(), true);
+        let task = tasks.remove(0);
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        assert!(!ctx.barrier.is_tripped());
+        assert_eq!(
+            ctx.get_request_body_value("/user/id"),
+            Some(&"alice".to_string().into())
+        );
+        assert_eq!(ctx.get_request_body_value("/tokens"), Some(&42u64.into()));
+    }
+
+    #[test]
+    fn skips_bodies_over_the_limit() {
+        let mock_host = MockWasmHost::new().with_request_body(br#"{"tokens": 42}"#);
+        let mut ctx = ReqRespCtx::new(Arc::new(mock_host));
+        ctx.set_current_request_body_buffer_size(14, false);
+
+        let task = Box::new(RequestBodyTask::new(vec!["/tokens".to_string()], 8));
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        assert!(!ctx.barrier.is_tripped());
+        assert_eq!(ctx.get_request_body_value("/tokens"), None);
+    }
+
+    #[test]
+    fn json_pointer_predicates_compare_the_field() {
+        let body = br#"{"model": "gpt-4", "max_tokens": 100, "temperature": 0.5, "stream": false}"#;
+        for (pointer, expected) in [
+            ("/model", json!("gpt-4")),
+            ("/max_tokens", json!(100)),
+            ("/temperature", json!(0.5)),
+            ("/stream", json!(false)),
+        ] {
+            assert_eq!(
+                test_pointer(body, pointer, expected),
+                Ok(AttributeState::Available(true)),
+                "{pointer}"
+            );
+        }
+
+        assert_eq!(
+            test_pointer(body, "/model", json!("gpt-3.5")),
+            Ok(AttributeState::Available(false))
+        );
+        assert_eq!(
+            test_pointer(body, "/max_tokens", json!(-100)),
+            Ok(AttributeState::Available(false))
+        );
+    }
+
+    #[test]
+    fn json_pointer_predicates_follow_nested_fields_and_indices() {
+        let body =
+            br#"{"messages": [{"role": "system"}, {"role": "user", "content": "hi \"there\""}]}"#;
+        assert_eq!(
+            test_pointer(body, "/messages/1/role", json!("user")),
+            Ok(AttributeState::Available(true))
+        );
+        assert_eq!(
+            test_pointer(body, "/messages/1/content", json!("hi \"there\"")),
+            Ok(AttributeState::Available(true))
+        );
+        assert_eq!(
+            test_pointer(body, "/messages/0/role", json!("user")),
+            Ok(AttributeState::Available(false))
+        );
+    }
+
+    #[test]
+    fn json_pointer_predicates_are_false_without_the_field() {
+        let body = br#"{"model": "gpt-4"}"#;
+        assert_eq!(
+            test_pointer(body, "/missing", json!("gpt-4")),
+            Ok(AttributeState::Available(false))
+        );
+        assert_eq!(
+            test_pointer(body, "/model/0", json!("gpt-4")),
+            Ok(AttributeState::Available(false))
+        );
+        assert_eq!(
+            test_pointer(b"model=gpt-4", "/model", json!("gpt-4")),
+            Ok(AttributeState::Available(false))
+        );
+    }
+
+    #[test]
+    fn json_pointer_predicates_wait_for_the_body() {
+        let predicate =
+            Predicate::from_json_pointer("/model", json!("gpt-4")).expect("generated CEL is valid");
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        ctx.set_current_request_body_buffer_size(8, false);
+        assert_eq!(predicate.test(&ctx), Ok(AttributeState::Pending));
+    }
+}
@@ -2,15 +2,16 @@ use std::sync::Arc;
 
 use cel::common::types::{CelString, CelUInt};
 use cel::{Env, Value};
-use tracing::error;
 
 use crate::data::attribute::AttributeState;
 use crate::data::cel::Predicate;
+use crate::data::grpc::GrpcErrResponse;
 use crate::data::Expression;
 use crate::kuadrant::pipeline::tasks::{NoopTerminalTask, Task, TaskOutcome};
 use crate::kuadrant::ReqRespCtx;
 use crate::metrics::METRICS;
 use crate::services::{cel_value_to_header_pairs, deny_response_struct_def};
+use crate::{flog_error, flog_warn};
 
 pub struct SendReplyTask {
     predicate: Option<Predicate>,
@@ -22,10 +23,12 @@ impl SendReplyTask {
     pub fn new(status_code: u32, headers: Vec<(String, String)>, body: Option<String>) -> Self {
         let headers = headers
             .into_iter()
-            .map(|(h, v)| format!("['''{h}''', '''{v}''']"))
+            .map(|(h, v)| format!("[{}, {}]", cel_string(&h), cel_string(&v)))
             .collect::<Vec<String>>()
             .join(", ");
-        let body_field = body.map(|b| format!("body: '''{b}'''")).unwrap_or_default();
+        let body_field = body
+            .map(|b| format!("body: {}", cel_string(&b)))
+            .unwrap_or_default();
         let expr = format!(
             "DenyResponse {{ status: {status_code}u, headers: [{headers}], {body_field} }}"
         );
@@ -55,6 +58,31 @@ impl SendReplyTask {
     }
 }
 
+impl From<GrpcErrResponse> for SendReplyTask {
+    fn from(response: GrpcErrResponse) -> Self {
+        Self::new(response.status_code, response.headers, Some(response.body))
+    }
+}
+
+/// Quotes `value` as a CEL string literal, escaping whatever would end or alter it.
+fn cel_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 impl TryFrom<Value> for SendReplyTask {
     type Error = String;
 
@@ -96,7 +124,7 @@ impl Task for SendReplyTask {
                     return TaskOutcome::Requeued(vec![self]);
                 }
                 Err(e) => {
-                    error!("Failed to evaluate predicate: {e:?}");
+                    flog_error!(ctx.log(), "Failed to evaluate predicate: {e:?}");
                     return TaskOutcome::Failed;
                 }
             }
@@ -108,12 +136,12 @@ impl Task for SendReplyTask {
             let mut cel_ctx = cel::Context::with_env(Arc::new(env));
             match self.deny_with.eval(ctx, &mut cel_ctx) {
                 Ok(AttributeState::Pending) => {
-                    error!("Unexpected pending state in deny expression");
+                    flog_error!(ctx.log(), "Unexpected pending state in deny expression");
                     return TaskOutcome::Failed;
                 }
                 Ok(AttributeState::Available(val @ Value::Struct(_))) => {
                     let Value::Struct(deny_response) = val else {
-                        error!("Invalid DenyResponse: {val:?}");
+                        flog_error!(ctx.log(), "Invalid DenyResponse: {val:?}");
                         return TaskOutcome::Failed;
                     };
 
@@ -136,11 +164,14 @@ impl Task for SendReplyTask {
                     (status.unwrap_or(500u32), headers, body)
                 }
                 Ok(AttributeState::Available(other)) => {
-                    error!("denyWith must return DenyResponse, got: {other:?}");
+                    flog_error!(
+                        ctx.log(),
+                        "denyWith must return DenyResponse, got: {other:?}"
+                    );
                     return TaskOutcome::Failed;
                 }
                 Err(e) => {
-                    error!("Failed to evaluate denyWith expression: {e}");
+                    flog_error!(ctx.log(), "Failed to evaluate denyWith expression: {e}");
                     return TaskOutcome::Failed;
                 }
             }
@@ -155,6 +186,16 @@ impl Task for SendReplyTask {
             headers_ref.push((tracker, value));
         }
 
+        if ctx.is_dry_run() {
+            flog_warn!(
+                ctx.log(),
+                "Dry run, not replying with status {} and body {:?}",
+                status_code,
+                body
+            );
+            return TaskOutcome::Done;
+        }
+
         METRICS.denied().increment();
 
         let body_bytes = body.as_ref().map(|s| s.as_bytes());
@@ -167,7 +208,7 @@ impl Task for SendReplyTask {
                 }
             }
             Err(e) => {
-                error!("Failed to send HTTP reply: {:?}", e);
+                flog_error!(ctx.log(), "Failed to send HTTP reply: {:?}", e);
                 TaskOutcome::Failed
             }
         }
@@ -211,4 +252,15 @@ mod tests {
         let outcome = task.apply(&mut ctx);
         assert!(matches!(outcome, TaskOutcome::Done));
     }
+
+    #[test]
+    fn test_cel_string_round_trips() {
+        let value = "{\"error\": \"'''quoted''' \\d\"}\n\u{7}";
+        let literal = cel_string(value);
+        let evaluated = cel::Program::compile(&literal)
+            .unwrap()
+            .execute(&cel::Context::default())
+            .unwrap();
+        assert_eq!(evaluated, Value::from(value.to_string()));
+    }
 }
@@ -1,9 +1,10 @@
-use tracing::error;
-
-use crate::data::attribute::AttributeState;
+use crate::data::attribute::{encode_list, AttributeState, Path};
 use crate::data::cel::Predicate;
 use crate::data::Expression;
-use crate::kuadrant::pipeline::tasks::{SendReplyTask, Task, TaskOutcome};
+use crate::flog_error;
+use crate::kuadrant::pipeline::tasks::{
+    ActionInput, ActionOutput, HostOperation, SendReplyTask, Task, TaskOutcome,
+};
 use crate::kuadrant::ReqRespCtx;
 use crate::services::MessageConverter;
 use cel::Value;
@@ -47,6 +48,34 @@ impl StoreTask {
             terminal,
         }
     }
+
+    /// Only values exported to the host produce a [`HostOperation`]; the value
+    /// itself is always kept on the request context.
+    pub fn transform(&self, value: &Value, mut input: ActionInput) -> Result<ActionOutput, String> {
+        if !self.export_to_host {
+            return Ok(ActionOutput::new(input));
+        }
+        let path: Path = self.path.as_str().into();
+        // Lists are held as a `ListValue`, so that they read back as a `Vec`
+        if let Value::List(items) = value {
+            let values = items
+                .iter()
+                .map(MessageConverter::cel_to_prost_value)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to convert list for '{}': {}", self.path, e))?;
+            input.properties.insert(path.clone(), encode_list(&values));
+            return Ok(ActionOutput::new(input)
+                .with_operation(HostOperation::SetPropertyList(path, values)));
+        }
+        let bytes = MessageConverter::cel_value_to_bytes(value).map_err(|e| {
+            format!(
+                "Failed to convert value to bytes for '{}': {}",
+                self.path, e
+            )
+        })?;
+        input.properties.insert(path.clone(), bytes.clone());
+        Ok(ActionOutput::new(input).with_operation(HostOperation::SetProperty(path, bytes)))
+    }
 }
 
 impl Task for StoreTask {
@@ -60,7 +89,7 @@ impl Task for StoreTask {
                     return TaskOutcome::Requeued(vec![self]);
                 }
                 Err(e) => {
-                    error!("Failed to evaluate predicate: {e:?}");
+                    flog_error!(ctx.log(), "Failed to evaluate predicate: {e:?}");
                     return TaskOutcome::Failed;
                 }
             }
@@ -72,7 +101,8 @@ impl Task for StoreTask {
                 let mut cel_ctx = cel::Context::default();
                 match expression.eval(ctx, &mut cel_ctx) {
                     Ok(AttributeState::Pending) => {
-                        error!(
+                        flog_error!(
+                            ctx.log(),
                             "Unexpected pending state in store expression for '{}'",
                             self.path
                         );
@@ -80,7 +110,8 @@ impl Task for StoreTask {
                     }
                     Ok(AttributeState::Available(val)) => val,
                     Err(e) => {
-                        error!(
+                        flog_error!(
+                            ctx.log(),
                             "Failed to evaluate store expression for '{}': {e}",
                             self.path
                         );
@@ -90,21 +121,26 @@ impl Task for StoreTask {
             }
         };
 
-        if self.export_to_host {
-            match MessageConverter::cel_value_to_bytes(&value) {
-                Ok(bytes) => {
-                    if let Err(e) = ctx.set_attribute(&self.path, &bytes) {
-                        error!("Failed to store attribute {}: {:?}", self.path, e);
-                        return TaskOutcome::Failed;
-                    }
-                }
-                Err(e) => {
-                    error!(
-                        "Failed to convert value to bytes for '{}': {}",
-                        self.path, e
+        match self.transform(&value, ActionInput::default()) {
+            Ok(output) => {
+                if let Err(e) = output.commit(ctx) {
+                    flog_error!(
+                        ctx.log(),
+                        "Failed to store attribute {}: {:?}",
+                        self.path,
+                        e
                     );
                     return TaskOutcome::Failed;
                 }
+                // Stored values are visible to the predicates whether exported or not
+                if let Err(e) = ctx.bump_metadata_generation() {
+                    flog_error!(ctx.log(), "Failed to bump metadata generation: {e:?}");
+                    return TaskOutcome::Failed;
+                }
+            }
+            Err(e) => {
+                flog_error!(ctx.log(), "{e}");
+                return TaskOutcome::Failed;
             }
         }
         ctx.store_value(self.path.clone(), value);
@@ -116,3 +152,101 @@ impl Task for StoreTask {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::attribute::get_metadata_generation;
+    use crate::kuadrant::MockWasmHost;
+    use std::sync::Arc;
+
+    #[test]
+    fn transform_without_export_has_no_operations() {
+        let task = StoreTask::new("user".to_string(), Value::from("alice"), false);
+
+        let output = task
+            .transform(&Value::from("alice"), ActionInput::default())
+            .unwrap();
+
+        assert!(output.operations.is_empty());
+        assert!(output.next_input.properties.is_empty());
+    }
+
+    #[test]
+    fn transform_with_export_sets_property() {
+        let task = StoreTask::new("auth.user".to_string(), Value::from("alice"), true);
+
+        let output = task
+            .transform(&Value::from("alice"), ActionInput::default())
+            .unwrap();
+
+        let path: Path = "auth.user".into();
+        assert_eq!(output.operations.len(), 1);
+        assert!(matches!(
+            &output.operations[0],
+            HostOperation::SetProperty(p, _) if *p == path
+        ));
+        assert!(output.next_input.properties.contains_key(&path));
+    }
+
+    #[test]
+    fn store_task_keeps_value_on_context() {
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let task = Box::new(StoreTask::new(
+            "auth.user".to_string(),
+            Value::from("alice"),
+            true,
+        ));
+
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        assert_eq!(
+            ctx.get_stored_value("auth.user"),
+            Some(&Value::from("alice"))
+        );
+        assert!(matches!(
+            ctx.get_attribute::<Vec<u8>>("auth.user"),
+            Ok(AttributeState::Available(Some(_)))
+        ));
+        assert_eq!(get_metadata_generation(&ctx), 1);
+    }
+
+    #[test]
+    fn store_task_exports_lists_as_list_values() {
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let groups = Value::List(Arc::new(vec![Value::from("admins"), Value::from("ops")]));
+        let task = Box::new(StoreTask::new("auth.groups".to_string(), groups, true));
+
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+        assert_eq!(
+            ctx.get_attribute::<Vec<String>>("auth.groups"),
+            Ok(AttributeState::Available(Some(vec![
+                "admins".to_string(),
+                "ops".to_string()
+            ])))
+        );
+    }
+
+    #[test]
+    fn store_task_without_export_bumps_the_metadata_generation() {
+        let mut ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let predicate = Predicate::new("tier == 'gold'").expect("valid CEL");
+        ctx.store_value("tier".to_string(), Value::from("free"));
+        assert_eq!(
+            predicate.test(&ctx).expect("evaluation should succeed"),
+            AttributeState::Available(false)
+        );
+
+        let task = Box::new(StoreTask::new(
+            "tier".to_string(),
+            Value::from("gold"),
+            false,
+        ));
+        assert!(matches!(task.apply(&mut ctx), TaskOutcome::Done));
+
+        assert_eq!(get_metadata_generation(&ctx), 1);
+        assert_eq!(
+            predicate.test(&ctx).expect("evaluation should succeed"),
+            AttributeState::Available(true)
+        );
+    }
+}
@@ -2,9 +2,9 @@ use crate::data::attribute::AttributeState;
 use crate::data::Headers;
 use crate::kuadrant::pipeline::tasks::{Task, TaskOutcome};
 use crate::kuadrant::ReqRespCtx;
+use crate::{flog_debug, flog_error, flog_warn};
 use event_parser::Event;
 use serde_json::Value;
-use tracing::{debug, error, warn};
 
 mod event_parser;
 
@@ -54,7 +54,7 @@ impl Task for TokenUsageTask {
                         return TaskOutcome::Requeued(vec![Box::new(task)]);
                     }
                     Err(e) => {
-                        error!("Failed to get response headers: {e:?}");
+                        flog_error!(ctx.log(), "Failed to get response headers: {e:?}");
                         return TaskOutcome::Failed;
                     }
                 }
@@ -67,14 +67,14 @@ impl Task for TokenUsageTask {
                     strategy.feed_buffer(bytes);
                 }
                 Ok(AttributeState::Available(None)) => {
-                    debug!("No buffer available");
+                    flog_debug!(ctx.log(), "No buffer available");
                 }
                 Ok(AttributeState::Pending) => {
                     task.strategy = Some(strategy);
                     return TaskOutcome::Requeued(vec![Box::new(task)]);
                 }
                 Err(e) => {
-                    error!("Failed to get response body: {e:?}");
+                    flog_error!(ctx.log(), "Failed to get response body: {e:?}");
                     return TaskOutcome::Failed;
                 }
             }
@@ -83,24 +83,14 @@ impl Task for TokenUsageTask {
         if ctx.is_end_of_stream() {
             for field in &task.expected_response_fields {
                 if let Some(json_value) = strategy.extract_property(field) {
-                    match json_value {
-                        Value::Bool(b) => ctx.set_response_body_value(field, b),
-                        Value::Number(n) => {
-                            if let Some(u) = n.as_u64() {
-                                ctx.set_response_body_value(field, u);
-                            } else if let Some(i) = n.as_i64() {
-                                ctx.set_response_body_value(field, i);
-                            } else if let Some(f) = n.as_f64() {
-                                ctx.set_response_body_value(field, f);
-                            }
-                        }
-                        Value::String(s) => ctx.set_response_body_value(field, s),
-                        Value::Null | Value::Array(_) | Value::Object(_) => {
-                            warn!("Unsupported json value type: {:?}", json_value);
+                    match json_to_value(&json_value) {
+                        Some(value) => ctx.set_response_body_value(field, value),
+                        None => {
+                            flog_warn!(ctx.log(), "Unsupported json value type: {:?}", json_value)
                         }
                     }
                 } else {
-                    warn!("Missing json property: {}", field);
+                    flog_warn!(ctx.log(), "Missing json property: {}", field);
                 }
             }
             return TaskOutcome::Done;
@@ -111,6 +101,24 @@ impl Task for TokenUsageTask {
     }
 }
 
+/// Converts a scalar JSON value for use in CEL, `None` for nulls, arrays and objects.
+pub(super) fn json_to_value(json_value: &Value) -> Option<cel::Value> {
+    match json_value {
+        Value::Bool(b) => Some((*b).into()),
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                Some(u.into())
+            } else if let Some(i) = n.as_i64() {
+                Some(i.into())
+            } else {
+                n.as_f64().map(Into::into)
+            }
+        }
+        Value::String(s) => Some(s.clone().into()),
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
 fn select_strategy(headers: &Headers) -> Box<dyn ExtractionStrategy> {
     if let Some(ct) = headers.get("content-type") {
         if ct.contains("text/event-stream") {
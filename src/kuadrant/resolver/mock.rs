@@ -3,7 +3,7 @@ use crate::data::attribute::{AttributeError, Path};
 use crate::services::ServiceError;
 use std::collections::HashMap;
 use std::sync::Mutex;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 #[derive(Default)]
 pub struct MockWasmHost {
@@ -11,7 +11,15 @@ pub struct MockWasmHost {
     maps: Mutex<HashMap<String, Vec<(String, String)>>>,
     grpc_response: Mutex<Option<Vec<u8>>>,
     pending_properties: Vec<Path>,
-    response_body: Option<Vec<u8>>,
+    request_body: Option<Vec<u8>>,
+    response_body: Mutex<Option<Vec<u8>>>,
+    current_time: Mutex<Option<SystemTime>>,
+    dispatched_calls: Mutex<usize>,
+    last_dispatched_headers: Mutex<Vec<(String, Vec<u8>)>>,
+    #[cfg(feature = "http-callout")]
+    http_call_response: Mutex<Option<Vec<u8>>>,
+    #[cfg(feature = "http-callout")]
+    last_http_call_body: Mutex<Option<Vec<u8>>>,
 }
 
 impl MockWasmHost {
@@ -27,7 +35,15 @@ impl MockWasmHost {
             maps: Mutex::new(HashMap::new()),
             grpc_response: Mutex::new(None),
             pending_properties: Vec::new(),
-            response_body: None,
+            request_body: None,
+            response_body: Mutex::new(None),
+            current_time: Mutex::new(None),
+            dispatched_calls: Mutex::new(0),
+            last_dispatched_headers: Mutex::new(Vec::new()),
+            #[cfg(feature = "http-callout")]
+            http_call_response: Mutex::new(None),
+            #[cfg(feature = "http-callout")]
+            last_http_call_body: Mutex::new(None),
         }
     }
 
@@ -52,11 +68,76 @@ impl MockWasmHost {
         self
     }
 
-    pub fn with_response_body(mut self, bytes: &[u8]) -> Self {
-        self.response_body = Some(bytes.to_vec());
+    pub fn with_request_body(mut self, bytes: &[u8]) -> Self {
+        self.request_body = Some(bytes.to_vec());
         self
     }
 
+    pub fn with_response_body(self, bytes: &[u8]) -> Self {
+        *self
+            .response_body
+            .lock()
+            .expect("response_body mutex poisoned") = Some(bytes.to_vec());
+        self
+    }
+
+    pub fn response_body(&self) -> Option<Vec<u8>> {
+        self.response_body
+            .lock()
+            .expect("response_body mutex poisoned")
+            .clone()
+    }
+
+    pub fn with_current_time(self, time: SystemTime) -> Self {
+        *self
+            .current_time
+            .lock()
+            .expect("current_time mutex poisoned") = Some(time);
+        self
+    }
+
+    pub fn advance_time(&self, by: Duration) {
+        let mut current_time = self
+            .current_time
+            .lock()
+            .expect("current_time mutex poisoned");
+        *current_time = Some(current_time.unwrap_or(SystemTime::UNIX_EPOCH) + by);
+    }
+
+    pub fn dispatched_calls(&self) -> usize {
+        *self
+            .dispatched_calls
+            .lock()
+            .expect("dispatched_calls mutex poisoned")
+    }
+
+    /// The metadata sent with the last gRPC call dispatched
+    pub fn last_dispatched_headers(&self) -> Vec<(String, Vec<u8>)> {
+        self.last_dispatched_headers
+            .lock()
+            .expect("last_dispatched_headers mutex poisoned")
+            .clone()
+    }
+
+    /// The body answered to the HTTP calls dispatched
+    #[cfg(feature = "http-callout")]
+    pub fn with_http_call_response(self, body: &[u8]) -> Self {
+        *self
+            .http_call_response
+            .lock()
+            .expect("http_call_response mutex poisoned") = Some(body.to_vec());
+        self
+    }
+
+    /// The body of the last HTTP call dispatched
+    #[cfg(feature = "http-callout")]
+    pub fn last_http_call_body(&self) -> Option<Vec<u8>> {
+        self.last_http_call_body
+            .lock()
+            .expect("last_http_call_body mutex poisoned")
+            .clone()
+    }
+
     pub fn get_property(&self, path: &Path) -> Option<Vec<u8>> {
         self.properties
             .lock()
@@ -92,6 +173,7 @@ impl AttributeResolver for MockWasmHost {
         let map_key = match map_type {
             proxy_wasm::types::MapType::HttpRequestHeaders => "request.headers",
             proxy_wasm::types::MapType::HttpResponseHeaders => "response.headers",
+            proxy_wasm::types::MapType::GrpcReceiveTrailingMetadata => "grpc.trailers",
             _ => {
                 return Err(AttributeError::Retrieval(format!(
                     "MockWasmHost does not support map type: {:?}",
@@ -163,12 +245,27 @@ impl AttributeResolver for MockWasmHost {
         Ok(())
     }
 
+    fn get_http_request_body(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Vec<u8>>, AttributeError> {
+        Ok(self.request_body.as_ref().map(|body| {
+            let end = std::cmp::min(start + max_size, body.len());
+            body[std::cmp::min(start, end)..end].to_vec()
+        }))
+    }
+
     fn get_http_response_body(
         &self,
         start: usize,
         max_size: usize,
     ) -> Result<Option<Vec<u8>>, AttributeError> {
-        match &self.response_body {
+        match &*self
+            .response_body
+            .lock()
+            .expect("response_body mutex poisoned")
+        {
             Some(body) => {
                 let buf_end_index = std::cmp::min(start + max_size, body.len());
                 let mut dst = vec![0; buf_end_index];
@@ -180,17 +277,45 @@ impl AttributeResolver for MockWasmHost {
         }
     }
 
+    fn set_http_response_body(
+        &self,
+        start: usize,
+        size: usize,
+        value: &[u8],
+    ) -> Result<(), AttributeError> {
+        let mut response_body = self
+            .response_body
+            .lock()
+            .expect("response_body mutex poisoned");
+        let body = response_body.get_or_insert_with(Vec::new);
+        let start = std::cmp::min(start, body.len());
+        let end = std::cmp::min(start + size, body.len());
+        body.splice(start..end, value.iter().copied());
+        Ok(())
+    }
+
     fn dispatch_grpc_call(
         &self,
         _upstream_name: &str,
         _service_name: &str,
         _method: &str,
-        _headers: Vec<(&str, &[u8])>,
-        _message: Vec<u8>,
+        headers: Vec<(&str, &[u8])>,
+        _message: &[u8],
         _timeout: Duration,
     ) -> Result<u32, ServiceError> {
         // todo(refactor): mock returns a fake token_id
         // in real tests, we'd need to store the message and allow retrieving responses
+        *self
+            .dispatched_calls
+            .lock()
+            .expect("dispatched_calls mutex poisoned") += 1;
+        *self
+            .last_dispatched_headers
+            .lock()
+            .expect("last_dispatched_headers mutex poisoned") = headers
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_vec()))
+            .collect();
         Ok(42)
     }
 
@@ -202,6 +327,41 @@ impl AttributeResolver for MockWasmHost {
             .ok_or_else(|| ServiceError::Retrieval("No response available".to_string()))
     }
 
+    #[cfg(feature = "http-callout")]
+    fn dispatch_http_call(
+        &self,
+        _upstream_name: &str,
+        headers: Vec<(&str, &str)>,
+        body: &[u8],
+        _timeout: Duration,
+    ) -> Result<u32, ServiceError> {
+        *self
+            .dispatched_calls
+            .lock()
+            .expect("dispatched_calls mutex poisoned") += 1;
+        *self
+            .last_dispatched_headers
+            .lock()
+            .expect("last_dispatched_headers mutex poisoned") = headers
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+            .collect();
+        *self
+            .last_http_call_body
+            .lock()
+            .expect("last_http_call_body mutex poisoned") = Some(body.to_vec());
+        Ok(43)
+    }
+
+    #[cfg(feature = "http-callout")]
+    fn get_http_call_response_body(&self, _body_size: usize) -> Result<Vec<u8>, ServiceError> {
+        self.http_call_response
+            .lock()
+            .expect("http_call_response mutex poisoned")
+            .clone()
+            .ok_or_else(|| ServiceError::Retrieval("No response available".to_string()))
+    }
+
     fn send_http_reply(
         &self,
         _status_code: u32,
@@ -210,4 +370,11 @@ impl AttributeResolver for MockWasmHost {
     ) -> Result<(), ServiceError> {
         Ok(())
     }
+
+    fn get_current_time(&self) -> SystemTime {
+        self.current_time
+            .lock()
+            .expect("current_time mutex poisoned")
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    }
 }
@@ -1,6 +1,6 @@
 use crate::data::attribute::{AttributeError, Path};
 use crate::services::ServiceError;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 mod wasm_host;
 pub use wasm_host::ProxyWasmHost;
@@ -28,25 +28,47 @@ pub trait AttributeResolver: Send + Sync {
         map_type: proxy_wasm::types::MapType,
         value: Vec<(&str, &str)>,
     ) -> Result<(), AttributeError>;
+    fn get_http_request_body(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Vec<u8>>, AttributeError>;
     fn get_http_response_body(
         &self,
         start: usize,
         max_size: usize,
     ) -> Result<Option<Vec<u8>>, AttributeError>;
+    fn set_http_response_body(
+        &self,
+        start: usize,
+        size: usize,
+        value: &[u8],
+    ) -> Result<(), AttributeError>;
     fn dispatch_grpc_call(
         &self,
         upstream_name: &str,
         service_name: &str,
         method: &str,
         headers: Vec<(&str, &[u8])>,
-        message: Vec<u8>,
+        message: &[u8],
         timeout: Duration,
     ) -> Result<u32, ServiceError>;
     fn get_grpc_response(&self, response_size: usize) -> Result<Vec<u8>, ServiceError>;
+    #[cfg(feature = "http-callout")]
+    fn dispatch_http_call(
+        &self,
+        upstream_name: &str,
+        headers: Vec<(&str, &str)>,
+        body: &[u8],
+        timeout: Duration,
+    ) -> Result<u32, ServiceError>;
+    #[cfg(feature = "http-callout")]
+    fn get_http_call_response_body(&self, body_size: usize) -> Result<Vec<u8>, ServiceError>;
     fn send_http_reply(
         &self,
         status_code: u32,
         headers: Vec<(&str, &str)>,
         body: Option<&[u8]>,
     ) -> Result<(), ServiceError>;
+    fn get_current_time(&self) -> SystemTime;
 }
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use tracing::{debug, error};
 
@@ -83,6 +83,26 @@ impl AttributeResolver for ProxyWasmHost {
         }
     }
 
+    fn get_http_request_body(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<proxy_wasm::types::Bytes>, AttributeError> {
+        match hostcalls::get_buffer(
+            proxy_wasm::types::BufferType::HttpRequestBody,
+            start,
+            max_size,
+        ) {
+            Ok(bytes) => Ok(bytes),
+            Err(Status::BadArgument) => {
+                Err(AttributeError::NotAvailable("request.body".to_string()))
+            }
+            Err(e) => Err(AttributeError::Retrieval(format!(
+                "Error getting http request body buffer: {e:?}"
+            ))),
+        }
+    }
+
     fn get_http_response_body(
         &self,
         start: usize,
@@ -103,13 +123,35 @@ impl AttributeResolver for ProxyWasmHost {
         }
     }
 
+    fn set_http_response_body(
+        &self,
+        start: usize,
+        size: usize,
+        value: &[u8],
+    ) -> Result<(), AttributeError> {
+        match hostcalls::set_buffer(
+            proxy_wasm::types::BufferType::HttpResponseBody,
+            start,
+            size,
+            value,
+        ) {
+            Ok(()) => Ok(()),
+            Err(Status::BadArgument) => {
+                Err(AttributeError::NotAvailable("response.body".to_string()))
+            }
+            Err(e) => Err(AttributeError::Set(format!(
+                "Error setting http response body buffer: {e:?}"
+            ))),
+        }
+    }
+
     fn dispatch_grpc_call(
         &self,
         upstream_name: &str,
         service_name: &str,
         method: &str,
         headers: Vec<(&str, &[u8])>,
-        message: Vec<u8>,
+        message: &[u8],
         timeout: Duration,
     ) -> Result<u32, ServiceError> {
         debug!(
@@ -121,7 +163,7 @@ impl AttributeResolver for ProxyWasmHost {
             service_name,
             method,
             headers,
-            Some(&message),
+            Some(message),
             timeout,
         ) {
             Ok(token_id) => {
@@ -154,6 +196,42 @@ impl AttributeResolver for ProxyWasmHost {
         .ok_or_else(|| ServiceError::Retrieval("No gRPC response body available".to_string()))
     }
 
+    #[cfg(feature = "http-callout")]
+    fn dispatch_http_call(
+        &self,
+        upstream_name: &str,
+        headers: Vec<(&str, &str)>,
+        body: &[u8],
+        timeout: Duration,
+    ) -> Result<u32, ServiceError> {
+        debug!(
+            "Dispatching HTTP call to {}, timeout: {:?}",
+            upstream_name, timeout
+        );
+        match hostcalls::dispatch_http_call(upstream_name, headers, Some(body), vec![], timeout) {
+            Ok(token_id) => {
+                debug!("HTTP call dispatched successfully, token_id: {}", token_id);
+                Ok(token_id)
+            }
+            Err(e) => {
+                error!("Failed to dispatch HTTP call to {}: {:?}", upstream_name, e);
+                Err(ServiceError::Dispatch(format!("{e:?}")))
+            }
+        }
+    }
+
+    #[cfg(feature = "http-callout")]
+    fn get_http_call_response_body(&self, body_size: usize) -> Result<Vec<u8>, ServiceError> {
+        debug!("Getting HTTP call response, size: {} bytes", body_size);
+        hostcalls::get_buffer(
+            proxy_wasm::types::BufferType::HttpCallResponseBody,
+            0,
+            body_size,
+        )
+        .map_err(|e| ServiceError::Retrieval(format!("Failed to get HTTP call response: {:?}", e)))?
+        .ok_or_else(|| ServiceError::Retrieval("No HTTP call response body available".to_string()))
+    }
+
     fn send_http_reply(
         &self,
         status_code: u32,
@@ -164,4 +242,11 @@ impl AttributeResolver for ProxyWasmHost {
         hostcalls::send_http_response(status_code, headers, body)
             .map_err(|e| ServiceError::Dispatch(format!("Failed to send HTTP reply: {:?}", e)))
     }
+
+    fn get_current_time(&self) -> SystemTime {
+        hostcalls::get_current_time().unwrap_or_else(|e| {
+            error!("Failed to get current time: {e:?}");
+            SystemTime::UNIX_EPOCH
+        })
+    }
 }
@@ -0,0 +1,200 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use proxy_wasm::types::MetricType;
+
+/// The service families whose calls are counted per action set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CallService {
+    Auth,
+    RateLimit,
+}
+
+/// How a call ended: `Rejected` is a denied request for auth and a limited one
+/// for rate limiting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CallOutcome {
+    Ok,
+    Error,
+    Rejected,
+}
+
+impl Display for CallService {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallService::Auth => write!(f, "auth"),
+            CallService::RateLimit => write!(f, "ratelimit"),
+        }
+    }
+}
+
+impl CallOutcome {
+    fn name(&self, service: CallService) -> &'static str {
+        match (self, service) {
+            (CallOutcome::Ok, _) => "ok",
+            (CallOutcome::Error, _) => "error",
+            (CallOutcome::Rejected, CallService::Auth) => "denied",
+            (CallOutcome::Rejected, CallService::RateLimit) => "limited",
+        }
+    }
+}
+
+pub trait MetricsHost {
+    fn define_metric(&self, metric_type: MetricType, name: &str) -> Option<u32>;
+    fn increment_metric(&self, metric_id: u32, offset: i64);
+    fn record_metric(&self, metric_id: u32, value: u64);
+}
+
+pub struct ProxyWasmMetricsHost;
+
+impl MetricsHost for ProxyWasmMetricsHost {
+    fn define_metric(&self, metric_type: MetricType, name: &str) -> Option<u32> {
+        if cfg!(target_arch = "wasm32") {
+            proxy_wasm::hostcalls::define_metric(metric_type, name).ok()
+        } else {
+            None
+        }
+    }
+
+    fn increment_metric(&self, metric_id: u32, offset: i64) {
+        if cfg!(target_arch = "wasm32") {
+            let _ = proxy_wasm::hostcalls::increment_metric(metric_id, offset);
+        }
+    }
+
+    fn record_metric(&self, metric_id: u32, value: u64) {
+        if cfg!(target_arch = "wasm32") {
+            let _ = proxy_wasm::hostcalls::record_metric(metric_id, value);
+        }
+    }
+}
+
+/// Counts the outcomes of the auth and rate limit calls of every action set,
+/// defining each metric with the host the first time it is used.
+pub struct MetricsCollector {
+    host: Box<dyn MetricsHost>,
+    metric_ids: RefCell<HashMap<String, Option<u32>>>,
+}
+
+impl Default for MetricsCollector {
+    fn default() -> Self {
+        Self::new(Box::new(ProxyWasmMetricsHost))
+    }
+}
+
+impl MetricsCollector {
+    pub fn new(host: Box<dyn MetricsHost>) -> Self {
+        Self {
+            host,
+            metric_ids: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn record_call(
+        &self,
+        action_set: &str,
+        service: CallService,
+        outcome: CallOutcome,
+        duration: Duration,
+    ) {
+        let counter = format!("{action_set}.kuadrant.{service}.{}", outcome.name(service));
+        if let Some(id) = self.metric_id(MetricType::Counter, counter) {
+            self.host.increment_metric(id, 1);
+        }
+        let histogram = format!("{action_set}.kuadrant.grpc.call_duration_ms");
+        if let Some(id) = self.metric_id(MetricType::Histogram, histogram) {
+            self.host
+                .record_metric(id, u64::try_from(duration.as_millis()).unwrap_or(u64::MAX));
+        }
+    }
+
+    fn metric_id(&self, metric_type: MetricType, name: String) -> Option<u32> {
+        *self
+            .metric_ids
+            .borrow_mut()
+            .entry(name)
+            .or_insert_with_key(|name| {
+                let id = self.host.define_metric(metric_type, name);
+                if id.is_none() {
+                    tracing::error!("failed to add metric: {}", name);
+                }
+                id
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct MockMetricsHost {
+        defined: RefCell<Vec<String>>,
+        increments: RefCell<Vec<(u32, i64)>>,
+        records: RefCell<Vec<(u32, u64)>>,
+    }
+
+    impl MetricsHost for Rc<MockMetricsHost> {
+        fn define_metric(&self, _metric_type: MetricType, name: &str) -> Option<u32> {
+            let mut defined = self.defined.borrow_mut();
+            defined.push(name.to_string());
+            u32::try_from(defined.len() - 1).ok()
+        }
+
+        fn increment_metric(&self, metric_id: u32, offset: i64) {
+            self.increments.borrow_mut().push((metric_id, offset));
+        }
+
+        fn record_metric(&self, metric_id: u32, value: u64) {
+            self.records.borrow_mut().push((metric_id, value));
+        }
+    }
+
+    #[test]
+    fn counts_outcomes_per_action_set() {
+        let host = Rc::new(MockMetricsHost::default());
+        let collector = MetricsCollector::new(Box::new(Rc::clone(&host)));
+
+        let duration = Duration::from_millis(12);
+        collector.record_call("a", CallService::Auth, CallOutcome::Rejected, duration);
+        collector.record_call("a", CallService::Auth, CallOutcome::Rejected, duration);
+        collector.record_call("b", CallService::RateLimit, CallOutcome::Rejected, duration);
+
+        assert_eq!(
+            *host.defined.borrow(),
+            vec![
+                "a.kuadrant.auth.denied",
+                "a.kuadrant.grpc.call_duration_ms",
+                "b.kuadrant.ratelimit.limited",
+                "b.kuadrant.grpc.call_duration_ms",
+            ]
+        );
+        assert_eq!(*host.increments.borrow(), vec![(0, 1), (0, 1), (2, 1)]);
+        assert_eq!(*host.records.borrow(), vec![(1, 12), (1, 12), (3, 12)]);
+    }
+
+    #[test]
+    fn names_every_outcome() {
+        let host = Rc::new(MockMetricsHost::default());
+        let collector = MetricsCollector::new(Box::new(Rc::clone(&host)));
+
+        for service in [CallService::Auth, CallService::RateLimit] {
+            for outcome in [CallOutcome::Ok, CallOutcome::Error] {
+                collector.record_call("policy", service, outcome, Duration::ZERO);
+            }
+        }
+
+        let defined = host.defined.borrow();
+        for name in [
+            "policy.kuadrant.auth.ok",
+            "policy.kuadrant.auth.error",
+            "policy.kuadrant.ratelimit.ok",
+            "policy.kuadrant.ratelimit.error",
+        ] {
+            assert!(defined.iter().any(|defined| defined == name), "{name}");
+        }
+    }
+}
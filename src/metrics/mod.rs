@@ -1,6 +1,10 @@
 use std::collections::BTreeMap;
 use std::sync::LazyLock;
 
+mod collector;
+
+pub use collector::{CallOutcome, CallService, MetricsCollector};
+
 const CONFIGS: &str = "kuadrant.configs";
 const HITS: &str = "kuadrant.hits";
 const MISSES: &str = "kuadrant.misses";
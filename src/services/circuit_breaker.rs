@@ -0,0 +1,134 @@
+use std::time::{Duration, SystemTime};
+
+use crate::configuration::CircuitBreakerConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    Closed,
+    Open { since: SystemTime },
+    HalfOpen { since: SystemTime },
+}
+
+/// Counts consecutive failures of a service and, past the threshold, lets calls
+/// be skipped until the cool-down elapses and a single trial call succeeds.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cool_down: Duration,
+    consecutive_failures: u32,
+    state: CircuitState,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cool_down: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cool_down,
+            consecutive_failures: 0,
+            state: CircuitState::Closed,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Whether a call may be dispatched at `now`. Once the cool-down has elapsed
+    /// an open circuit lets one trial call through and waits on its outcome.
+    pub fn allow_request(&mut self, now: SystemTime) -> bool {
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::Open { since } | CircuitState::HalfOpen { since } => {
+                if self.cooled_down(since, now) {
+                    self.state = CircuitState::HalfOpen { since: now };
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.state = CircuitState::Closed;
+    }
+
+    pub fn record_failure(&mut self, now: SystemTime) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        let tripped = match self.state {
+            CircuitState::Closed => self.consecutive_failures >= self.failure_threshold,
+            CircuitState::HalfOpen { .. } => true,
+            CircuitState::Open { .. } => false,
+        };
+        if tripped {
+            self.state = CircuitState::Open { since: now };
+        }
+    }
+
+    fn cooled_down(&self, since: SystemTime, now: SystemTime) -> bool {
+        now.duration_since(since)
+            .is_ok_and(|elapsed| elapsed >= self.cool_down)
+    }
+}
+
+impl From<&CircuitBreakerConfig> for CircuitBreaker {
+    fn from(config: &CircuitBreakerConfig) -> Self {
+        Self::new(config.failure_threshold, config.cool_down.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOL_DOWN: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let now = SystemTime::UNIX_EPOCH;
+        let mut breaker = CircuitBreaker::new(2, COOL_DOWN);
+
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allow_request(now));
+
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), CircuitState::Open { since: now });
+        assert!(!breaker.allow_request(now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn half_open_trial_closes_on_success() {
+        let now = SystemTime::UNIX_EPOCH;
+        let mut breaker = CircuitBreaker::new(1, COOL_DOWN);
+        breaker.record_failure(now);
+
+        let later = now + COOL_DOWN;
+        assert!(breaker.allow_request(later));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen { since: later });
+        assert!(!breaker.allow_request(later));
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allow_request(later));
+    }
+
+    #[test]
+    fn half_open_trial_reopens_on_failure() {
+        let now = SystemTime::UNIX_EPOCH;
+        let mut breaker = CircuitBreaker::new(3, COOL_DOWN);
+        for _ in 0..3 {
+            breaker.record_failure(now);
+        }
+
+        let later = now + COOL_DOWN;
+        assert!(breaker.allow_request(later));
+        breaker.record_failure(later);
+        assert_eq!(breaker.state(), CircuitState::Open { since: later });
+        assert!(!breaker.allow_request(later + Duration::from_secs(1)));
+    }
+}
@@ -1,4 +1,4 @@
-use std::cell::OnceCell;
+use std::cell::{Cell, OnceCell, RefCell};
 use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Duration;
@@ -6,10 +6,15 @@ use std::time::Duration;
 use cel::{Context, Env, Value};
 use prost::Message;
 use prost_reflect::DynamicMessage;
-use tracing::debug;
-
-use super::{Service, ServiceError};
-use crate::configuration::FailureMode;
+use tracing::{debug, warn};
+
+use super::{
+    health, BuildError, CircuitBreaker, GrpcRequest, GrpcRequestBuilder, ResponseCache, Service,
+    ServiceError,
+};
+use crate::configuration::{ErrorResponse, FailureMode, RetryPolicy};
+use crate::data::attribute::{self, AttributeError, AttributeState};
+use crate::data::grpc::{GrpcErrResponse, GRPC_STATUS_DETAILS_TRAILER};
 use crate::filter::{DescriptorKey, DescriptorManager};
 use crate::kuadrant::ReqRespCtx;
 
@@ -25,8 +30,21 @@ pub struct DynamicService {
     failure_mode: FailureMode,
     descriptor_manager: Rc<DescriptorManager>,
     cel_env: OnceCell<Arc<Env>>,
+    circuit_breaker: Option<RefCell<CircuitBreaker>>,
+    retry_policy: Option<RetryPolicy>,
+    response_cache: Option<RefCell<ResponseCache>>,
+    error_response: Option<ErrorResponse>,
+    use_grpc_status_details: bool,
+    health_check: bool,
+    healthy: Cell<bool>,
+    authority: Option<String>,
+    dynamic_metadata_key: Option<String>,
+    forwards_trailers: bool,
 }
 
+const GRPC_STATUS_UNAVAILABLE: u32 = 14;
+const RATE_LIMIT_OVER_LIMIT: i32 = 2;
+
 impl DynamicService {
     pub fn new(
         endpoint: String,
@@ -46,13 +64,154 @@ impl DynamicService {
             failure_mode,
             descriptor_manager,
             cel_env: Default::default(),
+            circuit_breaker: None,
+            retry_policy: None,
+            response_cache: None,
+            error_response: None,
+            use_grpc_status_details: false,
+            health_check: false,
+            healthy: Cell::new(true),
+            authority: None,
+            dynamic_metadata_key: None,
+            forwards_trailers: false,
         }
     }
 
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Option<CircuitBreaker>) -> Self {
+        self.circuit_breaker = circuit_breaker.map(RefCell::new);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: Option<RetryPolicy>) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_response_cache(mut self, response_cache: Option<ResponseCache>) -> Self {
+        self.response_cache = response_cache.map(RefCell::new);
+        self
+    }
+
+    pub fn with_error_response(mut self, error_response: Option<ErrorResponse>) -> Self {
+        self.error_response = error_response;
+        self
+    }
+
+    pub fn with_grpc_status_details(mut self, use_grpc_status_details: bool) -> Self {
+        self.use_grpc_status_details = use_grpc_status_details;
+        self
+    }
+
+    pub fn with_health_check(mut self, health_check: bool) -> Self {
+        self.health_check = health_check;
+        self
+    }
+
+    /// Dispatches the calls with this `:authority` in place of the upstream name
+    pub fn with_authority(mut self, authority: Option<String>) -> Self {
+        self.authority = authority;
+        self
+    }
+
+    /// Sends the forwarded request trailers, once received, along its calls
+    pub fn with_forwarded_trailers(mut self, forwards_trailers: bool) -> Self {
+        self.forwards_trailers = forwards_trailers;
+        self
+    }
+
+    /// Reads the `dynamic_metadata` of replies carrying none from the Envoy
+    /// dynamic metadata under this `filter_metadata` key
+    pub fn with_dynamic_metadata_key(mut self, dynamic_metadata_key: Option<String>) -> Self {
+        self.dynamic_metadata_key = dynamic_metadata_key;
+        self
+    }
+
+    /// Marks the upstream as passing or failing its health check; calls to an
+    /// upstream failing it are not dispatched
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.set(healthy);
+    }
+
+    /// The `grpc.health.v1.Health/Check` request for this service, if it is
+    /// configured to be checked at startup
+    pub fn health_check_request(&self) -> Option<Result<GrpcRequest, BuildError>> {
+        self.health_check.then(|| {
+            health::health_check_request(
+                &self.upstream_name,
+                self.authority.clone(),
+                &self.service_name,
+                self.timeout,
+            )
+        })
+    }
+
     pub fn failure_mode(&self) -> FailureMode {
         self.failure_mode
     }
 
+    pub fn error_response(&self) -> Option<&ErrorResponse> {
+        self.error_response.as_ref()
+    }
+
+    /// The reply carrying the reason the service attached to a failed call, if
+    /// the service is configured to use it and the trailer decodes. A service
+    /// with `failureMode: allow` never has one, its failed calls let requests through.
+    pub fn status_details_response(&self, ctx: &ReqRespCtx) -> Option<GrpcErrResponse> {
+        if !self.use_grpc_status_details || self.failure_mode != FailureMode::Deny {
+            return None;
+        }
+        let trailer = match ctx.get_grpc_response_trailer(GRPC_STATUS_DETAILS_TRAILER) {
+            Ok(trailer) => trailer?,
+            Err(e) => {
+                warn!("Failed to get {GRPC_STATUS_DETAILS_TRAILER} trailer: {e:?}");
+                return None;
+            }
+        };
+        let response = GrpcErrResponse::from_status_details(&trailer);
+        if response.is_none() {
+            warn!("Ignoring malformed {GRPC_STATUS_DETAILS_TRAILER} trailer");
+        }
+        response
+    }
+
+    /// Whether a call answered with `status_code` on its `attempt`th dispatch
+    /// is to be dispatched again
+    pub fn should_retry(&self, status_code: u32, attempt: u32) -> bool {
+        status_code == GRPC_STATUS_UNAVAILABLE
+            && self
+                .retry_policy
+                .as_ref()
+                .is_some_and(|policy| attempt < policy.max_attempts)
+    }
+
+    /// How long to wait before dispatching a call again after its `attempt`th
+    /// dispatch failed
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        self.retry_policy.as_ref().map_or(Duration::ZERO, |policy| {
+            let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+            let factor = policy.multiplier.powi(exponent);
+            Duration::try_from_secs_f64(policy.base_delay.0.as_secs_f64() * factor)
+                .unwrap_or(Duration::MAX)
+        })
+    }
+
+    /// The delay of the first retry, if calls are retried at all
+    pub fn retry_base_delay(&self) -> Option<Duration> {
+        self.retry_policy.as_ref().map(|policy| policy.base_delay.0)
+    }
+
+    /// Feeds the outcome of a call into the circuit breaker, if any
+    pub fn record_outcome(&self, ctx: &ReqRespCtx, success: bool) {
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            let mut circuit_breaker = circuit_breaker.borrow_mut();
+            if success {
+                circuit_breaker.record_success();
+            } else {
+                circuit_breaker.record_failure(ctx.current_time());
+            }
+        }
+    }
+
     pub fn cel_env(&self) -> Result<Arc<Env>, ServiceError> {
         match self.cel_env.get() {
             Some(env) => Ok(Arc::clone(env)),
@@ -79,6 +238,11 @@ impl DynamicService {
         ctx: &mut ReqRespCtx,
         cel_value: &Value,
     ) -> Result<u32, ServiceError> {
+        let message = self.encode_value(cel_value)?;
+        self.dispatch_message(ctx, message, None)
+    }
+
+    pub fn encode_value(&self, cel_value: &Value) -> Result<Vec<u8>, ServiceError> {
         let input_descriptor = self.input_descriptor()?;
 
         debug!("Converting CEL value to protobuf message");
@@ -91,15 +255,111 @@ impl DynamicService {
         request_message
             .encode(&mut message_bytes)
             .map_err(|e| ServiceError::Dispatch(format!("Failed to encode message: {}", e)))?;
+        Ok(message_bytes)
+    }
 
-        self.dispatch(
-            ctx,
-            &self.upstream_name,
-            &self.service_name,
-            &self.method,
-            message_bytes,
-            self.timeout,
-        )
+    /// Builds the request carrying `message`, `timeout` taking precedence over
+    /// the one of the service.
+    pub fn build_request(
+        &self,
+        ctx: &ReqRespCtx,
+        message: Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> Result<GrpcRequest, BuildError> {
+        let metadata = if self.forwards_trailers {
+            ctx.forwarded_trailers().to_vec()
+        } else {
+            Vec::new()
+        };
+        GrpcRequestBuilder::new(self.upstream_name.as_str())
+            .service(self.service_name.as_str())
+            .method(self.method.as_str())
+            .authority(self.authority.clone())
+            .timeout(timeout.unwrap_or(self.timeout))
+            .message(message)
+            .metadata(metadata)
+            .build()
+    }
+
+    /// Whether the upstream is to be called at all, passing its health check
+    /// and with its circuit closed
+    fn admit(&self, ctx: &ReqRespCtx) -> Result<(), ServiceError> {
+        if !self.healthy.get() {
+            return Err(ServiceError::Unhealthy);
+        }
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            if !circuit_breaker
+                .borrow_mut()
+                .allow_request(ctx.current_time())
+            {
+                return Err(ServiceError::CircuitOpen);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn dispatch_message(
+        &self,
+        ctx: &mut ReqRespCtx,
+        message: Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> Result<u32, ServiceError> {
+        self.admit(ctx)?;
+
+        let request = self.build_request(ctx, message, timeout)?;
+        let result = self.dispatch(ctx, request);
+        if let Err(ServiceError::Dispatch(_)) = result {
+            self.record_outcome(ctx, false);
+        }
+        result
+    }
+
+    /// Dispatches `message` again once `delay` has elapsed, returning the
+    /// placeholder token its response is awaited under
+    pub fn schedule_message(
+        &self,
+        ctx: &mut ReqRespCtx,
+        message: Vec<u8>,
+        timeout: Option<Duration>,
+        delay: Duration,
+    ) -> Result<u32, ServiceError> {
+        self.admit(ctx)?;
+
+        let request = self.build_request(ctx, message, timeout)?;
+        ctx.schedule_grpc_call(request, delay)
+    }
+
+    pub fn caches_responses(&self) -> bool {
+        self.response_cache.is_some()
+    }
+
+    /// The response previously received for the same request `message`, if still fresh
+    pub fn cached_response(&self, ctx: &ReqRespCtx, message: &[u8]) -> Option<Vec<u8>> {
+        self.response_cache
+            .as_ref()
+            .and_then(|cache| cache.borrow_mut().get(message, ctx.current_time()))
+    }
+
+    /// Caches `response` to the request `message` if it is an over limit one
+    pub fn cache_response(&self, ctx: &ReqRespCtx, message: Vec<u8>, response: Vec<u8>) {
+        if let Some(cache) = &self.response_cache {
+            if self.is_over_limit(&response) {
+                cache
+                    .borrow_mut()
+                    .insert(message, response, ctx.current_time());
+            }
+        }
+    }
+
+    fn is_over_limit(&self, response: &[u8]) -> bool {
+        self.parse_message(response.to_vec())
+            .ok()
+            .and_then(|response| {
+                response
+                    .get_field_by_name("overall_code")
+                    .and_then(|code| code.as_enum_number())
+            })
+            == Some(RATE_LIMIT_OVER_LIMIT)
     }
 
     fn method_descriptor(&self) -> Result<prost_reflect::MethodDescriptor, ServiceError> {
@@ -136,13 +396,61 @@ impl DynamicService {
         Ok(self.method_descriptor()?.output())
     }
 
+    /// The `Struct` the host holds under the configured `filter_metadata`
+    /// key, where Authorino can leave its results rather than in its reply
+    pub fn extract_metadata_from_dynamic_metadata(
+        &self,
+        ctx: &ReqRespCtx,
+    ) -> Result<Option<prost_types::Struct>, AttributeError> {
+        let Some(key) = &self.dynamic_metadata_key else {
+            return Ok(None);
+        };
+        Ok(match attribute::dynamic_metadata(ctx, key)? {
+            AttributeState::Available(metadata) => metadata,
+            AttributeState::Pending => None,
+        })
+    }
+
+    /// The `response` with the `dynamic_metadata` found on the host, when it
+    /// has such a field yet carries none of its own
+    pub fn with_host_dynamic_metadata(
+        &self,
+        ctx: &ReqRespCtx,
+        response: Vec<u8>,
+    ) -> Result<Vec<u8>, ServiceError> {
+        let metadata = match self.extract_metadata_from_dynamic_metadata(ctx) {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => return Ok(response),
+            Err(e) => {
+                return Err(ServiceError::Decode(format!(
+                    "Failed to read dynamic metadata: {e:?}"
+                )))
+            }
+        };
+        let mut message = self.parse_message(response.clone())?;
+        let Some(field) = message.descriptor().get_field_by_name("dynamic_metadata") else {
+            return Ok(response);
+        };
+        if message.has_field(&field) {
+            return Ok(response);
+        }
+        let Some(struct_descriptor) = field.kind().as_message().cloned() else {
+            return Err(ServiceError::Decode(
+                "dynamic_metadata is not a message field".to_string(),
+            ));
+        };
+        let value = DynamicMessage::decode(struct_descriptor, metadata.encode_to_vec().as_slice())
+            .map_err(|e| ServiceError::Decode(format!("Invalid dynamic metadata: {e}")))?;
+        message.set_field(&field, prost_reflect::Value::Message(value));
+        Ok(message.encode_to_vec())
+    }
+
     pub fn response_cel_context(
         &self,
-        ctx: &mut ReqRespCtx,
-        response_size: usize,
+        message: Vec<u8>,
         name: &str,
     ) -> Result<Context<'_>, ServiceError> {
-        let response = self.get_response(ctx, response_size)?;
+        let response = self.parse_message(message)?;
         let cel_value = MessageConverter::dynamic_message_to_cel(&response).map_err(|e| {
             ServiceError::Decode(format!("Failed to convert message to CEL: {}", e))
         })?;
@@ -191,7 +499,9 @@ impl Service for DynamicService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::configuration::Timeout;
     use crate::filter::{DescriptorKey, DescriptorManager};
+    use crate::kuadrant::MockWasmHost;
     use cel::Program;
     use prost_reflect::DescriptorPool;
     use prost_types::{
@@ -339,4 +649,349 @@ mod tests {
         let parsed = service.parse_message(response_bytes);
         assert!(parsed.is_ok());
     }
+
+    #[test]
+    fn test_retries_unavailable_until_attempts_are_exhausted() {
+        let service = DynamicService::new(
+            "test-cluster".to_string(),
+            "test.TestService".to_string(),
+            "TestMethod".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Deny,
+            create_test_descriptor_manager(),
+        );
+        assert!(!service.should_retry(GRPC_STATUS_UNAVAILABLE, 1));
+
+        let service = service.with_retry_policy(Some(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Timeout(Duration::from_millis(500)),
+            multiplier: 3.0,
+        }));
+        let attempts = (1..)
+            .take_while(|attempt| service.should_retry(GRPC_STATUS_UNAVAILABLE, *attempt))
+            .count();
+        assert_eq!(attempts, 2);
+        assert!(!service.should_retry(GRPC_STATUS_UNAVAILABLE, 3));
+        assert!(!service.should_retry(7, 1));
+
+        assert_eq!(service.retry_delay(1), Duration::from_millis(500));
+        assert_eq!(service.retry_delay(2), Duration::from_millis(1500));
+        assert_eq!(service.retry_delay(3), Duration::from_millis(4500));
+    }
+
+    #[test]
+    fn test_extracts_the_configured_dynamic_metadata() {
+        use crate::data::attribute::Path;
+        use prost_types::value::Kind;
+
+        let metadata = prost_types::Struct {
+            fields: [(
+                "user".to_string(),
+                prost_types::Value {
+                    kind: Some(Kind::StringValue("alice".to_string())),
+                },
+            )]
+            .into(),
+        };
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new().with_property(
+            Path::from_parts([
+                "metadata",
+                "filter_metadata",
+                "envoy.filters.http.ext_authz",
+            ]),
+            metadata.encode_to_vec(),
+        )));
+        let service = DynamicService::new(
+            "test-cluster".to_string(),
+            "test.TestService".to_string(),
+            "TestMethod".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Deny,
+            create_test_descriptor_manager(),
+        );
+        assert_eq!(
+            service.extract_metadata_from_dynamic_metadata(&ctx),
+            Ok(None)
+        );
+
+        let service =
+            service.with_dynamic_metadata_key(Some("envoy.filters.http.ext_authz".to_string()));
+        let extracted = service
+            .extract_metadata_from_dynamic_metadata(&ctx)
+            .expect("Failed to read dynamic metadata")
+            .expect("Dynamic metadata not found");
+        assert_eq!(
+            extracted.fields.get("user").and_then(|v| v.kind.clone()),
+            Some(Kind::StringValue("alice".to_string()))
+        );
+
+        // TestResponse has no dynamic_metadata field to fill in
+        let response = vec![10, 2, 111, 107];
+        assert_eq!(
+            service
+                .with_host_dynamic_metadata(&ctx, response.clone())
+                .expect("Failed to merge dynamic metadata"),
+            response
+        );
+    }
+
+    #[test]
+    fn test_action_timeout_overrides_the_service_timeout() {
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let service = DynamicService::new(
+            "test-cluster".to_string(),
+            "test.TestService".to_string(),
+            "TestMethod".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Deny,
+            create_test_descriptor_manager(),
+        );
+
+        let request = service
+            .build_request(&ctx, vec![], Some(Duration::from_millis(150)))
+            .expect("Failed to build request");
+        assert_eq!(request.timeout(), Duration::from_millis(150));
+
+        let request = service
+            .build_request(&ctx, vec![], None)
+            .expect("Failed to build request");
+        assert_eq!(request.timeout(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_forwarded_trailers_are_sent_by_the_services_forwarding_them() {
+        let mock_host = Arc::new(MockWasmHost::new());
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        let token = vec![0xff, 0x00, b'a', 0xc3];
+        ctx.forward_request_trailers(
+            &["X-Auth-Token".to_string()],
+            vec![("x-auth-token".to_string(), token.clone())],
+        );
+        let service = |forwards_trailers| {
+            DynamicService::new(
+                "test-cluster".to_string(),
+                "test.TestService".to_string(),
+                "TestMethod".to_string(),
+                Duration::from_secs(1),
+                FailureMode::Deny,
+                create_test_descriptor_manager(),
+            )
+            .with_forwarded_trailers(forwards_trailers)
+        };
+        let carries_token = |mock_host: &MockWasmHost| {
+            mock_host
+                .last_dispatched_headers()
+                .contains(&("x-auth-token".to_string(), token.clone()))
+        };
+
+        service(false)
+            .dispatch_message(&mut ctx, vec![], None)
+            .expect("Failed to dispatch");
+        assert!(!carries_token(&mock_host));
+
+        // every call of a forwarding service gets them, not only the next one
+        let forwarding = service(true);
+        for _ in 0..2 {
+            forwarding
+                .dispatch_message(&mut ctx, vec![], None)
+                .expect("Failed to dispatch");
+            assert!(carries_token(&mock_host));
+        }
+    }
+
+    #[test]
+    fn test_requests_carry_the_authority_override() {
+        let ctx = ReqRespCtx::new(Arc::new(MockWasmHost::new()));
+        let service = DynamicService::new(
+            "test-cluster".to_string(),
+            "test.TestService".to_string(),
+            "TestMethod".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Deny,
+            create_test_descriptor_manager(),
+        );
+        let request = service
+            .build_request(&ctx, vec![], None)
+            .expect("Failed to build request");
+        assert_eq!(request.grpc_service(), "test-cluster");
+
+        let service = service
+            .with_authority(Some("limitador.kuadrant.svc".to_string()))
+            .with_health_check(true);
+        let request = service
+            .build_request(&ctx, vec![], None)
+            .expect("Failed to build request");
+        assert_eq!(request.upstream_name(), "test-cluster");
+        assert_ne!(request.grpc_service(), "test-cluster");
+        assert!(request.grpc_service().contains("limitador.kuadrant.svc"));
+
+        let health_check = service
+            .health_check_request()
+            .expect("health check to be configured")
+            .expect("Failed to build request");
+        assert_eq!(health_check.grpc_service(), request.grpc_service());
+    }
+
+    #[test]
+    fn test_unhealthy_upstream_skips_dispatch() {
+        use crate::kuadrant::MockWasmHost;
+        use std::time::SystemTime;
+
+        let service = DynamicService::new(
+            "test-cluster".to_string(),
+            "test.TestService".to_string(),
+            "TestMethod".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Deny,
+            create_test_descriptor_manager(),
+        );
+
+        let mock_host = Arc::new(MockWasmHost::new().with_current_time(SystemTime::UNIX_EPOCH));
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        let cel_ctx = Context::with_env(service.cel_env().expect("Failed to build CEL env"));
+        let cel_value = Program::compile(r#"test.TestRequest { message: "hello" }"#)
+            .expect("Failed to compile")
+            .execute(&cel_ctx)
+            .expect("Failed to execute");
+
+        service.set_healthy(false);
+        assert!(matches!(
+            service.dispatch_value(&mut ctx, &cel_value),
+            Err(ServiceError::Unhealthy)
+        ));
+        assert_eq!(mock_host.dispatched_calls(), 0);
+
+        service.set_healthy(true);
+        assert!(service.dispatch_value(&mut ctx, &cel_value).is_ok());
+        assert_eq!(mock_host.dispatched_calls(), 1);
+    }
+
+    #[test]
+    fn test_open_circuit_skips_dispatch() {
+        use crate::kuadrant::MockWasmHost;
+        use std::time::SystemTime;
+
+        let service = DynamicService::new(
+            "test-cluster".to_string(),
+            "test.TestService".to_string(),
+            "TestMethod".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Allow,
+            create_test_descriptor_manager(),
+        )
+        .with_circuit_breaker(Some(CircuitBreaker::new(1, Duration::from_secs(5))));
+
+        let mock_host = Arc::new(MockWasmHost::new().with_current_time(SystemTime::UNIX_EPOCH));
+        let mut ctx = ReqRespCtx::new(mock_host.clone());
+        let cel_ctx = Context::with_env(service.cel_env().expect("Failed to build CEL env"));
+        let cel_value = Program::compile(r#"test.TestRequest { message: "hello" }"#)
+            .expect("Failed to compile")
+            .execute(&cel_ctx)
+            .expect("Failed to execute");
+
+        service.record_outcome(&ctx, false);
+        assert!(matches!(
+            service.dispatch_value(&mut ctx, &cel_value),
+            Err(ServiceError::CircuitOpen)
+        ));
+        assert_eq!(mock_host.dispatched_calls(), 0);
+
+        mock_host.advance_time(Duration::from_secs(5));
+        assert!(service.dispatch_value(&mut ctx, &cel_value).is_ok());
+        assert_eq!(mock_host.dispatched_calls(), 1);
+    }
+
+    #[test]
+    fn test_only_over_limit_responses_are_cached() {
+        use crate::kuadrant::MockWasmHost;
+        use std::time::SystemTime;
+
+        const RATE_LIMIT_SERVICE: &str = "envoy.service.ratelimit.v3.RateLimitService";
+        let manager = Rc::new(DescriptorManager::default());
+        manager.add_expected(DescriptorKey::new(
+            "limitador".to_string(),
+            RATE_LIMIT_SERVICE.to_string(),
+        ));
+        let service = DynamicService::new(
+            "limitador".to_string(),
+            RATE_LIMIT_SERVICE.to_string(),
+            "ShouldRateLimit".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Deny,
+            manager.clone(),
+        )
+        .with_response_cache(Some(ResponseCache::new(Duration::from_secs(2), 16)));
+
+        let pool = manager
+            .get_pool("limitador", RATE_LIMIT_SERVICE)
+            .expect("Pool not found");
+        let response_desc = pool
+            .get_message_by_name("envoy.service.ratelimit.v3.RateLimitResponse")
+            .expect("Message not found");
+        let response = |code: i32| {
+            let mut response = DynamicMessage::new(response_desc.clone());
+            response.set_field_by_name("overall_code", prost_reflect::Value::EnumNumber(code));
+            response.encode_to_vec()
+        };
+
+        let mock_host = Arc::new(MockWasmHost::new().with_current_time(SystemTime::UNIX_EPOCH));
+        let ctx = ReqRespCtx::new(mock_host.clone());
+
+        service.cache_response(&ctx, b"under limit".to_vec(), response(1));
+        assert_eq!(service.cached_response(&ctx, b"under limit"), None);
+
+        service.cache_response(&ctx, b"over limit".to_vec(), response(2));
+        assert_eq!(
+            service.cached_response(&ctx, b"over limit"),
+            Some(response(2))
+        );
+        assert_eq!(service.cached_response(&ctx, b"other descriptors"), None);
+
+        mock_host.advance_time(Duration::from_secs(2));
+        assert_eq!(service.cached_response(&ctx, b"over limit"), None);
+    }
+
+    #[test]
+    fn test_status_details_response_from_trailer() {
+        use crate::kuadrant::MockWasmHost;
+
+        // google.rpc.Status { code: RESOURCE_EXHAUSTED, message: "OVER_LIMIT", details: [
+        //   LocalizedMessage { locale: "en-US", message: "Too many requests" } ] }
+        let trailer = "CAgSCk9WRVJfTElNSVQaTQovdHlwZS5nb29nbGVhcGlzLmNvbS9nb29nbGUucnBjLkxvY2FsaXplZE1lc3NhZ2USGgoFZW4tVVMSEVRvbyBtYW55IHJlcXVlc3Rz";
+        let mock_host = MockWasmHost::new().with_map(
+            "grpc.trailers".to_string(),
+            vec![(GRPC_STATUS_DETAILS_TRAILER.to_string(), trailer.to_string())],
+        );
+        let ctx = ReqRespCtx::new(Arc::new(mock_host));
+        let service = DynamicService::new(
+            "test-cluster".to_string(),
+            "test.TestService".to_string(),
+            "TestMethod".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Deny,
+            create_test_descriptor_manager(),
+        );
+        assert_eq!(service.status_details_response(&ctx), None);
+
+        let service = service.with_grpc_status_details(true);
+        assert_eq!(
+            service.status_details_response(&ctx),
+            Some(GrpcErrResponse {
+                status_code: 429,
+                headers: Vec::new(),
+                body: "Too many requests".to_string(),
+            })
+        );
+
+        let service = DynamicService::new(
+            "test-cluster".to_string(),
+            "test.TestService".to_string(),
+            "TestMethod".to_string(),
+            Duration::from_secs(1),
+            FailureMode::Allow,
+            create_test_descriptor_manager(),
+        )
+        .with_grpc_status_details(true);
+        assert_eq!(service.status_details_response(&ctx), None);
+    }
 }
@@ -201,6 +201,21 @@ pub fn cel_value_to_header_pairs(value: &Value) -> Vec<(String, String)> {
     pairs
 }
 
+/// The header names in a list of strings, other items are skipped
+pub fn cel_value_to_header_names(value: &Value) -> Vec<String> {
+    let Value::List(items) = value else {
+        return vec![];
+    };
+
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Value::String(name) => Some(name.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
 pub struct MessageConverter;
 
 impl MessageConverter {
@@ -222,7 +237,7 @@ impl MessageConverter {
         }
     }
 
-    fn cel_to_prost_value(value: &Value) -> Result<prost_types::Value, ConversionError> {
+    pub fn cel_to_prost_value(value: &Value) -> Result<prost_types::Value, ConversionError> {
         let kind = match value {
             Value::Null => prost_types::value::Kind::NullValue(0),
             Value::Float(f) => prost_types::value::Kind::NumberValue(*f),
@@ -1691,6 +1706,24 @@ mod tests {
         assert!(pairs.is_empty());
     }
 
+    #[test]
+    fn cel_value_to_header_names_skips_non_strings() {
+        let list = Value::List(Arc::new(vec![
+            Value::String(Arc::new("x-internal-token".to_string())),
+            Value::Int(1),
+            Value::String(Arc::new("x-backend-version".to_string())),
+        ]));
+
+        assert_eq!(
+            cel_value_to_header_names(&list),
+            vec![
+                "x-internal-token".to_string(),
+                "x-backend-version".to_string()
+            ]
+        );
+        assert!(cel_value_to_header_names(&Value::Null).is_empty());
+    }
+
     #[test]
     fn cel_value_to_header_pairs_list_of_lists() {
         let list = Value::List(Arc::new(vec![
@@ -0,0 +1,283 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use prost::Message;
+
+use super::ServiceError;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// `envoy.config.core.v3.GrpcService`, only its `envoy_grpc` target
+#[derive(Clone, PartialEq, Message)]
+struct GrpcService {
+    #[prost(message, optional, tag = "1")]
+    envoy_grpc: Option<EnvoyGrpc>,
+}
+
+/// `envoy.config.core.v3.GrpcService.EnvoyGrpc`
+#[derive(Clone, PartialEq, Message)]
+struct EnvoyGrpc {
+    #[prost(string, tag = "1")]
+    cluster_name: String,
+    #[prost(string, tag = "2")]
+    authority: String,
+}
+
+/// A gRPC call ready to be dispatched to an upstream cluster.
+#[derive(Debug)]
+pub struct GrpcRequest {
+    upstream_name: String,
+    grpc_service: String,
+    service_name: String,
+    method: String,
+    message: Vec<u8>,
+    timeout: Duration,
+    metadata: Vec<(String, Vec<u8>)>,
+}
+
+impl GrpcRequest {
+    pub fn upstream_name(&self) -> &str {
+        &self.upstream_name
+    }
+
+    /// What the call is dispatched to: the upstream name, or an
+    /// `envoy.config.core.v3.GrpcService` serialized with the upstream name and
+    /// the authority overriding its own, which Envoy takes in its place
+    pub fn grpc_service(&self) -> &str {
+        &self.grpc_service
+    }
+
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn message(&self) -> &[u8] {
+        &self.message
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sent verbatim as metadata of this call only, after the headers
+    /// forwarded with every call
+    pub fn metadata(&self) -> &[(String, Vec<u8>)] {
+        &self.metadata
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BuildError {
+    MissingField(&'static str),
+    /// An authority too long for the serialized target to be passed to the host
+    InvalidAuthority(String),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::MissingField(field) => write!(f, "gRPC request is missing {}", field),
+            BuildError::InvalidAuthority(authority) => {
+                write!(
+                    f,
+                    "gRPC request authority {} cannot be dispatched",
+                    authority
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<BuildError> for ServiceError {
+    fn from(e: BuildError) -> Self {
+        ServiceError::Dispatch(e.to_string())
+    }
+}
+
+/// Builds a [`GrpcRequest`]; the upstream, service and method are required,
+/// the message and metadata default to empty, the timeout to 200ms and the
+/// authority to the one of the upstream.
+pub struct GrpcRequestBuilder {
+    upstream_name: String,
+    authority: Option<String>,
+    service_name: Option<String>,
+    method: Option<String>,
+    message: Vec<u8>,
+    timeout: Duration,
+    metadata: Vec<(String, Vec<u8>)>,
+}
+
+impl GrpcRequestBuilder {
+    pub fn new(upstream_name: impl Into<String>) -> Self {
+        Self {
+            upstream_name: upstream_name.into(),
+            authority: None,
+            service_name: None,
+            method: None,
+            message: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+            metadata: Vec::new(),
+        }
+    }
+
+    pub fn service(mut self, service_name: impl Into<String>) -> Self {
+        self.service_name = Some(service_name.into());
+        self
+    }
+
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+
+    pub fn authority(mut self, authority: Option<String>) -> Self {
+        self.authority = authority;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn message(mut self, message: Vec<u8>) -> Self {
+        self.message = message;
+        self
+    }
+
+    pub fn metadata(mut self, metadata: Vec<(String, Vec<u8>)>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn build(self) -> Result<GrpcRequest, BuildError> {
+        if self.upstream_name.is_empty() {
+            return Err(BuildError::MissingField("upstream_name"));
+        }
+        let grpc_service = match self.authority {
+            None => self.upstream_name.clone(),
+            Some(authority) => {
+                let target = GrpcService {
+                    envoy_grpc: Some(EnvoyGrpc {
+                        cluster_name: self.upstream_name.clone(),
+                        authority: authority.clone(),
+                    }),
+                }
+                .encode_to_vec();
+                // The host takes the target as a string, which lengths past a
+                // single varint byte would not make
+                String::from_utf8(target).map_err(|_| BuildError::InvalidAuthority(authority))?
+            }
+        };
+        Ok(GrpcRequest {
+            upstream_name: self.upstream_name,
+            grpc_service,
+            service_name: self
+                .service_name
+                .ok_or(BuildError::MissingField("service_name"))?,
+            method: self.method.ok_or(BuildError::MissingField("method"))?,
+            message: self.message,
+            timeout: self.timeout,
+            metadata: self.metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_request_with_defaults() {
+        let request = GrpcRequestBuilder::new("limitador-cluster")
+            .service("envoy.service.ratelimit.v3.RateLimitService")
+            .method("ShouldRateLimit")
+            .message(vec![1, 2, 3])
+            .build()
+            .unwrap();
+
+        assert_eq!(request.upstream_name(), "limitador-cluster");
+        assert_eq!(
+            request.service_name(),
+            "envoy.service.ratelimit.v3.RateLimitService"
+        );
+        assert_eq!(request.method(), "ShouldRateLimit");
+        assert_eq!(request.message(), &[1, 2, 3]);
+        assert_eq!(request.timeout(), Duration::from_millis(200));
+        assert_eq!(request.grpc_service(), "limitador-cluster");
+
+        let request = GrpcRequestBuilder::new("limitador-cluster")
+            .service("envoy.service.ratelimit.v3.RateLimitService")
+            .method("ShouldRateLimit")
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        assert_eq!(request.timeout(), Duration::from_secs(5));
+        assert!(request.message().is_empty());
+        assert!(request.metadata().is_empty());
+    }
+
+    #[test]
+    fn authority_overrides_are_dispatched_as_a_grpc_service() {
+        let request = GrpcRequestBuilder::new("shared-cluster")
+            .authority(Some("limitador.kuadrant.svc".to_string()))
+            .service("envoy.service.ratelimit.v3.RateLimitService")
+            .method("ShouldRateLimit")
+            .build()
+            .unwrap();
+
+        assert_eq!(request.upstream_name(), "shared-cluster");
+        assert_eq!(
+            GrpcService::decode(request.grpc_service().as_bytes()).unwrap(),
+            GrpcService {
+                envoy_grpc: Some(EnvoyGrpc {
+                    cluster_name: "shared-cluster".to_string(),
+                    authority: "limitador.kuadrant.svc".to_string(),
+                }),
+            }
+        );
+
+        let authority = "a".repeat(200);
+        assert_eq!(
+            GrpcRequestBuilder::new("shared-cluster")
+                .authority(Some(authority.clone()))
+                .service("envoy.service.ratelimit.v3.RateLimitService")
+                .method("ShouldRateLimit")
+                .build()
+                .unwrap_err(),
+            BuildError::InvalidAuthority(authority)
+        );
+    }
+
+    #[test]
+    fn missing_required_fields_fail_to_build() {
+        assert_eq!(
+            GrpcRequestBuilder::new("")
+                .service("svc")
+                .method("Check")
+                .build()
+                .unwrap_err(),
+            BuildError::MissingField("upstream_name")
+        );
+        assert_eq!(
+            GrpcRequestBuilder::new("cluster")
+                .method("Check")
+                .build()
+                .unwrap_err(),
+            BuildError::MissingField("service_name")
+        );
+        assert_eq!(
+            GrpcRequestBuilder::new("cluster")
+                .service("svc")
+                .build()
+                .unwrap_err(),
+            BuildError::MissingField("method")
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use prost::Message;
+
+use super::{BuildError, GrpcRequest, GrpcRequestBuilder, ServiceError};
+use std::time::Duration;
+
+const HEALTH_SERVICE: &str = "grpc.health.v1.Health";
+const HEALTH_METHOD: &str = "Check";
+const SERVING: i32 = 1;
+
+/// `grpc.health.v1.HealthCheckRequest`
+#[derive(Clone, PartialEq, Message)]
+struct HealthCheckRequest {
+    #[prost(string, tag = "1")]
+    service: String,
+}
+
+/// `grpc.health.v1.HealthCheckResponse`
+#[derive(Clone, PartialEq, Message)]
+struct HealthCheckResponse {
+    #[prost(int32, tag = "1")]
+    status: i32,
+}
+
+/// A health check to dispatch at startup for a configured service.
+pub struct HealthCheck {
+    pub service: String,
+    pub request: GrpcRequest,
+}
+
+/// Builds the `grpc.health.v1.Health/Check` request asking `upstream_name`,
+/// under `authority` when overridden, about the gRPC service `service_name`.
+pub fn health_check_request(
+    upstream_name: &str,
+    authority: Option<String>,
+    service_name: &str,
+    timeout: Duration,
+) -> Result<GrpcRequest, BuildError> {
+    GrpcRequestBuilder::new(upstream_name)
+        .authority(authority)
+        .service(HEALTH_SERVICE)
+        .method(HEALTH_METHOD)
+        .timeout(timeout)
+        .message(
+            HealthCheckRequest {
+                service: service_name.to_string(),
+            }
+            .encode_to_vec(),
+        )
+        .build()
+}
+
+/// Whether the health check response reports the service as `SERVING`
+pub fn is_serving(message: &[u8]) -> Result<bool, ServiceError> {
+    HealthCheckResponse::decode(message)
+        .map(|response| response.status == SERVING)
+        .map_err(|e| ServiceError::Decode(format!("HealthCheckResponse: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_health_check_for_the_service() {
+        let request = health_check_request(
+            "limitador-cluster",
+            None,
+            "envoy.service.ratelimit.v3.RateLimitService",
+            Duration::from_secs(1),
+        )
+        .unwrap();
+
+        assert_eq!(request.upstream_name(), "limitador-cluster");
+        assert_eq!(request.service_name(), "grpc.health.v1.Health");
+        assert_eq!(request.method(), "Check");
+        assert_eq!(request.timeout(), Duration::from_secs(1));
+        assert_eq!(
+            HealthCheckRequest::decode(request.message())
+                .unwrap()
+                .service,
+            "envoy.service.ratelimit.v3.RateLimitService"
+        );
+    }
+
+    #[test]
+    fn only_serving_is_healthy() {
+        assert!(is_serving(&HealthCheckResponse { status: 1 }.encode_to_vec()).unwrap());
+        assert!(!is_serving(&HealthCheckResponse { status: 2 }.encode_to_vec()).unwrap());
+        assert!(!is_serving(&[]).unwrap());
+        assert!(is_serving(&[0xff]).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::time::Duration;
+
+use cel::objects::Key;
+use cel::Value;
+use serde_json::Value as JsonValue;
+
+use super::ServiceError;
+use crate::configuration::{ErrorResponse, FailureMode, HttpCalloutConfig};
+use crate::kuadrant::ReqRespCtx;
+
+/// An HTTP call ready to be dispatched to an upstream cluster.
+#[derive(Debug, PartialEq)]
+pub struct HttpCalloutRequest {
+    upstream_name: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    timeout: Duration,
+}
+
+impl HttpCalloutRequest {
+    pub fn upstream_name(&self) -> &str {
+        &self.upstream_name
+    }
+
+    /// The pseudo-headers of the call along with its content type
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+/// A service answering over plain HTTP with a JSON body holding its decision,
+/// for sidecars that do not speak gRPC.
+pub struct HttpCalloutService {
+    upstream_name: String,
+    path: String,
+    decision_path: JsonPath,
+    timeout: Duration,
+    failure_mode: FailureMode,
+    error_response: Option<ErrorResponse>,
+}
+
+impl HttpCalloutService {
+    pub fn new(
+        endpoint: String,
+        config: &HttpCalloutConfig,
+        timeout: Duration,
+        failure_mode: FailureMode,
+    ) -> Result<Self, ServiceError> {
+        let decision_path = JsonPath::parse(&config.decision_path).map_err(|e| {
+            ServiceError::Dispatch(format!(
+                "Invalid decisionPath `{}`: {e}",
+                config.decision_path
+            ))
+        })?;
+        Ok(Self {
+            upstream_name: endpoint,
+            path: config.path.clone(),
+            decision_path,
+            timeout,
+            failure_mode,
+            error_response: None,
+        })
+    }
+
+    pub fn with_error_response(mut self, error_response: Option<ErrorResponse>) -> Self {
+        self.error_response = error_response;
+        self
+    }
+
+    pub fn failure_mode(&self) -> FailureMode {
+        self.failure_mode
+    }
+
+    pub fn error_response(&self) -> Option<&ErrorResponse> {
+        self.error_response.as_ref()
+    }
+
+    /// POSTs `value`, as JSON, to the configured path of the upstream
+    pub fn build_http_request(
+        &self,
+        value: &Value,
+        timeout: Option<Duration>,
+    ) -> Result<HttpCalloutRequest, ServiceError> {
+        let body = serde_json::to_vec(&cel_to_json(value)?)
+            .map_err(|e| ServiceError::Dispatch(format!("Failed to encode JSON body: {e}")))?;
+        Ok(HttpCalloutRequest {
+            upstream_name: self.upstream_name.clone(),
+            headers: vec![
+                (":method".to_string(), "POST".to_string()),
+                (":path".to_string(), self.path.clone()),
+                (":authority".to_string(), self.upstream_name.clone()),
+                ("content-type".to_string(), "application/json".to_string()),
+            ],
+            body,
+            timeout: timeout.unwrap_or(self.timeout),
+        })
+    }
+
+    pub fn dispatch_value(
+        &self,
+        ctx: &ReqRespCtx,
+        value: &Value,
+        timeout: Option<Duration>,
+    ) -> Result<u32, ServiceError> {
+        ctx.dispatch_http_call(self.build_http_request(value, timeout)?)
+    }
+
+    /// Whether the JSON `body` the service answered with allows the request;
+    /// anything but `true` at the decision path denies it.
+    pub fn decide(&self, body: &[u8]) -> Result<bool, ServiceError> {
+        let json: JsonValue = serde_json::from_slice(body)
+            .map_err(|e| ServiceError::Decode(format!("HTTP callout response: {e}")))?;
+        Ok(self.decision_path.select(&json) == Some(&JsonValue::Bool(true)))
+    }
+}
+
+fn cel_to_json(value: &Value) -> Result<JsonValue, ServiceError> {
+    Ok(match value {
+        Value::Null => JsonValue::Null,
+        Value::Bool(b) => JsonValue::Bool(*b),
+        Value::Int(i) => JsonValue::from(*i),
+        Value::UInt(u) => JsonValue::from(*u),
+        Value::Float(f) => JsonValue::from(*f),
+        Value::String(s) => JsonValue::String(s.to_string()),
+        Value::List(items) => {
+            JsonValue::Array(items.iter().map(cel_to_json).collect::<Result<_, _>>()?)
+        }
+        Value::Map(m) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in m.map.iter() {
+                let Key::String(key) = key else {
+                    return Err(ServiceError::Dispatch(format!(
+                        "JSON object keys must be strings, got {key:?}"
+                    )));
+                };
+                object.insert(key.to_string(), cel_to_json(value)?);
+            }
+            JsonValue::Object(object)
+        }
+        other => {
+            return Err(ServiceError::Dispatch(format!(
+                "Cannot encode {other:?} as JSON"
+            )))
+        }
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+/// The subset of JSONPath selecting a single value: `$`, then any of
+/// `.field`, `['field']` and `[index]`.
+#[derive(Debug, PartialEq)]
+pub struct JsonPath(Vec<Segment>);
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let mut rest = path
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| "must start with `$`".to_string())?;
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after_dot) = rest.strip_prefix('.') {
+                let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+                let (field, tail) = after_dot.split_at(end);
+                if field.is_empty() {
+                    return Err("empty field name".to_string());
+                }
+                segments.push(Segment::Field(field.to_string()));
+                rest = tail;
+            } else if let Some(after_bracket) = rest.strip_prefix('[') {
+                let (selector, tail) = after_bracket
+                    .split_once(']')
+                    .ok_or_else(|| "unclosed `[`".to_string())?;
+                let quoted = selector
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| selector.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+                segments.push(match quoted {
+                    Some(field) => Segment::Field(field.to_string()),
+                    None => Segment::Index(
+                        selector
+                            .parse()
+                            .map_err(|_| format!("invalid index `{selector}`"))?,
+                    ),
+                });
+                rest = tail;
+            } else {
+                return Err(format!("unexpected `{rest}`"));
+            }
+        }
+        Ok(Self(segments))
+    }
+
+    pub fn select<'a>(&self, json: &'a JsonValue) -> Option<&'a JsonValue> {
+        self.0
+            .iter()
+            .try_fold(json, |value, segment| match segment {
+                Segment::Field(field) => value.get(field),
+                Segment::Index(index) => value.get(index),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn service(decision_path: &str) -> HttpCalloutService {
+        HttpCalloutService::new(
+            "opa-cluster".to_string(),
+            &HttpCalloutConfig {
+                path: "/v1/data/authz".to_string(),
+                decision_path: decision_path.to_string(),
+            },
+            Duration::from_millis(100),
+            FailureMode::Deny,
+        )
+        .expect("valid decision path")
+    }
+
+    #[test]
+    fn json_path_selects_fields_and_indices() {
+        let json = json!({"result": {"allow": [false, true], "the key": "x"}});
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&json).cloned();
+
+        assert_eq!(select("$"), Some(json.clone()));
+        assert_eq!(select("$.result.allow[1]"), Some(json!(true)));
+        assert_eq!(select("$['result'][\"the key\"]"), Some(json!("x")));
+        assert_eq!(select("$.result.allow[2]"), None);
+        assert_eq!(select("$.missing"), None);
+    }
+
+    #[test]
+    fn json_path_rejects_malformed_paths() {
+        for malformed in ["result.allow", "$.", "$..allow", "$[0", "$[one]", "$ allow"] {
+            assert!(
+                JsonPath::parse(malformed).is_err(),
+                "{malformed:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn builds_a_json_post_to_the_configured_path() {
+        let value = cel::Program::compile(r#"{"user": "alice", "groups": ["admin"], "n": 1}"#)
+            .unwrap()
+            .execute(&cel::Context::default())
+            .unwrap();
+        let request = service("$.allow").build_http_request(&value, None).unwrap();
+
+        assert_eq!(request.upstream_name(), "opa-cluster");
+        assert_eq!(request.timeout(), Duration::from_millis(100));
+        assert!(request
+            .headers()
+            .contains(&(":path".to_string(), "/v1/data/authz".to_string())));
+        assert!(request
+            .headers()
+            .contains(&(":method".to_string(), "POST".to_string())));
+        let body: JsonValue = serde_json::from_slice(request.body()).unwrap();
+        assert_eq!(body, json!({"user": "alice", "groups": ["admin"], "n": 1}));
+    }
+
+    #[test]
+    fn only_true_at_the_decision_path_allows() {
+        let service = service("$.result.allow");
+        assert!(service.decide(br#"{"result": {"allow": true}}"#).unwrap());
+        assert!(!service.decide(br#"{"result": {"allow": false}}"#).unwrap());
+        assert!(!service.decide(br#"{"result": {"allow": "true"}}"#).unwrap());
+        assert!(!service.decide(br#"{"result": {}}"#).unwrap());
+        assert!(service.decide(b"not json").is_err());
+    }
+
+    #[test]
+    fn invalid_decision_path_is_rejected() {
+        assert!(HttpCalloutService::new(
+            "opa-cluster".to_string(),
+            &HttpCalloutConfig {
+                path: "/".to_string(),
+                decision_path: "result.allow".to_string(),
+            },
+            Duration::from_millis(100),
+            FailureMode::Deny,
+        )
+        .is_err());
+    }
+}
@@ -1,15 +1,29 @@
-use crate::configuration::{FailureMode, Service as ServiceConfig, ServiceType};
+use crate::configuration::{ErrorResponse, FailureMode, Service as ServiceConfig, ServiceType};
 use crate::filter::DescriptorManager;
 use crate::kuadrant::ReqRespCtx;
-use std::{rc::Rc, time::Duration};
+use std::rc::Rc;
+use std::time::Duration;
 
+mod circuit_breaker;
 mod dynamic;
+mod grpc_request;
+mod health;
+#[cfg(feature = "http-callout")]
+mod http_callout;
+mod response_cache;
 mod tracing;
 
+pub use circuit_breaker::CircuitBreaker;
 pub use dynamic::converters::{
-    cel_value_to_header_pairs, deny_response_struct_def, MessageConverter,
+    cel_value_to_header_names, cel_value_to_header_pairs, deny_response_struct_def,
+    MessageConverter,
 };
 pub use dynamic::DynamicService;
+pub use grpc_request::{BuildError, GrpcRequest, GrpcRequestBuilder};
+pub use health::{is_serving, HealthCheck};
+#[cfg(feature = "http-callout")]
+pub use http_callout::{HttpCalloutRequest, HttpCalloutService};
+pub use response_cache::ResponseCache;
 pub use tracing::TracingService;
 
 #[derive(Clone)]
@@ -20,6 +34,8 @@ pub enum ServiceInstance {
     RateLimitReport(Rc<DynamicService>),
     Tracing(Option<Rc<TracingService>>),
     Dynamic(Rc<DynamicService>),
+    #[cfg(feature = "http-callout")]
+    HttpCallout(Rc<HttpCalloutService>),
 }
 
 impl ServiceInstance {
@@ -31,6 +47,62 @@ impl ServiceInstance {
             ServiceInstance::RateLimitReport(service) => service.failure_mode(),
             ServiceInstance::Tracing(_) => FailureMode::Allow,
             ServiceInstance::Dynamic(service) => service.failure_mode(),
+            #[cfg(feature = "http-callout")]
+            ServiceInstance::HttpCallout(service) => service.failure_mode(),
+        }
+    }
+
+    /// The startup health check request of a service configured with one
+    pub fn health_check_request(&self) -> Option<Result<GrpcRequest, BuildError>> {
+        match self {
+            ServiceInstance::Auth(service)
+            | ServiceInstance::RateLimit(service)
+            | ServiceInstance::RateLimitCheck(service)
+            | ServiceInstance::RateLimitReport(service)
+            | ServiceInstance::Dynamic(service) => service.health_check_request(),
+            ServiceInstance::Tracing(_) => None,
+            #[cfg(feature = "http-callout")]
+            ServiceInstance::HttpCallout(_) => None,
+        }
+    }
+
+    /// The delay of the first retry of a service retrying its calls
+    pub fn retry_base_delay(&self) -> Option<Duration> {
+        match self {
+            ServiceInstance::Auth(service)
+            | ServiceInstance::RateLimit(service)
+            | ServiceInstance::RateLimitCheck(service)
+            | ServiceInstance::RateLimitReport(service)
+            | ServiceInstance::Dynamic(service) => service.retry_base_delay(),
+            ServiceInstance::Tracing(_) => None,
+            #[cfg(feature = "http-callout")]
+            ServiceInstance::HttpCallout(_) => None,
+        }
+    }
+
+    pub fn set_healthy(&self, healthy: bool) {
+        match self {
+            ServiceInstance::Auth(service)
+            | ServiceInstance::RateLimit(service)
+            | ServiceInstance::RateLimitCheck(service)
+            | ServiceInstance::RateLimitReport(service)
+            | ServiceInstance::Dynamic(service) => service.set_healthy(healthy),
+            ServiceInstance::Tracing(_) => {}
+            #[cfg(feature = "http-callout")]
+            ServiceInstance::HttpCallout(_) => {}
+        }
+    }
+
+    pub fn error_response(&self) -> Option<&ErrorResponse> {
+        match self {
+            ServiceInstance::Auth(service)
+            | ServiceInstance::RateLimit(service)
+            | ServiceInstance::RateLimitCheck(service)
+            | ServiceInstance::RateLimitReport(service)
+            | ServiceInstance::Dynamic(service) => service.error_response(),
+            ServiceInstance::Tracing(_) => None,
+            #[cfg(feature = "http-callout")]
+            ServiceInstance::HttpCallout(service) => service.error_response(),
         }
     }
 
@@ -38,45 +110,86 @@ impl ServiceInstance {
         service: ServiceConfig,
         descriptor_manager: &Rc<DescriptorManager>,
     ) -> Result<Self, ServiceError> {
+        let circuit_breaker = service
+            .circuit_breaker
+            .as_ref()
+            .filter(|_| service.failure_mode == FailureMode::Allow)
+            .map(CircuitBreaker::from);
+        let response_cache = service.response_cache.as_ref().map(ResponseCache::from);
+        let authority = service.endpoint.authority_override;
         match service.service_type {
-            ServiceType::Auth => Ok(ServiceInstance::Auth(Rc::new(DynamicService::new(
-                service.endpoint,
-                "envoy.service.auth.v3.Authorization".to_string(),
-                "Check".to_string(),
-                service.timeout.0,
-                service.failure_mode,
-                Rc::clone(descriptor_manager),
-            )))),
-            ServiceType::RateLimit => Ok(ServiceInstance::RateLimit(Rc::new(DynamicService::new(
-                service.endpoint,
-                "envoy.service.ratelimit.v3.RateLimitService".to_string(),
-                "ShouldRateLimit".to_string(),
-                service.timeout.0,
-                service.failure_mode,
-                Rc::clone(descriptor_manager),
-            )))),
+            ServiceType::Auth => Ok(ServiceInstance::Auth(Rc::new(
+                DynamicService::new(
+                    service.endpoint.name,
+                    "envoy.service.auth.v3.Authorization".to_string(),
+                    "Check".to_string(),
+                    service.timeout.0,
+                    service.failure_mode,
+                    Rc::clone(descriptor_manager),
+                )
+                .with_circuit_breaker(circuit_breaker)
+                .with_retry_policy(service.retry_policy)
+                .with_error_response(service.error_response)
+                .with_grpc_status_details(service.use_grpc_status_details)
+                .with_health_check(service.health_check)
+                .with_authority(authority)
+                .with_forwarded_trailers(true)
+                .with_dynamic_metadata_key(service.dynamic_metadata_key),
+            ))),
+            ServiceType::RateLimit => Ok(ServiceInstance::RateLimit(Rc::new(
+                DynamicService::new(
+                    service.endpoint.name,
+                    "envoy.service.ratelimit.v3.RateLimitService".to_string(),
+                    "ShouldRateLimit".to_string(),
+                    service.timeout.0,
+                    service.failure_mode,
+                    Rc::clone(descriptor_manager),
+                )
+                .with_circuit_breaker(circuit_breaker)
+                .with_retry_policy(service.retry_policy)
+                .with_error_response(service.error_response)
+                .with_grpc_status_details(service.use_grpc_status_details)
+                .with_health_check(service.health_check)
+                .with_authority(authority)
+                .with_forwarded_trailers(true)
+                .with_response_cache(response_cache),
+            ))),
             ServiceType::RateLimitCheck => Ok(ServiceInstance::RateLimitCheck(Rc::new(
                 DynamicService::new(
-                    service.endpoint,
+                    service.endpoint.name,
                     "kuadrant.service.ratelimit.v1.RateLimitService".to_string(),
                     "CheckRateLimit".to_string(),
                     service.timeout.0,
                     service.failure_mode,
                     Rc::clone(descriptor_manager),
-                ),
+                )
+                .with_circuit_breaker(circuit_breaker)
+                .with_retry_policy(service.retry_policy)
+                .with_error_response(service.error_response)
+                .with_grpc_status_details(service.use_grpc_status_details)
+                .with_health_check(service.health_check)
+                .with_authority(authority)
+                .with_forwarded_trailers(true)
+                .with_response_cache(response_cache),
             ))),
             ServiceType::RateLimitReport => Ok(ServiceInstance::RateLimitReport(Rc::new(
                 DynamicService::new(
-                    service.endpoint,
+                    service.endpoint.name,
                     "kuadrant.service.ratelimit.v1.RateLimitService".to_string(),
                     "Report".to_string(),
                     service.timeout.0,
                     service.failure_mode,
                     Rc::clone(descriptor_manager),
-                ),
+                )
+                .with_circuit_breaker(circuit_breaker)
+                .with_retry_policy(service.retry_policy)
+                .with_error_response(service.error_response)
+                .with_grpc_status_details(service.use_grpc_status_details)
+                .with_health_check(service.health_check)
+                .with_authority(authority),
             ))),
             ServiceType::Tracing => Ok(ServiceInstance::Tracing(Some(Rc::new(
-                TracingService::new(service.endpoint, service.timeout.0),
+                TracingService::new(service.endpoint.name, service.timeout.0),
             )))),
             ServiceType::Dynamic => {
                 let grpc_service = service.grpc_service.as_ref().ok_or_else(|| {
@@ -86,14 +199,41 @@ impl ServiceInstance {
                     ServiceError::Dispatch("Missing grpc_method for Dynamic service".to_string())
                 })?;
 
-                Ok(ServiceInstance::Dynamic(Rc::new(DynamicService::new(
-                    service.endpoint,
-                    grpc_service.clone(),
-                    grpc_method.clone(),
-                    service.timeout.0,
-                    service.failure_mode,
-                    Rc::clone(descriptor_manager),
-                ))))
+                Ok(ServiceInstance::Dynamic(Rc::new(
+                    DynamicService::new(
+                        service.endpoint.name,
+                        grpc_service.clone(),
+                        grpc_method.clone(),
+                        service.timeout.0,
+                        service.failure_mode,
+                        Rc::clone(descriptor_manager),
+                    )
+                    .with_circuit_breaker(circuit_breaker)
+                    .with_retry_policy(service.retry_policy)
+                    .with_error_response(service.error_response)
+                    .with_grpc_status_details(service.use_grpc_status_details)
+                    .with_health_check(service.health_check)
+                    .with_authority(authority)
+                    .with_forwarded_trailers(true),
+                )))
+            }
+            #[cfg(feature = "http-callout")]
+            ServiceType::HttpCallout => {
+                let http_callout = service.http_callout.as_ref().ok_or_else(|| {
+                    ServiceError::Dispatch(
+                        "Missing http_callout for HttpCallout service".to_string(),
+                    )
+                })?;
+
+                Ok(ServiceInstance::HttpCallout(Rc::new(
+                    HttpCalloutService::new(
+                        service.endpoint.name,
+                        http_callout,
+                        service.timeout.0,
+                        service.failure_mode,
+                    )?
+                    .with_error_response(service.error_response),
+                )))
             }
         }
     }
@@ -104,6 +244,10 @@ pub enum ServiceError {
     Dispatch(String),
     Decode(String),
     Retrieval(String),
+    DeadlineExceeded,
+    CircuitOpen,
+    /// The upstream failed its health check
+    Unhealthy,
 }
 
 impl std::fmt::Display for ServiceError {
@@ -114,6 +258,13 @@ impl std::fmt::Display for ServiceError {
             ServiceError::Retrieval(msg) => {
                 write!(f, "Failed to retrieve gRPC response: {}", msg)
             }
+            ServiceError::DeadlineExceeded => {
+                write!(f, "Request deadline exceeded before gRPC dispatch")
+            }
+            ServiceError::CircuitOpen => write!(f, "Circuit open, gRPC call skipped"),
+            ServiceError::Unhealthy => {
+                write!(f, "Upstream failed its health check, gRPC call skipped")
+            }
         }
     }
 }
@@ -123,16 +274,8 @@ impl std::error::Error for ServiceError {}
 pub trait Service {
     type Response;
 
-    fn dispatch(
-        &self,
-        ctx: &mut ReqRespCtx,
-        upstream: &str,
-        service: &str,
-        method: &str,
-        message: Vec<u8>,
-        timeout: Duration,
-    ) -> Result<u32, ServiceError> {
-        ctx.dispatch_grpc_call(upstream, service, method, message, timeout)
+    fn dispatch(&self, ctx: &mut ReqRespCtx, request: GrpcRequest) -> Result<u32, ServiceError> {
+        ctx.dispatch_grpc_call(request)
     }
 
     fn parse_message(&self, message: Vec<u8>) -> Result<Self::Response, ServiceError>;
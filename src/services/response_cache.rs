@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use crate::configuration::ResponseCacheConfig;
+
+struct CachedResponse {
+    message: Vec<u8>,
+    expires_at: SystemTime,
+}
+
+/// Over limit responses keyed on the encoded request message, so requests
+/// carrying the same descriptors within `ttl` are denied without a gRPC call.
+/// Responses letting a request through are never cached, as each of them
+/// consumes some of the limit. At most `max_entries` are kept.
+///
+/// Owned by a service and shared by every HTTP context through its `Rc`. The
+/// filter runs on a single thread and never holds a borrow across a hostcall
+/// dispatch, so the `RefCell` it lives in cannot be borrowed twice.
+pub struct ResponseCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: HashMap<Vec<u8>, CachedResponse>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &[u8], now: SystemTime) -> Option<Vec<u8>> {
+        match self.entries.get(key) {
+            Some(entry) if now < entry.expires_at => Some(entry.message.clone()),
+            Some(_) => {
+                self.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&mut self, key: Vec<u8>, message: Vec<u8>, now: SystemTime) {
+        self.entries.retain(|_, entry| now < entry.expires_at);
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => {
+                    self.entries.remove(&oldest);
+                }
+                None => return,
+            }
+        }
+        self.entries.insert(
+            key,
+            CachedResponse {
+                message,
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+}
+
+impl From<&ResponseCacheConfig> for ResponseCache {
+    fn from(config: &ResponseCacheConfig) -> Self {
+        Self::new(config.ttl.0, config.max_entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(2);
+    const MAX_ENTRIES: usize = 2;
+
+    #[test]
+    fn serves_identical_requests_from_cache() {
+        let now = SystemTime::UNIX_EPOCH;
+        let mut cache = ResponseCache::new(TTL, MAX_ENTRIES);
+        assert_eq!(cache.get(b"descriptors", now), None);
+
+        cache.insert(b"descriptors".to_vec(), b"OVER_LIMIT".to_vec(), now);
+        assert_eq!(
+            cache.get(b"descriptors", now + Duration::from_secs(1)),
+            Some(b"OVER_LIMIT".to_vec())
+        );
+        assert_eq!(cache.get(b"other descriptors", now), None);
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let now = SystemTime::UNIX_EPOCH;
+        let mut cache = ResponseCache::new(TTL, MAX_ENTRIES);
+        cache.insert(b"descriptors".to_vec(), b"OVER_LIMIT".to_vec(), now);
+
+        assert_eq!(cache.get(b"descriptors", now + TTL), None);
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn inserting_evicts_expired_entries() {
+        let now = SystemTime::UNIX_EPOCH;
+        let mut cache = ResponseCache::new(TTL, MAX_ENTRIES);
+        cache.insert(b"first".to_vec(), b"OVER_LIMIT".to_vec(), now);
+        cache.insert(b"second".to_vec(), b"OVER_LIMIT".to_vec(), now + TTL);
+
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(
+            cache.get(b"second", now + TTL),
+            Some(b"OVER_LIMIT".to_vec())
+        );
+    }
+
+    #[test]
+    fn inserting_beyond_max_entries_evicts_the_oldest() {
+        let now = SystemTime::UNIX_EPOCH;
+        let mut cache = ResponseCache::new(TTL, MAX_ENTRIES);
+        cache.insert(b"first".to_vec(), b"OVER_LIMIT".to_vec(), now);
+        cache.insert(
+            b"second".to_vec(),
+            b"OVER_LIMIT".to_vec(),
+            now + Duration::from_millis(1),
+        );
+        cache.insert(
+            b"third".to_vec(),
+            b"OVER_LIMIT".to_vec(),
+            now + Duration::from_millis(2),
+        );
+
+        assert_eq!(cache.entries.len(), MAX_ENTRIES);
+        assert_eq!(cache.get(b"first", now), None);
+        assert!(cache.get(b"second", now).is_some());
+        assert!(cache.get(b"third", now).is_some());
+    }
+}
@@ -3,7 +3,7 @@ use std::time::Duration;
 use prost::Message;
 use tracing::{debug, info};
 
-use super::{Service, ServiceError};
+use super::{GrpcRequestBuilder, Service, ServiceError};
 use crate::kuadrant::ReqRespCtx;
 use crate::{WASM_SHIM_GIT_HASH, WASM_SHIM_NAME, WASM_SHIM_PROFILE, WASM_SHIM_VERSION};
 use opentelemetry::KeyValue;
@@ -97,14 +97,13 @@ impl TracingService {
             outgoing_message.len()
         );
 
-        self.dispatch(
-            ctx,
-            &self.upstream_name,
-            &self.service_name,
-            &self.method,
-            outgoing_message,
-            self.timeout,
-        )
+        let request = GrpcRequestBuilder::new(self.upstream_name.as_str())
+            .service(self.service_name.as_str())
+            .method(self.method.as_str())
+            .timeout(self.timeout)
+            .message(outgoing_message)
+            .build()?;
+        self.dispatch(ctx, request)
     }
 
     fn build_export_request(
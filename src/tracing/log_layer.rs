@@ -12,6 +12,9 @@ where
         _ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
         let level = event.metadata().level();
+        if *level > tracing::Level::WARN && !super::sampling::logs_sampled() {
+            return;
+        }
 
         struct MessageVisitor(String);
 
@@ -1,9 +1,11 @@
 mod log_layer;
 mod processor;
 mod propagation;
+mod sampling;
 
 pub use processor::{get_span_processor, BufferingSpanProcessor};
 pub use propagation::{HeadersExtractor, HeadersInjector};
+pub use sampling::{HostRandom, RandomSource, SampledLogger};
 
 use log_layer::LogLayer;
 use opentelemetry::trace::TracerProvider;
@@ -112,12 +114,12 @@ pub fn update_log_level() {
     }
 }
 
-/// Records an error on the current span and logs it.
+/// Records an error on the current span and logs it, through `$logger`.
 #[macro_export]
 macro_rules! record_error {
-    ($($arg:tt)*) => {{
+    ($logger:expr, $($arg:tt)*) => {{
         use tracing::field;
-        tracing::error!($($arg)*);
+        $crate::flog_error!($logger, $($arg)*);
         let span = tracing::Span::current();
         span.record("otel.status_code", "ERROR");
         span.record("otel.status_message", &field::display(format_args!($($arg)*)));
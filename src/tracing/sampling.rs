@@ -0,0 +1,112 @@
+use std::cell::Cell;
+
+thread_local! {
+    static LOGS_SAMPLED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Whether the request being processed on this thread emits its logs
+pub(super) fn logs_sampled() -> bool {
+    LOGS_SAMPLED.with(Cell::get)
+}
+
+/// A source of uniformly distributed numbers in `[0.0, 1.0)`
+pub trait RandomSource {
+    fn next_unit(&mut self) -> f32;
+}
+
+/// Draws from the host's random source, which backs UUID generation
+pub struct HostRandom;
+
+impl RandomSource for HostRandom {
+    fn next_unit(&mut self) -> f32 {
+        let bits = uuid::Uuid::new_v4().as_u64_pair().0;
+        // The top 24 bits fit the f32 mantissa exactly
+        (bits >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Decides per request whether the debug, info and trace logs emitted while
+/// processing it reach the host. Warnings and errors are always emitted.
+#[derive(Clone, Copy, Debug)]
+pub struct SampledLogger {
+    sample_rate: f32,
+}
+
+impl SampledLogger {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn should_log(&self, rng: &mut dyn RandomSource) -> bool {
+        self.sample_rate >= 1.0 || rng.next_unit() < self.sample_rate
+    }
+
+    /// Applies a request's sampling decision until the returned guard is dropped
+    pub fn scope(sampled: bool) -> SamplingGuard {
+        SamplingGuard {
+            previous: LOGS_SAMPLED.with(|cell| cell.replace(sampled)),
+        }
+    }
+}
+
+pub struct SamplingGuard {
+    previous: bool,
+}
+
+impl Drop for SamplingGuard {
+    fn drop(&mut self) {
+        LOGS_SAMPLED.with(|cell| cell.set(self.previous));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A linear congruential generator, so sampling is reproducible
+    struct SeededRandom(u64);
+
+    impl RandomSource for SeededRandom {
+        fn next_unit(&mut self) -> f32 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+    }
+
+    fn sampled_fraction(sample_rate: f32, requests: usize) -> f32 {
+        let logger = SampledLogger::new(sample_rate);
+        let mut rng = SeededRandom(42);
+        let sampled = (0..requests)
+            .filter(|_| logger.should_log(&mut rng))
+            .count();
+        sampled as f32 / requests as f32
+    }
+
+    #[test]
+    fn emits_approximately_the_sample_rate() {
+        for rate in [0.1, 0.25, 0.5, 0.9] {
+            let fraction = sampled_fraction(rate, 10_000);
+            assert!(
+                (fraction - rate).abs() < 0.02,
+                "sampled {fraction} of requests at rate {rate}"
+            );
+        }
+        assert_eq!(sampled_fraction(0.0, 1_000), 0.0);
+        assert_eq!(sampled_fraction(1.0, 1_000), 1.0);
+    }
+
+    #[test]
+    fn scope_restores_previous_decision() {
+        assert!(logs_sampled());
+        {
+            let _guard = SampledLogger::scope(false);
+            assert!(!logs_sampled());
+        }
+        assert!(logs_sampled());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::util::common::wasm_module;
+use proxy_wasm_test_framework::tester;
+use proxy_wasm_test_framework::types::{
+    Action, BufferType, LogLevel, MapType, MetricType, ReturnType,
+};
+use serial_test::serial;
+
+pub mod util;
+
+// 2023-11-14T22:13:20Z
+const REQUEST_START_NANOS: u64 = 1_700_000_000_000_000_000;
+const REQUEST_END_NANOS: u64 = REQUEST_START_NANOS + 250_000_000;
+
+fn config(access_log: bool) -> String {
+    format!(
+        r#"{{
+    "bypassPaths": ["/healthz"],
+    "observability": {{
+        "accessLog": {access_log}
+    }},
+    "services": {{
+        "limitador": {{
+            "type": "ratelimit",
+            "endpoint": "limitador-cluster",
+            "failureMode": "deny",
+            "timeout": "5s"
+        }}
+    }},
+    "actionSets": []
+}}"#
+    )
+}
+
+fn configure(module: &mut tester::Tester, root_context: i32, config: &str) {
+    module
+        .call_proxy_on_context_create(root_context, 0)
+        .expect_log(Some(LogLevel::Info), Some("#1 set_root_context"))
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+    module
+        .call_proxy_on_configure(root_context, 0)
+        .expect_log(Some(LogLevel::Info), Some("#1 on_configure"))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.configs"))
+        .returning(Some(1))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.hits"))
+        .returning(Some(2))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.misses"))
+        .returning(Some(3))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.allowed"))
+        .returning(Some(4))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.denied"))
+        .returning(Some(5))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.errors"))
+        .returning(Some(6))
+        .expect_increment_metric(Some(1), Some(1))
+        .expect_get_buffer_bytes(Some(BufferType::PluginConfiguration))
+        .returning(Some(config.as_bytes()))
+        .expect_get_log_level()
+        .returning(Some(LogLevel::Info))
+        .execute_and_expect(ReturnType::Bool(true))
+        .unwrap();
+}
+
+#[test]
+#[serial]
+fn it_logs_the_completed_exchange() {
+    let args = tester::MockSettings {
+        wasm_path: wasm_module(),
+        quiet: false,
+        allow_unexpected: false,
+    };
+    let mut module = tester::mock(args).unwrap();
+
+    module
+        .call_start()
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    let root_context = 1;
+    configure(&mut module, root_context, &config(true));
+
+    let http_context = 2;
+    module
+        .call_proxy_on_context_create(http_context, root_context)
+        .expect_get_log_level()
+        .returning(Some(LogLevel::Info))
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    module
+        .call_proxy_on_request_headers(http_context, 0, false)
+        .expect_get_current_time_nanos()
+        .returning(Some(REQUEST_START_NANOS))
+        .expect_get_header_map_value(Some(MapType::HttpRequestHeaders), Some(":method"))
+        .returning(Some("GET"))
+        .expect_get_header_map_value(Some(MapType::HttpRequestHeaders), Some(":path"))
+        .returning(Some("/healthz"))
+        .expect_get_header_map_value(Some(MapType::HttpRequestHeaders), Some(":path"))
+        .returning(Some("/healthz"))
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+
+    module
+        .call_proxy_on_response_headers(http_context, 0, false)
+        .expect_increment_metric(Some(4), Some(1))
+        .expect_get_header_map_value(Some(MapType::HttpResponseHeaders), Some(":status"))
+        .returning(Some("200"))
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+
+    module
+        .call_proxy_on_log(http_context)
+        .expect_get_current_time_nanos()
+        .returning(Some(REQUEST_END_NANOS))
+        .expect_log(
+            Some(LogLevel::Info),
+            Some(
+                r#"{"context_id":2,"ts":"2023-11-14T22:13:20.000000Z","method":"GET","authority":null,"path":"/healthz","status":200,"matched_action_set":null,"auth_decision":"skip","rl_decision":"skip","duration_ms":250}"#,
+            ),
+        )
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+}
+
+#[test]
+#[serial]
+fn it_logs_nothing_when_access_log_is_disabled() {
+    let args = tester::MockSettings {
+        wasm_path: wasm_module(),
+        quiet: false,
+        allow_unexpected: false,
+    };
+    let mut module = tester::mock(args).unwrap();
+
+    module
+        .call_start()
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    let root_context = 1;
+    configure(&mut module, root_context, &config(false));
+
+    let http_context = 2;
+    module
+        .call_proxy_on_context_create(http_context, root_context)
+        .expect_get_log_level()
+        .returning(Some(LogLevel::Info))
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    module
+        .call_proxy_on_request_headers(http_context, 0, false)
+        .expect_get_header_map_value(Some(MapType::HttpRequestHeaders), Some(":path"))
+        .returning(Some("/healthz"))
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+
+    module
+        .call_proxy_on_response_headers(http_context, 0, false)
+        .expect_increment_metric(Some(4), Some(1))
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+
+    // neither the time nor any log line is asked for
+    module
+        .call_proxy_on_log(http_context)
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+}
@@ -0,0 +1,274 @@
+use crate::util::common::{wasm_module, LOG_LEVEL};
+use crate::util::data;
+use proxy_wasm_test_framework::tester;
+use proxy_wasm_test_framework::types::{
+    Action, BufferType, LogLevel, MapType, MetricType, ReturnType,
+};
+use serial_test::serial;
+
+pub mod util;
+
+fn config(mode: &str, dry_run: bool) -> String {
+    r#"{
+    "overloadMode": "{mode}",
+    "dryRun": {dry_run},
+    "services": {
+        "limitador": {
+            "type": "ratelimit",
+            "endpoint": "limitador-cluster",
+            "failureMode": "deny",
+            "timeout": "5s"
+        }
+    },
+    "actionSets": [
+        {
+            "name": "some-name",
+            "routeRuleConditions": {
+                "hostnames": ["*.toystore.com"]
+            },
+            "actions": [
+                {
+                    "service": "limitador",
+                    "scope": "RLS-domain",
+                    "conditionalData": [
+                        {
+                            "data": [
+                                {
+                                    "static": {
+                                        "key": "admin",
+                                        "value": "1"
+                                    }
+                                }
+                            ]
+                        }
+                    ]
+                }
+            ]
+        }
+    ]
+}"#
+    .replace("{mode}", mode)
+    .replace("{dry_run}", &dry_run.to_string())
+}
+
+fn configure(module: &mut tester::Tester, root_context: i32, mode: &str, dry_run: bool) {
+    let config = config(mode, dry_run);
+    module
+        .call_proxy_on_context_create(root_context, 0)
+        .expect_log(Some(LogLevel::Info), Some("#1 set_root_context"))
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+    module
+        .call_proxy_on_configure(root_context, 0)
+        .expect_log(Some(LogLevel::Info), Some("#1 on_configure"))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.configs"))
+        .returning(Some(1))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.hits"))
+        .returning(Some(2))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.misses"))
+        .returning(Some(3))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.allowed"))
+        .returning(Some(4))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.denied"))
+        .returning(Some(5))
+        .expect_define_metric(Some(MetricType::Counter), Some("kuadrant.errors"))
+        .returning(Some(6))
+        .expect_increment_metric(Some(1), Some(1))
+        .expect_get_buffer_bytes(Some(BufferType::PluginConfiguration))
+        .returning(Some(config.as_bytes()))
+        .expect_get_log_level()
+        .returning(Some(LOG_LEVEL))
+        .execute_and_expect(ReturnType::Bool(true))
+        .unwrap();
+}
+
+#[test]
+#[serial]
+fn it_lets_requests_through_when_overloaded_in_allow_mode() {
+    let args = tester::MockSettings {
+        wasm_path: wasm_module(),
+        quiet: false,
+        allow_unexpected: false,
+    };
+    let mut module = tester::mock(args).unwrap();
+
+    module
+        .call_start()
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    let root_context = 1;
+    configure(&mut module, root_context, "allow", false);
+
+    let http_context = 2;
+    module
+        .call_proxy_on_context_create(http_context, root_context)
+        .expect_get_log_level()
+        .returning(Some(LOG_LEVEL))
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    // no gRPC call dispatched
+    module
+        .call_proxy_on_request_headers(http_context, 0, false)
+        .expect_get_header_map_value(
+            Some(MapType::HttpRequestHeaders),
+            Some("x-envoy-overloaded"),
+        )
+        .returning(Some("true"))
+        .expect_get_header_map_value(Some(MapType::HttpRequestHeaders), Some("x-envoy-internal"))
+        .returning(Some("true"))
+        .expect_log(
+            Some(LogLevel::Warn),
+            Some("#2 envoy is overloaded, skipping request"),
+        )
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+
+    module
+        .call_proxy_on_response_headers(http_context, 0, false)
+        .expect_increment_metric(Some(4), Some(1))
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+}
+
+#[test]
+#[serial]
+fn it_denies_requests_when_overloaded_in_deny_mode() {
+    let args = tester::MockSettings {
+        wasm_path: wasm_module(),
+        quiet: false,
+        allow_unexpected: false,
+    };
+    let mut module = tester::mock(args).unwrap();
+
+    module
+        .call_start()
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    let root_context = 1;
+    configure(&mut module, root_context, "deny", false);
+
+    let http_context = 2;
+    module
+        .call_proxy_on_context_create(http_context, root_context)
+        .expect_get_log_level()
+        .returning(Some(LOG_LEVEL))
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    // no gRPC call dispatched
+    module
+        .call_proxy_on_request_headers(http_context, 0, false)
+        .expect_get_header_map_value(
+            Some(MapType::HttpRequestHeaders),
+            Some("x-envoy-overloaded"),
+        )
+        .returning(Some("true"))
+        .expect_get_header_map_value(Some(MapType::HttpRequestHeaders), Some("x-envoy-internal"))
+        .returning(Some("true"))
+        .expect_log(
+            Some(LogLevel::Warn),
+            Some("#2 envoy is overloaded, denying request"),
+        )
+        .expect_send_local_response(
+            Some(503),
+            Some("Service Unavailable.\n"),
+            Some(vec![]),
+            Some(-1),
+        )
+        .expect_increment_metric(Some(5), Some(1))
+        .execute_and_expect(ReturnType::Action(Action::Pause))
+        .unwrap();
+}
+
+#[test]
+#[serial]
+fn it_does_not_reply_when_overloaded_in_dry_run() {
+    let args = tester::MockSettings {
+        wasm_path: wasm_module(),
+        quiet: false,
+        allow_unexpected: false,
+    };
+    let mut module = tester::mock(args).unwrap();
+
+    module
+        .call_start()
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    let root_context = 1;
+    configure(&mut module, root_context, "deny", true);
+
+    let http_context = 2;
+    module
+        .call_proxy_on_context_create(http_context, root_context)
+        .expect_get_log_level()
+        .returning(Some(LOG_LEVEL))
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    module
+        .call_proxy_on_request_headers(http_context, 0, false)
+        .expect_get_header_map_value(
+            Some(MapType::HttpRequestHeaders),
+            Some("x-envoy-overloaded"),
+        )
+        .returning(Some("true"))
+        .expect_get_header_map_value(Some(MapType::HttpRequestHeaders), Some("x-envoy-internal"))
+        .returning(Some("true"))
+        .expect_log(
+            Some(LogLevel::Warn),
+            Some("#2 envoy is overloaded, denying request"),
+        )
+        .expect_log(
+            Some(LogLevel::Warn),
+            Some("#2 dry run, not replying with status 503"),
+        )
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+}
+
+#[test]
+#[serial]
+fn it_ignores_the_overloaded_header_on_external_requests() {
+    let args = tester::MockSettings {
+        wasm_path: wasm_module(),
+        quiet: false,
+        allow_unexpected: false,
+    };
+    let mut module = tester::mock(args).unwrap();
+
+    module
+        .call_start()
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    let root_context = 1;
+    configure(&mut module, root_context, "deny", false);
+
+    let http_context = 2;
+    module
+        .call_proxy_on_context_create(http_context, root_context)
+        .expect_get_log_level()
+        .returning(Some(LOG_LEVEL))
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    // set by the client, not envoy: the request is handled as any other
+    module
+        .call_proxy_on_request_headers(http_context, 0, false)
+        .expect_get_header_map_value(
+            Some(MapType::HttpRequestHeaders),
+            Some("x-envoy-overloaded"),
+        )
+        .returning(Some("true"))
+        .expect_get_header_map_value(Some(MapType::HttpRequestHeaders), Some("x-envoy-internal"))
+        .returning(None)
+        .expect_get_property(Some(vec!["request", "host"]))
+        .returning(Some("example.com".as_bytes()))
+        .expect_increment_metric(Some(3), Some(1))
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+}